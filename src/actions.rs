@@ -0,0 +1,621 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::diff::Version;
+use crate::notify::NotifyEvent;
+use crate::settings::Settings;
+use crate::tools::{Tool, Tools};
+
+/// Entries of the action menu; most of them shell out to an optional tool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    FindNote,
+    SearchContents,
+    ExportHtml,
+    ExportPdf,
+    CommitVault,
+    ReadAloud,
+    ExportTasks,
+    SyncTasks,
+    OpenInPager,
+    ExportAnnotations,
+    CompareNotes,
+    ExportAnki,
+    CopyLink,
+    InsertSnippet,
+    JournalEntry,
+    ClearScratch,
+    ArchiveScratch,
+    NoteFromClipboard,
+}
+
+impl Action {
+    pub const ALL: [Action; 18] = [
+        Action::FindNote,
+        Action::SearchContents,
+        Action::ExportHtml,
+        Action::ExportPdf,
+        Action::CommitVault,
+        Action::ReadAloud,
+        Action::ExportTasks,
+        Action::SyncTasks,
+        Action::OpenInPager,
+        Action::ExportAnnotations,
+        Action::CompareNotes,
+        Action::ExportAnki,
+        Action::CopyLink,
+        Action::InsertSnippet,
+        Action::JournalEntry,
+        Action::ClearScratch,
+        Action::ArchiveScratch,
+        Action::NoteFromClipboard,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::FindNote => "Find note by name",
+            Action::SearchContents => "Search note contents",
+            Action::ExportHtml => "Export note to HTML",
+            Action::ExportPdf => "Export note to PDF",
+            Action::CommitVault => "Commit vault changes",
+            Action::ReadAloud => "Read note aloud",
+            Action::ExportTasks => "Export note tasks",
+            Action::SyncTasks => "Sync completed tasks back",
+            Action::OpenInPager => "Open note in pager",
+            Action::ExportAnnotations => "Export note annotations",
+            Action::CompareNotes => "Compare the two marked notes",
+            Action::ExportAnki => "Export flashcards for Anki",
+            Action::CopyLink => "Copy link to note",
+            Action::InsertSnippet => "Insert a snippet into the note",
+            Action::JournalEntry => "Write a journal entry in today's note",
+            Action::ClearScratch => "Clear the scratch note",
+            Action::ArchiveScratch => "Archive the scratch note",
+            Action::NoteFromClipboard => "New note from the clipboard",
+        }
+    }
+
+    /// Tools the action cannot run without
+    fn requires(&self, settings: &Settings) -> &'static [Tool] {
+        match self {
+            Action::FindNote | Action::SearchContents => &[Tool::Fzf],
+            // pandoc is only used for citations
+            Action::ExportHtml => &[],
+            // `pdf_converter` may name a program of its own
+            Action::ExportPdf if settings.pdf_converter.trim().is_empty() => &[Tool::PdfConverter],
+            Action::ExportPdf => &[],
+            Action::CommitVault => &[Tool::Git],
+            Action::ReadAloud => &[Tool::Speech],
+            // Taskwarrior is only needed for that backend, which the task module checks
+            Action::ExportTasks | Action::SyncTasks => &[],
+            // $PAGER may stand in for less, which `page_note` checks
+            Action::OpenInPager => &[],
+            Action::ExportAnnotations | Action::CompareNotes | Action::ExportAnki => &[],
+            // Without a clipboard program the terminal is asked to copy
+            Action::CopyLink => &[],
+            Action::InsertSnippet | Action::JournalEntry => &[],
+            Action::ClearScratch | Action::ArchiveScratch => &[],
+            Action::NoteFromClipboard => &[Tool::Paste],
+        }
+    }
+
+    /// What to use instead when the action is unavailable
+    fn fallback(&self) -> Option<&'static str> {
+        match self {
+            Action::FindNote => Some("navigate the list with j/k, gg/G and R to re-root"),
+            Action::ExportPdf => Some("set pdf_converter in settings.json"),
+            Action::SearchContents
+            | Action::ExportHtml
+            | Action::CommitVault
+            | Action::ReadAloud
+            | Action::ExportTasks
+            | Action::SyncTasks
+            | Action::OpenInPager
+            | Action::ExportAnnotations
+            | Action::CompareNotes
+            | Action::ExportAnki
+            | Action::CopyLink
+            | Action::InsertSnippet
+            | Action::JournalEntry
+            | Action::ClearScratch
+            | Action::ArchiveScratch
+            | Action::NoteFromClipboard => None,
+        }
+    }
+
+    /// The notification event for actions that may take a while, announced when they finish
+    pub fn job(&self) -> Option<NotifyEvent> {
+        match self {
+            Action::ExportHtml
+            | Action::ExportPdf
+            | Action::ExportTasks
+            | Action::ExportAnnotations
+            | Action::ExportAnki => Some(NotifyEvent::Export),
+            Action::SyncTasks => Some(NotifyEvent::Sync),
+            Action::CommitVault => Some(NotifyEvent::Backup),
+            _ => None,
+        }
+    }
+
+    /// Actions that take over the terminal (the TUI is suspended while they run)
+    pub fn is_interactive(&self) -> bool {
+        matches!(
+            self,
+            Action::FindNote | Action::SearchContents | Action::OpenInPager
+        )
+    }
+
+    /// Actions that work on the selected note
+    pub fn needs_note(&self) -> bool {
+        matches!(
+            self,
+            Action::ExportHtml
+                | Action::ExportPdf
+                | Action::ReadAloud
+                | Action::ExportTasks
+                | Action::OpenInPager
+                | Action::ExportAnnotations
+                | Action::CopyLink
+                | Action::InsertSnippet
+        )
+    }
+
+    /// Why the action can't run, or None if every required tool is installed
+    pub fn unavailable_reason(&self, tools: &Tools, settings: &Settings) -> Option<String> {
+        let missing: Vec<&str> = self
+            .requires(settings)
+            .iter()
+            .filter(|tool| !tools.has(**tool))
+            .map(|tool| tool.label())
+            .collect();
+        if missing.is_empty() {
+            return None;
+        }
+        let mut reason = format!("Needs {} (not found on PATH)", missing.join(" and "));
+        if let Some(fallback) = self.fallback() {
+            reason.push_str(&format!(" - instead, {}", fallback));
+        }
+        Some(reason)
+    }
+
+    /// Extra detail for an available action that runs in a degraded mode
+    pub fn note(&self, tools: &Tools) -> Option<&'static str> {
+        match self {
+            Action::SearchContents if !tools.has(Tool::Ripgrep) => {
+                Some("rg not found, using the slower built-in search")
+            }
+            Action::OpenInPager if !tools.has(Tool::MarkdownRenderer) => {
+                Some("glow or bat not found, showing the raw markdown")
+            }
+            _ => None,
+        }
+    }
+}
+
+/// First non-empty line of a command's output, for the status bar
+fn summarize(output: &std::process::Output) -> String {
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    stderr
+        .lines()
+        .chain(stdout.lines())
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l.trim().to_string())
+        .unwrap_or_else(|| output.status.to_string())
+}
+
+fn run_captured(command: &mut Command) -> io::Result<String> {
+    let output = command.stdin(Stdio::null()).output()?;
+    if output.status.success() {
+        Ok(summarize(&output))
+    } else {
+        Err(io::Error::other(summarize(&output)))
+    }
+}
+
+/// Where notes are exported: `export_directory` ("~/" allowed), or the note's own folder
+pub fn export_directory(settings: &Settings, note: &Path) -> PathBuf {
+    let directory = settings.export_directory.trim();
+    if directory.is_empty() {
+        note.parent().unwrap_or(Path::new(".")).to_path_buf()
+    } else if let Some(rest) = directory.strip_prefix("~/") {
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(rest)
+    } else {
+        PathBuf::from(directory)
+    }
+}
+
+/// Convert a note to a standalone HTML file in the export directory
+/// With pandoc and a bibliography, `[@key]` citations become formatted references; otherwise
+/// the note is rendered as `lair export --html` renders it
+pub fn export_html(settings: &Settings, tools: &Tools, note: &Path) -> io::Result<PathBuf> {
+    let directory = export_directory(settings, note);
+    let bibliography =
+        crate::citations::bibliography_path(settings).filter(|path| path.is_file());
+    let (Some(pandoc), Some(bibliography)) = (tools.path(Tool::Pandoc), bibliography) else {
+        return crate::site::export_note(settings, note, &directory);
+    };
+    fs::create_dir_all(&directory)?;
+    let target = directory
+        .join(note.file_stem().unwrap_or_default())
+        .with_extension("html");
+    let mut command = Command::new(pandoc);
+    command.arg("--standalone").arg("--citeproc").arg("--bibliography").arg(bibliography);
+    run_captured(command.arg(note).arg("-o").arg(&target))?;
+    Ok(target)
+}
+
+/// Convert a note to PDF in the export directory, rendering it to HTML first and handing that
+/// to `pdf_converter`, or to wkhtmltopdf or weasyprint
+/// The HTML is written beside the PDF, so the note's images are found, and removed after
+pub fn export_pdf(settings: &Settings, tools: &Tools, note: &Path) -> io::Result<PathBuf> {
+    let template = match settings.pdf_converter.trim() {
+        "" => tools
+            .path(Tool::PdfConverter)
+            .map(|program| program.to_string_lossy().to_string())
+            .ok_or_else(|| {
+                io::Error::other(format!(
+                    "needs {} (not found on PATH), or a pdf_converter in settings.json",
+                    Tool::PdfConverter.label()
+                ))
+            })?,
+        template => template.to_string(),
+    };
+    let directory = export_directory(settings, note);
+    let stem = note.file_stem().unwrap_or_default().to_string_lossy();
+    let target = directory.join(format!("{}.pdf", stem));
+    // The page's links are made relative to where it's written, so images still load from there
+    let page = std::env::temp_dir().join(format!("lair-{}-{}.html", std::process::id(), stem));
+    let html = crate::site::export_note_as(settings, note, &page)?;
+    let result = convert_to_pdf(&template, &html, &target);
+    let _ = fs::remove_file(&html);
+    result.map(|()| target)
+}
+
+/// Run a converter command, e.g. "weasyprint {input} {output}"
+/// Without placeholders the paths are appended in that order
+fn convert_to_pdf(template: &str, input: &Path, output: &Path) -> io::Result<()> {
+    let mut words = template.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::other("no PDF converter configured"))?;
+    let mut command = Command::new(program);
+    let mut placed = false;
+    for word in words {
+        placed |= word.contains("{input}") || word.contains("{output}");
+        command.arg(
+            word.replace("{input}", &input.to_string_lossy())
+                .replace("{output}", &output.to_string_lossy()),
+        );
+    }
+    if !placed {
+        // Recent wkhtmltopdf only loads the note's images when asked to
+        if Path::new(program).file_name().is_some_and(|n| n == "wkhtmltopdf") {
+            command.arg("--enable-local-file-access");
+        }
+        command.arg(input).arg(output);
+    }
+    run_captured(&mut command)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))?;
+    Ok(())
+}
+
+/// Stage and commit everything in the vault
+pub fn commit_vault(git: &Path, vault: &Path) -> io::Result<String> {
+    run_captured(Command::new(git).arg("-C").arg(vault).args(["add", "-A"]))?;
+    let message = format!("Update notes {}", chrono::Local::now().format("%Y-%m-%d %H:%M"));
+    run_captured(Command::new(git).arg("-C").arg(vault).args(["commit", "-m", &message]))
+}
+
+/// Start reading a note aloud in the background
+pub fn read_aloud(speech: &Path, note: &Path) -> io::Result<()> {
+    Command::new(speech)
+        .arg("-f")
+        .arg(note)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Put text on the clipboard with a clipboard program, or failing that with the OSC 52
+/// escape sequence, which most terminals (and tmux, with `set-clipboard on`) act on, even
+/// over SSH
+pub fn copy_to_clipboard(tools: &Tools, text: &str) -> io::Result<()> {
+    if let Some(program) = tools.path(Tool::Clipboard) {
+        let mut command = Command::new(program);
+        match program.file_name().and_then(|n| n.to_str()) {
+            Some("xclip") => command.args(["-selection", "clipboard"]),
+            Some("xsel") => command.args(["--clipboard", "--input"]),
+            _ => &mut command,
+        };
+        let copied = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .and_then(|mut child| {
+                if let Some(mut stdin) = child.stdin.take() {
+                    stdin.write_all(text.as_bytes())?;
+                }
+                child.wait()
+            });
+        // wl-copy without a Wayland session, or xclip without X, falls through to OSC 52
+        if copied.is_ok_and(|status| status.success()) {
+            return Ok(());
+        }
+    }
+    let encoded = BASE64.encode(text);
+    let mut stdout = io::stdout();
+    write!(stdout, "\x1b]52;c;{}\x07", encoded)?;
+    stdout.flush()
+}
+
+/// The text on the clipboard, read with a clipboard program
+/// Unlike copying there's no OSC 52 fallback, as few terminals answer a request to read it
+pub fn paste_from_clipboard(paste: &Path) -> io::Result<String> {
+    let mut command = Command::new(paste);
+    match paste.file_name().and_then(|n| n.to_str()) {
+        Some("xclip") => command.args(["-selection", "clipboard", "-o"]),
+        Some("xsel") => command.args(["--clipboard", "--output"]),
+        Some("wl-paste") => command.arg("--no-newline"),
+        _ => &mut command,
+    };
+    let output = command.stdin(Stdio::null()).stderr(Stdio::null()).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!("{} exited with {}", paste.display(), output.status)));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// A new note in `folder` (today's date folder when None) holding the clipboard's text, named
+/// like a note created without a name and made as `lair new` makes one, hooks included
+pub fn note_from_clipboard(
+    settings: &Settings,
+    paste: &Path,
+    folder: Option<&PathBuf>,
+) -> Result<(PathBuf, Option<String>), String> {
+    let text = paste_from_clipboard(paste).map_err(|e| e.to_string())?;
+    if text.trim().is_empty() {
+        return Err("the clipboard is empty".to_string());
+    }
+    let mut content = text.trim_end().to_string();
+    content.push('\n');
+    crate::cli::create_note(settings, "", folder, Some(&content))
+}
+
+/// The pager command: `$PAGER` (which may include arguments), else less or more
+fn pager_command(tools: &Tools) -> io::Result<Command> {
+    if let Ok(pager) = env::var("PAGER") {
+        let mut words = pager.split_whitespace();
+        if let Some(program) = words.next() {
+            let mut command = Command::new(program);
+            command.args(words);
+            return Ok(command);
+        }
+    }
+    let pager = tools
+        .path(Tool::Pager)
+        .ok_or_else(|| io::Error::other("no pager found, set $PAGER or install less"))?;
+    let mut command = Command::new(pager);
+    if pager.file_name().is_some_and(|n| n == "less") {
+        command.arg("-R"); // Pass the renderer's colours through
+    }
+    Ok(command)
+}
+
+/// A markdown note rendered for the terminal by glow or bat, if either is installed
+/// Anything that goes wrong falls back to the raw text
+fn render_for_terminal(tools: &Tools, note: &Path) -> Option<Vec<u8>> {
+    let renderer = tools.path(Tool::MarkdownRenderer)?;
+    let is_markdown = note
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"));
+    if !is_markdown {
+        return None;
+    }
+    let mut command = Command::new(renderer);
+    if renderer.file_name().is_some_and(|n| n == "glow") {
+        // An explicit style keeps glow's colours when its output isn't a terminal
+        command.args(["--style", "dark"]);
+    } else {
+        command.args(["--color=always", "--style=plain", "--paging=never", "--language=markdown"]);
+    }
+    let output = command
+        .arg(note)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then_some(output.stdout)
+}
+
+/// Show a note in the pager, rendered first when a markdown renderer is available
+/// Expects the terminal to be handed over to the pager (TUI suspended)
+pub fn page_note(tools: &Tools, note: &Path) -> io::Result<()> {
+    let content = match render_for_terminal(tools, note) {
+        Some(rendered) => rendered,
+        None => fs::read(note)?,
+    };
+    let mut child = pager_command(tools)?.stdin(Stdio::piped()).spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes the pipe, which isn't an error here
+        let _ = stdin.write_all(&content);
+    }
+    child.wait()?;
+    Ok(())
+}
+
+/// Open two notes in the configured diff or merge tool, e.g. "delta {left} {right}"
+/// Without placeholders the paths are appended in that order; the exit status is ignored,
+/// since diff tools use it to report that the files differ
+pub fn run_diff_tool(template: &str, left: &Path, right: &Path) -> io::Result<()> {
+    let mut words = template.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::other("no diff tool configured"))?;
+    let mut command = Command::new(program);
+    let mut placed = false;
+    for word in words {
+        placed |= word.contains("{left}") || word.contains("{right}");
+        command.arg(
+            word.replace("{left}", &left.to_string_lossy())
+                .replace("{right}", &right.to_string_lossy()),
+        );
+    }
+    if !placed {
+        command.arg(left).arg(right);
+    }
+    command
+        .status()
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", program, e)))?;
+    Ok(())
+}
+
+/// Open two versions of a note in the diff tool, revisions by way of temporary copies that are
+/// removed once it exits
+pub fn run_diff_tool_on_versions(template: &str, left: &Version, right: &Version) -> io::Result<()> {
+    let left_file = left.to_file()?;
+    let result = right
+        .to_file()
+        .and_then(|right_file| {
+            let compared = run_diff_tool(template, &left_file, &right_file);
+            if right.revision.is_some() {
+                let _ = fs::remove_file(&right_file);
+            }
+            compared
+        });
+    if left.revision.is_some() {
+        let _ = fs::remove_file(&left_file);
+    }
+    result
+}
+
+/// Notes to pick from, limited to one language when `language` is given
+fn candidate_notes(settings: &Settings, language: Option<&str>) -> Vec<PathBuf> {
+    crate::vault::all_files(settings)
+        .into_iter()
+        .filter(|f| crate::vault::is_note(f, settings))
+        .filter(|f| {
+            language.is_none_or(|language| {
+                fs::read_to_string(f)
+                    .ok()
+                    .and_then(|content| crate::note::language(&content))
+                    .is_some_and(|l| l == language)
+            })
+        })
+        .collect()
+}
+
+/// Every line of the given notes as "path:line:text", for searching without rg
+fn builtin_search_lines(notes: &[PathBuf], vault: &Path) -> String {
+    let mut lines = String::new();
+    for file in notes {
+        let Ok(content) = fs::read_to_string(file) else {
+            continue;
+        };
+        let relative = file.strip_prefix(vault).unwrap_or(file).display().to_string();
+        for (n, line) in content.lines().enumerate() {
+            if !line.trim().is_empty() {
+                lines.push_str(&format!("{}:{}:{}\n", relative, n + 1, line));
+            }
+        }
+    }
+    lines
+}
+
+/// Let the user pick a note with fzf, returning its path
+/// With a `language`, only notes whose `lang:` matches are offered
+/// Expects the terminal to be handed over to fzf (TUI suspended)
+pub fn pick_note(
+    action: Action,
+    tools: &Tools,
+    settings: &Settings,
+    language: Option<&str>,
+) -> io::Result<Option<PathBuf>> {
+    let Some(fzf) = tools.path(Tool::Fzf) else {
+        return Ok(None);
+    };
+    let vault = Path::new(&settings.notes_directory);
+    let mut fzf_command = Command::new(fzf);
+    fzf_command.current_dir(vault).stdout(Stdio::piped());
+
+    let mut rg_child = None;
+    let input = match (action, tools.path(Tool::Ripgrep)) {
+        (Action::SearchContents, Some(rg)) => {
+            let mut rg_command = Command::new(rg);
+            rg_command
+                .args(["--line-number", "--no-heading", "--color=never", "--glob"])
+                .arg(format!("!{}", crate::hooks::VAULT_CONFIG_DIR))
+                .current_dir(vault)
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null());
+            if vault.join(crate::vault::LAIR_IGNORE_FILE).is_file() {
+                rg_command.arg("--ignore-file").arg(crate::vault::LAIR_IGNORE_FILE);
+            }
+            if let Some(archive) = crate::vault::hidden_archive(settings) {
+                rg_command.arg("--glob").arg(format!("!{}/**", archive));
+            }
+            match language {
+                // rg can't read frontmatter, so hand it the matching notes explicitly
+                Some(_) => {
+                    rg_command.arg("--with-filename").arg("--");
+                    for note in candidate_notes(settings, language) {
+                        rg_command.arg(note.strip_prefix(vault).unwrap_or(&note));
+                    }
+                }
+                None => {
+                    rg_command.arg(".");
+                }
+            }
+            let mut child = rg_command.spawn()?;
+            if let Some(stdout) = child.stdout.take() {
+                fzf_command.stdin(stdout);
+            }
+            rg_child = Some(child);
+            None
+        }
+        (Action::SearchContents, None) => {
+            Some(builtin_search_lines(&candidate_notes(settings, language), vault))
+        }
+        _ => {
+            let names: Vec<String> = candidate_notes(settings, language)
+                .into_iter()
+                .map(|f| f.strip_prefix(vault).unwrap_or(&f).display().to_string())
+                .collect();
+            Some(names.join("\n"))
+        }
+    };
+
+    if input.is_some() {
+        fzf_command.stdin(Stdio::piped());
+    }
+    let mut child = fzf_command.spawn()?;
+    if let Some(input) = input
+        && let Some(mut stdin) = child.stdin.take()
+    {
+        // fzf may exit before reading everything, which isn't an error here
+        let _ = stdin.write_all(input.as_bytes());
+    }
+    let output = child.wait_with_output()?;
+    if let Some(mut rg) = rg_child {
+        let _ = rg.kill();
+        let _ = rg.wait();
+    }
+
+    let selection = String::from_utf8_lossy(&output.stdout);
+    let line = selection.lines().next().unwrap_or("").trim();
+    if line.is_empty() {
+        return Ok(None); // Cancelled
+    }
+    let relative = match action {
+        Action::SearchContents => line.split(':').next().unwrap_or(line),
+        _ => line,
+    };
+    Ok(Some(vault.join(relative)))
+}
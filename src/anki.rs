@@ -0,0 +1,150 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::folders::FolderTags;
+use crate::settings::Settings;
+
+/// A question and its answer
+struct Card {
+    front: String,
+    back: String,
+}
+
+/// Where the deck file goes: `anki_file` ("~/" allowed), ~/lair-anki.txt when empty
+fn export_path(settings: &Settings) -> PathBuf {
+    let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    let file = settings.anki_file.trim();
+    if file.is_empty() {
+        home().join("lair-anki.txt")
+    } else if let Some(rest) = file.strip_prefix("~/") {
+        home().join(rest)
+    } else {
+        PathBuf::from(file)
+    }
+}
+
+/// The cards in a note: `front :: back` lines anywhere, and in a `type: flashcards` note
+/// each `##` section, its heading the front and its text the back
+fn cards(content: &str) -> Vec<Card> {
+    let mut cards = Vec::new();
+    let body = crate::frontmatter::body(content);
+    let mut in_code_block = false;
+    for line in body.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        let Some((front, back)) = line.split_once(" :: ") else {
+            continue;
+        };
+        let front = front.trim().trim_start_matches(['-', '*']).trim();
+        if !front.is_empty() && !back.trim().is_empty() {
+            cards.push(Card {
+                front: front.to_string(),
+                back: back.trim().to_string(),
+            });
+        }
+    }
+
+    let is_flashcards = crate::frontmatter::parse(content)
+        .and_then(|fm| fm.get("type").map(|t| t.trim().eq_ignore_ascii_case("flashcards")))
+        .unwrap_or(false);
+    if is_flashcards {
+        let (_, sections) = crate::cards::split_sections(body);
+        for section in sections {
+            let back = section.text.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+            // A section of `::` lines holds cards of its own rather than one answer
+            if back.trim().is_empty() || back.contains(" :: ") {
+                continue;
+            }
+            cards.push(Card {
+                front: section.title,
+                back: back.trim().to_string(),
+            });
+        }
+    }
+    cards
+}
+
+/// The deck for a note: its `deck:` field, or `anki_deck` with the note's folders as subdecks
+fn deck(settings: &Settings, note: &Path, content: &str) -> String {
+    if let Some(deck) = crate::frontmatter::parse(content)
+        .and_then(|fm| fm.get("deck").map(|d| d.trim().to_string()))
+        .filter(|d| !d.is_empty())
+    {
+        return deck;
+    }
+    let vault = Path::new(&settings.notes_directory);
+    let mut parts = vec![settings.anki_deck.trim().to_string()];
+    if let Some(folder) = note.parent().and_then(|p| p.strip_prefix(vault).ok()) {
+        parts.extend(folder.iter().map(|c| c.to_string_lossy().to_string()));
+    }
+    parts.retain(|p| !p.is_empty());
+    if parts.is_empty() {
+        "Default".to_string()
+    } else {
+        parts.join("::")
+    }
+}
+
+/// A field for the HTML-enabled import: markup escaped, line breaks kept
+fn field(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+/// Write every card in the vault to a file Anki imports as Basic notes (File > Import), with
+/// the deck and tags of each card in columns of their own
+/// Returns the file and the number of cards
+pub fn export(settings: &Settings) -> io::Result<(PathBuf, usize)> {
+    let mut folder_tags = FolderTags::new(settings);
+    let mut output = String::from(
+        "#separator:tab\n#html:true\n#notetype:Basic\n#deck column:3\n#tags column:4\n",
+    );
+    let mut count = 0;
+    for note in crate::vault::all_files(settings) {
+        if !crate::vault::is_note(&note, settings) {
+            continue;
+        }
+        let Ok(content) = fs::read_to_string(&note) else {
+            continue;
+        };
+        let cards = cards(&content);
+        if cards.is_empty() {
+            continue;
+        }
+        let deck = deck(settings, &note, &content);
+        let mut tags = crate::note::tags(&content);
+        tags.extend(folder_tags.for_note(&note));
+        tags.sort();
+        tags.dedup();
+        // Anki nests tags with `::` and splits them on spaces
+        let tags: Vec<String> = tags
+            .iter()
+            .map(|t| t.replace('/', "::").replace(' ', "_"))
+            .collect();
+        for card in cards {
+            output.push_str(&format!(
+                "{}\t{}\t{}\t{}\n",
+                field(&card.front),
+                field(&card.back),
+                field(&deck),
+                tags.join(" ")
+            ));
+            count += 1;
+        }
+    }
+    if count == 0 {
+        return Err(io::Error::other("no flashcards found in the vault"));
+    }
+    let target = export_path(settings);
+    fs::write(&target, output)?;
+    Ok((target, count))
+}
@@ -0,0 +1,178 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// A review comment on one line of a note, kept outside the note itself
+/// The line is found again by its text, so edits elsewhere in the note don't detach it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Annotation {
+    pub quote: String, // Text of the annotated line, as shown in the preview
+    pub line: usize,   // Where the line was when annotated, to pick between repeated lines
+    pub comment: String,
+    pub created: String,
+}
+
+/// Sidecar file for a note: `.lair/annotations/<vault-relative path>.json`
+pub fn sidecar_path(settings: &Settings, note: &Path) -> PathBuf {
+    let vault = Path::new(&settings.notes_directory);
+    let relative = note.strip_prefix(vault).unwrap_or(note);
+    let mut path = crate::vault::config_dir(settings)
+        .join("annotations")
+        .join(relative)
+        .into_os_string();
+    path.push(".json");
+    PathBuf::from(path)
+}
+
+/// A note's annotations (none if it has no sidecar yet)
+pub fn load(settings: &Settings, note: &Path) -> io::Result<Vec<Annotation>> {
+    match fs::read_to_string(sidecar_path(settings, note)) {
+        Ok(content) => serde_json::from_str(&content).map_err(io::Error::other),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write a note's annotations back, removing the sidecar once there are none
+fn save(settings: &Settings, note: &Path, annotations: &[Annotation]) -> io::Result<()> {
+    let path = sidecar_path(settings, note);
+    if annotations.is_empty() {
+        return match fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let json = serde_json::to_string_pretty(annotations).map_err(io::Error::other)?;
+    fs::write(path, json)
+}
+
+pub fn add(settings: &Settings, note: &Path, annotation: Annotation) -> io::Result<()> {
+    let mut annotations = load(settings, note)?;
+    annotations.push(annotation);
+    save(settings, note, &annotations)
+}
+
+/// Remove the annotations placed on rendered line `line`, returning how many there were
+pub fn remove_on_line(
+    settings: &Settings,
+    note: &Path,
+    text: &Text,
+    line: usize,
+) -> io::Result<usize> {
+    let annotations = load(settings, note)?;
+    let placed = place(&annotations, text);
+    let before = annotations.len();
+    let kept: Vec<Annotation> = annotations
+        .into_iter()
+        .zip(placed)
+        .filter(|(_, placed)| *placed != Some(line))
+        .map(|(annotation, _)| annotation)
+        .collect();
+    let removed = before - kept.len();
+    if removed > 0 {
+        save(settings, note, &kept)?;
+    }
+    Ok(removed)
+}
+
+/// The visible text of a rendered line, trimmed
+pub fn plain_line(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|s| s.content.as_ref())
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// The rendered line each annotation belongs to: the line showing its quote nearest to
+/// where it was made, or None when the text is gone
+pub fn place(annotations: &[Annotation], text: &Text) -> Vec<Option<usize>> {
+    let lines: Vec<String> = text.lines.iter().map(plain_line).collect();
+    annotations
+        .iter()
+        .map(|annotation| {
+            lines
+                .iter()
+                .enumerate()
+                .filter(|(_, line)| **line == annotation.quote)
+                .map(|(i, _)| i)
+                .min_by_key(|i| i.abs_diff(annotation.line))
+        })
+        .collect()
+}
+
+fn marker_style() -> Style {
+    Style::default()
+        .fg(Color::Yellow)
+        .add_modifier(Modifier::BOLD)
+}
+
+/// Put a numbered margin marker after each annotated line
+pub fn mark(text: &mut Text<'static>, placed: &[Option<usize>]) {
+    for (i, line) in placed.iter().enumerate() {
+        if let Some(line) = line.and_then(|l| text.lines.get_mut(l)) {
+            line.spans
+                .push(Span::styled(format!(" ◆{}", i + 1), marker_style()));
+        }
+    }
+}
+
+/// The comments as footnotes, in marker order; detached ones quote their lost line
+pub fn footnotes(annotations: &[Annotation], placed: &[Option<usize>]) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::styled(
+        "─".repeat(40),
+        Style::default().fg(Color::DarkGray),
+    )];
+    for (i, (annotation, placed)) in annotations.iter().zip(placed).enumerate() {
+        let mut spans = vec![
+            Span::styled(format!("◆{} ", i + 1), marker_style()),
+            Span::raw(annotation.comment.clone()),
+        ];
+        if placed.is_none() {
+            spans.push(Span::styled(
+                format!("  (detached from \"{}\")", annotation.quote),
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+/// Write a note's annotations to `<note>.annotations.md` next to it, for sharing a review
+pub fn export(settings: &Settings, note: &Path) -> io::Result<(PathBuf, usize)> {
+    let annotations = load(settings, note)?;
+    if annotations.is_empty() {
+        return Err(io::Error::other("the note has no annotations"));
+    }
+    let name = note
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let stem = note
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let mut output = format!("# Annotations on {}\n", name);
+    for (i, annotation) in annotations.iter().enumerate() {
+        output.push_str(&format!(
+            "\n{}. > {}\n\n   {} ({})\n",
+            i + 1,
+            annotation.quote,
+            annotation.comment,
+            annotation.created
+        ));
+    }
+    let target = note.with_file_name(format!("{}.annotations.md", stem));
+    fs::write(&target, output)?;
+    Ok((target, annotations.len()))
+}
@@ -1,9 +1,44 @@
+use ratatui::layout::Rect;
 use ratatui::widgets::ListState;
 use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::Arc;
+use std::sync::mpsc::Receiver;
+#[cfg(unix)]
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::browse;
+use crate::actions::{self, Action};
+use crate::browse::{self, BrowseView};
+use crate::calendar::{self, DayNotes};
+use crate::diff::{Comparison, Version};
+use crate::editor::{InternalEditor, TextBuffer};
+use crate::habits::{self, Habit};
+use crate::history::{self, Revision};
+use crate::hooks::{self, HookEvent};
+use crate::annotations::{self, Annotation};
+use crate::citations::{self, Reference};
+use crate::ledger;
+use crate::log;
+use crate::links::LinkFormat;
+use crate::lint::Problem;
+use crate::note::NoteInfo;
+use crate::reading::{self, ReadingItem, ReadingStatus};
+use crate::images::GraphicsProtocol;
+use crate::plan::{self, Plan};
+use crate::preview::{MAX_PREVIEW_SIZE, MIN_PREVIEW_SIZE, Preview, PreviewPosition};
+use crate::restructure::{self, RestructureMode};
+use crate::notify::{self, NotifyEvent, NotifyStyle};
+use crate::scan::ScanUpdate;
+use crate::state::UiState;
+use crate::storage::VaultStorage;
+use crate::tools::{Tool, Tools};
+use crate::update::{self, UpdateInfo};
+use crate::usage::Usage;
+use crate::viewer::Viewer;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CurrentScreen {
     Main,
     Browsing,
@@ -11,6 +46,30 @@ pub enum CurrentScreen {
     CreatingFolder,
     Exiting,
     Settings,
+    PlanReview,
+    ConfirmDelete,
+    Stats,
+    Actions,
+    Viewing,
+    Habits,
+    Ledger,
+    Log,
+    Reading,
+    Citing,
+    Comparing,
+    Changelog,
+    Usage,
+    Problems,
+    NoteInfo,
+    InternalEdit,
+    History,
+    OpenWith,
+    Calendar,
+    QuickAppend,
+    Snippets,
+    NoteExists,
+    JournalEntry,
+    TemplatePrompt,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -24,8 +83,17 @@ pub struct App {
     pub current_file: Option<String>,
     pub current_screen: CurrentScreen,
     pub note_name_input: String, // For entering new note name
+    pub note_template: Option<String>, // Template the new note starts from
+    pub template_names: Vec<String>,   // Templates offered in the new note popup
+    pub template_prompts: Vec<String>, // The template's questions still to be asked
+    pub template_answers: crate::templates::Answers,
+    pub prompt_input: String,
+    pub pending_note: Option<String>, // File name of the new note waiting on the answers
+    pub taken_note_name: Option<(String, String)>, // Name already in the folder and the numbered one offered, while NoteExists asks
     pub folder_name_input: String, // For entering new folder name
     pub settings: crate::settings::Settings,
+    pub storage: Arc<dyn VaultStorage>, // Where the vault's files are read and written
+    pub ui_state: UiState, // Pane sizes changed from the keyboard, persisted separately
     pub settings_field_inputs: [String; 3], // Input buffers for each settings field
     pub active_settings_field: Option<SettingsField>, // Which field is currently being edited
     pub browse_list_state: ListState,       // State for browse list selection
@@ -33,20 +101,179 @@ pub struct App {
     pub browse_paths: Vec<Option<std::path::PathBuf>>, // Corresponding paths (None for folder headers)
     pub expanded_folders: HashSet<PathBuf>, // Set of expanded folder paths
     pub target_directory: Option<PathBuf>, // Directory where new note/folder should be created (from browse)
+    pub browse_root: Option<PathBuf>, // Temporary browse root (None = vault root)
+    pub browse_view: BrowseView,      // Folder tree or notes grouped by language
+    pub status_message: Option<String>, // Transient message shown in the footer
+    pub restructure_folder: Option<PathBuf>, // Folder the restructure assistant operates on
+    pub restructure_mode: RestructureMode,
+    pub bulk_plan: Option<Plan>, // Bulk operation under review (restructure, purge, archive)
+    pub plan_list_state: ListState,
+    pub list_page_size: usize, // Visible rows of the list on screen, updated on render
+    pub pending_g: bool,       // First 'g' of a vim-style "gg" has been pressed
+    pub pending_delete: Option<PathBuf>, // Note awaiting delete confirmation
+    pub stats: Option<crate::stats::VaultStats>, // Last computed stats, kept while a refresh runs
+    pub preview: Option<Preview>, // Rendered selected note for the preview pane
+    pub viewer: Option<Viewer>,   // Note open in the read-only viewer (the active tab)
+    tabs: Vec<Viewer>,            // The other viewer tabs, in order around the active one
+    pub tab_index: usize,         // Position of the active tab
+    pub viewer_return: CurrentScreen, // Screen the viewer goes back to when left
+    pub marked: Vec<PathBuf>,     // Notes picked for a multi-note action, in the order marked
+    pub comparison: Option<Comparison>, // Notes open in the diff viewer
+    pub comparison_return: CurrentScreen, // Screen the diff viewer goes back to when closed
+    pub viewer_wrap: bool,         // Soft-wrap long lines in the viewer, for the rest of the session
+    pub viewer_line_numbers: bool, // Number the lines in the viewer, for the rest of the session
+    pub habits: Vec<Habit>,
+    pub habit_list_state: ListState, // Selected habit (row of the grid)
+    pub habit_day: usize,            // Selected day, counted back from today
+    pub ledger_input: String,
+    pub ledger_summary: Vec<(String, i64)>, // This month's totals per category, in cents
+    pub log_input: String,
+    pub log_entries: Vec<log::Entry>, // Every log note's entries, oldest first
+    pub log_scroll: usize,            // Rows scrolled back from the newest entry
+    pub calendar_date: chrono::NaiveDate, // Selected day
+    pub calendar_days: Vec<DayNotes>,     // What each day of the selected month holds
+    pub calendar_input: Option<String>,   // Date being typed to jump to
+    pub reading_items: Vec<ReadingItem>,
+    pub reading_list_state: ListState,
+    pub reading_input: Option<String>, // Item being captured, while the add line is open
+    pub citation_input: String,
+    pub citation_list_state: ListState,
+    pub references: Arc<Vec<Reference>>, // Bibliography loaded for citation completion
+    annotations: Option<(PathBuf, Option<SystemTime>, Vec<Annotation>)>, // Last note's sidecar
+    pub flash_until: Option<Instant>, // Header is inverted until then (visual notification)
+    detached_edits: Vec<DetachedEdit>, // Files open in editors running beside the TUI
+    pub focused: Option<bool>, // Whether the terminal has focus, once it has reported a change
+    pub tools: Tools,                 // Optional external programs found on PATH
+    pub graphics: Option<GraphicsProtocol>, // Terminal image support, if any
+    pub image_placement: Option<(PathBuf, Rect)>, // Image the last frame wants drawn, and where
+    pub drawn_image: Option<(PathBuf, Rect)>,     // Image currently on screen
+    pub action_list_state: ListState, // Selection in the action menu
+    pub open_with_list_state: ListState, // Selection in the "open with…" menu
+    pub append_input: String,
+    pub append_timestamp: bool, // The line being appended gets the time, toggled with Tab
+    pub snippet_names: Vec<String>, // Snippets offered for adding to the selected note
+    pub snippet_list_state: ListState, // Selection in the snippet menu
+    pub journal_input: TextBuffer, // The journal entry being written
+    pub journal_from: CurrentScreen, // Screen the journal entry popup shows over
+    scan_rx: Option<Receiver<ScanUpdate>>, // Results from the background tree scan, while running
+    scan_started: Option<Instant>,
+    scan_fresh: bool, // No batch received yet, so the next batch replaces the list
+    scan_restore: Option<SelectionRestore>, // Selection to restore as scan results arrive
+    stats_rx: Option<Receiver<crate::stats::VaultStats>>, // Pending stats computation
+    update_rx: Option<Receiver<std::io::Result<UpdateInfo>>>, // Pending update check
+    #[cfg(unix)]
+    remote_requests: Receiver<(crate::remote::Request, Sender<crate::remote::Reply>)>, // From the socket and D-Bus
+    #[cfg(unix)]
+    remote: Option<crate::remote::Server>, // The control socket, when `control_socket` is on
+    #[cfg(target_os = "linux")]
+    dbus: Option<zbus::blocking::Connection>, // On the session bus, when `dbus_service` is on
+    pub update: Option<UpdateInfo>, // Newer releases found by the update check
+    pub changelog_scroll: u16,
+    pub usage: Option<Usage>, // Usage counts, while usage insights are on
+    tracked_screen: Option<CurrentScreen>, // Screen last counted as visited
+    pub problems: Vec<Problem>, // Found by the last lint run, in vault order
+    pub problem_list_state: ListState,
+    lint_rx: Option<Receiver<Vec<Problem>>>, // Pending lint run
+    pub note_info: Option<(PathBuf, NoteInfo)>, // Figures shown in the note info popup
+    pub internal_editor: Option<InternalEditor>, // Note being edited inside LAIR
+    editor_return: CurrentScreen, // Screen the internal editor goes back to when closed
+    pub history: Option<(PathBuf, Vec<Revision>)>, // Note on the history screen, and its commits
+    pub history_list_state: ListState,
+    pub history_mark: Option<usize>, // Revision picked to compare the selected one against
+    history_confirm_restore: bool,   // R was pressed once on the selected revision
+    pub copied_link: Option<(PathBuf, LinkFormat)>, // Last link copied, so C again copies the next format
+}
+/// Move a list selection by `delta` rows, clamped to the list bounds
+/// isize::MIN / isize::MAX jump to the first / last item
+pub fn move_list_selection(state: &mut ListState, len: usize, delta: isize) {
+    if len == 0 {
+        state.select(None);
+        return;
+    }
+    let current = state.selected().unwrap_or(0) as isize;
+    let target = current.saturating_add(delta).clamp(0, len as isize - 1);
+    state.select(Some(target as usize));
+}
+
+/// Selection to restore once a background reload brings the previous entry back
+struct SelectionRestore {
+    index: Option<usize>,    // Previously selected index, for an approximate fallback
+    path: Option<PathBuf>,   // Previously selected path
+    display: Option<String>, // Display text, if a folder header (no path) was selected
+}
+
+/// A file open in a detached editor, watched for saves
+struct DetachedEdit {
+    path: PathBuf,
+    modified: Option<SystemTime>, // As last seen
+    text: Option<String>,         // As last seen, to find where each save changed it
+    child: Option<Child>,         // Until it exits; many GUI editors hand off and exit at once
 }
+
+/// Scans taking longer than this trigger the scan-complete notification
+const SLOW_SCAN: Duration = Duration::from_secs(1);
+
+/// How long a visual flash notification lasts
+const FLASH_DURATION: Duration = Duration::from_millis(300);
+
 impl App {
     pub fn new() -> App {
         let settings = crate::settings::Settings::load();
+        citations::configure(&settings);
+        crate::preview::configure(&settings);
+        crate::theme::configure(&settings);
         let notes_dir = settings.notes_directory.clone();
         let editor = settings.editor.clone();
         let file_format = settings.default_file_format.clone();
+        let tools = Tools::detect();
+        let usage = settings.usage_insights.then(Usage::load);
+        let update_rx = match tools.path(Tool::Curl) {
+            Some(curl) if settings.check_for_updates => Some(update::spawn_check(curl.to_path_buf())),
+            _ => None,
+        };
+        let mut status_message = None;
+        #[cfg(unix)]
+        let (requests, remote_requests) = std::sync::mpsc::channel();
+        #[cfg(unix)]
+        let remote = if settings.control_socket {
+            match crate::remote::Server::start(&crate::remote::socket_path(), requests.clone()) {
+                Ok(server) => Some(server),
+                Err(e) => {
+                    status_message = Some(format!("No control socket: {}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        #[cfg(target_os = "linux")]
+        let dbus = if settings.dbus_service {
+            match crate::dbus::start(&settings, requests) {
+                Ok(connection) => Some(connection),
+                Err(e) => {
+                    status_message = Some(format!("No D-Bus service: {}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
 
         App {
             current_screen: CurrentScreen::Main,
             current_file: None,
             note_name_input: String::new(),
+            note_template: None,
+            template_names: Vec::new(),
+            template_prompts: Vec::new(),
+            template_answers: crate::templates::Answers::new(),
+            prompt_input: String::new(),
+            pending_note: None,
+            taken_note_name: None,
             folder_name_input: String::new(),
+            storage: crate::storage::open(&settings),
             settings,
+            ui_state: UiState::load(),
             settings_field_inputs: [notes_dir, editor, file_format],
             active_settings_field: None,
             browse_list_state: ListState::default(),
@@ -54,6 +281,87 @@ impl App {
             browse_paths: Vec::new(),
             expanded_folders: HashSet::new(),
             target_directory: None,
+            browse_root: None,
+            browse_view: BrowseView::Tree,
+            status_message,
+            restructure_folder: None,
+            restructure_mode: RestructureMode::Flatten,
+            bulk_plan: None,
+            plan_list_state: ListState::default(),
+            list_page_size: 10,
+            pending_g: false,
+            pending_delete: None,
+            stats: None,
+            preview: None,
+            viewer: None,
+            tabs: Vec::new(),
+            tab_index: 0,
+            viewer_return: CurrentScreen::Browsing,
+            marked: Vec::new(),
+            comparison: None,
+            comparison_return: CurrentScreen::Browsing,
+            viewer_wrap: true,
+            viewer_line_numbers: false,
+            habits: Vec::new(),
+            habit_list_state: ListState::default(),
+            habit_day: 0,
+            ledger_input: String::new(),
+            ledger_summary: Vec::new(),
+            log_input: String::new(),
+            log_entries: Vec::new(),
+            log_scroll: 0,
+            calendar_date: chrono::Local::now().date_naive(),
+            calendar_days: Vec::new(),
+            calendar_input: None,
+            reading_items: Vec::new(),
+            reading_list_state: ListState::default(),
+            reading_input: None,
+            citation_input: String::new(),
+            citation_list_state: ListState::default(),
+            references: Arc::new(Vec::new()),
+            annotations: None,
+            flash_until: None,
+            detached_edits: Vec::new(),
+            focused: None,
+            tools,
+            graphics: crate::images::detect(),
+            image_placement: None,
+            drawn_image: None,
+            action_list_state: ListState::default(),
+            open_with_list_state: ListState::default(),
+            append_input: String::new(),
+            append_timestamp: false,
+            snippet_names: Vec::new(),
+            snippet_list_state: ListState::default(),
+            journal_input: TextBuffer::new(""),
+            journal_from: CurrentScreen::Main,
+            scan_rx: None,
+            scan_started: None,
+            scan_fresh: false,
+            scan_restore: None,
+            stats_rx: None,
+            update_rx,
+            #[cfg(unix)]
+            remote_requests,
+            #[cfg(unix)]
+            remote,
+            #[cfg(target_os = "linux")]
+            dbus,
+            update: None,
+            changelog_scroll: 0,
+            usage,
+            tracked_screen: None,
+            problems: Vec::new(),
+            problem_list_state: ListState::default(),
+            lint_rx: None,
+            note_info: None,
+            internal_editor: None,
+            editor_return: CurrentScreen::Main,
+            history: None,
+            history_list_state: ListState::default(),
+            history_mark: None,
+            history_confirm_restore: false,
+            copied_link: None,
         }
     }
 
@@ -62,6 +370,7 @@ impl App {
         self.settings.notes_directory = self.settings_field_inputs[0].clone();
         self.settings.editor = self.settings_field_inputs[1].clone();
         self.settings.default_file_format = self.settings_field_inputs[2].clone();
+        citations::configure(&self.settings); // Relative paths follow the notes directory
         self.settings.save()?;
         Ok(())
     }
@@ -73,6 +382,7 @@ impl App {
         self.settings_field_inputs[2] = self.settings.default_file_format.clone();
     }
 
+    /// Reload the browse tree in the background, keeping the current selection
     pub fn load_browse_items(&mut self) {
         // Preserve the currently selected path or folder header before reloading
         let selected_idx = self.browse_list_state.selected();
@@ -86,52 +396,653 @@ impl App {
             .and_then(|idx| self.browse_items.get(idx))
             .map(|(text, _)| text.clone());
 
-        match crate::browse::get_files_as_list_items_with_paths(&self.settings, &self.expanded_folders) {
-            Ok((items, paths)) => {
-                self.browse_items = items;
-                self.browse_paths = paths;
-
-                // Try to restore selection
-                if let Some(path_to_find) = selected_path {
-                    // Find the index of the path we had selected before
-                    if let Some(new_idx) = self.browse_paths.iter().position(|p| {
-                        p.as_ref().map(|p2| p2 == &path_to_find).unwrap_or(false)
-                    }) {
-                        self.browse_list_state.select(Some(new_idx));
-                    } else if !self.browse_items.is_empty() {
-                        // Path not found, try to maintain approximate position
-                        let old_idx = selected_idx.unwrap_or(0);
-                        let new_idx = old_idx.min(self.browse_items.len().saturating_sub(1));
-                        self.browse_list_state.select(Some(new_idx));
+        self.start_scan(SelectionRestore {
+            index: selected_idx,
+            display: if selected_path.is_none() { selected_display } else { None },
+            path: selected_path,
+        });
+    }
+
+    /// Reload the browse tree in the background, selecting `path` once it shows up
+    pub fn load_browse_items_selecting(&mut self, path: PathBuf) {
+        self.start_scan(SelectionRestore {
+            index: self.browse_list_state.selected(),
+            path: Some(path),
+            display: None,
+        });
+    }
+
+    /// Start a background scan, replacing (and thereby cancelling) any scan in progress
+    fn start_scan(&mut self, restore: SelectionRestore) {
+        let root = self.browse_base_dir();
+        self.scan_rx = Some(crate::scan::spawn_scan(
+            self.settings.clone(),
+            root,
+            self.expanded_folders.clone(),
+            self.browse_view,
+        ));
+        // The old items stay on screen until the first batch arrives
+        self.scan_fresh = true;
+        self.scan_started = Some(Instant::now());
+        self.scan_restore = Some(restore);
+    }
+
+    /// Whether a background scan is still streaming results
+    pub fn is_scanning(&self) -> bool {
+        self.scan_rx.is_some()
+    }
+
+    /// Apply any results the scanning thread has sent since the last call
+    /// Returns true if anything arrived
+    pub fn poll_scan(&mut self) -> bool {
+        let Some(rx) = &self.scan_rx else {
+            return false;
+        };
+        let updates: Vec<ScanUpdate> = rx.try_iter().collect();
+        let changed = !updates.is_empty();
+
+        for update in updates {
+            match update {
+                ScanUpdate::Batch((items, paths)) => {
+                    if std::mem::take(&mut self.scan_fresh) {
+                        self.browse_items = items;
+                        self.browse_paths = paths;
                     } else {
-                        self.browse_list_state.select(None);
+                        self.browse_items.extend(items);
+                        self.browse_paths.extend(paths);
                     }
-                } else if let Some(display_to_find) = selected_display {
-                    // Was a folder header, try to find the same header
-                    if let Some(new_idx) = self.browse_items.iter().position(|(text, _)| text == &display_to_find) {
-                        self.browse_list_state.select(Some(new_idx));
-                    } else if !self.browse_items.is_empty() {
-                        // Header not found, try to maintain approximate position
-                        let old_idx = selected_idx.unwrap_or(0);
-                        let new_idx = old_idx.min(self.browse_items.len().saturating_sub(1));
-                        self.browse_list_state.select(Some(new_idx));
-                    } else {
-                        self.browse_list_state.select(None);
+                    self.restore_selection(false);
+                }
+                ScanUpdate::Done(Ok(())) => {
+                    self.scan_rx = None;
+                    if self.scan_started.take().is_some_and(|t| t.elapsed() > SLOW_SCAN) {
+                        self.notify(NotifyEvent::ScanComplete, "Vault scan finished");
+                    }
+                    if std::mem::take(&mut self.scan_fresh) {
+                        self.browse_items.clear();
+                        self.browse_paths.clear();
                     }
-                } else if !self.browse_items.is_empty() {
-                    // No previous selection, select first item
-                    self.browse_list_state.select(Some(0));
-                } else {
+                    self.restore_selection(true);
+                }
+                ScanUpdate::Done(Err(_)) => {
+                    self.scan_rx = None;
+                    self.scan_restore = None;
+                    self.browse_items = vec![("Error loading notes".to_string(), false)];
+                    self.browse_paths = vec![None];
                     self.browse_list_state.select(None);
                 }
             }
-            Err(_) => {
-                self.browse_items = vec![("Error loading notes".to_string(), false)];
-                self.browse_paths = vec![None];
-                self.browse_list_state.select(None);
+        }
+        changed
+    }
+
+    /// Open the stats screen, computing the numbers in the background
+    /// Nothing is computed at startup; stale stats stay visible while they refresh
+    pub fn open_stats(&mut self) {
+        if self.stats_rx.is_none() {
+            self.stats_rx = Some(crate::stats::spawn_compute(self.settings.clone()));
+        }
+        self.current_screen = CurrentScreen::Stats;
+    }
+
+    /// Whether the stats are still being computed
+    pub fn is_computing_stats(&self) -> bool {
+        self.stats_rx.is_some()
+    }
+
+    /// Pick up finished stats, returning true if they arrived
+    fn poll_stats(&mut self) -> bool {
+        let Some(rx) = &self.stats_rx else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(stats) => {
+                self.stats = Some(stats);
+                self.stats_rx = None;
+                true
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => false,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.stats_rx = None;
+                self.status_message = Some("Failed to compute statistics".to_string());
+                true
+            }
+        }
+    }
+
+    /// Lint the whole vault and show the problems list
+    pub fn open_problems(&mut self) {
+        if self.lint_rx.is_none() {
+            self.lint_rx = Some(crate::lint::spawn_lint(self.settings.clone()));
+        }
+        self.current_screen = CurrentScreen::Problems;
+    }
+
+    /// Whether the vault is still being linted
+    pub fn is_linting(&self) -> bool {
+        self.lint_rx.is_some()
+    }
+
+    fn poll_lint(&mut self) -> bool {
+        let Some(rx) = &self.lint_rx else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(problems) => {
+                self.problems = problems;
+                self.lint_rx = None;
+                move_list_selection(&mut self.problem_list_state, self.problems.len(), 0);
+                true
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => false,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.lint_rx = None;
+                self.status_message = Some("Failed to check the notes".to_string());
+                true
+            }
+        }
+    }
+
+    /// Open the note of the selected problem in the viewer, coming back here when it's left
+    pub fn open_problem(&mut self) {
+        let Some(problem) = self
+            .problem_list_state
+            .selected()
+            .and_then(|i| self.problems.get(i))
+        else {
+            return;
+        };
+        let path = problem.path.clone();
+        self.view_note(&path, CurrentScreen::Problems);
+    }
+
+    /// Lint a note the editor was just closed on, when `lint.on_edit` is set, replacing its
+    /// entries in the problems list
+    pub fn lint_edited(&mut self, note: &Path) {
+        if !self.settings.lint.on_edit {
+            return;
+        }
+        let found = crate::lint::Linter::new(&self.settings).check(note);
+        if let Some(first) = found.first() {
+            let name = note.file_name().unwrap_or_default().to_string_lossy();
+            self.status_message = Some(format!(
+                "{} problem(s) in {}: {} (P on the main screen lists them)",
+                found.len(),
+                name,
+                first.message
+            ));
+        }
+        let at = self
+            .problems
+            .iter()
+            .position(|p| p.path == note)
+            .unwrap_or(self.problems.len());
+        self.problems.retain(|p| p.path != note);
+        let at = at.min(self.problems.len());
+        self.problems.splice(at..at, found);
+        move_list_selection(&mut self.problem_list_state, self.problems.len(), 0);
+    }
+
+    /// Turn the startup update check on or off, checking straight away when turned on
+    pub fn toggle_update_checks(&mut self) {
+        self.settings.check_for_updates = !self.settings.check_for_updates;
+        if let Err(e) = self.settings.save() {
+            self.status_message = Some(format!("Failed to save settings: {}", e));
+            return;
+        }
+        if !self.settings.check_for_updates {
+            self.update_rx = None;
+            self.update = None;
+            return;
+        }
+        match self.tools.path(Tool::Curl) {
+            Some(curl) => self.update_rx = Some(update::spawn_check(curl.to_path_buf())),
+            None => {
+                self.status_message =
+                    Some("Update checks need curl, which isn't installed".to_string())
+            }
+        }
+    }
+
+    /// Turn usage insights on or off; turning them off keeps what was recorded until cleared
+    pub fn toggle_usage_insights(&mut self) {
+        self.settings.usage_insights = !self.settings.usage_insights;
+        if let Err(e) = self.settings.save() {
+            self.status_message = Some(format!("Failed to save settings: {}", e));
+            return;
+        }
+        if self.settings.usage_insights {
+            self.usage = Some(Usage::load());
+        } else {
+            self.save_usage();
+            self.usage = None;
+        }
+    }
+
+    /// Write the usage counts out, if they're being recorded
+    /// Failures are ignored: the counts are a convenience, not worth interrupting for
+    pub fn save_usage(&mut self) {
+        if let Some(usage) = &mut self.usage {
+            let _ = usage.save();
+        }
+    }
+
+    /// Count a visit whenever a different screen comes up
+    fn track_screen(&mut self) {
+        if self.tracked_screen == Some(self.current_screen) {
+            return;
+        }
+        self.tracked_screen = Some(self.current_screen);
+        if let Some(usage) = &mut self.usage {
+            usage.record_screen(&format!("{:?}", self.current_screen));
+        }
+    }
+
+    /// Whether keys are going into a text field, where they're content rather than commands
+    /// Every screen is listed, so a new one has to say whether it takes text
+    fn is_typing(&self) -> bool {
+        let viewer_input = self
+            .viewer
+            .as_ref()
+            .is_some_and(|v| v.search_input.is_some() || v.comment_input.is_some());
+        match self.current_screen {
+            CurrentScreen::Editing
+            | CurrentScreen::CreatingFolder
+            | CurrentScreen::Ledger
+            | CurrentScreen::Log
+            | CurrentScreen::Citing
+            | CurrentScreen::InternalEdit
+            | CurrentScreen::QuickAppend
+            | CurrentScreen::JournalEntry
+            | CurrentScreen::TemplatePrompt => true,
+            CurrentScreen::Settings => self.active_settings_field.is_some(),
+            CurrentScreen::Reading => self.reading_input.is_some(),
+            CurrentScreen::Calendar => self.calendar_input.is_some(),
+            CurrentScreen::Viewing => viewer_input,
+            CurrentScreen::Main
+            | CurrentScreen::Browsing
+            | CurrentScreen::Exiting
+            | CurrentScreen::PlanReview
+            | CurrentScreen::ConfirmDelete
+            | CurrentScreen::Stats
+            | CurrentScreen::Actions
+            | CurrentScreen::Habits
+            | CurrentScreen::Comparing
+            | CurrentScreen::Changelog
+            | CurrentScreen::Usage
+            | CurrentScreen::Problems
+            | CurrentScreen::NoteInfo
+            | CurrentScreen::History
+            | CurrentScreen::OpenWith
+            | CurrentScreen::Snippets
+            | CurrentScreen::NoteExists => false,
+        }
+    }
+
+    /// Count a key press on the current screen; typed text is never recorded
+    pub fn record_key(&mut self, key: &ratatui::crossterm::event::KeyEvent) {
+        if self.is_typing() {
+            return;
+        }
+        let screen = format!("{:?}", self.current_screen);
+        if let Some(usage) = &mut self.usage {
+            usage.record_key(&screen, key);
+        }
+    }
+
+    /// Whether a file is edited in LAIR's own editor rather than an external program
+    pub fn uses_internal_editor(&self, path: &Path) -> bool {
+        crate::editor::is_internal(self.editor_for(path))
+    }
+
+    /// The editor command for a file, per its extension
+    pub fn editor_for(&self, path: &Path) -> &str {
+        crate::editor::command_for(&self.settings, path)
+    }
+
+    /// Open a note in the internal editor, going back to `from` when it's closed
+    pub fn open_internal_editor(&mut self, path: &Path, from: CurrentScreen) {
+        match InternalEditor::open(&self.storage, path) {
+            Ok(mut editor) => {
+                if let Some(line) = self.last_position(path) {
+                    editor.buffer.go_to_line(line);
+                }
+                self.internal_editor = Some(editor);
+                self.editor_return = from;
+                self.current_screen = CurrentScreen::InternalEdit;
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Unable to open {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    pub fn save_internal_edit(&mut self) {
+        let Some(editor) = &mut self.internal_editor else {
+            return;
+        };
+        self.status_message = Some(match editor.save() {
+            Ok(()) => "Saved".to_string(),
+            Err(e) => format!("Unable to save: {}", e),
+        });
+    }
+
+    /// Leave the internal editor; unsaved changes take a second Esc to be thrown away
+    /// Closing it is handled like an external editor exiting
+    pub fn close_internal_editor(&mut self) {
+        let Some(editor) = &mut self.internal_editor else {
+            return;
+        };
+        if editor.modified && !editor.confirm_discard {
+            editor.confirm_discard = true;
+            self.status_message =
+                Some("Unsaved changes - Ctrl+S saves them, Esc again discards them".to_string());
+            return;
+        }
+        let (path, line) = (editor.path.clone(), editor.buffer.row + 1);
+        self.internal_editor = None;
+        self.current_screen = self.editor_return;
+        self.remember_position(&path, line);
+        self.run_hook(HookEvent::PostEdit, &path);
+        self.lint_edited(&path);
+        if let Some(viewer) = &mut self.viewer
+            && viewer.page.path == path
+        {
+            viewer.reload();
+        }
+        self.preview = None;
+        if self.current_screen == CurrentScreen::Browsing {
+            self.load_browse_items();
+        }
+        self.current_file = Some(path.to_string_lossy().to_string());
+    }
+
+    /// Show the word count, reading time and other figures of the selected note
+    pub fn open_note_info(&mut self) {
+        let Some(path) = self.get_selected_file_path().cloned() else {
+            return;
+        };
+        if !crate::vault::is_note(&path, &self.settings) {
+            self.status_message = Some("Not a note".to_string());
+            return;
+        }
+        match self.storage.read_to_string(&path) {
+            Ok(content) => {
+                self.note_info = Some((path, crate::note::info(&content)));
+                self.current_screen = CurrentScreen::NoteInfo;
+            }
+            Err(e) => self.status_message = Some(format!("Unable to read the note: {}", e)),
+        }
+    }
+
+    /// Show the usage counts
+    pub fn open_usage(&mut self) {
+        if self.usage.is_some() {
+            self.current_screen = CurrentScreen::Usage;
+        } else {
+            self.status_message =
+                Some("Usage insights are off; turn them on in Settings with Y".to_string());
+        }
+    }
+
+    /// Forget the recorded usage and start counting again from today
+    pub fn clear_usage(&mut self) {
+        if let Err(e) = Usage::clear() {
+            self.status_message = Some(format!("Failed to clear usage: {}", e));
+            return;
+        }
+        self.usage = Some(Usage::load());
+        self.status_message = Some("Usage cleared".to_string());
+    }
+
+    /// The next request from `lair remote` or D-Bus waiting to be carried out, with the way to
+    /// answer it
+    #[cfg(unix)]
+    pub fn next_remote_request(&self) -> Option<(crate::remote::Request, Sender<crate::remote::Reply>)> {
+        self.remote_requests.try_recv().ok()
+    }
+
+    /// Whether requests are listened for, so low-power mode wakes up for them
+    pub fn is_listening(&self) -> bool {
+        #[cfg(target_os = "linux")]
+        if self.dbus.is_some() {
+            return true;
+        }
+        #[cfg(unix)]
+        if self.remote.is_some() {
+            return true;
+        }
+        false
+    }
+
+    /// Pick up the update check's result, returning true if it arrived
+    /// A failed check stays quiet, as there is nothing to act on
+    fn poll_update_check(&mut self) -> bool {
+        let Some(rx) = &self.update_rx else {
+            return false;
+        };
+        match rx.try_recv() {
+            Ok(result) => {
+                self.update = result.ok().filter(|info| info.latest().is_some());
+                self.update_rx = None;
+                true
+            }
+            Err(std::sync::mpsc::TryRecvError::Empty) => false,
+            Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+                self.update_rx = None;
+                false
+            }
+        }
+    }
+
+    /// Show the release notes of the newer versions
+    pub fn open_changelog(&mut self) {
+        if self.update.is_some() {
+            self.changelog_scroll = 0;
+            self.current_screen = CurrentScreen::Changelog;
+        } else if self.update_rx.is_some() {
+            self.status_message = Some("Still checking for updates".to_string());
+        } else if self.settings.check_for_updates {
+            self.status_message = Some(format!(
+                "No release newer than {} found",
+                update::CURRENT_VERSION
+            ));
+        } else {
+            self.status_message =
+                Some("Update checks are off; turn them on in Settings with U".to_string());
+        }
+    }
+
+    /// Advance background state once per loop iteration
+    /// Returns true if the screen needs redrawing
+    pub fn tick(&mut self) -> bool {
+        let mut changed = self.poll_scan();
+        changed |= self.poll_stats();
+        changed |= self.poll_update_check();
+        changed |= self.poll_lint();
+        self.track_screen();
+        changed |= self.reload_changed_notes();
+        changed |= self.poll_detached_edits();
+        if self.flash_until.is_some_and(|until| Instant::now() >= until) {
+            self.flash_until = None;
+            changed = true;
+        }
+        changed
+    }
+
+    /// Re-read the previewed and viewed notes if they changed on disk, e.g. in another editor
+    fn reload_changed_notes(&mut self) -> bool {
+        let mut changed = false;
+        if let Some(preview) = &self.preview
+            && preview.is_stale()
+        {
+            self.preview = Some(crate::preview::load(&self.storage, &preview.path));
+            changed = true;
+        }
+        if let Some(viewer) = &mut self.viewer
+            && viewer.page.is_stale()
+        {
+            viewer.reload();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Whether a note is on screen that should be re-read when it changes on disk, or one is
+    /// open in a detached editor
+    pub fn is_watching_note(&self) -> bool {
+        self.preview.is_some() || self.viewer.is_some() || !self.detached_edits.is_empty()
+    }
+
+    /// Watch a file just opened in a detached editor, for as long as LAIR runs or the file exists
+    pub fn watch_detached_edit(&mut self, path: &Path, child: Child) {
+        self.detached_edits.retain(|edit| edit.path != path);
+        self.detached_edits.push(DetachedEdit {
+            path: path.to_path_buf(),
+            modified: self.storage.modified(path).ok(),
+            text: self.storage.read_to_string(path).ok(),
+            child: Some(child),
+        });
+    }
+
+    /// Handle each save from a detached editor as an edit: the post-edit hook and linting run,
+    /// and the list, preview and viewer pick up the new content
+    fn poll_detached_edits(&mut self) -> bool {
+        let mut saved = Vec::new();
+        let storage = self.storage.clone();
+        self.detached_edits.retain_mut(|edit| {
+            // Reap the editor once it exits, so it doesn't linger as a zombie
+            if let Some(child) = &mut edit.child
+                && !matches!(child.try_wait(), Ok(None))
+            {
+                edit.child = None;
+            }
+            let modified = storage.modified(&edit.path).ok();
+            if modified.is_none() && edit.child.is_none() {
+                return false;
+            }
+            if modified.is_some() && modified != edit.modified {
+                edit.modified = modified;
+                let text = storage.read_to_string(&edit.path).ok();
+                let line = edit
+                    .text
+                    .as_deref()
+                    .zip(text.as_deref())
+                    .and_then(|(before, after)| crate::editor::first_changed_line(before, after));
+                edit.text = text;
+                saved.push((edit.path.clone(), line));
             }
+            true
+        });
+        for (path, line) in &saved {
+            if let Some(line) = line {
+                self.remember_position(path, *line);
+            }
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            self.status_message = Some(format!("{} saved in the editor", name));
+            self.run_hook(HookEvent::PostEdit, path);
+            self.lint_edited(path);
+            // What the hook wrote (a formatter, say) isn't another save from the editor
+            if let Some(edit) = self.detached_edits.iter_mut().find(|edit| &edit.path == path) {
+                edit.modified = self.storage.modified(path).ok();
+                edit.text = self.storage.read_to_string(path).ok();
+            }
+        }
+        if !saved.is_empty() {
+            self.load_browse_items();
+        }
+        !saved.is_empty()
+    }
+
+    /// The line a note was last edited at, where the editor's `{line}` starts next time
+    pub fn last_position(&self, note: &Path) -> Option<usize> {
+        self.ui_state.positions.get(note).copied()
+    }
+
+    /// Remember where a note was edited, forgetting notes that have since gone
+    /// Saved quietly: a failure only costs the position
+    pub fn remember_position(&mut self, note: &Path, line: usize) {
+        let storage = &self.storage;
+        self.ui_state.positions.retain(|path, _| storage.exists(path));
+        self.ui_state.positions.insert(note.to_path_buf(), line);
+        let _ = self.ui_state.save();
+    }
+
+    /// Whether background work is pending that needs the loop to keep ticking
+    pub fn is_busy(&self) -> bool {
+        self.is_scanning()
+            || self.is_computing_stats()
+            || self.update_rx.is_some()
+            || self.lint_rx.is_some()
+            || self.flash_until.is_some()
+    }
+
+    /// Announce an event with the style configured for it
+    /// `message` is the text of a desktop notification, which is only sent while the terminal
+    /// isn't focused; without a notifier installed the bell rings instead
+    pub fn notify(&mut self, event: NotifyEvent, message: &str) {
+        match self.settings.notifications.style_for(event) {
+            NotifyStyle::Off => {}
+            NotifyStyle::Bell => notify::ring_bell(),
+            NotifyStyle::Flash => self.flash_until = Some(Instant::now() + FLASH_DURATION),
+            NotifyStyle::Desktop if self.focused == Some(true) => {}
+            NotifyStyle::Desktop => match self.tools.path(Tool::Notifier) {
+                Some(notifier) => {
+                    let _ = notify::send_desktop(notifier, message);
+                }
+                None => notify::ring_bell(),
+            },
+        }
+    }
+
+    /// Whether a visual flash notification is currently showing
+    pub fn is_flashing(&self) -> bool {
+        self.flash_until.is_some_and(|until| Instant::now() < until)
+    }
+
+    /// Try to restore the selection from before the reload
+    /// While the scan is still running the selection is only clamped, so it can still jump
+    /// to the previous entry when it arrives; once `finished`, fall back to the old position
+    fn restore_selection(&mut self, finished: bool) {
+        let Some(restore) = &self.scan_restore else {
+            return;
+        };
+
+        let found = if let Some(path_to_find) = &restore.path {
+            // Find the index of the path we had selected before
+            self.browse_paths
+                .iter()
+                .position(|p| p.as_ref() == Some(path_to_find))
+        } else if let Some(display_to_find) = &restore.display {
+            // Was a folder header, try to find the same header
+            self.browse_items
+                .iter()
+                .position(|(text, _)| text == display_to_find)
+        } else {
+            None
+        };
+
+        if let Some(new_idx) = found {
+            self.browse_list_state.select(Some(new_idx));
+            self.scan_restore = None;
+        } else if self.browse_items.is_empty() {
+            self.browse_list_state.select(None);
+        } else if restore.path.is_none() && restore.display.is_none() {
+            // No previous selection, select first item
+            self.browse_list_state.select(Some(0));
+            self.scan_restore = None;
+        } else {
+            // Not found (yet), try to maintain approximate position
+            let old_idx = restore.index.unwrap_or(0);
+            let new_idx = old_idx.min(self.browse_items.len().saturating_sub(1));
+            self.browse_list_state.select(Some(new_idx));
+        }
+
+        if finished {
+            self.scan_restore = None;
         }
     }
+
     /// Navigate up in browse list
     pub fn browse_up(&mut self) {
         if let Some(selected) = self.browse_list_state.selected() {
@@ -156,80 +1067,1473 @@ impl App {
 
     /// Get the selected file path (if a file is selected)
     pub fn get_selected_file_path(&self) -> Option<&std::path::PathBuf> {
-        if let Some(selected) = self.browse_list_state.selected() {
-            if let Some(Some(path)) = self.browse_paths.get(selected) {
-                if path.is_file() {
-                    return Some(path);
-                }
-            }
+        if let Some(selected) = self.browse_list_state.selected()
+            && let Some(Some(path)) = self.browse_paths.get(selected)
+            && self.storage.is_file(path)
+        {
+            return Some(path);
         }
         None
     }
 
-    /// Get the selected directory path (if a directory is selected) or parent of selected file
-    /// Returns the directory where new items should be created
-    pub fn get_selected_directory(&self) -> PathBuf {
-        if let Some(selected) = self.browse_list_state.selected() {
-            if let Some(Some(path)) = self.browse_paths.get(selected) {
-                if path.is_dir() {
-                    // If a directory is selected, use that directory
-                    return path.clone();
-                } else if path.is_file() {
-                    // If a file is selected, use its parent directory
-                    return path.parent().unwrap_or_else(|| Path::new(&self.settings.notes_directory)).to_path_buf();
-                }
+    /// The preview's share of the browse screen, in percent
+    pub fn preview_size(&self) -> u16 {
+        self.ui_state
+            .preview_size
+            .unwrap_or(self.settings.preview_size)
+            .clamp(MIN_PREVIEW_SIZE, MAX_PREVIEW_SIZE)
+    }
+
+    /// Grow (or shrink, for a negative step) the preview's share of the browse screen
+    /// Remembered in the UI state; `preview_size` in the settings file is the default
+    pub fn resize_preview(&mut self, step: i16) {
+        if self.settings.preview_position == PreviewPosition::Off {
+            self.status_message =
+                Some("The preview is off (see preview_position in the settings)".to_string());
+            return;
+        }
+        let size = self
+            .preview_size()
+            .saturating_add_signed(step)
+            .clamp(MIN_PREVIEW_SIZE, MAX_PREVIEW_SIZE);
+        self.ui_state.preview_size = Some(size);
+        self.status_message = Some(match self.ui_state.save() {
+            Ok(()) => format!("Preview size: {}%", size),
+            Err(e) => format!("Preview size: {}% (not saved: {})", size, e),
+        });
+    }
+
+    /// Make sure the preview shows the selected file, re-reading it only when the selection changes
+    /// (edits made elsewhere are picked up by `tick`)
+    pub fn update_preview(&mut self) {
+        let Some(path) = self.get_selected_file_path().cloned() else {
+            self.preview = None;
+            return;
+        };
+        if self.preview.as_ref().is_some_and(|p| p.path == path) {
+            return;
+        }
+        self.preview = Some(crate::preview::load(&self.storage, &path));
+    }
+
+    /// Expand the folders leading to `path` and select it once the tree reloads
+    pub fn reveal(&mut self, path: PathBuf) {
+        if !path.starts_with(self.browse_base_dir()) {
+            self.browse_root = None;
+        }
+        let base = self.browse_base_dir();
+        let mut dir = path.parent();
+        while let Some(d) = dir {
+            if !d.starts_with(&base) || d == base {
+                break;
             }
+            self.expanded_folders.insert(d.to_path_buf());
+            dir = d.parent();
         }
-        // Nothing selected or invalid selection, use base notes directory
-        PathBuf::from(&self.settings.notes_directory)
+        self.load_browse_items_selecting(path);
     }
 
-    /// Create a new folder in the target directory (or selected directory if target not set)
-    pub fn create_new_folder(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let parent_folder = self.target_directory.clone().unwrap_or_else(|| self.get_selected_directory());
-        
-        // Use folder_name_input if provided, otherwise use timestamp
-        let new_folder_name = if self.folder_name_input.trim().is_empty() {
-            let datetime = chrono::Utc::now().format("%Y-%m-%d_%H-%M");
-            datetime.to_string()
-        } else {
-            self.folder_name_input.trim().to_string()
+    /// Switch the browse list between the folder tree and the language groups
+    pub fn toggle_browse_view(&mut self) {
+        self.browse_view = match self.browse_view {
+            BrowseView::Tree => BrowseView::Languages,
+            BrowseView::Languages => BrowseView::Tree,
         };
-        
-        let new_folder_path = Path::new(&new_folder_name);
-        browse::make_new_folder(&parent_folder, new_folder_path)?;
-        
-        // Clear input and reset target directory
-        self.folder_name_input.clear();
-        let target_dir = self.target_directory.take();
-        
-        // Reload browse items to show the new folder
         self.load_browse_items();
-        
-        // If we were creating in a specific directory, expand it so the new folder is visible
-        if let Some(dir) = target_dir {
-            self.expanded_folders.insert(dir);
-            // Reload again to show the expanded folder's contents
-            self.load_browse_items();
+    }
+
+    /// Language of the selected note while browsing by language, used to narrow searches
+    pub fn selected_language(&self) -> Option<String> {
+        if self.browse_view != BrowseView::Languages {
+            return None;
         }
-        
-        Ok(())
+        let content = self.storage.read_to_string(self.get_selected_file_path()?).ok()?;
+        crate::note::language(&content)
     }
 
-    /// Toggle expand/collapse state of the selected folder
-    pub fn toggle_folder_expansion(&mut self) {
-        if let Some(selected) = self.browse_list_state.selected() {
-            if let Some(Some(path)) = self.browse_paths.get(selected) {
-                if path.is_dir() {
-                    if self.expanded_folders.contains(path) {
-                        self.expanded_folders.remove(path);
-                    } else {
-                        self.expanded_folders.insert(path.clone());
-                    }
-                    // Reload items to reflect expansion state (preserves selection)
-                    self.load_browse_items();
+    /// Open the selected note in the read-only viewer, in a new tab unless it's already open
+    pub fn open_viewer(&mut self) {
+        let Some(path) = self.get_selected_file_path().cloned() else {
+            return;
+        };
+        self.view_note(&path, CurrentScreen::Browsing);
+    }
+
+    /// Open a note in the viewer, going back to `from` when the viewer is left
+    pub fn view_note(&mut self, path: &Path, from: CurrentScreen) {
+        match self.tab_position(path) {
+            Some(index) => self.switch_tab(index),
+            None => {
+                if let Some(current) = self.viewer.take() {
+                    self.tabs.insert(self.tab_index, current);
                 }
+                self.tab_index = self.tabs.len();
+                self.viewer = Some(Viewer::open(&self.storage, path));
             }
         }
+        self.viewer_return = from;
+        self.current_screen = CurrentScreen::Viewing;
+    }
+
+    /// Switch the viewer between wrapping long lines and scrolling them sideways
+    pub fn toggle_viewer_wrap(&mut self) {
+        self.viewer_wrap = !self.viewer_wrap;
+        if let Some(viewer) = &mut self.viewer {
+            viewer.column = 0;
+        }
+        self.status_message = Some(if self.viewer_wrap {
+            "Wrapping long lines".to_string()
+        } else {
+            "Not wrapping: ←→ scroll sideways".to_string()
+        });
+    }
+
+    /// Whether the viewer wraps the open page; tables never are, as it would break up their grid
+    pub fn viewer_wraps(&self) -> bool {
+        self.viewer_wrap
+            && self
+                .viewer
+                .as_ref()
+                .is_none_or(|v| crate::csv::delimiter(&v.page.path).is_none())
+    }
+
+    pub fn toggle_viewer_line_numbers(&mut self) {
+        self.viewer_line_numbers = !self.viewer_line_numbers;
+        let windowed = self.viewer.as_ref().is_some_and(|v| v.page.window.is_some());
+        self.status_message = Some(match (self.viewer_line_numbers, windowed) {
+            (true, true) => "Line numbers on (not shown for part of a large file)".to_string(),
+            (true, false) => "Line numbers on".to_string(),
+            (false, _) => "Line numbers off".to_string(),
+        });
+    }
+
+    /// The notes open in viewer tabs, in tab order
+    pub fn tab_paths(&self) -> Vec<&Path> {
+        let mut paths: Vec<&Path> = self.tabs.iter().map(|t| t.page.path.as_path()).collect();
+        if let Some(viewer) = &self.viewer {
+            paths.insert(self.tab_index, &viewer.page.path);
+        }
+        paths
+    }
+
+    fn tab_position(&self, path: &Path) -> Option<usize> {
+        self.tab_paths().iter().position(|p| *p == path)
+    }
+
+    /// Make tab `index` the active one
+    pub fn switch_tab(&mut self, index: usize) {
+        if index > self.tabs.len() || index == self.tab_index {
+            return;
+        }
+        if let Some(current) = self.viewer.take() {
+            self.tabs.insert(self.tab_index, current);
+        }
+        self.activate_tab(index);
+    }
+
+    /// Take tab `index` out of the background, re-reading its note if it changed meanwhile
+    fn activate_tab(&mut self, index: usize) {
+        let mut viewer = self.tabs.remove(index);
+        if viewer.page.is_stale() {
+            viewer.reload();
+        }
+        self.viewer = Some(viewer);
+        self.tab_index = index;
+    }
+
+    /// Switch to the next (or previous) tab, wrapping around
+    pub fn cycle_tab(&mut self, forward: bool) {
+        let count = self.tabs.len() + 1;
+        let index = if forward {
+            (self.tab_index + 1) % count
+        } else {
+            (self.tab_index + count - 1) % count
+        };
+        self.switch_tab(index);
+    }
+
+    /// Close the active tab, moving to its neighbour, or back to browsing after the last one
+    pub fn close_tab(&mut self) {
+        self.viewer = None;
+        if self.tabs.is_empty() {
+            self.tab_index = 0;
+            self.current_screen = self.viewer_return;
+            return;
+        }
+        self.activate_tab(self.tab_index.min(self.tabs.len() - 1));
+    }
+
+    /// Mark the selected note for a multi-note action, or unmark it
+    pub fn toggle_mark(&mut self) {
+        let Some(path) = self.get_selected_file_path().cloned() else {
+            return;
+        };
+        match self.marked.iter().position(|p| *p == path) {
+            Some(i) => {
+                self.marked.remove(i);
+            }
+            None => self.marked.push(path),
+        }
+    }
+
+    /// The two marked notes, when exactly two are marked, the first marked as the old version
+    pub fn marked_pair(&self) -> Option<(PathBuf, PathBuf)> {
+        match self.marked.as_slice() {
+            [left, right] => Some((left.clone(), right.clone())),
+            _ => None,
+        }
+    }
+
+    /// Open the diff viewer on the two marked notes, the first marked as the old version
+    pub fn compare_marked(&mut self) {
+        let Some((left, right)) = self.marked_pair() else {
+            self.status_message = Some(format!(
+                "Mark exactly two notes with M to compare them ({} marked)",
+                self.marked.len()
+            ));
+            self.current_screen = CurrentScreen::Browsing;
+            return;
+        };
+        match Comparison::open(&left, &right) {
+            Ok(comparison) => {
+                self.comparison = Some(comparison);
+                self.comparison_return = CurrentScreen::Browsing;
+                self.current_screen = CurrentScreen::Comparing;
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Unable to compare the notes: {}", e));
+                self.current_screen = CurrentScreen::Browsing;
+            }
+        }
+    }
+
+    /// List the commits that changed the selected note, when the vault is a git repository
+    pub fn open_history(&mut self) {
+        let Some(note) = self.get_selected_file_path().cloned() else {
+            return;
+        };
+        let Some(git) = self.tools.path(Tool::Git) else {
+            self.status_message = Some("git not found on PATH".to_string());
+            return;
+        };
+        match history::log(git, &note) {
+            Ok(revisions) if revisions.is_empty() => {
+                self.status_message = Some("No commits have changed this note".to_string());
+            }
+            Ok(revisions) => {
+                self.history = Some((note, revisions));
+                self.history_list_state.select(Some(0));
+                self.history_mark = None;
+                self.history_confirm_restore = false;
+                self.current_screen = CurrentScreen::History;
+            }
+            Err(e) => self.status_message = Some(format!("No history: {}", e)),
+        }
+    }
+
+    /// Mark the selected revision to compare against, or clear the mark if it's the one marked
+    pub fn toggle_history_mark(&mut self) {
+        let selected = self.history_list_state.selected();
+        self.history_mark = if self.history_mark == selected {
+            None
+        } else {
+            selected
+        };
+    }
+
+    /// Compare the selected revision with the marked one, or with the note on disk when
+    /// nothing is marked, the older version on the left
+    pub fn compare_revision(&mut self) {
+        if let Some((left, right)) = self.revision_sides() {
+            self.comparison = Some(Comparison::of(left, right));
+            self.comparison_return = CurrentScreen::History;
+            self.current_screen = CurrentScreen::Comparing;
+        }
+    }
+
+    /// The two versions `compare_revision` compares, older first
+    pub fn revision_sides(&mut self) -> Option<(Version, Version)> {
+        let (Some((note, revisions)), Some(selected)) =
+            (&self.history, self.history_list_state.selected())
+        else {
+            return None;
+        };
+        let Some(git) = self.tools.path(Tool::Git) else {
+            self.status_message = Some("git not found on PATH".to_string());
+            return None;
+        };
+        let version = |i: usize| -> std::io::Result<Version> {
+            let revision = &revisions[i];
+            Ok(Version::at_revision(note, &revision.hash, history::show(git, note, revision)?))
+        };
+        // The list is newest first, so the higher index is the older revision
+        let sides = match self.history_mark {
+            Some(mark) if mark != selected => {
+                version(mark.max(selected)).and_then(|old| Ok((old, version(mark.min(selected))?)))
+            }
+            _ => version(selected).and_then(|old| Ok((old, Version::file(note)?))),
+        };
+        match sides {
+            Ok(sides) => Some(sides),
+            Err(e) => {
+                self.status_message = Some(format!("Unable to compare: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Put the selected revision's text back in the note, once R is pressed a second time
+    pub fn restore_revision(&mut self) {
+        let (Some((note, revisions)), Some(selected)) =
+            (&self.history, self.history_list_state.selected())
+        else {
+            return;
+        };
+        let revision = &revisions[selected];
+        if !self.history_confirm_restore {
+            self.history_confirm_restore = true;
+            self.status_message = Some(format!(
+                "Press R again to replace the note with its text from {} ({})",
+                revision.hash, revision.date
+            ));
+            return;
+        }
+        self.history_confirm_restore = false;
+        let Some(git) = self.tools.path(Tool::Git) else {
+            self.status_message = Some("git not found on PATH".to_string());
+            return;
+        };
+        let note = note.clone();
+        let message = match history::restore(self.storage.as_ref(), git, &note, revision) {
+            Ok(()) => format!("Restored the note from {}", revision.hash),
+            Err(e) => format!("Unable to restore the note: {}", e),
+        };
+        self.status_message = Some(message);
+        if let Some(viewer) = &mut self.viewer
+            && viewer.page.path == note
+        {
+            viewer.reload();
+        }
+        self.preview = None;
+    }
+
+    /// Any key other than R on the history screen calls off a pending restore
+    pub fn cancel_history_restore(&mut self) {
+        self.history_confirm_restore = false;
+    }
+
+    /// Jump to the target of the link under the viewer's cursor
+    pub fn follow_link(&mut self) {
+        let Some(viewer) = &mut self.viewer else {
+            return;
+        };
+        let Some(link) = viewer.selected_link() else {
+            self.status_message = Some("No link selected (Tab moves between links)".to_string());
+            return;
+        };
+        if (link.target.contains("://") && !crate::links::is_uri(&link.target))
+            || link.target.starts_with("mailto:")
+        {
+            self.status_message = Some(format!("External link: {}", link.target));
+            return;
+        }
+        match crate::viewer::resolve_link(&viewer.page.path, link, &self.settings) {
+            Some(path) => viewer.follow(&path),
+            None => self.status_message = Some(format!("No note found for '{}'", link.target)),
+        }
+    }
+
+    /// Get the selected directory path (if a directory is selected) or parent of selected file
+    /// Returns the directory where new items should be created
+    pub fn get_selected_directory(&self) -> PathBuf {
+        if let Some(selected) = self.browse_list_state.selected()
+            && let Some(Some(path)) = self.browse_paths.get(selected)
+        {
+            if self.storage.is_dir(path) {
+                // If a directory is selected, use that directory
+                return path.clone();
+            } else if self.storage.is_file(path) {
+                // If a file is selected, use its parent directory
+                return path.parent().unwrap_or_else(|| Path::new(&self.settings.notes_directory)).to_path_buf();
+            }
+        }
+        // Nothing selected or invalid selection, use the current browse root
+        self.browse_base_dir()
+    }
+
+    /// Directory the browse tree is currently rooted at
+    pub fn browse_base_dir(&self) -> PathBuf {
+        self.browse_root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.settings.notes_directory))
+    }
+
+    /// Breadcrumb for the current browse root, e.g. "Root / projects / acme"
+    pub fn browse_breadcrumb(&self) -> String {
+        let mut crumbs = vec!["Root".to_string()];
+        if let Some(root) = &self.browse_root
+            && let Ok(relative) = root.strip_prefix(&self.settings.notes_directory)
+        {
+            crumbs.extend(
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy().to_string()),
+            );
+        }
+        crumbs.join(" / ")
+    }
+
+    /// Re-root the browse tree into the selected directory
+    pub fn reroot_to_selected(&mut self) {
+        if let Some(selected) = self.browse_list_state.selected()
+            && let Some(Some(path)) = self.browse_paths.get(selected)
+            && self.storage.is_dir(path)
+        {
+            self.browse_root = Some(path.clone());
+            self.browse_list_state.select(Some(0));
+            self.load_browse_items();
+        }
+    }
+
+    /// Move the browse root up one level, returning to the vault root at the top
+    pub fn reroot_up(&mut self) {
+        let Some(root) = self.browse_root.take() else {
+            return;
+        };
+        let vault_root = PathBuf::from(&self.settings.notes_directory);
+        if let Some(parent) = root.parent()
+            && parent != vault_root
+            && parent.starts_with(&vault_root)
+        {
+            self.browse_root = Some(parent.to_path_buf());
+        }
+        // Keep the folder we came out of selected
+        self.load_browse_items_selecting(root);
+    }
+
+    /// Create a new folder in the target directory (or selected directory if target not set)
+    pub fn create_new_folder(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let parent_folder = self.target_directory.clone().unwrap_or_else(|| self.get_selected_directory());
+        
+        // Use folder_name_input if provided, otherwise use timestamp
+        let new_folder_name = if self.folder_name_input.trim().is_empty() {
+            let datetime = chrono::Local::now().format("%Y-%m-%d_%H-%M");
+            datetime.to_string()
+        } else {
+            self.folder_name_input.trim().to_string()
+        };
+        if new_folder_name.trim_matches('.').is_empty() {
+            return Err(format!("'{}' can't be a folder name", new_folder_name).into());
+        }
+        
+        let new_folder_path = Path::new(&new_folder_name);
+        browse::make_new_folder(self.storage.as_ref(), &parent_folder, new_folder_path)?;
+        
+        // Clear input and reset target directory
+        self.folder_name_input.clear();
+        let target_dir = self.target_directory.take();
+        
+        // Reload browse items to show the new folder
+        self.load_browse_items();
+        
+        // If we were creating in a specific directory, expand it so the new folder is visible
+        if let Some(dir) = target_dir {
+            self.expanded_folders.insert(dir);
+            // Reload again to show the expanded folder's contents
+            self.load_browse_items();
+        }
+        
+        Ok(())
+    }
+
+    /// Toggle expand/collapse state of the selected folder
+    pub fn toggle_folder_expansion(&mut self) {
+        if let Some(selected) = self.browse_list_state.selected()
+            && let Some(Some(path)) = self.browse_paths.get(selected)
+            && self.storage.is_dir(path)
+        {
+            if self.expanded_folders.contains(path) {
+                self.expanded_folders.remove(path);
+            } else {
+                self.expanded_folders.insert(path.clone());
+            }
+            // Reload items to reflect expansion state (preserves selection)
+            self.load_browse_items();
+        }
+    }
+
+    /// Show a bulk operation plan on the review screen
+    fn review_plan(&mut self, plan: Plan) {
+        self.plan_list_state
+            .select(if plan.operations.is_empty() { None } else { Some(0) });
+        self.bulk_plan = Some(plan);
+        self.current_screen = CurrentScreen::PlanReview;
+    }
+
+    /// Open the restructure assistant for the selected folder
+    pub fn start_restructure(&mut self) {
+        self.restructure_folder = Some(self.get_selected_directory());
+        self.restructure_mode = RestructureMode::Flatten;
+        self.refresh_restructure_plan();
+    }
+
+    /// Recompute the restructure plan for the current folder and mode
+    pub fn refresh_restructure_plan(&mut self) {
+        let Some(folder) = self.restructure_folder.clone() else {
+            return;
+        };
+        let operations = match restructure::plan(self.restructure_mode, &folder) {
+            Ok(operations) => operations,
+            Err(e) => {
+                self.status_message = Some(format!("Error planning restructure: {}", e));
+                Vec::new()
+            }
+        };
+        let title = format!("Restructure: {}", self.restructure_mode.label());
+        self.review_plan(Plan::new(title, folder, operations));
+    }
+
+    /// Switch between flatten and re-bucket, recomputing the plan
+    pub fn toggle_restructure_mode(&mut self) {
+        if self.bulk_plan.as_ref().is_some_and(|p| p.is_applied()) {
+            return;
+        }
+        self.restructure_mode = self.restructure_mode.toggle();
+        self.refresh_restructure_plan();
+    }
+
+    /// Review removing all empty folders below the selected folder
+    pub fn start_purge_empty_folders(&mut self) {
+        let folder = self.get_selected_directory();
+        self.restructure_folder = None;
+        let operations = match plan::plan_purge_empty_folders(&folder) {
+            Ok(operations) => operations,
+            Err(e) => {
+                self.status_message = Some(format!("Error planning purge: {}", e));
+                Vec::new()
+            }
+        };
+        self.review_plan(Plan::new("Purge empty folders", folder, operations));
+    }
+
+    /// Review archiving expired notes and notes matched by the folder rules
+    pub fn start_archive_review(&mut self) {
+        self.restructure_folder = None;
+        let operations = crate::archive::plan_archive(&self.settings);
+        let vault = PathBuf::from(&self.settings.notes_directory);
+        self.review_plan(Plan::new("Archive expired notes", vault, operations));
+    }
+
+    /// Apply the plan under review, unless dry-run mode is on
+    pub fn apply_plan(&mut self) {
+        if self.settings.dry_run {
+            self.status_message =
+                Some("Dry run is on: nothing was changed (Ctrl+D to turn it off)".to_string());
+            return;
+        }
+        let Some(plan) = self.bulk_plan.as_mut() else {
+            return;
+        };
+        if plan.is_applied() {
+            return;
+        }
+        let (succeeded, failed) = plan.apply();
+        self.status_message = Some(format!("Applied {} operations, {} failed", succeeded, failed));
+    }
+
+    /// Export the plan under review as a log in the vault
+    pub fn export_plan_log(&mut self) {
+        let Some(plan) = &self.bulk_plan else {
+            return;
+        };
+        self.status_message = Some(match plan.export_log(Path::new(&self.settings.notes_directory)) {
+            Ok(path) => format!("Log written to {}", path.display()),
+            Err(e) => format!("Could not write log: {}", e),
+        });
+    }
+
+    /// Leave the review screen and return to the browser
+    pub fn close_plan(&mut self) {
+        self.bulk_plan = None;
+        self.restructure_folder = None;
+        self.current_screen = CurrentScreen::Browsing;
+        self.load_browse_items();
+    }
+
+    /// Flip the global dry-run switch and persist it
+    pub fn toggle_dry_run(&mut self) {
+        self.settings.dry_run = !self.settings.dry_run;
+        let state = if self.settings.dry_run { "on" } else { "off" };
+        self.status_message = Some(match self.settings.save() {
+            Ok(()) => format!("Dry run {}", state),
+            Err(e) => format!("Dry run {} (could not save settings: {})", state, e),
+        });
+    }
+
+    /// Open the new note popup, with the templates on offer and none chosen
+    pub fn start_new_note(&mut self) {
+        self.note_name_input.clear();
+        self.note_template = None;
+        self.template_answers.clear();
+        self.template_names = crate::templates::list(&self.settings);
+        self.current_screen = CurrentScreen::Editing;
+    }
+
+    /// Move the template choice for the new note by `delta`, wrapping through "none"
+    pub fn move_template(&mut self, delta: isize) {
+        if self.template_names.is_empty() {
+            self.status_message = Some(format!(
+                "No templates in {}",
+                crate::templates::templates_dir(&self.settings).display()
+            ));
+            return;
+        }
+        // Position 0 is no template, the rest follow the list
+        let count = self.template_names.len() as isize + 1;
+        let current = match &self.note_template {
+            None => 0,
+            Some(name) => self
+                .template_names
+                .iter()
+                .position(|n| n == name)
+                .map_or(0, |i| i as isize + 1),
+        };
+        let next = (current + delta).rem_euclid(count);
+        self.note_template = match next {
+            0 => None,
+            i => self.template_names.get(i as usize - 1).cloned(),
+        };
+    }
+
+    /// Ask the chosen template's `{{prompt:…}}` questions not answered yet, a popup each, for a
+    /// new note to be created as `file_name`; false when there's nothing to ask
+    pub fn ask_template_prompts(&mut self, file_name: &str) -> bool {
+        let Some(name) = &self.note_template else {
+            return false;
+        };
+        let prompts = crate::templates::prompts(&self.settings, name).unwrap_or_default();
+        self.template_prompts = prompts
+            .into_iter()
+            .filter(|prompt| !self.template_answers.contains_key(prompt))
+            .collect();
+        if self.template_prompts.is_empty() {
+            return false;
+        }
+        self.prompt_input.clear();
+        self.pending_note = Some(file_name.to_string());
+        self.current_screen = CurrentScreen::TemplatePrompt;
+        true
+    }
+
+    /// Record the answer to the question being asked, returning the pending note's file name
+    /// once every question has an answer
+    pub fn answer_template_prompt(&mut self) -> Option<String> {
+        if !self.template_prompts.is_empty() {
+            let prompt = self.template_prompts.remove(0);
+            let answer = std::mem::take(&mut self.prompt_input);
+            self.template_answers.insert(prompt, answer.trim().to_string());
+        }
+        if self.template_prompts.is_empty() {
+            self.pending_note.take()
+        } else {
+            None
+        }
+    }
+
+    /// The chosen template's text for a new note titled `title` in `folder`, with the answers
+    /// to its questions; the choice and the answers are used up
+    pub fn template_content(&mut self, title: &str, folder: &Path) -> Option<String> {
+        let name = self.note_template.take()?;
+        let answers = std::mem::take(&mut self.template_answers);
+        match crate::templates::render(&self.settings, &name, title, folder, &answers) {
+            Ok(content) => Some(content),
+            Err(e) => {
+                self.status_message = Some(format!("Unable to apply template {}: {}", name, e));
+                None
+            }
+        }
+    }
+
+    /// Give a new, still empty note the default tags of its folders as frontmatter, so
+    /// they stay with it if it's moved and show up when searching the vault
+    pub fn apply_folder_tags(&mut self, note: &Path) {
+        if let Err(e) = crate::folders::apply_default_tags(&self.settings, note) {
+            self.status_message = Some(format!("Unable to add folder tags: {}", e));
+        }
+    }
+
+    /// Show or hide the archive folder everywhere the vault is listed or searched
+    pub fn toggle_show_archived(&mut self) {
+        self.settings.show_archived = !self.settings.show_archived;
+        let state = if self.settings.show_archived {
+            "shown"
+        } else {
+            "hidden"
+        };
+        self.status_message = Some(match self.settings.save() {
+            Ok(()) => format!("Archived notes {}", state),
+            Err(e) => format!("Archived notes {} (could not save settings: {})", state, e),
+        });
+        // Anything listed from the vault was filtered with the old setting
+        if !self.settings.show_archived
+            && self
+                .browse_root
+                .as_ref()
+                .is_some_and(|root| root.starts_with(crate::archive::archive_dir(&self.settings)))
+        {
+            self.browse_root = None;
+        }
+        self.stats = None;
+        self.load_browse_items();
+    }
+
+    /// Run the vault hook for `event`, reporting failures in the status message
+    /// Returns false if the hook ran and failed (used by pre-* hooks to veto the action)
+    pub fn run_hook(&mut self, event: HookEvent, note: &Path) -> bool {
+        self.run_create_hook(event, note, None)
+    }
+
+    /// `run_hook`, telling creation hooks which template the note is made from
+    /// After an edit, the `post_edit_commands` run too, as `hooks::run_post_edit` has them
+    pub fn run_create_hook(&mut self, event: HookEvent, note: &Path, template: Option<&str>) -> bool {
+        if event == HookEvent::PostEdit {
+            let result = hooks::run_post_edit(&self.settings, note);
+            if let Err(message) = &result {
+                self.status_message = Some(message.clone());
+            }
+            return result.is_ok();
+        }
+        let vault = PathBuf::from(&self.settings.notes_directory);
+        match hooks::run_create_hook(&vault, event, note, template) {
+            Ok(None) => true,
+            Ok(Some(outcome)) if outcome.success => true,
+            Ok(Some(outcome)) => {
+                self.status_message =
+                    Some(format!("{} hook failed: {}", event.script_name(), outcome.message));
+                false
+            }
+            Err(e) => {
+                self.status_message = Some(format!("{} hook error: {}", event.script_name(), e));
+                false
+            }
+        }
+    }
+
+    /// Open the habit tracker with today selected
+    pub fn open_habits(&mut self) {
+        match habits::load_all(&self.settings) {
+            Ok(loaded) => self.habits = loaded,
+            Err(e) => self.status_message = Some(format!("Failed to load habits: {}", e)),
+        }
+        move_list_selection(&mut self.habit_list_state, self.habits.len(), 0);
+        self.habit_day = 0;
+        self.current_screen = CurrentScreen::Habits;
+    }
+
+    /// Flip the selected habit for the selected day, writing it back to the habit's note
+    pub fn toggle_habit(&mut self) {
+        let Some(habit) = self
+            .habit_list_state
+            .selected()
+            .and_then(|i| self.habits.get_mut(i))
+        else {
+            return;
+        };
+        let today = chrono::Local::now().date_naive();
+        let Some(day) = today.checked_sub_days(chrono::Days::new(self.habit_day as u64)) else {
+            return;
+        };
+        if let Err(e) = habits::toggle_day(habit, day) {
+            self.status_message = Some(format!("Failed to update {}: {}", habit.name, e));
+        }
+    }
+
+    /// A note's annotations, re-read only when its sidecar file changes
+    pub fn annotations_for(&mut self, note: &Path) -> Vec<Annotation> {
+        let sidecar = annotations::sidecar_path(&self.settings, note);
+        let modified = self.storage.modified(&sidecar).ok();
+        if let Some((path, cached_modified, cached)) = &self.annotations
+            && path == note
+            && *cached_modified == modified
+        {
+            return cached.clone();
+        }
+        let loaded = annotations::load(&self.settings, note).unwrap_or_else(|e| {
+            self.status_message = Some(format!("Failed to read annotations: {}", e));
+            Vec::new()
+        });
+        self.annotations = Some((note.to_path_buf(), modified, loaded.clone()));
+        loaded
+    }
+
+    /// Save the comment typed in the viewer against the line under its cursor
+    pub fn save_annotation(&mut self) {
+        let Some(viewer) = &mut self.viewer else {
+            return;
+        };
+        let comment = viewer.comment_input.take().unwrap_or_default();
+        let Some(line) = viewer.line_cursor.take() else {
+            return;
+        };
+        if comment.trim().is_empty() {
+            return;
+        }
+        let quote = viewer
+            .page
+            .text
+            .lines
+            .get(line)
+            .map(annotations::plain_line)
+            .unwrap_or_default();
+        let annotation = Annotation {
+            quote,
+            line,
+            comment: comment.trim().to_string(),
+            created: chrono::Local::now().format("%Y-%m-%d %H:%M").to_string(),
+        };
+        let note = viewer.page.path.clone();
+        self.annotations = None;
+        match annotations::add(&self.settings, &note, annotation) {
+            Ok(()) => self.status_message = Some("Annotation added".to_string()),
+            Err(e) => self.status_message = Some(format!("Failed to save annotation: {}", e)),
+        }
+    }
+
+    /// Remove the annotations on the line under the viewer's cursor
+    pub fn delete_annotations_at_cursor(&mut self) {
+        let Some(viewer) = &self.viewer else {
+            return;
+        };
+        let Some(line) = viewer.line_cursor else {
+            return;
+        };
+        let note = viewer.page.path.clone();
+        let result = annotations::remove_on_line(&self.settings, &note, &viewer.page.text, line);
+        self.annotations = None;
+        match result {
+            Ok(0) => self.status_message = Some("No annotation on this line".to_string()),
+            Ok(n) => self.status_message = Some(format!("Removed {} annotation(s)", n)),
+            Err(e) => self.status_message = Some(format!("Failed to update annotations: {}", e)),
+        }
+    }
+
+    /// Tick or untick the checkbox under the viewer's task cursor, in the note itself
+    pub fn toggle_task_at_cursor(&mut self) {
+        let Some(viewer) = &mut self.viewer else {
+            return;
+        };
+        let Some((done, text, occurrence, index)) = viewer.selected_task() else {
+            return;
+        };
+        let path = viewer.page.path.clone();
+        match crate::tasks::toggle(&path, &text, done, occurrence, index) {
+            Ok(now_done) => {
+                viewer.reload();
+                self.preview = None;
+                self.status_message = Some(format!(
+                    "{} \"{}\"",
+                    if now_done { "Ticked" } else { "Unticked" },
+                    text
+                ));
+            }
+            Err(e) => self.status_message = Some(format!("Failed to update the task: {}", e)),
+        }
+    }
+
+    /// Open citation completion for the selected note
+    pub fn open_citations(&mut self) {
+        if self.get_selected_file_path().is_none() {
+            self.status_message = Some("Select a note to cite in".to_string());
+            return;
+        }
+        if citations::bibliography_path(&self.settings).is_none() {
+            self.status_message =
+                Some("No bibliography configured (set bibliography in the settings)".to_string());
+            return;
+        }
+        match citations::library() {
+            Ok(references) => self.references = references,
+            Err(e) => {
+                self.status_message = Some(format!("Failed to read the bibliography: {}", e));
+                return;
+            }
+        }
+        self.citation_input.clear();
+        self.citation_list_state.select(Some(0));
+        self.current_screen = CurrentScreen::Citing;
+    }
+
+    /// Bibliography entries matching what has been typed so far
+    pub fn citation_matches(&self) -> Vec<&Reference> {
+        citations::matching(&self.references, &self.citation_input)
+    }
+
+    /// Complete the typed text to the selected key
+    pub fn complete_citation(&mut self) {
+        let selected = self.citation_list_state.selected().unwrap_or(0);
+        if let Some(key) = self.citation_matches().get(selected).map(|r| r.key.clone()) {
+            self.citation_input = key;
+        }
+    }
+
+    /// Cite the selected entry at the end of the selected note
+    pub fn insert_citation(&mut self) {
+        let selected = self.citation_list_state.selected().unwrap_or(0);
+        let Some(key) = self.citation_matches().get(selected).map(|r| r.key.clone()) else {
+            self.status_message = Some("No matching reference".to_string());
+            return;
+        };
+        let Some(note) = self.get_selected_file_path().cloned() else {
+            return;
+        };
+        self.current_screen = CurrentScreen::Browsing;
+        match citations::insert(&note, &key) {
+            Ok(()) => self.status_message = Some(format!("Cited [@{}]", key)),
+            Err(e) => self.status_message = Some(format!("Failed to insert citation: {}", e)),
+        }
+    }
+
+    /// Open the reading list for triage
+    pub fn open_reading(&mut self) {
+        self.reading_input = None;
+        self.reload_reading();
+        self.current_screen = CurrentScreen::Reading;
+    }
+
+    fn reload_reading(&mut self) {
+        match reading::load(&self.settings) {
+            Ok(items) => self.reading_items = items,
+            Err(e) => self.status_message = Some(format!("Failed to load reading list: {}", e)),
+        }
+        move_list_selection(&mut self.reading_list_state, self.reading_items.len(), 0);
+    }
+
+    /// Add the captured title / URL to the reading list and select it
+    pub fn submit_reading_item(&mut self) {
+        let Some(input) = self.reading_input.take() else {
+            return;
+        };
+        let today = chrono::Local::now().date_naive();
+        match reading::add(&self.settings, &input, today) {
+            Ok(item) => {
+                self.status_message = Some(format!("Added '{}'", item.title));
+                self.reload_reading();
+                self.reading_list_state.select(Some(self.reading_items.len().saturating_sub(1)));
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to add to the reading list: {}", e));
+                self.reading_input = Some(input);
+            }
+        }
+    }
+
+    /// Set the selected item's status; finished items move into a literature note
+    pub fn set_reading_status(&mut self, status: ReadingStatus) {
+        let Some(item) = self
+            .reading_list_state
+            .selected()
+            .and_then(|i| self.reading_items.get(i))
+        else {
+            return;
+        };
+        let result = if status == ReadingStatus::Done {
+            let today = chrono::Local::now().date_naive();
+            reading::finish(&self.settings, item, today).map(|path| {
+                let vault = Path::new(&self.settings.notes_directory);
+                format!("Finished - moved to {}", path.strip_prefix(vault).unwrap_or(&path).display())
+            })
+        } else {
+            reading::set_status(&self.settings, item, status)
+                .map(|()| format!("Marked '{}' as {}", item.title, status.keyword()))
+        };
+        match result {
+            Ok(message) => self.status_message = Some(message),
+            Err(e) => self.status_message = Some(format!("Failed to update the reading list: {}", e)),
+        }
+        self.reload_reading();
+    }
+
+    /// Open expense quick-capture with this month's totals
+    pub fn open_ledger(&mut self) {
+        self.ledger_input.clear();
+        self.refresh_ledger_summary();
+        self.current_screen = CurrentScreen::Ledger;
+    }
+
+    fn refresh_ledger_summary(&mut self) {
+        let path = ledger::ledger_path(&self.settings, chrono::Local::now().date_naive());
+        match ledger::summary(&path) {
+            Ok(summary) => self.ledger_summary = summary,
+            Err(e) => self.status_message = Some(format!("Failed to read ledger: {}", e)),
+        }
+    }
+
+    /// Append the typed expense to this month's ledger, staying open for the next one
+    pub fn submit_ledger_entry(&mut self) {
+        let today = chrono::Local::now().date_naive();
+        let entry = match ledger::parse_input(&self.ledger_input, today) {
+            Ok(entry) => entry,
+            Err(e) => {
+                self.status_message = Some(e);
+                return;
+            }
+        };
+        match ledger::append(&self.settings, &entry) {
+            Ok(_) => {
+                self.status_message = Some(format!(
+                    "Added {} to {}",
+                    ledger::format_amount(entry.amount),
+                    entry.category
+                ));
+                self.ledger_input.clear();
+                self.refresh_ledger_summary();
+            }
+            Err(e) => self.status_message = Some(format!("Failed to write ledger: {}", e)),
+        }
+    }
+
+    /// Open log mode, with the entries of every log note
+    pub fn open_log(&mut self) {
+        self.log_input.clear();
+        self.log_entries = log::entries(&self.settings);
+        self.log_scroll = 0;
+        self.current_screen = CurrentScreen::Log;
+    }
+
+    /// Append the typed line to the current log note, staying open for the next one
+    pub fn submit_log_entry(&mut self) {
+        let now = chrono::Local::now().naive_local();
+        match log::append(&self.settings, &self.log_input, now) {
+            Ok(_) => {
+                self.log_input.clear();
+                self.log_entries = log::entries(&self.settings);
+                self.log_scroll = 0;
+            }
+            Err(e) => self.status_message = Some(format!("Failed to write the log: {}", e)),
+        }
+    }
+
+    /// Open the calendar on today
+    pub fn open_calendar(&mut self) {
+        self.calendar_input = None;
+        self.calendar_date = chrono::Local::now().date_naive();
+        self.calendar_days = calendar::month(&self.settings, self.calendar_date);
+        self.current_screen = CurrentScreen::Calendar;
+    }
+
+    /// Select a day in the calendar, reading the vault again when the month changes
+    pub fn calendar_go(&mut self, date: chrono::NaiveDate) {
+        use chrono::Datelike;
+        let month_changed = date.with_day(1) != self.calendar_date.with_day(1);
+        self.calendar_date = date;
+        if month_changed {
+            self.calendar_days = calendar::month(&self.settings, date);
+        }
+    }
+
+    /// Jump to the typed date, if it reads as one
+    pub fn submit_calendar_input(&mut self) {
+        let Some(input) = self.calendar_input.take() else {
+            return;
+        };
+        if input.trim().is_empty() {
+            return;
+        }
+        let today = chrono::Local::now().date_naive();
+        match calendar::parse(&input, today, self.calendar_date) {
+            Some(date) => self.calendar_go(date),
+            None => self.status_message = Some(format!("Couldn't read '{}' as a date", input.trim())),
+        }
+    }
+
+    /// The selected day's daily note, created (and handed to the post-create hook) if needed
+    pub fn calendar_daily_note(&mut self) -> Option<PathBuf> {
+        match calendar::create_daily(&self.settings, self.calendar_date) {
+            Ok((path, created)) => {
+                if created {
+                    self.run_hook(HookEvent::PostCreate, &path);
+                    self.calendar_days = calendar::month(&self.settings, self.calendar_date);
+                }
+                Some(path)
+            }
+            Err(e) => {
+                self.status_message = Some(format!("Failed to create the daily note: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Open the action menu over the browse screen
+    pub fn open_actions(&mut self) {
+        if self.action_list_state.selected().is_none() {
+            self.action_list_state.select(Some(0));
+        }
+        self.current_screen = CurrentScreen::Actions;
+    }
+
+    /// Ask for a line to add to the end of the selected note, over the browse screen
+    pub fn open_quick_append(&mut self) {
+        let Some(path) = self.get_selected_file_path() else {
+            return;
+        };
+        if !crate::vault::is_note(path, &self.settings) {
+            self.status_message = Some("Lines can only be appended to notes".to_string());
+            return;
+        }
+        self.append_input.clear();
+        self.append_timestamp = self.settings.append_timestamp;
+        self.current_screen = CurrentScreen::QuickAppend;
+    }
+
+    /// Append the typed line to the selected note, as "- 2026-10-16 14:03 text" when timestamped,
+    /// and go back to browsing
+    /// It counts as an edit, so post-edit hooks and linting run
+    pub fn submit_quick_append(&mut self) {
+        let text = self.append_input.trim().to_string();
+        let Some(path) = self.get_selected_file_path().cloned() else {
+            self.current_screen = CurrentScreen::Browsing;
+            return;
+        };
+        if text.is_empty() {
+            self.status_message = Some("Type something to append".to_string());
+            return;
+        }
+        let line = if self.append_timestamp {
+            crate::note::timestamped(&text)
+        } else {
+            text
+        };
+        self.current_screen = CurrentScreen::Browsing;
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        match crate::note::append_line(self.storage.as_ref(), &path, &line) {
+            Ok(()) => {
+                self.status_message = Some(format!("Appended to {}", name));
+                self.run_hook(HookEvent::PostEdit, &path);
+                self.lint_edited(&path);
+            }
+            Err(e) => self.status_message = Some(format!("Failed to append to {}: {}", name, e)),
+        }
+    }
+
+    /// Offer the snippets for adding to the selected note, over the browse screen
+    pub fn open_snippets(&mut self) {
+        let Some(path) = self.get_selected_file_path() else {
+            return;
+        };
+        if !crate::vault::is_note(path, &self.settings) {
+            self.status_message = Some("Snippets can only be added to notes".to_string());
+            return;
+        }
+        self.snippet_names = crate::templates::list_snippets(&self.settings);
+        if self.snippet_names.is_empty() {
+            self.status_message = Some(format!(
+                "No snippets in {}",
+                crate::templates::snippets_dir(&self.settings).display()
+            ));
+            return;
+        }
+        move_list_selection(&mut self.snippet_list_state, self.snippet_names.len(), 0);
+        self.current_screen = CurrentScreen::Snippets;
+    }
+
+    /// Add the chosen snippet to the end of the selected note, with its variables filled in
+    /// The next edit of the note starts at the snippet, and adding it counts as an edit
+    pub fn insert_snippet(&mut self) {
+        self.current_screen = CurrentScreen::Browsing;
+        let (Some(name), Some(note)) = (
+            self.snippet_list_state
+                .selected()
+                .and_then(|i| self.snippet_names.get(i))
+                .cloned(),
+            self.get_selected_file_path().cloned(),
+        ) else {
+            return;
+        };
+        if !crate::vault::is_note(&note, &self.settings) {
+            self.status_message = Some("Snippets can only be added to notes".to_string());
+            return;
+        }
+        let result = crate::templates::render_snippet(&self.settings, &name, &note)
+            .and_then(|text| crate::note::append_block(self.storage.as_ref(), &note, &text));
+        match result {
+            Ok(line) => {
+                let file = note.file_name().unwrap_or_default().to_string_lossy();
+                self.status_message = Some(format!("Added {} to {}", name, file));
+                self.remember_position(&note, line);
+                self.run_hook(HookEvent::PostEdit, &note);
+                self.lint_edited(&note);
+            }
+            Err(e) => self.status_message = Some(format!("Unable to add snippet {}: {}", name, e)),
+        }
+    }
+
+    /// Start a journal entry for today's daily note, in a popup over the current screen
+    pub fn open_journal_entry(&mut self) {
+        self.journal_input = TextBuffer::new("");
+        self.journal_from = self.current_screen;
+        self.current_screen = CurrentScreen::JournalEntry;
+    }
+
+    /// Add the journal entry to today's daily note under a `## 14:03` heading, creating the note
+    /// if need be, and close the popup
+    /// It counts as an edit, so post-edit hooks and linting run
+    pub fn submit_journal_entry(&mut self) {
+        let text = self.journal_input.text();
+        let text = text.trim_end();
+        if text.trim().is_empty() {
+            self.status_message = Some("Type something for the entry".to_string());
+            return;
+        }
+        let now = chrono::Local::now();
+        let entry = format!("## {}\n\n{}", now.format("%H:%M"), text);
+        self.current_screen = self.journal_from;
+        let (path, created) = match calendar::create_daily(&self.settings, now.date_naive()) {
+            Ok(daily) => daily,
+            Err(e) => {
+                self.status_message = Some(format!("Failed to create the daily note: {}", e));
+                return;
+            }
+        };
+        if created {
+            self.run_hook(HookEvent::PostCreate, &path);
+        }
+        match crate::note::append_block(self.storage.as_ref(), &path, &entry) {
+            Ok(line) => {
+                self.status_message = Some("Journal entry added to today's note".to_string());
+                self.remember_position(&path, line);
+                self.run_hook(HookEvent::PostEdit, &path);
+                self.lint_edited(&path);
+                if self.current_screen == CurrentScreen::Browsing {
+                    self.load_browse_items();
+                }
+            }
+            Err(e) => self.status_message = Some(format!("Failed to add the journal entry: {}", e)),
+        }
+    }
+
+    /// Offer the `alternate_editors` for the selected file, over the browse screen
+    pub fn open_open_with(&mut self) {
+        if self.get_selected_file_path().is_none() {
+            return;
+        }
+        if self.settings.alternate_editors.is_empty() {
+            self.status_message = Some(
+                "No alternate editors: add commands to alternate_editors in settings.json".to_string(),
+            );
+            return;
+        }
+        let count = self.settings.alternate_editors.len();
+        if self.open_with_list_state.selected().is_none_or(|i| i >= count) {
+            self.open_with_list_state.select(Some(0));
+        }
+        self.current_screen = CurrentScreen::OpenWith;
+    }
+
+    pub fn selected_alternate_editor(&self) -> Option<String> {
+        self.open_with_list_state
+            .selected()
+            .and_then(|i| self.settings.alternate_editors.get(i))
+            .cloned()
+    }
+
+    pub fn selected_action(&self) -> Option<Action> {
+        self.action_list_state
+            .selected()
+            .and_then(|i| Action::ALL.get(i).copied())
+    }
+
+    /// Run a non-interactive action, reporting the outcome in the status bar
+    /// Unavailable actions only explain what is missing
+    pub fn run_action(&mut self, action: Action) {
+        if let Some(reason) = action.unavailable_reason(&self.tools, &self.settings) {
+            self.status_message = Some(reason);
+            return;
+        }
+        if let Some(usage) = &mut self.usage {
+            usage.record_action(action.label());
+        }
+        if action == Action::CompareNotes {
+            self.compare_marked();
+            return;
+        }
+        let note = self.get_selected_file_path().cloned();
+        if action.needs_note() && note.is_none() {
+            self.status_message = Some("Select a note first".to_string());
+            return;
+        }
+        self.current_screen = CurrentScreen::Browsing;
+
+        let vault = PathBuf::from(&self.settings.notes_directory);
+        let result = match (action, note) {
+            (Action::ExportHtml, Some(note)) => Some(
+                actions::export_html(&self.settings, &self.tools, &note)
+                    .map(|target| format!("Exported {}", target.display())),
+            ),
+            (Action::ExportPdf, Some(note)) => Some(
+                actions::export_pdf(&self.settings, &self.tools, &note)
+                    .map(|target| format!("Exported {}", target.display())),
+            ),
+            (Action::CommitVault, _) => self
+                .tools
+                .path(Tool::Git)
+                .map(|git| actions::commit_vault(git, &vault)),
+            (Action::ReadAloud, Some(note)) => self
+                .tools
+                .path(Tool::Speech)
+                .map(|speech| actions::read_aloud(speech, &note))
+                .map(|r| r.map(|()| "Reading aloud…".to_string())),
+            (Action::ExportTasks, Some(note)) => Some(
+                crate::tasks::export_note(&self.settings, &self.tools, &note)
+                    .map(|n| format!("Exported {} task(s)", n)),
+            ),
+            (Action::ExportAnnotations, Some(note)) => Some(
+                annotations::export(&self.settings, &note).map(|(target, count)| {
+                    format!("Exported {} annotation(s) to {}", count, target.display())
+                }),
+            ),
+            (Action::ExportAnki, _) => Some(
+                crate::anki::export(&self.settings)
+                    .map(|(target, count)| format!("Exported {} card(s) to {}", count, target.display())),
+            ),
+            (Action::CopyLink, Some(note)) => {
+                self.copy_link(&note);
+                return;
+            }
+            (Action::InsertSnippet, Some(_)) => {
+                self.open_snippets();
+                return;
+            }
+            (Action::JournalEntry, _) => {
+                self.open_journal_entry();
+                return;
+            }
+            (Action::NoteFromClipboard, _) => {
+                self.note_from_clipboard();
+                return;
+            }
+            (Action::ClearScratch, _) => Some(
+                crate::scratch::clear(&self.settings).map(|()| "Scratch note cleared".to_string()),
+            ),
+            (Action::ArchiveScratch, _) => Some(
+                crate::scratch::archive(&self.settings, chrono::Local::now().naive_local()).map(
+                    |archived| match archived {
+                        Some(target) => format!("Scratch note archived to {}", target.display()),
+                        None => "The scratch note is empty".to_string(),
+                    },
+                ),
+            ),
+            (Action::SyncTasks, _) => Some(
+                crate::tasks::sync_completed(&self.settings, &self.tools)
+                    .map(|n| format!("Ticked {} completed task(s)", n)),
+            ),
+            _ => None,
+        };
+        let message = match result {
+            Some(Ok(message)) => {
+                self.preview = None; // The note may have changed
+                self.load_browse_items();
+                message
+            }
+            Some(Err(e)) => format!("{} failed: {}", action.label(), e),
+            None => return,
+        };
+        if let Some(event) = action.job() {
+            self.notify(event, &message);
+        }
+        self.status_message = Some(message);
+    }
+
+    /// Copy a link to the note in the `link_format` format, or in the next format when the
+    /// last key copied a link to the same note
+    pub fn copy_link(&mut self, note: &Path) {
+        let format = match &self.copied_link {
+            Some((path, format)) if path == note => format.next(),
+            _ => self.settings.link_format,
+        };
+        let link = crate::links::link(&self.settings, note, format);
+        self.status_message = Some(match actions::copy_to_clipboard(&self.tools, &link) {
+            Ok(()) => format!(
+                "Copied {} (C again for the {})",
+                link,
+                format.next().label()
+            ),
+            Err(e) => format!("Unable to copy the link: {}", e),
+        });
+        self.copied_link = Some((note.to_path_buf(), format));
+    }
+
+    /// Create a note holding the clipboard's text in the selected folder, and select it
+    pub fn note_from_clipboard(&mut self) {
+        let Some(paste) = self.tools.path(Tool::Paste) else {
+            return;
+        };
+        let folder = self.get_selected_directory();
+        match actions::note_from_clipboard(&self.settings, paste, Some(&folder)) {
+            Ok((path, problem)) => {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                self.status_message =
+                    Some(problem.unwrap_or_else(|| format!("Created {} from the clipboard", name)));
+                self.load_browse_items();
+                self.reveal(path);
+            }
+            Err(e) => self.status_message = Some(format!("New note from the clipboard failed: {}", e)),
+        }
+    }
+
+    /// Handle the result of an interactive fzf pick
+    pub fn finish_pick(&mut self, picked: std::io::Result<Option<PathBuf>>) {
+        self.current_screen = CurrentScreen::Browsing;
+        match picked {
+            Ok(Some(path)) => self.reveal(path),
+            Ok(None) => {}
+            Err(e) => self.status_message = Some(format!("fzf failed: {}", e)),
+        }
+    }
+
+    /// Handle the pager exiting
+    pub fn finish_paging(&mut self, paged: std::io::Result<()>) {
+        self.current_screen = CurrentScreen::Browsing;
+        if let Err(e) = paged {
+            self.status_message = Some(format!("Pager failed: {}", e));
+        }
+    }
+
+    /// Return from the external diff tool
+    pub fn finish_external_diff(&mut self, compared: std::io::Result<()>) {
+        self.current_screen = CurrentScreen::Browsing;
+        if let Err(e) = compared {
+            self.status_message = Some(format!("Diff tool failed: {}", e));
+        }
+    }
+
+    /// Ask for confirmation before deleting the selected note or empty folder
+    pub fn request_delete_selected(&mut self) {
+        if let Some(selected) = self.browse_list_state.selected()
+            && let Some(Some(path)) = self.browse_paths.get(selected)
+        {
+            self.pending_delete = Some(path.clone());
+            self.current_screen = CurrentScreen::ConfirmDelete;
+        }
+    }
+
+    /// Delete the note awaiting confirmation, unless the pre-delete hook vetoes it
+    pub fn confirm_delete(&mut self) {
+        self.current_screen = CurrentScreen::Browsing;
+        let Some(path) = self.pending_delete.take() else {
+            return;
+        };
+        if !self.run_hook(HookEvent::PreDelete, &path) {
+            return;
+        }
+        let result = if self.storage.is_dir(&path) {
+            self.storage.remove_dir(&path)
+        } else {
+            self.storage.remove_file(&path)
+        };
+        self.status_message = Some(match result {
+            Ok(()) => format!("Deleted {}", path.display()),
+            Err(e) => format!("Could not delete {}: {}", path.display(), e),
+        });
+        self.load_browse_items();
     }
 }
@@ -0,0 +1,76 @@
+use crate::plan::Operation;
+use crate::settings::Settings;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Folder-level rule: archive notes under `folder` not modified for `untouched_days`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveRule {
+    pub folder: String, // Relative to the vault root; empty for the whole vault
+    pub untouched_days: u64,
+}
+
+/// Absolute path of the archive folder
+pub fn archive_dir(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(&settings.archive_directory)
+}
+
+/// Parse an `expires:` value, accepting a plain date or a timestamp starting with one
+fn parse_expiry(value: &str) -> Option<NaiveDate> {
+    let date = value.get(..10).unwrap_or(value);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// Whether the note's frontmatter `expires:` date has passed
+fn is_expired(path: &Path, today: NaiveDate) -> bool {
+    let Ok(content) = fs::read_to_string(path) else {
+        return false;
+    };
+    crate::frontmatter::parse(&content)
+        .and_then(|fm| fm.get("expires").and_then(parse_expiry))
+        .is_some_and(|expires| expires < today)
+}
+
+/// Whether a folder rule matches the note (under the rule's folder and untouched long enough)
+fn matches_rule(path: &Path, vault: &Path, rule: &ArchiveRule, now: SystemTime) -> bool {
+    if !path.starts_with(vault.join(&rule.folder)) {
+        return false;
+    }
+    let max_age = Duration::from_secs(rule.untouched_days * 24 * 60 * 60);
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| now.duration_since(modified).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+/// Plan moving expired notes and notes matched by folder rules into the archive folder
+/// Notes keep their vault-relative location inside the archive
+pub fn plan_archive(settings: &Settings) -> Vec<Operation> {
+    let vault = PathBuf::from(&settings.notes_directory);
+    let archive = archive_dir(settings);
+    let today = chrono::Local::now().date_naive();
+    let now = SystemTime::now();
+
+    crate::vault::all_files(settings)
+        .into_iter()
+        .filter(|path| !path.starts_with(&archive))
+        .filter(|path| {
+            is_expired(path, today)
+                || settings
+                    .archive_rules
+                    .iter()
+                    .any(|rule| matches_rule(path, &vault, rule, now))
+        })
+        .filter_map(|path| {
+            let relative = path.strip_prefix(&vault).ok()?.to_path_buf();
+            Some(Operation::Move {
+                to: archive.join(relative),
+                from: path,
+            })
+        })
+        .collect()
+}
@@ -1,132 +1,203 @@
 use crate::settings::Settings;
-use std::collections::HashSet;
+use crate::storage::VaultStorage;
+use crate::vault::VaultFilter;
+use std::collections::{BTreeMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::fs::create_dir_all;
+use std::sync::Arc;
 
-/// Check if a path should be shown based on expanded folders
-/// A path is shown if all its parent directories (except base) are expanded
-fn should_show_path(path: &Path, base_dir: &Path, expanded_folders: &HashSet<PathBuf>) -> bool {
-    if path == base_dir {
-        return false;
+/// Read the direct children of a directory, sorted, skipping ignored paths
+/// Entries pinned in the folder's `.lair.toml` come first, in the order they're pinned
+fn read_children(
+    storage: &dyn VaultStorage,
+    dir_path: &Path,
+    filter: &VaultFilter,
+) -> std::io::Result<Vec<PathBuf>> {
+    let mut children: Vec<PathBuf> = storage
+        .read_dir(dir_path)?
+        .into_iter()
+        .filter(|path| !filter.is_ignored(path, storage.is_dir(path)))
+        .collect();
+    children.sort();
+    let pinned = crate::folders::pinned(storage, dir_path);
+    if !pinned.is_empty() {
+        children.sort_by_key(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            pinned.iter().position(|p| *p == name).unwrap_or(pinned.len())
+        });
     }
-    
-    // Check all parent directories up to base_dir
-    let mut current = path;
-    while let Some(parent) = current.parent() {
-        if parent == base_dir {
-            // Reached base directory, all parents are expanded
-            return true;
+    Ok(children)
+}
+
+/// Display items (display_text, is_file) paired with their paths (None for folder headers)
+pub type BrowseList = (Vec<(String, bool)>, Vec<Option<PathBuf>>);
+
+/// How the browse list arranges the vault
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowseView {
+    /// The folder tree
+    Tree,
+    /// Every note, grouped under the language from its `lang:` frontmatter
+    Languages,
+}
+
+/// Number of items collected before a batch is handed to the caller
+const BATCH_SIZE: usize = 200;
+
+/// State for one walk of the tree, emitting items in display order as batches
+struct TreeScan<'a> {
+    storage: Arc<dyn VaultStorage>,
+    filter: VaultFilter,
+    expanded_folders: &'a HashSet<PathBuf>,
+    items: Vec<(String, bool)>,
+    paths: Vec<Option<PathBuf>>,
+    emit: &'a mut dyn FnMut(BrowseList) -> bool,
+    cancelled: bool, // The receiver went away, stop walking
+}
+
+impl TreeScan<'_> {
+    /// Hand the collected items to the caller
+    fn flush(&mut self) {
+        if self.items.is_empty() || self.cancelled {
+            return;
         }
-        
-        // If this parent is not expanded, don't show the path
-        if !expanded_folders.contains(&parent.to_path_buf()) {
-            return false;
+        let batch = (std::mem::take(&mut self.items), std::mem::take(&mut self.paths));
+        if !(self.emit)(batch) {
+            self.cancelled = true;
         }
-        
-        current = parent;
     }
-    
-    true
-}
 
+    /// Recursively add items for a directory and its children
+    /// Only expanded folders are read, so collapsed parts of the vault are never touched
+    fn add_directory_items(&mut self, dir_path: &Path, depth: usize) -> std::io::Result<()> {
+        for child_path in read_children(self.storage.as_ref(), dir_path, &self.filter)? {
+            if self.cancelled {
+                return Ok(());
+            }
 
-/// Recursively add items for a directory and its children
-fn add_directory_items(
-    dir_path: &Path,
-    base_dir: &Path,
-    expanded_folders: &HashSet<PathBuf>,
-    paths_by_parent: &std::collections::BTreeMap<PathBuf, Vec<PathBuf>>,
-    items: &mut Vec<(String, bool)>,
-    paths: &mut Vec<Option<PathBuf>>,
-    depth: usize,
-) {
-    // Get children of this directory
-    if let Some(children) = paths_by_parent.get(dir_path) {
-        let mut sorted_children = children.clone();
-        sorted_children.sort();
-        
-        for child_path in sorted_children {
             let display_name = child_path
                 .file_name()
                 .and_then(|n| n.to_str())
                 .unwrap_or("")
                 .to_string();
 
-            let is_file = child_path.is_file();
-            let is_expanded = child_path.is_dir() && expanded_folders.contains(&child_path);
+            let is_file = self.storage.is_file(&child_path);
+            let is_dir = self.storage.is_dir(&child_path);
+            let is_expanded = is_dir && self.expanded_folders.contains(&child_path);
             let expand_indicator = if is_expanded { "▼ " } else { "▶ " };
             
             // Indent based on depth
             let item_indent = "  ".repeat(depth);
             
-            let display_text = if child_path.is_dir() {
+            let display_text = if is_dir {
                 format!("{} {}📁 {}", item_indent, expand_indicator, display_name)
             } else {
                 format!("{} 📄 {}", item_indent, display_name)
             };
 
-            items.push((display_text, is_file));
-            paths.push(Some(child_path.clone()));
+            self.items.push((display_text, is_file));
+            self.paths.push(Some(child_path.clone()));
+            if self.items.len() >= BATCH_SIZE {
+                self.flush();
+            }
 
-            // If this is an expanded directory, recursively add its children
+            // If this is an expanded directory, read and add its children on demand
+            // An unreadable subfolder just shows up empty rather than failing the whole tree
             if is_expanded {
-                add_directory_items(&child_path, base_dir, expanded_folders, paths_by_parent, items, paths, depth + 1);
+                let _ = self.add_directory_items(&child_path, depth + 1);
+                // Flush after each folder so slow drives show progress
+                self.flush();
             }
         }
+        Ok(())
     }
 }
 
-// Return both list items and their corresponding paths, filtered by expanded folders
-pub fn get_files_as_list_items_with_paths(
+// Walk the tree, handing list items and their corresponding paths to `emit` in display order
+// Only paths whose parent folders are expanded are included; `emit` returns false to stop early
+// The tree is built from `root`, which is the vault root unless the browser has been re-rooted
+pub fn scan_list_items_with_paths(
     settings: &Settings,
+    root: &Path,
     expanded_folders: &HashSet<PathBuf>,
-) -> Result<(Vec<(String, bool)>, Vec<Option<PathBuf>>), Box<dyn std::error::Error>> {
-    let base_dir = Path::new(&settings.notes_directory);
-    let pattern = base_dir.join("**/*").to_string_lossy().to_string();
-
-    let mut items: Vec<(String, bool)> = Vec::new(); // (display_text, is_file)
-    let mut paths: Vec<Option<PathBuf>> = Vec::new();
-
-    // Collect all paths first
-    let mut all_paths: Vec<PathBuf> = Vec::new();
-    for entry in glob::glob(&pattern)? {
-        let path = entry?;
-        if path != base_dir {
-            all_paths.push(path);
-        }
-    }
+    emit: &mut dyn FnMut(BrowseList) -> bool,
+) -> std::io::Result<()> {
+    let base_dir = root;
+
+    // Ignore files, the vault's config folder and .git are never shown
+    let mut scan = TreeScan {
+        storage: crate::storage::open(settings),
+        filter: VaultFilter::new(settings),
+        expanded_folders,
+        items: Vec::new(),
+        paths: Vec::new(),
+        emit,
+        cancelled: false,
+    };
+
+    // Add root folder header (named after the folder when re-rooted)
+    let root_label = if base_dir == Path::new(&settings.notes_directory) {
+        "Root".to_string()
+    } else {
+        base_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("Root")
+            .to_string()
+    };
+    scan.items.push((format!("📂 {}", root_label), false));
+    scan.paths.push(None); // Folder headers have no path
+
+    // Recursively add items starting from root (depth 1 for root's children)
+    scan.add_directory_items(base_dir, 1)?;
+    scan.flush();
 
-    // Sort paths to ensure consistent ordering
-    all_paths.sort();
+    Ok(())
+}
 
-    // Group paths by their parent directory
-    let mut paths_by_parent: std::collections::BTreeMap<PathBuf, Vec<PathBuf>> = std::collections::BTreeMap::new();
-    for path in all_paths {
-        // Only show paths whose parent folders are expanded
-        if !should_show_path(&path, base_dir, expanded_folders) {
+/// List the notes below `root` grouped by language, languages sorted and unlabelled notes last
+/// Every note has to be read for its frontmatter, so the list is emitted in one go at the end
+pub fn scan_language_groups(
+    settings: &Settings,
+    root: &Path,
+    emit: &mut dyn FnMut(BrowseList) -> bool,
+) -> std::io::Result<()> {
+    let mut groups: BTreeMap<String, Vec<PathBuf>> = BTreeMap::new();
+    let mut unlabelled = Vec::new();
+    let storage = crate::storage::open(settings);
+    for path in crate::vault::files_under(settings, root) {
+        if !crate::vault::is_note(&path, settings) {
             continue;
         }
-
-        if let Some(parent) = path.parent() {
-            let parent_path = parent.to_path_buf();
-            paths_by_parent.entry(parent_path).or_insert_with(Vec::new).push(path);
+        let language = storage
+            .read_to_string(&path)
+            .ok()
+            .and_then(|content| crate::note::language(&content));
+        match language {
+            Some(language) => groups.entry(language).or_default().push(path),
+            None => unlabelled.push(path),
         }
     }
 
-    // Add root folder header
-    items.push((format!("📂 Root"), false));
-    paths.push(None); // Folder headers have no path
-
-    // Recursively add items starting from root (depth 0 for root's children)
-    add_directory_items(base_dir, base_dir, expanded_folders, &paths_by_parent, &mut items, &mut paths, 1);
-
-    Ok((items, paths))
+    let mut items = Vec::new();
+    let mut paths = Vec::new();
+    let rest = (!unlabelled.is_empty()).then(|| ("no language".to_string(), unlabelled));
+    for (language, notes) in groups.into_iter().chain(rest) {
+        items.push((format!("🌐 {} ({})", language, notes.len()), false));
+        paths.push(None); // Group headers have no path
+        for note in notes {
+            let relative = note.strip_prefix(root).unwrap_or(&note).display().to_string();
+            items.push((format!("   📄 {}", relative), true));
+            paths.push(Some(note));
+        }
+    }
+    emit((items, paths));
+    Ok(())
 }
 
-pub fn make_new_folder(parent_folder: &Path, new_folder: &Path) ->Result<(), Box<dyn std::error::Error>> {
+pub fn make_new_folder(storage: &dyn VaultStorage, parent_folder: &Path, new_folder: &Path) ->Result<(), Box<dyn std::error::Error>> {
     let new_folder_str = format!("{}/{}",parent_folder.display(),new_folder.display());
     let new_folder_path = Path::new(&new_folder_str);
 
-    create_dir_all(new_folder_path)?;
+    storage.create_dir_all(new_folder_path)?;
     Ok(())
 }
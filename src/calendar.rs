@@ -0,0 +1,169 @@
+use chrono::{Datelike, Months, NaiveDate};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// What the vault holds for a day of the calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayNotes {
+    Empty,
+    Notes, // The date folder has notes, but no daily note
+    Daily,
+}
+
+const MONTHS: [&str; 12] = [
+    "january", "february", "march", "april", "may", "june", "july", "august", "september",
+    "october", "november", "december",
+];
+
+/// The date folder for a day, as new notes are filed: `24-06-14`
+fn date_folder(settings: &Settings, date: NaiveDate) -> PathBuf {
+    Path::new(&settings.notes_directory).join(date.format("%y-%m-%d").to_string())
+}
+
+/// The daily note for a day, whether or not it exists yet
+pub fn daily_note(settings: &Settings, date: NaiveDate) -> PathBuf {
+    date_folder(settings, date).join(format!("daily.{}", settings.default_file_format))
+}
+
+/// Create the daily note for a day unless it exists, returning it and whether it's new
+pub fn create_daily(settings: &Settings, date: NaiveDate) -> io::Result<(PathBuf, bool)> {
+    let path = daily_note(settings, date);
+    if path.exists() {
+        return Ok((path, false));
+    }
+    fs::create_dir_all(date_folder(settings, date))?;
+    fs::File::create(&path)?;
+    crate::note::fill_created_note(settings, &path)?;
+    Ok((path, true))
+}
+
+/// What each day of `date`'s month holds, the first day first
+pub fn month(settings: &Settings, date: NaiveDate) -> Vec<DayNotes> {
+    (1..=days_in_month(date))
+        .filter_map(|day| date.with_day(day))
+        .map(|day| {
+            let folder = date_folder(settings, day);
+            if daily_note(settings, day).exists() {
+                DayNotes::Daily
+            } else if fs::read_dir(&folder).is_ok_and(|mut entries| entries.next().is_some()) {
+                DayNotes::Notes
+            } else {
+                DayNotes::Empty
+            }
+        })
+        .collect()
+}
+
+pub fn days_in_month(date: NaiveDate) -> u32 {
+    let first = date.with_day(1).unwrap_or(date);
+    first
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// Move by whole months, keeping the day where the month is long enough
+pub fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let moved = if months < 0 {
+        date.checked_sub_months(Months::new(months.unsigned_abs()))
+    } else {
+        date.checked_add_months(Months::new(months as u32))
+    };
+    moved.unwrap_or(date)
+}
+
+/// The month a word names: its full name or any start of it at least three letters long
+fn month_number(word: &str) -> Option<u32> {
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|m| m.starts_with(word))
+        .map(|i| i as u32 + 1)
+}
+
+/// Read a typed date, as loosely as it's likely to be typed: "2023-12-05", "23-12-05" (the
+/// date folders), "2023-12", "mar 3", "3rd march 2024", "december", "15", "today"
+/// Whatever is left out comes from `shown`: "mar 3" is in the shown year, "15" in the shown
+/// month, and a month or year alone goes to its first day
+pub fn parse(input: &str, today: NaiveDate, shown: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "today" => return Some(today),
+        "yesterday" => return today.pred_opt(),
+        "tomorrow" => return today.succ_opt(),
+        _ => {}
+    }
+    // chrono reads "23" as the year 23 for %Y, so the year's length picks the format
+    if let Some((year, _)) = input.split_once(['-', '/']) {
+        let format = match (year.len(), input.contains('/')) {
+            (2, false) => Some("%y-%m-%d"),
+            (4, false) => Some("%Y-%m-%d"),
+            (4, true) => Some("%Y/%m/%d"),
+            _ => None,
+        };
+        if let Some(date) = format.and_then(|f| NaiveDate::parse_from_str(&input, f).ok()) {
+            return Some(date);
+        }
+    }
+    if let Some((year, month)) = input.split_once(['-', '/'])
+        && year.len() == 4
+        && let (Ok(year), Ok(month)) = (year.parse::<i32>(), month.parse::<u32>())
+    {
+        return NaiveDate::from_ymd_opt(year, month, 1);
+    }
+
+    let (mut year, mut month, mut day) = (None, None, None);
+    for word in input.split(|c: char| c.is_whitespace() || c == ',' || c == '.') {
+        if word.is_empty() {
+            continue;
+        }
+        let number = ["st", "nd", "rd", "th"]
+            .iter()
+            .find_map(|suffix| word.strip_suffix(suffix))
+            .unwrap_or(word);
+        if let Ok(n) = number.parse::<u32>() {
+            match (n, number.len()) {
+                (_, 4) => year = Some(n as i32),
+                (1..=31, _) if day.is_none() => day = Some(n),
+                (0..=99, 2) if year.is_none() => year = Some(2000 + n as i32),
+                _ => return None,
+            }
+        } else {
+            month = Some(month_number(word)?);
+        }
+    }
+    let (year, month, day) = match (year, month, day) {
+        (None, None, None) => return None,
+        // A year alone, or with a month: its first day
+        (Some(year), month, None) => (year, month.unwrap_or(1), 1),
+        (year, Some(month), day) => (year.unwrap_or(shown.year()), month, day.unwrap_or(1)),
+        (None, None, Some(day)) => (shown.year(), shown.month(), day),
+        (Some(_), None, Some(_)) => return None,
+    };
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_typed_dates() {
+        let today = date(2024, 6, 14);
+        let shown = date(2024, 6, 1);
+        assert_eq!(parse("23-12-05", today, shown), Some(date(2023, 12, 5)));
+        assert_eq!(parse("2023-12-05", today, shown), Some(date(2023, 12, 5)));
+        assert_eq!(parse("2023/12/05", today, shown), Some(date(2023, 12, 5)));
+        assert_eq!(parse("2023-12", today, shown), Some(date(2023, 12, 1)));
+        assert_eq!(parse("mar 3", today, shown), Some(date(2024, 3, 3)));
+    }
+}
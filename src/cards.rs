@@ -0,0 +1,193 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use std::path::Path;
+
+use crate::frontmatter::{self, Frontmatter};
+use crate::markdown::{self, RenderedLink};
+
+/// A note type rendered as a card: fields shown up top and sections pulled to the front
+struct CardKind {
+    types: &'static [&'static str], // Values of the `type:` frontmatter field
+    label: &'static str,
+    fields: &'static [&'static str],
+    sections: &'static [&'static [&'static str]], // Each entry lists alternative heading names
+}
+
+const KINDS: [CardKind; 2] = [
+    CardKind {
+        types: &["recipe"],
+        label: "RECIPE",
+        fields: &["servings", "time", "prep", "cook", "source"],
+        sections: &[
+            &["ingredients"],
+            &["steps", "method", "instructions", "directions"],
+        ],
+    },
+    CardKind {
+        types: &["cheatsheet", "reference"],
+        label: "REFERENCE",
+        fields: &["command", "summary", "version"],
+        sections: &[&["flags", "options"], &["examples", "usage"]],
+    },
+];
+
+/// A `##`-or-deeper section of the body
+pub struct Section<'a> {
+    pub title: String,
+    pub text: &'a str, // Heading line included
+}
+
+/// Split a body into the text before its first subheading and the sections after it
+/// Top-level `# Title` lines stay in the leading text
+pub fn split_sections(body: &str) -> (&str, Vec<Section<'_>>) {
+    let mut starts = Vec::new();
+    let mut in_code_block = false;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block && trimmed.starts_with("##") {
+            let title = trimmed.trim_start_matches('#').trim().to_string();
+            starts.push((offset, title));
+        }
+        offset += line.len();
+    }
+
+    let preamble = &body[..starts.first().map(|(start, _)| *start).unwrap_or(body.len())];
+    let sections = starts
+        .iter()
+        .enumerate()
+        .map(|(i, (start, title))| {
+            let end = starts.get(i + 1).map(|(next, _)| *next).unwrap_or(body.len());
+            Section {
+                title: title.clone(),
+                text: &body[*start..end],
+            }
+        })
+        .collect();
+    (preamble, sections)
+}
+
+/// Builds the card, keeping link positions in step with the lines
+struct CardWriter {
+    lines: Vec<Line<'static>>,
+    links: Vec<RenderedLink>,
+}
+
+impl CardWriter {
+    fn push_markdown(&mut self, text: &str) {
+        let (rendered, links) = markdown::render(text);
+        if rendered.lines.is_empty() {
+            return;
+        }
+        let offset = self.lines.len();
+        self.links.extend(links.into_iter().map(|mut link| {
+            link.line += offset;
+            link
+        }));
+        self.lines.extend(rendered.lines);
+        self.lines.push(Line::default());
+    }
+
+    /// A banner for a pulled-forward section, replacing its own heading
+    fn push_section(&mut self, section: &Section) {
+        let body = section.text.split_once('\n').map(|(_, rest)| rest).unwrap_or("");
+        let rule = "─".repeat(36usize.saturating_sub(section.title.chars().count()));
+        self.lines.push(Line::styled(
+            format!("── {} {}", section.title, rule),
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+        ));
+        self.push_markdown(body);
+    }
+}
+
+/// Card title: frontmatter `title`, else the first `# ` heading, else the file name
+/// Also returns the leading text with that heading removed
+fn title(frontmatter: &Frontmatter, preamble: &str, path: &Path) -> (String, String) {
+    let heading = preamble.lines().find_map(|l| l.strip_prefix("# "));
+    let rest: String = match heading {
+        Some(h) => preamble
+            .lines()
+            .filter(|l| l.strip_prefix("# ") != Some(h))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => preamble.to_string(),
+    };
+    let title = frontmatter
+        .get("title")
+        .map(str::to_string)
+        .or_else(|| heading.map(|h| h.trim().to_string()))
+        .unwrap_or_else(|| {
+            path.file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default()
+        });
+    (title, rest)
+}
+
+/// Render a note as a card if its `type:` frontmatter names a known card kind
+pub fn render(path: &Path, content: &str) -> Option<(Text<'static>, Vec<RenderedLink>)> {
+    let frontmatter = frontmatter::parse(content)?;
+    let note_type = frontmatter.get("type")?.trim().to_lowercase();
+    let kind = KINDS.iter().find(|k| k.types.contains(&note_type.as_str()))?;
+
+    let (preamble, sections) = split_sections(frontmatter::body(content));
+    let (title, description) = title(&frontmatter, preamble, path);
+    let mut card = CardWriter {
+        lines: Vec::new(),
+        links: Vec::new(),
+    };
+
+    card.lines.push(Line::styled(
+        format!("▌{}", kind.label),
+        Style::default().fg(Color::DarkGray),
+    ));
+    card.lines.push(Line::styled(
+        title,
+        Style::default()
+            .fg(Color::Magenta)
+            .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+    ));
+    let mut fields = Vec::new();
+    for name in kind.fields {
+        let Some(value) = frontmatter.get(name) else {
+            continue;
+        };
+        if !fields.is_empty() {
+            fields.push(Span::raw("   "));
+        }
+        fields.push(Span::styled(format!("{}: ", name), Style::default().fg(Color::DarkGray)));
+        fields.push(Span::styled(
+            value.to_string(),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if !fields.is_empty() {
+        card.lines.push(Line::from(fields));
+    }
+    card.lines.push(Line::default());
+    card.push_markdown(&description);
+
+    // Key sections first, in the kind's order, then everything else as written
+    let mut shown = vec![false; sections.len()];
+    for names in kind.sections {
+        let found = sections
+            .iter()
+            .position(|s| names.iter().any(|n| s.title.eq_ignore_ascii_case(n)));
+        if let Some(i) = found
+            && !shown[i]
+        {
+            card.push_section(&sections[i]);
+            shown[i] = true;
+        }
+    }
+    for (section, _) in sections.iter().zip(&shown).filter(|(_, shown)| !**shown) {
+        card.push_markdown(section.text);
+    }
+
+    while card.lines.last().is_some_and(|l| l.spans.is_empty()) {
+        card.lines.pop();
+    }
+    Some((Text::from(card.lines), card.links))
+}
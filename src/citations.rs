@@ -0,0 +1,426 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::settings::Settings;
+
+/// An entry of the bibliography
+#[derive(Debug, Clone, Default)]
+pub struct Reference {
+    pub key: String,
+    pub authors: Vec<(String, String)>, // Family and given names
+    pub title: String,
+    pub year: Option<String>,
+    pub container: Option<String>, // Journal, book or publisher
+    pub link: Option<String>,      // DOI link or URL
+}
+
+impl Reference {
+    /// Short inline form, e.g. "Smith 2020" or "Smith et al. 2020"
+    pub fn label(&self) -> String {
+        let names = match self.authors.as_slice() {
+            [] => self.title.clone(),
+            [(one, _)] => one.clone(),
+            [(first, _), (second, _)] => format!("{} & {}", first, second),
+            [(first, _), ..] => format!("{} et al.", first),
+        };
+        format!("{} {}", names, self.year.as_deref().unwrap_or("n.d."))
+    }
+
+    /// Full reference as markdown, for the references list
+    pub fn formatted(&self) -> String {
+        let authors: Vec<String> = self
+            .authors
+            .iter()
+            .map(|(family, given)| {
+                if given.is_empty() {
+                    family.clone()
+                } else {
+                    format!("{}, {}", family, given)
+                }
+            })
+            .collect();
+        let mut text = String::new();
+        match authors.split_last() {
+            Some((last, [])) => text.push_str(&format!("{} ", last)),
+            Some((last, others)) => text.push_str(&format!("{} & {} ", others.join("; "), last)),
+            None => {}
+        }
+        text.push_str(&format!(
+            "({}). *{}*.",
+            self.year.as_deref().unwrap_or("n.d."),
+            self.title
+        ));
+        if let Some(container) = &self.container {
+            text.push_str(&format!(" {}.", container));
+        }
+        if let Some(link) = &self.link {
+            text.push_str(&format!(" <{}>", link));
+        }
+        text
+    }
+}
+
+/// The configured bibliography file: `~/` paths are expanded, relative ones are
+/// resolved against the vault; None when it isn't set
+pub fn bibliography_path(settings: &Settings) -> Option<PathBuf> {
+    let file = settings.bibliography.trim();
+    if file.is_empty() {
+        return None;
+    }
+    if let Some(rest) = file.strip_prefix("~/") {
+        return Some(
+            dirs::home_dir()
+                .unwrap_or_else(|| PathBuf::from("."))
+                .join(rest),
+        );
+    }
+    Some(Path::new(&settings.notes_directory).join(file))
+}
+
+/// Strip BibTeX braces and escapes from a field value and collapse whitespace
+fn clean(value: &str) -> String {
+    let unescaped: String = value
+        .replace("\\&", "&")
+        .replace("\\%", "%")
+        .replace("\\_", "_")
+        .replace("\\$", "$")
+        .chars()
+        .filter(|c| *c != '{' && *c != '}')
+        .collect();
+    unescaped.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Length of `text` up to the `close` that ends the current group, skipping nested braces
+fn group_len(text: &str, close: char) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Read a field value (`{...}`, `"..."` or a bare word, joined with `#`), returning it and the rest
+fn read_value(mut text: &str) -> (String, &str) {
+    let mut value = String::new();
+    loop {
+        text = text.trim_start();
+        let (part, rest) = if let Some(inner) = text.strip_prefix('{') {
+            let len = group_len(inner, '}').unwrap_or(inner.len());
+            (&inner[..len], inner.get(len + 1..).unwrap_or(""))
+        } else if let Some(inner) = text.strip_prefix('"') {
+            let len = group_len(inner, '"').unwrap_or(inner.len());
+            (&inner[..len], inner.get(len + 1..).unwrap_or(""))
+        } else {
+            let len = text.find([',', '#', '}']).unwrap_or(text.len());
+            (text[..len].trim(), &text[len..])
+        };
+        value.push_str(part);
+        text = rest.trim_start();
+        match text.strip_prefix('#') {
+            Some(next) => text = next,
+            None => return (value, text),
+        }
+    }
+}
+
+/// "Family, Given" or "Given Family" for each name in an `and`-separated list
+fn parse_names(names: &str) -> Vec<(String, String)> {
+    names
+        .split(" and ")
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(|name| match name.split_once(',') {
+            Some((family, given)) => (family.trim().to_string(), given.trim().to_string()),
+            None => match name.rsplit_once(' ') {
+                Some((given, family)) => (family.to_string(), given.to_string()),
+                None => (name.to_string(), String::new()),
+            },
+        })
+        .collect()
+}
+
+fn parse_bibtex_entry(body: &str) -> Option<Reference> {
+    let (key, mut rest) = body.split_once(',')?;
+    let mut fields: Vec<(String, String)> = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        let Some(eq) = rest.find('=') else {
+            break;
+        };
+        let name = rest[..eq].trim().to_lowercase();
+        let (value, remaining) = read_value(&rest[eq + 1..]);
+        fields.push((name, clean(&value)));
+        rest = remaining;
+    }
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(n, v)| n == name && !v.is_empty())
+            .map(|(_, v)| v.clone())
+    };
+    let year = field("year").or_else(|| field("date").map(|d| d.chars().take(4).collect()));
+    let link = field("doi")
+        .map(|doi| format!("https://doi.org/{}", doi))
+        .or_else(|| field("url"));
+    Some(Reference {
+        key: key.trim().to_string(),
+        authors: parse_names(
+            &field("author")
+                .or_else(|| field("editor"))
+                .unwrap_or_default(),
+        ),
+        title: field("title").unwrap_or_default(),
+        year,
+        container: field("journal")
+            .or_else(|| field("booktitle"))
+            .or_else(|| field("publisher")),
+        link,
+    })
+}
+
+/// Entries of a BibTeX file; `@comment`, `@string` and `@preamble` blocks are skipped
+fn parse_bibtex(text: &str) -> Vec<Reference> {
+    let mut references = Vec::new();
+    let mut rest = text;
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let Some(open) = rest.find(['{', '(']) else {
+            break;
+        };
+        let kind = rest[..open].trim().to_lowercase();
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphabetic()) {
+            continue; // An `@` in free text, not the start of an entry
+        }
+        let close = if rest[open..].starts_with('{') {
+            '}'
+        } else {
+            ')'
+        };
+        let body_start = open + 1;
+        let Some(len) = group_len(&rest[body_start..], close) else {
+            break;
+        };
+        let body = &rest[body_start..body_start + len];
+        rest = &rest[body_start + len + 1..];
+        let is_entry = !matches!(kind.as_str(), "comment" | "string" | "preamble");
+        if is_entry && let Some(reference) = parse_bibtex_entry(body) {
+            references.push(reference);
+        }
+    }
+    references
+}
+
+/// Entries of a CSL JSON file (an array of items, as exported by Zotero)
+fn parse_csl_json(text: &str) -> io::Result<Vec<Reference>> {
+    let items: Vec<serde_json::Value> = serde_json::from_str(text).map_err(io::Error::other)?;
+    let string = |item: &serde_json::Value, name: &str| match &item[name] {
+        serde_json::Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    };
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let key = string(item, "id")?;
+            let authors = item["author"]
+                .as_array()
+                .map(|names| {
+                    names
+                        .iter()
+                        .filter_map(|name| {
+                            let family =
+                                string(name, "family").or_else(|| string(name, "literal"))?;
+                            Some((family, string(name, "given").unwrap_or_default()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let issued = &item["issued"];
+            let year = match &issued["date-parts"][0][0] {
+                serde_json::Value::Number(n) => Some(n.to_string()),
+                serde_json::Value::String(s) => Some(s.clone()),
+                _ => string(issued, "raw").map(|raw| raw.chars().take(4).collect()),
+            };
+            Some(Reference {
+                key,
+                authors,
+                title: string(item, "title").unwrap_or_default(),
+                year,
+                container: string(item, "container-title").or_else(|| string(item, "publisher")),
+                link: string(item, "DOI")
+                    .map(|doi| format!("https://doi.org/{}", doi))
+                    .or_else(|| string(item, "URL")),
+            })
+        })
+        .collect())
+}
+
+/// Parse a bibliography, choosing the format from the extension (`.json` is CSL JSON)
+pub fn load(path: &Path) -> io::Result<Vec<Reference>> {
+    let text = fs::read_to_string(path)?;
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    if is_json {
+        parse_csl_json(&text)
+    } else {
+        Ok(parse_bibtex(&text))
+    }
+}
+
+/// The bibliography previews resolve citations against
+/// Previews are loaded without access to the settings, so the path is set once up front
+static BIBLIOGRAPHY: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The last parsed bibliography, reused until the file changes
+type Cache = (PathBuf, Option<SystemTime>, Arc<Vec<Reference>>);
+static CACHE: Mutex<Option<Cache>> = Mutex::new(None);
+
+/// Point previews at the bibliography from the settings
+pub fn configure(settings: &Settings) {
+    if let Ok(mut path) = BIBLIOGRAPHY.lock() {
+        *path = bibliography_path(settings);
+    }
+}
+
+/// The configured bibliography (empty when there is none), re-read when the file changes
+pub fn library() -> io::Result<Arc<Vec<Reference>>> {
+    let Some(path) = BIBLIOGRAPHY.lock().ok().and_then(|p| p.clone()) else {
+        return Ok(Arc::new(Vec::new()));
+    };
+    let modified = fs::metadata(&path).and_then(|m| m.modified()).ok();
+    let mut cache = CACHE
+        .lock()
+        .map_err(|_| io::Error::other("bibliography cache poisoned"))?;
+    if let Some((cached_path, cached_modified, references)) = cache.as_ref()
+        && *cached_path == path
+        && *cached_modified == modified
+    {
+        return Ok(references.clone());
+    }
+    let references = Arc::new(load(&path)?);
+    *cache = Some((path, modified, references.clone()));
+    Ok(references)
+}
+
+/// Whether `c` may appear in a citation key (pandoc's rules, roughly)
+fn is_key_char(c: char) -> bool {
+    c.is_alphanumeric() || "_-:.#$%&+?<>~/".contains(c)
+}
+
+/// Inline text for the inside of a `[@key; @other, p. 4]` group, or None if it isn't one
+/// Keys missing from the bibliography are shown as `?key`
+fn resolve_group(group: &str, references: &[Reference], cited: &mut Vec<String>) -> Option<String> {
+    let mut parts = Vec::new();
+    for part in group.split(';') {
+        let part = part.trim();
+        let after = part.strip_prefix("-@").or_else(|| part.strip_prefix('@'))?;
+        let key_len = after.find(|c: char| !is_key_char(c)).unwrap_or(after.len());
+        let key = after[..key_len].trim_end_matches(['.', ':']);
+        if key.is_empty() {
+            return None;
+        }
+        let locator = after[key.len()..].trim();
+        let label = match references.iter().find(|r| r.key == key) {
+            Some(reference) => {
+                if !cited.iter().any(|c| c == key) {
+                    cited.push(key.to_string());
+                }
+                reference.label()
+            }
+            None => format!("?{}", key),
+        };
+        parts.push(format!("{}{}", label, locator));
+    }
+    Some(format!("({})", parts.join("; ")))
+}
+
+/// Replace `[@key]` citations with "(Author Year)" and append a references list
+/// Returns None when the note cites nothing from the bibliography
+pub fn resolve(content: &str) -> Option<String> {
+    if !content.contains("[@") {
+        return None;
+    }
+    let references = library().ok()?;
+    if references.is_empty() {
+        return None;
+    }
+
+    let mut cited: Vec<String> = Vec::new();
+    let mut output = String::with_capacity(content.len());
+    let mut in_code_block = false;
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+        if in_code_block {
+            output.push_str(line);
+            continue;
+        }
+        let mut rest = line;
+        while let Some(start) = rest.find("[@") {
+            let Some(len) = rest[start..].find(']') else {
+                break;
+            };
+            let group = &rest[start + 1..start + len];
+            output.push_str(&rest[..start]);
+            match resolve_group(group, &references, &mut cited) {
+                Some(inline) => output.push_str(&inline),
+                None => output.push_str(&rest[start..=start + len]),
+            }
+            rest = &rest[start + len + 1..];
+        }
+        output.push_str(rest);
+    }
+    if cited.is_empty() {
+        return None;
+    }
+
+    let mut entries: Vec<&Reference> = references
+        .iter()
+        .filter(|r| cited.contains(&r.key))
+        .collect();
+    entries.sort_by_key(|r| r.label().to_lowercase());
+    output.push_str("\n\n## References\n\n");
+    for entry in entries {
+        output.push_str(&format!("- {}\n", entry.formatted()));
+    }
+    Some(output)
+}
+
+/// Bibliography entries whose key, title or authors contain `query` (case-insensitive)
+pub fn matching<'a>(references: &'a [Reference], query: &str) -> Vec<&'a Reference> {
+    let query = query.trim().trim_start_matches('@').to_lowercase();
+    references
+        .iter()
+        .filter(|r| {
+            query.is_empty()
+                || r.key.to_lowercase().contains(&query)
+                || r.title.to_lowercase().contains(&query)
+                || r.authors
+                    .iter()
+                    .any(|(family, _)| family.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+/// Cite `key` at the end of a note, after its last line of text
+pub fn insert(note: &Path, key: &str) -> io::Result<()> {
+    let content = fs::read_to_string(note)?;
+    let citation = format!("[@{}]", key);
+    let body = content.trim_end();
+    let updated = if body.is_empty() {
+        format!("{}\n", citation)
+    } else {
+        format!("{} {}\n", body, citation)
+    };
+    fs::write(note, updated)
+}
@@ -0,0 +1,845 @@
+use crate::actions;
+use crate::hooks::{self, HookEvent};
+use crate::plan::{Operation, Plan};
+#[cfg(unix)]
+use crate::remote::Request;
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+use crate::tools::{Tool, Tools};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Command-line interface; without a command, the TUI is started
+#[derive(Parser)]
+#[command(name = "lair", version, about = "A terminal notes vault")]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Run an action and exit, with status 0 on success and 1 on failure
+    #[arg(long, value_name = "name")]
+    action: Option<ActionName>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a note in today's folder, named as in the TUI, and print its path
+    New {
+        /// The note's name, a timestamp when none is given
+        name: Vec<String>,
+        /// Name the note, as the words do
+        #[arg(long = "name", value_name = "name")]
+        named: Option<String>,
+        /// Start the note with what's piped in
+        #[arg(long)]
+        stdin: bool,
+    },
+    /// Open a note in the editor; the path may be relative to the vault and leave off the
+    /// extension
+    Open {
+        note: String,
+        /// Open it in the TUI's viewer instead
+        #[arg(long)]
+        view: bool,
+    },
+    /// Append text (or what's piped in) to today's daily note, or to a note of the vault,
+    /// creating it if need be
+    Capture(CaptureArgs),
+    /// Do the same in the LAIR that's running, when its control_socket setting is on
+    #[cfg(unix)]
+    Remote {
+        #[command(subcommand)]
+        command: RemoteCommand,
+    },
+    /// Print a note; the name may be any part of its path, or its letters in order (acpl for
+    /// projects/acme/plan.md), if only one note matches
+    Cat { note: String },
+    /// Print the vault's notes, or those in a folder of it
+    List {
+        folder: Option<String>,
+        /// Print an array of notes, each with its path, title, modified time and tags
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the lines of notes containing the query, ignoring case
+    Search {
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Print an array of notes, each with its path, title, modified time, tags and the
+        /// matching lines
+        #[arg(long)]
+        json: bool,
+    },
+    /// Check the vault for broken links, empty notes, attachments no note mentions and empty
+    /// date folders
+    Doctor {
+        /// Remove the empty notes and folders and move the attachments into the archive
+        #[arg(long)]
+        fix: bool,
+    },
+    /// The same as doctor --fix, to run from cron
+    Gc,
+    /// Serve a REST API for the vault on localhost, for browser extensions and shortcuts;
+    /// clients need the api_token setting
+    Serve {
+        /// The port to listen on, api_port when not given
+        #[arg(long)]
+        port: Option<u16>,
+    },
+    /// Serve the vault's notes to AI assistants as Model Context Protocol tools, over stdin
+    /// and stdout
+    Mcp,
+    /// Render every note to an HTML page in a directory, in the same folders and with links
+    /// between notes kept, plus an index page
+    Export {
+        #[arg(long, value_name = "dir")]
+        html: PathBuf,
+    },
+    /// Print vault statistics (counts, words, activity per day, tags)
+    Stats {
+        #[arg(long, conflicts_with = "csv")]
+        json: bool,
+        #[arg(long)]
+        csv: bool,
+    },
+    /// Manage the note templates in .lair/templates
+    Templates {
+        #[command(subcommand)]
+        command: TemplatesCommand,
+    },
+    /// Open a lair://vault/path/to/note link in the TUI's viewer
+    OpenUri {
+        uri: String,
+        /// Open it in the editor instead
+        #[arg(long)]
+        edit: bool,
+    },
+    /// Make LAIR the desktop's handler for lair:// links
+    RegisterUri,
+}
+
+#[derive(Args)]
+struct CaptureArgs {
+    /// The note to append to, relative to the vault; today's daily note when not given
+    #[arg(long)]
+    note: Option<String>,
+    /// The text, what's piped in when none is given
+    text: Vec<String>,
+}
+
+#[cfg(unix)]
+#[derive(Subcommand)]
+enum RemoteCommand {
+    /// Open a note in the running LAIR's editor
+    Open {
+        note: String,
+        /// Open it in the viewer instead
+        #[arg(long)]
+        view: bool,
+    },
+    /// Append text to a note in the running LAIR
+    Capture(CaptureArgs),
+}
+
+#[derive(Subcommand)]
+enum TemplatesCommand {
+    /// List the note templates
+    List,
+    /// Add a templates pack from a git repository, or update it
+    Pull { url: String, name: Option<String> },
+    /// Update every templates pack that was pulled
+    Update,
+}
+
+/// The actions `--action` runs
+#[derive(Clone, Copy, ValueEnum)]
+enum ActionName {
+    /// Create today's daily note unless it exists, print its path
+    NewDaily,
+    /// Create a note in today's folder holding the clipboard's text, print its path
+    NewFromClipboard,
+    /// Tick the checkboxes of exported tasks completed since
+    Sync,
+    /// Commit the vault's changes with git
+    Backup,
+}
+
+/// What should happen once a subcommand has run
+pub enum Outcome {
+    Exit(i32),                          // Stop with this exit code
+    Open { note: PathBuf, edit: bool }, // Start the TUI on this note, in the viewer or editor
+}
+
+/// Run a command-line subcommand instead of the TUI; None when there's none to run
+pub fn run(cli: Cli) -> Result<Option<Outcome>, Box<dyn Error>> {
+    if let Some(name) = cli.action {
+        return action(name).map(|code| Some(Outcome::Exit(code)));
+    }
+    let Some(command) = cli.command else {
+        return Ok(None);
+    };
+    let code = match command {
+        Command::New { name, named, stdin } => new_note(name, named, stdin),
+        Command::Capture(args) => capture(&args),
+        #[cfg(unix)]
+        Command::Remote { command } => remote(command),
+        Command::Cat { note } => cat(&note),
+        Command::Open { note, view } => return open(&note, view).map(Some),
+        Command::List { folder, json } => list(folder.as_deref(), json),
+        Command::Search { query, json } => search(&query.join(" "), json),
+        Command::Doctor { fix } => doctor(fix),
+        Command::Gc => doctor(true),
+        Command::Serve { port } => serve(port),
+        Command::Mcp => crate::mcp::serve(&Settings::load()).map(|()| 0).map_err(Into::into),
+        Command::Export { html } => export_html(&html),
+        Command::Stats { json, csv } => stats(json, csv),
+        Command::Templates { command } => templates(command),
+        Command::OpenUri { uri, edit } => return open_uri(&uri, edit).map(Some),
+        Command::RegisterUri => register_uri(),
+    };
+    code.map(|code| Some(Outcome::Exit(code)))
+}
+
+/// `lair open-uri <uri> [--edit]`, what the desktop runs for a `lair://` link
+/// Notes edited in an external editor are opened straight away, without the TUI
+fn open_uri(uri: &str, edit: bool) -> Result<Outcome, Box<dyn Error>> {
+    let settings = Settings::load();
+    let Some(note) = crate::links::uri_path(&settings, uri).filter(|p| p.is_file()) else {
+        eprintln!("No note found for {}", uri);
+        return Ok(Outcome::Exit(1));
+    };
+    open_note(&settings, note, edit)
+}
+
+/// `lair open <note> [--view]`, for opening a note from a script or another terminal
+fn open(path: &str, view: bool) -> Result<Outcome, Box<dyn Error>> {
+    let settings = Settings::load();
+    let Some(note) = find_note(&settings, path) else {
+        eprintln!("No note found for {}", path);
+        return Ok(Outcome::Exit(1));
+    };
+    open_note(&settings, note, !view)
+}
+
+/// The note a path given on the command line means: a file as given, else relative to the
+/// vault, with or without the note extension
+fn find_note(settings: &Settings, path: &str) -> Option<PathBuf> {
+    let vault = Path::new(&settings.notes_directory);
+    let extension = format!(".{}", settings.default_file_format);
+    [
+        PathBuf::from(path),
+        vault.join(path),
+        vault.join(format!("{}{}", path, extension)),
+    ]
+    .into_iter()
+    .find(|p| p.is_file())
+    .and_then(|p| std::path::absolute(p).ok())
+}
+
+/// The note a loosely typed name means: the note `find_note` finds, else the only note whose
+/// vault-relative path matches best, trying in turn a file name equal to it, a file name
+/// containing it, a path containing it, and a path holding its letters in order
+/// Err lists the notes when several match equally well
+fn match_note(settings: &Settings, query: &str) -> Result<PathBuf, String> {
+    if let Some(note) = find_note(settings, query) {
+        return Ok(note);
+    }
+    let vault = Path::new(&settings.notes_directory);
+    let query = query.to_lowercase();
+    let in_order = |text: &str| {
+        let mut letters = text.chars();
+        query.chars().all(|q| letters.any(|c| c == q))
+    };
+    let mut best: Option<(usize, Vec<PathBuf>)> = None;
+    for file in crate::vault::all_files(settings) {
+        if !crate::vault::is_note(&file, settings) {
+            continue;
+        }
+        let stem = file.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+        let relative = file
+            .strip_prefix(vault)
+            .unwrap_or(&file)
+            .with_extension("")
+            .to_string_lossy()
+            .to_lowercase();
+        let rank = if stem == query {
+            0
+        } else if stem.contains(&query) {
+            1
+        } else if relative.contains(&query) {
+            2
+        } else if in_order(&relative) {
+            3
+        } else {
+            continue;
+        };
+        match &mut best {
+            Some((best_rank, notes)) if *best_rank == rank => notes.push(file),
+            Some((best_rank, _)) if *best_rank < rank => {}
+            _ => best = Some((rank, vec![file])),
+        }
+    }
+    match best {
+        Some((_, notes)) if notes.len() == 1 => Ok(notes.into_iter().next().unwrap_or_default()),
+        Some((_, notes)) => {
+            let names: Vec<String> = notes
+                .iter()
+                .map(|n| n.strip_prefix(vault).unwrap_or(n).display().to_string())
+                .collect();
+            Err(format!("'{}' matches several notes:\n  {}", query, names.join("\n  ")))
+        }
+        None => Err(format!("No note matches '{}'", query)),
+    }
+}
+
+/// `lair cat <note>`: the note's text on stdout, to pipe into grep, bat and the like
+fn cat(query: &str) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    match match_note(&settings, query) {
+        Ok(note) => {
+            std::io::stdout().write_all(&crate::storage::open(&settings).read(&note)?)?;
+            Ok(0)
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            Ok(1)
+        }
+    }
+}
+
+/// Open a note in the viewer or editor: external editors are run straight away, without the
+/// TUI, with the post-edit hook and commands after
+fn open_note(settings: &Settings, note: PathBuf, edit: bool) -> Result<Outcome, Box<dyn Error>> {
+    let editor = crate::editor::command_for(settings, &note);
+    if !edit || crate::editor::is_internal(editor) {
+        return Ok(Outcome::Open { note, edit });
+    }
+    let (editor, fallback) = match crate::editor::resolve(editor) {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            eprintln!("{}", message);
+            return Ok(Outcome::Exit(1));
+        }
+    };
+    if let Some(fallback) = fallback {
+        eprintln!("{}", fallback);
+    }
+    crate::editor::external_command(&editor, &note, None)?.status()?;
+    if let Err(message) = hooks::run_post_edit(settings, &note) {
+        eprintln!("{}", message);
+    }
+    Ok(Outcome::Exit(0))
+}
+
+/// `lair register-uri`: a desktop entry that hands `lair://` links to `lair open-uri` in a
+/// terminal, set as the default handler with xdg-mime (freedesktop desktops)
+fn register_uri() -> Result<i32, Box<dyn Error>> {
+    let Some(applications) = dirs::data_dir().map(|d| d.join("applications")) else {
+        eprintln!("No data directory to put the desktop entry in");
+        return Ok(1);
+    };
+    std::fs::create_dir_all(&applications)?;
+    let entry = applications.join("lair-uri.desktop");
+    let exe = std::env::current_exe()?;
+    std::fs::write(
+        &entry,
+        format!(
+            "[Desktop Entry]\nType=Application\nName=LAIR\nExec=\"{}\" open-uri %u\nTerminal=true\nNoDisplay=true\nMimeType=x-scheme-handler/lair;\n",
+            exe.display()
+        ),
+    )?;
+    println!("Wrote {}", entry.display());
+    let Some(xdg_mime) = crate::tools::find_in_path("xdg-mime") else {
+        eprintln!("xdg-mime not found; set lair-uri.desktop as the handler for x-scheme-handler/lair by hand");
+        return Ok(1);
+    };
+    let status = std::process::Command::new(xdg_mime)
+        .args(["default", "lair-uri.desktop", "x-scheme-handler/lair"])
+        .status()?;
+    if !status.success() {
+        eprintln!("xdg-mime failed: {}", status);
+        return Ok(1);
+    }
+    println!("lair:// links now open in LAIR");
+    Ok(0)
+}
+
+/// `lair --action <name>`, for cron jobs and desktop shortcuts
+fn action(name: ActionName) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let vault = PathBuf::from(&settings.notes_directory);
+    let result = match name {
+        ActionName::NewDaily => new_daily(&settings, &vault),
+        ActionName::NewFromClipboard => new_from_clipboard(&settings),
+        ActionName::Sync => crate::tasks::sync_completed(&settings, &Tools::detect())
+            .map(|n| format!("Ticked {} completed task(s)", n))
+            .map_err(|e| e.to_string()),
+        ActionName::Backup => {
+            let tools = Tools::detect();
+            match tools.path(Tool::Git) {
+                Some(git) => actions::commit_vault(git, &vault).map_err(|e| e.to_string()),
+                None => Err("git not found on PATH".to_string()),
+            }
+        }
+    };
+    match result {
+        Ok(message) => {
+            println!("{}", message);
+            Ok(0)
+        }
+        Err(message) => {
+            let name = name.to_possible_value().map(|v| v.get_name().to_string());
+            eprintln!("{} failed: {}", name.unwrap_or_default(), message);
+            Ok(1)
+        }
+    }
+}
+
+/// Today's `daily` note in the date folder, created (and handed to the post-create hook)
+/// only if it doesn't exist yet
+fn new_daily(settings: &Settings, vault: &Path) -> Result<String, String> {
+    let (path, created) =
+        crate::calendar::create_daily(settings, chrono::Local::now().date_naive())
+            .map_err(|e| e.to_string())?;
+    if created {
+        post_create(vault, &path)?;
+    }
+    Ok(path.display().to_string())
+}
+
+/// A new note in today's date folder holding the clipboard's text
+fn new_from_clipboard(settings: &Settings) -> Result<String, String> {
+    let tools = Tools::detect();
+    let paste = tools
+        .path(Tool::Paste)
+        .ok_or_else(|| format!("{} not found on PATH", Tool::Paste.label()))?;
+    let (path, problem) = actions::note_from_clipboard(settings, paste, None)?;
+    if let Some(message) = problem {
+        eprintln!("{}", message);
+    }
+    Ok(path.display().to_string())
+}
+
+/// `lair new [name] [--stdin]`: a note in today's date folder, named and filled in as the TUI's
+/// new note popup does without a template, and handed to the creation hooks
+/// With `--stdin` the note starts with what's piped in, e.g. `make | lair new --stdin --name build-log`
+fn new_note(mut words: Vec<String>, named: Option<String>, stdin: bool) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    words.extend(named);
+    let name = words.join(" ");
+    let content = if stdin {
+        Some(std::io::read_to_string(std::io::stdin())?)
+    } else {
+        None
+    };
+    match create_note(&settings, &name, None, content.as_deref()) {
+        Ok((path, problem)) => {
+            if let Some(message) = problem {
+                eprintln!("{}", message);
+            }
+            println!("{}", path.display());
+            Ok(0)
+        }
+        Err(message) => {
+            eprintln!("{}", message);
+            Ok(1)
+        }
+    }
+}
+
+/// Create a note in `folder`, else today's folder, as `lair new` does, named as in the TUI and
+/// holding `content` if given, else filled from the template and frontmatter settings
+/// Err when it can't be created: the name is taken, the pre-create hook refused or writing
+/// failed; a note that was created comes with what went wrong filling it in, if anything
+pub fn create_note(
+    settings: &Settings,
+    name: &str,
+    folder: Option<&PathBuf>,
+    content: Option<&str>,
+) -> Result<(PathBuf, Option<String>), String> {
+    let vault = PathBuf::from(&settings.notes_directory);
+    let file_name = crate::note::new_note_file_name(settings, name);
+    if !crate::vault::is_file_name(&file_name) {
+        return Err(format!(
+            "{} isn't a plain file name: no / or .., and no leading .",
+            file_name
+        ));
+    }
+    let folder = crate::note::note_directory(&settings.notes_directory, folder);
+    let path = folder.join(&file_name);
+    if path.exists() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    match hooks::run_create_hook(&vault, HookEvent::PreCreate, &path, None) {
+        Ok(Some(outcome)) if !outcome.success => {
+            return Err(format!("pre-create hook failed: {}", outcome.message));
+        }
+        Err(e) => return Err(format!("pre-create hook error: {}", e)),
+        _ => {}
+    }
+    let path = crate::note::create_note_file(
+        crate::storage::open(settings).as_ref(),
+        &settings.notes_directory,
+        Some(&file_name),
+        &settings.default_file_format,
+        Some(&folder),
+        content,
+    )
+    .map_err(|e| format!("Unable to create {}: {}", path.display(), e))?;
+    let problem = crate::folders::apply_default_tags(settings, &path)
+        .map_err(|e| format!("Unable to add the folder's tags: {}", e))
+        .and_then(|()| crate::note::fill_new_note(settings, &path, name, &folder, content.is_some()))
+        .and_then(|()| post_create(&vault, &path))
+        .err();
+    Ok((path, problem))
+}
+
+/// `lair capture [--note <note>] [text]`: add text to a note without opening anything, for
+/// global hotkeys and other programs; with no text, what's piped in is added
+/// Text goes to today's daily note unless `--note` names another, relative to the vault, which
+/// is created if it doesn't exist; one line is timestamped when `append_timestamp` is set,
+/// several are added as a block
+fn capture(args: &CaptureArgs) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let vault = PathBuf::from(&settings.notes_directory);
+    let (note, text) = match capture_input(args)? {
+        Ok(input) => input,
+        Err(code) => return Ok(code),
+    };
+    let path = capture_path(&settings, note);
+    if crate::note::capture(&settings, &path, &text)?
+        && let Err(message) = post_create(&vault, &path)
+    {
+        eprintln!("{}", message);
+    }
+    if let Err(message) = hooks::run_post_edit(&settings, &path) {
+        eprintln!("{}", message);
+    }
+    println!("{}", path.display());
+    Ok(0)
+}
+
+/// `lair remote open|capture ...`: have the running TUI open a note or capture text, through
+/// its control socket; the answer is printed, and the exit status is 1 when it's an error or
+/// no LAIR is listening
+#[cfg(unix)]
+fn remote(command: RemoteCommand) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let request = match command {
+        RemoteCommand::Open { note: path, view } => {
+            let Some(note) = find_note(&settings, &path) else {
+                eprintln!("No note {} in the vault", path);
+                return Ok(1);
+            };
+            Request::Open { note, edit: !view }
+        }
+        RemoteCommand::Capture(args) => {
+            let (note, text) = match capture_input(&args)? {
+                Ok(input) => input,
+                Err(code) => return Ok(code),
+            };
+            Request::Capture {
+                note: capture_path(&settings, note),
+                text,
+            }
+        }
+    };
+    match crate::remote::send(&request) {
+        Ok(Ok(message)) => {
+            println!("{}", message);
+            Ok(0)
+        }
+        Ok(Err(message)) => {
+            eprintln!("{}", message);
+            Ok(1)
+        }
+        Err(e) => {
+            eprintln!(
+                "No LAIR is listening at {} ({}); is control_socket on?",
+                crate::remote::socket_path().display(),
+                e
+            );
+            Ok(1)
+        }
+    }
+}
+
+/// The note and text `lair capture [--note <note>] [text]` is given, the text being what's
+/// piped in when there's none in the arguments
+/// The inner Err is the exit code, once what's wrong has been said
+fn capture_input(args: &CaptureArgs) -> std::io::Result<Result<(Option<&str>, String), i32>> {
+    let note = args.note.as_deref();
+    let text = if args.text.is_empty() && !std::io::stdin().is_terminal() {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        args.text.join(" ")
+    };
+    let text = text.trim_end();
+    if text.trim().is_empty() {
+        eprintln!("Nothing to capture");
+        return Ok(Err(1));
+    }
+    Ok(Ok((note, text.to_string())))
+}
+
+/// The note `lair capture` appends to: the one `--note` names, which may not exist yet, or
+/// today's daily note
+pub fn capture_path(settings: &Settings, note: Option<&str>) -> PathBuf {
+    let Some(note) = note else {
+        return crate::calendar::daily_note(settings, chrono::Local::now().date_naive());
+    };
+    find_note(settings, note).unwrap_or_else(|| {
+        let mut path = Path::new(&settings.notes_directory).join(note);
+        if path.extension().is_none() {
+            path.set_extension(&settings.default_file_format);
+        }
+        path
+    })
+}
+
+/// `lair list [folder]`: the notes of the vault, or of a folder in it, one vault-relative path
+/// per line
+fn list(folder: Option<&str>, json: bool) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let vault = PathBuf::from(&settings.notes_directory);
+    let dir = folder.map_or(vault.clone(), |folder| vault.join(folder));
+    let storage = crate::storage::open(&settings);
+    if !storage.is_dir(&dir) {
+        eprintln!("No folder {} in the vault", dir.display());
+        return Ok(1);
+    }
+    let mut records = Vec::new();
+    for file in crate::vault::files_in(storage.as_ref(), &settings, &dir) {
+        if !crate::vault::is_note(&file, &settings) {
+            continue;
+        }
+        if json {
+            records.push(note_record(storage.as_ref(), &vault, &file));
+        } else {
+            println!("{}", file.strip_prefix(&vault).unwrap_or(&file).display());
+        }
+    }
+    if json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    }
+    Ok(0)
+}
+
+/// A note as `--json` output describes it: its vault-relative path, title, modified time
+/// (RFC 3339, local time) and tags
+pub fn note_record(storage: &dyn VaultStorage, vault: &Path, note: &Path) -> serde_json::Value {
+    let content = storage.read_to_string(note).unwrap_or_default();
+    let modified = storage
+        .modified(note)
+        .ok()
+        .map(|m| chrono::DateTime::<chrono::Local>::from(m).to_rfc3339());
+    serde_json::json!({
+        "path": note.strip_prefix(vault).unwrap_or(note).to_string_lossy(),
+        "title": crate::links::title(storage, note),
+        "modified": modified,
+        "tags": crate::note::tags(&content),
+    })
+}
+
+/// `lair search <query>`: every line of a note containing the query, ignoring case, as
+/// "path:line:text" with the path relative to the vault; exits with 1 when nothing matches
+/// With `--json`, each note that matches is a record with its `matches`, line and text
+fn search(query: &str, json: bool) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let vault = PathBuf::from(&settings.notes_directory);
+    let found = search_notes(&settings, query);
+    if json {
+        let storage = crate::storage::open(&settings);
+        let records: Vec<serde_json::Value> = found
+            .iter()
+            .map(|(note, matches)| search_record(storage.as_ref(), &vault, note, matches))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&records)?);
+    } else {
+        for (note, matches) in &found {
+            let relative = note.strip_prefix(&vault).unwrap_or(note);
+            for (line, text) in matches {
+                println!("{}:{}:{}", relative.display(), line, text);
+            }
+        }
+    }
+    Ok(if found.is_empty() { 1 } else { 0 })
+}
+
+/// The notes with lines containing the query, ignoring case, and those lines, numbered from 1
+pub fn search_notes(settings: &Settings, query: &str) -> Vec<(PathBuf, Vec<(usize, String)>)> {
+    let query = query.to_lowercase();
+    let storage = crate::storage::open(settings);
+    let vault = Path::new(&settings.notes_directory);
+    let mut found = Vec::new();
+    for file in crate::vault::files_in(storage.as_ref(), settings, vault) {
+        if !crate::vault::is_note(&file, settings) {
+            continue;
+        }
+        let Ok(content) = storage.read_to_string(&file) else {
+            continue;
+        };
+        let matches: Vec<(usize, String)> = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(&query))
+            .map(|(n, line)| (n + 1, line.to_string()))
+            .collect();
+        if !matches.is_empty() {
+            found.push((file, matches));
+        }
+    }
+    found
+}
+
+/// A note's record with the lines a search found in it as its `matches`
+pub fn search_record(
+    storage: &dyn VaultStorage,
+    vault: &Path,
+    note: &Path,
+    matches: &[(usize, String)],
+) -> serde_json::Value {
+    let mut record = note_record(storage, vault, note);
+    record["matches"] = matches
+        .iter()
+        .map(|(line, text)| serde_json::json!({ "line": line, "text": text }))
+        .collect();
+    record
+}
+
+/// Hand a note the CLI created to the post-create hook
+pub fn post_create(vault: &Path, note: &Path) -> Result<(), String> {
+    match hooks::run_hook(vault, HookEvent::PostCreate, note) {
+        Ok(Some(outcome)) if !outcome.success => {
+            Err(format!("post-create hook failed: {}", outcome.message))
+        }
+        Err(e) => Err(format!("post-create hook error: {}", e)),
+        _ => Ok(()),
+    }
+}
+
+/// `lair templates list|pull|update`, for sharing templates through a git repository
+fn templates(command: TemplatesCommand) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let results = match command {
+        TemplatesCommand::List => {
+            for name in crate::templates::list(&settings) {
+                println!("{}", name);
+            }
+            return Ok(0);
+        }
+        TemplatesCommand::Pull { url, name } => {
+            let tools = Tools::detect();
+            let Some(git) = tools.path(Tool::Git) else {
+                eprintln!("git not found on PATH");
+                return Ok(1);
+            };
+            vec![crate::templates::pull(git, &settings, &url, name.as_deref())]
+        }
+        TemplatesCommand::Update => {
+            let tools = Tools::detect();
+            let Some(git) = tools.path(Tool::Git) else {
+                eprintln!("git not found on PATH");
+                return Ok(1);
+            };
+            crate::templates::update_all(git, &settings)
+        }
+    };
+    let mut code = 0;
+    for result in results {
+        match result {
+            Ok(message) => println!("{}", message),
+            Err(message) => {
+                eprintln!("{}", message);
+                code = 1;
+            }
+        }
+    }
+    Ok(code)
+}
+
+/// `lair doctor [--fix]` and `lair gc`: one line per finding, then with `fix` what was done
+/// about them, which is also logged to `.lair/logs`
+/// While dry-run is on the fixes are only listed, as in the TUI's bulk operations
+/// Exits with 1 when something is left to see to: a finding with no fix, or a fix that failed
+fn doctor(fix: bool) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let vault = PathBuf::from(&settings.notes_directory);
+    let findings = crate::doctor::check(&settings);
+    for finding in &findings {
+        let relative = finding.path.strip_prefix(&vault).unwrap_or(&finding.path);
+        println!("{}: {}", relative.display(), finding.message);
+    }
+    let unfixable = findings.iter().filter(|f| f.fix.is_none()).count();
+    if !fix {
+        return Ok(if findings.is_empty() { 0 } else { 1 });
+    }
+    let operations: Vec<Operation> = findings.into_iter().filter_map(|f| f.fix).collect();
+    if operations.is_empty() {
+        return Ok(if unfixable == 0 { 0 } else { 1 });
+    }
+    if settings.dry_run {
+        for operation in &operations {
+            println!("would {}", operation.describe(&vault));
+        }
+        println!("Dry run is on: nothing was changed (set dry_run to false in settings.json)");
+        return Ok(1);
+    }
+    let mut plan = Plan::new("Vault doctor", vault.clone(), operations);
+    let (_, failed) = plan.apply();
+    for line in plan.report_lines() {
+        println!("{}", line);
+    }
+    if let Err(e) = plan.export_log(&vault) {
+        eprintln!("Could not write the log: {}", e);
+    }
+    Ok(if unfixable + failed == 0 { 0 } else { 1 })
+}
+
+/// `lair serve [--port <port>]`: the vault's REST API, until stopped
+fn serve(port: Option<u16>) -> Result<i32, Box<dyn Error>> {
+    let settings = Settings::load();
+    let port = port.unwrap_or(settings.api_port);
+    if settings.api_token.trim().is_empty() {
+        eprintln!("Set api_token in settings.json first; clients send it to be let in");
+        return Ok(1);
+    }
+    println!(
+        "Serving {} on http://127.0.0.1:{}",
+        settings.notes_directory, port
+    );
+    crate::server::serve(&settings, port)?;
+    Ok(0)
+}
+
+/// `lair export --html <dir>`: the vault as a static site, to publish or browse read-only
+fn export_html(dir: &Path) -> Result<i32, Box<dyn Error>> {
+    match crate::site::export(&Settings::load(), dir) {
+        Ok(count) => {
+            println!("Exported {} notes to {}", count, dir.display());
+            Ok(0)
+        }
+        Err(e) => {
+            eprintln!("Unable to export to {}: {}", dir.display(), e);
+            Ok(1)
+        }
+    }
+}
+
+/// `lair stats [--json|--csv]`
+fn stats(json: bool, csv: bool) -> Result<i32, Box<dyn Error>> {
+    let stats = crate::stats::compute(&Settings::load());
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats.to_json())?);
+    } else if csv {
+        print!("{}", stats.to_csv());
+    } else {
+        println!("notes: {}", stats.notes);
+        println!("other files: {}", stats.other_files);
+        println!("words: {}", stats.words);
+        println!("active days: {}", stats.activity.len());
+        println!("tags: {}", stats.tags.len());
+    }
+    Ok(0)
+}
@@ -0,0 +1,93 @@
+use pulldown_cmark::Alignment;
+use ratatui::text::Text;
+use std::path::Path;
+
+/// Cells wider than this are cut short, so one long field doesn't push the rest off screen
+const MAX_CELL_WIDTH: usize = 40;
+
+/// The field separator of a `.csv` or `.tsv` file, None for any other file
+pub fn delimiter(path: &Path) -> Option<char> {
+    let ext = path.extension()?.to_str()?;
+    if ext.eq_ignore_ascii_case("csv") {
+        Some(',')
+    } else if ext.eq_ignore_ascii_case("tsv") {
+        Some('\t')
+    } else {
+        None
+    }
+}
+
+/// Split delimited text into rows of fields
+/// Fields in double quotes may hold the delimiter and line breaks, with `""` for a quote
+fn parse(content: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                // A line break inside a field would break the grid
+                '\r' | '\n' => field.push(' '),
+                c => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+fn truncate(cell: String) -> String {
+    if cell.chars().count() <= MAX_CELL_WIDTH {
+        return cell;
+    }
+    let mut cut: String = cell.chars().take(MAX_CELL_WIDTH - 1).collect();
+    cut.push('…');
+    cut
+}
+
+fn is_number(cell: &str) -> bool {
+    let cell = cell.trim();
+    !cell.is_empty() && cell.replace(',', "").parse::<f64>().is_ok()
+}
+
+/// Render delimited text as a grid, the first row as its header
+/// Columns of numbers (below the header) are right-aligned
+pub fn render(content: &str, delimiter: char) -> Text<'static> {
+    let rows: Vec<Vec<String>> = parse(content, delimiter)
+        .into_iter()
+        .map(|row| row.into_iter().map(truncate).collect())
+        .collect();
+    let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+    let alignments = (0..columns)
+        .map(|c| {
+            let mut cells = rows.iter().skip(1).filter_map(|row| row.get(c)).peekable();
+            if cells.peek().is_some() && cells.all(|cell| cell.trim().is_empty() || is_number(cell)) {
+                Alignment::Right
+            } else {
+                Alignment::None
+            }
+        })
+        .collect();
+    crate::markdown::grid(rows, alignments)
+}
@@ -0,0 +1,68 @@
+use zbus::blocking::{Connection, connection};
+use zbus::{fdo, interface};
+
+use crate::remote::{Request, Requests};
+use crate::settings::Settings;
+
+/// The name the running LAIR takes on the session bus, and where its object is
+pub const BUS_NAME: &str = "org.lair.Lair";
+pub const OBJECT_PATH: &str = "/org/lair/Lair";
+
+/// The `org.lair.Capture` interface, whose calls become the same requests `lair remote` makes
+struct Capture {
+    settings: Settings,
+    requests: Requests,
+}
+
+impl Capture {
+    fn ask(&self, request: Request) -> fdo::Result<String> {
+        match crate::remote::ask(&self.requests, request) {
+            Some(Ok(message)) => Ok(message),
+            Some(Err(message)) => Err(fdo::Error::Failed(message)),
+            None => Err(fdo::Error::Failed("LAIR is closing".to_string())),
+        }
+    }
+}
+
+#[interface(name = "org.lair.Capture")]
+impl Capture {
+    /// Append text to a note of the vault, created if need be, or to today's daily note when
+    /// `note` is empty; answers with the note's path
+    /// `note` is relative to the vault, and may not leave it
+    fn capture(&self, note: &str, text: &str) -> fdo::Result<String> {
+        let text = text.trim_end();
+        if text.trim().is_empty() {
+            return Err(fdo::Error::InvalidArgs("Nothing to capture".to_string()));
+        }
+        let note = match note.trim() {
+            "" => crate::cli::capture_path(&self.settings, None),
+            note => crate::mcp::named_note(&self.settings, note).map_err(fdo::Error::InvalidArgs)?,
+        };
+        self.ask(Request::Capture {
+            note,
+            text: text.to_string(),
+        })
+    }
+
+    /// Open a note of the vault, given relative to it, in the editor, or in the viewer unless
+    /// `edit` is set
+    fn open(&self, note: &str, edit: bool) -> fdo::Result<String> {
+        let Some(note) = crate::server::note_path(&self.settings, note) else {
+            return Err(fdo::Error::FileNotFound(format!("No note {} in the vault", note)));
+        };
+        self.ask(Request::Open { note, edit })
+    }
+}
+
+/// Offer the interface on the session bus until the connection is dropped
+/// Err when there's no session bus, or another LAIR has the name
+pub fn start(settings: &Settings, requests: Requests) -> zbus::Result<Connection> {
+    let capture = Capture {
+        settings: settings.clone(),
+        requests,
+    };
+    connection::Builder::session()?
+        .name(BUS_NAME)?
+        .serve_at(OBJECT_PATH, capture)?
+        .build()
+}
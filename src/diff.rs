@@ -0,0 +1,282 @@
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Text};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Past this many line pairs the changed middle is shown as one replaced block instead of
+/// being aligned line by line, to keep the comparison table small
+const MAX_ALIGNED_PAIRS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffKind {
+    Same,
+    Removed, // Only in the left note
+    Added,   // Only in the right note
+}
+
+#[derive(Debug, Clone)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+/// Line-by-line differences turning `old` into `new` (longest common subsequence)
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let line = |kind, text: &str| DiffLine {
+        kind,
+        text: text.to_string(),
+    };
+
+    // The unchanged start and end need no alignment
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut lines: Vec<DiffLine> = old[..prefix].iter().map(|t| line(DiffKind::Same, t)).collect();
+    if a.len().saturating_mul(b.len()) > MAX_ALIGNED_PAIRS {
+        lines.extend(a.iter().map(|t| line(DiffKind::Removed, t)));
+        lines.extend(b.iter().map(|t| line(DiffKind::Added, t)));
+    } else {
+        // common[i][j]: length of the longest common subsequence of a[i..] and b[j..]
+        let width = b.len() + 1;
+        let mut common = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                common[i * width + j] = if a[i] == b[j] {
+                    common[(i + 1) * width + j + 1] + 1
+                } else {
+                    common[(i + 1) * width + j].max(common[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(line(DiffKind::Same, a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len()
+                || (i < a.len() && common[(i + 1) * width + j] >= common[i * width + j + 1])
+            {
+                lines.push(line(DiffKind::Removed, a[i]));
+                i += 1;
+            } else {
+                lines.push(line(DiffKind::Added, b[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|t| line(DiffKind::Same, t)));
+    lines
+}
+
+/// One side of a comparison: a note as it is on disk, or as a git commit recorded it
+#[derive(Debug, Clone)]
+pub struct Version {
+    pub path: PathBuf,
+    pub revision: Option<String>, // Short commit hash, None for the note on disk
+    text: String,
+}
+
+impl Version {
+    pub fn file(path: &Path) -> io::Result<Version> {
+        Ok(Version {
+            path: path.to_path_buf(),
+            revision: None,
+            text: fs::read_to_string(path)?,
+        })
+    }
+
+    pub fn at_revision(path: &Path, revision: &str, text: String) -> Version {
+        Version {
+            path: path.to_path_buf(),
+            revision: Some(revision.to_string()),
+            text,
+        }
+    }
+
+    /// A file holding this version, for an external diff tool: the note itself when it's on
+    /// disk, else a copy of the revision in the temporary folder, `plan@1a2b3c4.md`
+    pub fn to_file(&self) -> io::Result<PathBuf> {
+        let Some(revision) = &self.revision else {
+            return Ok(self.path.clone());
+        };
+        let stem = self.path.file_stem().unwrap_or_default().to_string_lossy();
+        let mut name = format!("{}@{}", stem, revision);
+        if let Some(extension) = self.path.extension() {
+            name = format!("{}.{}", name, extension.to_string_lossy());
+        }
+        let copy = std::env::temp_dir().join(name);
+        fs::write(&copy, &self.text)?;
+        Ok(copy)
+    }
+}
+
+/// Two notes compared in the diff viewer, the left one taken as the old version
+pub struct Comparison {
+    pub left: Version,
+    pub right: Version,
+    pub lines: Vec<DiffLine>,
+    pub scroll: usize,      // First visible row
+    pub page_size: usize,   // Visible rows on screen, updated on render
+    pub side_by_side: bool, // Notes in two columns instead of one interleaved list
+}
+
+impl Comparison {
+    pub fn open(left: &Path, right: &Path) -> io::Result<Comparison> {
+        Ok(Comparison::of(Version::file(left)?, Version::file(right)?))
+    }
+
+    pub fn of(left: Version, right: Version) -> Comparison {
+        Comparison {
+            lines: diff_lines(&left.text, &right.text),
+            left,
+            right,
+            scroll: 0,
+            page_size: 10,
+            side_by_side: false,
+        }
+    }
+
+    /// Compare the other way round, re-reading sides that are files on disk
+    pub fn swap(&mut self) -> io::Result<()> {
+        let reread = |version: &Version| match version.revision {
+            Some(_) => Ok(version.clone()),
+            None => Version::file(&version.path),
+        };
+        let side_by_side = self.side_by_side;
+        *self = Comparison::of(reread(&self.right)?, reread(&self.left)?);
+        self.side_by_side = side_by_side;
+        Ok(())
+    }
+
+    /// Switch between the unified and side-by-side layouts
+    pub fn toggle_layout(&mut self) {
+        self.side_by_side = !self.side_by_side;
+        self.scroll_by(0);
+    }
+
+    /// Rows of the side-by-side layout, pairing a left line with a right one
+    /// A run of removed lines sits beside the added lines that follow it, so replaced
+    /// lines line up with their replacements
+    fn side_rows(&self) -> Vec<(Option<&DiffLine>, Option<&DiffLine>)> {
+        let mut rows = Vec::new();
+        let mut i = 0;
+        while i < self.lines.len() {
+            if self.lines[i].kind == DiffKind::Same {
+                rows.push((Some(&self.lines[i]), Some(&self.lines[i])));
+                i += 1;
+                continue;
+            }
+            let run = |from: usize, kind| {
+                self.lines[from..]
+                    .iter()
+                    .take_while(|l| l.kind == kind)
+                    .collect::<Vec<_>>()
+            };
+            let removed = run(i, DiffKind::Removed);
+            let added = run(i + removed.len(), DiffKind::Added);
+            i += removed.len() + added.len();
+            for row in 0..removed.len().max(added.len()) {
+                rows.push((removed.get(row).copied(), added.get(row).copied()));
+            }
+        }
+        rows
+    }
+
+    /// Whether each displayed row shows a change, in the current layout
+    fn changed_rows(&self) -> Vec<bool> {
+        if self.side_by_side {
+            self.side_rows()
+                .iter()
+                .map(|(left, _)| !left.is_some_and(|l| l.kind == DiffKind::Same))
+                .collect()
+        } else {
+            self.lines.iter().map(|l| l.kind != DiffKind::Same).collect()
+        }
+    }
+
+    /// Lines only in the left note and lines only in the right one
+    pub fn counts(&self) -> (usize, usize) {
+        let count = |kind| self.lines.iter().filter(|l| l.kind == kind).count();
+        (count(DiffKind::Removed), count(DiffKind::Added))
+    }
+
+    /// Scroll by `delta` rows, clamped so the last row stays reachable
+    /// isize::MIN / isize::MAX jump to the top / bottom
+    pub fn scroll_by(&mut self, delta: isize) {
+        let max = self.changed_rows().len().saturating_sub(self.page_size) as isize;
+        self.scroll = (self.scroll as isize).saturating_add(delta).clamp(0, max.max(0)) as usize;
+    }
+
+    /// Scroll to the start of the next (or previous) run of changed lines
+    /// Returns false when there is none left to scroll to in that direction
+    pub fn next_change(&mut self, forward: bool) -> bool {
+        let changed = self.changed_rows();
+        let starts_change = |i: usize| changed[i] && (i == 0 || !changed[i - 1]);
+        let found = if forward {
+            (self.scroll + 1..changed.len()).find(|&i| starts_change(i))
+        } else {
+            (0..self.scroll.min(changed.len())).rev().find(|&i| starts_change(i))
+        };
+        let Some(i) = found else {
+            return false;
+        };
+        let before = self.scroll;
+        self.scroll = i;
+        self.scroll_by(0);
+        self.scroll != before
+    }
+
+    /// The differences, with removed lines in red and added ones in green
+    pub fn text(&self) -> Text<'static> {
+        let lines: Vec<Line> = self
+            .lines
+            .iter()
+            .map(|line| match line.kind {
+                DiffKind::Same => Line::raw(format!("  {}", line.text)),
+                DiffKind::Removed => {
+                    Line::styled(format!("- {}", line.text), Style::default().fg(Color::Red))
+                }
+                DiffKind::Added => {
+                    Line::styled(format!("+ {}", line.text), Style::default().fg(Color::Green))
+                }
+            })
+            .collect();
+        Text::from(lines)
+    }
+
+    /// The left and right columns of the side-by-side layout, with changed lines
+    /// highlighted and blank filler where one side has nothing to match
+    pub fn side_texts(&self) -> (Text<'static>, Text<'static>) {
+        let cell = |line: Option<&DiffLine>| match line {
+            None => Line::raw(""),
+            Some(line) => match line.kind {
+                DiffKind::Same => Line::raw(line.text.clone()),
+                DiffKind::Removed => {
+                    Line::styled(line.text.clone(), Style::default().fg(Color::Red))
+                }
+                DiffKind::Added => {
+                    Line::styled(line.text.clone(), Style::default().fg(Color::Green))
+                }
+            },
+        };
+        let (left, right): (Vec<Line>, Vec<Line>) = self
+            .side_rows()
+            .into_iter()
+            .map(|(left, right)| (cell(left), cell(right)))
+            .unzip();
+        (Text::from(left), Text::from(right))
+    }
+}
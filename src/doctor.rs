@@ -0,0 +1,148 @@
+use chrono::NaiveDate;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::lint::LintSettings;
+use crate::plan::Operation;
+use crate::settings::Settings;
+
+/// Notes changed more recently than this aren't called empty, as one may be open in an editor
+const EMPTY_NOTE_GRACE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Files the doctor treats as attachments: images, documents, audio and video
+/// Anything else, such as a bibliography or a tool's config, is none of its business
+const ATTACHMENT_EXTENSIONS: [&str; 22] = [
+    "png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "tiff", "heic", "pdf", "epub", "docx",
+    "xlsx", "pptx", "odt", "mp3", "m4a", "wav", "ogg", "flac", "mp4", "webm",
+];
+
+/// Something wrong with the vault as a whole, and the operation that fixes it if one can
+pub struct Finding {
+    pub path: PathBuf,
+    pub message: String,
+    pub fix: Option<Operation>,
+}
+
+/// Check the vault's health: broken links, empty notes, attachments no note mentions, and date
+/// folders with nothing in them
+/// Hidden files and folders, the archive and the bibliography are left alone
+/// Empty notes are removed, orphaned attachments moved into the archive and empty date folders
+/// removed by the fixes; broken links are only reported
+pub fn check(settings: &Settings) -> Vec<Finding> {
+    let vault = Path::new(&settings.notes_directory);
+    let archive = crate::archive::archive_dir(settings);
+    let files = crate::vault::all_files(settings);
+    let bibliography = crate::citations::bibliography_path(settings);
+    let (notes, others): (Vec<&PathBuf>, Vec<&PathBuf>) = files
+        .iter()
+        .filter(|f| !f.starts_with(&archive) && !crate::vault::is_hidden(settings, f))
+        .partition(|f| crate::vault::is_note(f, settings));
+    let attachments = others.into_iter().filter(|f| {
+        is_attachment(f) && bibliography.as_deref() != Some(f.as_path()) && !is_export(f, &notes)
+    });
+    let mut findings = Vec::new();
+
+    // Only the broken links check of the linter, whatever the lint settings say
+    let mut links_only = settings.clone();
+    links_only.lint = LintSettings {
+        missing_title: false,
+        missing_tags: false,
+        broken_links: true,
+        max_line_length: 0,
+        todo_days: 0,
+        on_edit: false,
+    };
+    for problem in crate::lint::lint_vault(&links_only) {
+        let message = match problem.line {
+            Some(line) => format!("line {}: {}", line, problem.message),
+            None => problem.message,
+        };
+        findings.push(Finding {
+            path: problem.path,
+            message,
+            fix: None,
+        });
+    }
+
+    let now = SystemTime::now();
+    let mut contents = Vec::new();
+    for note in &notes {
+        let Ok(content) = fs::read_to_string(note) else {
+            continue;
+        };
+        let settled = fs::metadata(note)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_some_and(|age| age > EMPTY_NOTE_GRACE);
+        if content.trim().is_empty() && settled {
+            findings.push(Finding {
+                path: note.to_path_buf(),
+                message: "Empty note".to_string(),
+                fix: Some(Operation::RemoveFile(note.to_path_buf())),
+            });
+        }
+        contents.push(content);
+    }
+
+    // An attachment counts as used when any note mentions its file name, linked or not
+    for attachment in attachments {
+        let Some(name) = attachment.file_name().map(|n| n.to_string_lossy().to_string()) else {
+            continue;
+        };
+        let encoded = name.replace(' ', "%20");
+        if contents.iter().any(|c| c.contains(&name) || c.contains(&encoded)) {
+            continue;
+        }
+        let relative = attachment.strip_prefix(vault).unwrap_or(attachment);
+        findings.push(Finding {
+            path: attachment.to_path_buf(),
+            message: "No note mentions this attachment".to_string(),
+            fix: Some(Operation::Move {
+                from: attachment.to_path_buf(),
+                to: archive.join(relative),
+            }),
+        });
+    }
+
+    for folder in empty_date_folders(vault) {
+        findings.push(Finding {
+            path: folder.clone(),
+            message: "Empty date folder".to_string(),
+            fix: Some(Operation::RemoveDir(folder)),
+        });
+    }
+    findings
+}
+
+fn is_attachment(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ATTACHMENT_EXTENSIONS.iter().any(|a| ext.eq_ignore_ascii_case(a)))
+}
+
+/// Whether a file is a note exported beside it, `plan.pdf` next to `plan.md`
+fn is_export(path: &Path, notes: &[&PathBuf]) -> bool {
+    let stem = path.with_extension("");
+    notes.iter().any(|note| note.with_extension("") == stem)
+}
+
+/// The `24-06-14` folders at the vault root with nothing in them
+fn empty_date_folders(vault: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(vault) else {
+        return Vec::new();
+    };
+    let mut folders: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| NaiveDate::parse_from_str(n, "%y-%m-%d").is_ok())
+                && fs::read_dir(path).is_ok_and(|mut entries| entries.next().is_none())
+        })
+        .collect();
+    folders.sort();
+    folders
+}
@@ -0,0 +1,360 @@
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// The `editor` setting that edits notes inside LAIR instead of starting a program
+pub const INTERNAL_EDITOR: &str = "internal";
+
+pub fn is_internal(editor: &str) -> bool {
+    editor.trim().eq_ignore_ascii_case(INTERNAL_EDITOR)
+}
+
+/// Editors tried, in order, when neither the setting nor $VISUAL / $EDITOR gives one that's
+/// installed
+const DEFAULT_EDITORS: [&str; 4] = ["nvim", "vim", "vi", "nano"];
+
+/// Whether an editor command's program can be started: a path to an executable, or a name
+/// found on PATH
+fn is_installed(command: &str) -> bool {
+    let Some(program) = command.split_whitespace().next() else {
+        return false;
+    };
+    if program.contains(std::path::MAIN_SEPARATOR) {
+        crate::hooks::is_executable(Path::new(program))
+    } else {
+        crate::tools::find_in_path(program).is_some()
+    }
+}
+
+/// The editor command to run for a configured one: that command if its program is installed,
+/// else $VISUAL, then $EDITOR, then the first of nvim, vim, vi and nano found
+/// Alongside it comes a note for the status bar when something other than the configured
+/// command is used; Err when nothing is installed at all
+pub fn resolve(configured: &str) -> Result<(String, Option<String>), String> {
+    let configured = configured.trim();
+    if is_installed(configured) {
+        return Ok((configured.to_string(), None));
+    }
+    let missing = if configured.is_empty() {
+        "No editor set".to_string()
+    } else {
+        format!("Editor '{}' not found", configured)
+    };
+    for variable in ["VISUAL", "EDITOR"] {
+        if let Ok(command) = std::env::var(variable)
+            && is_installed(&command)
+        {
+            let note = format!("{}, used ${} ({})", missing, variable, command.trim());
+            return Ok((command.trim().to_string(), Some(note)));
+        }
+    }
+    DEFAULT_EDITORS
+        .iter()
+        .find(|editor| is_installed(editor))
+        .map(|editor| (editor.to_string(), Some(format!("{}, used {}", missing, editor))))
+        .ok_or_else(|| format!("{} and no $VISUAL, $EDITOR, nvim, vim, vi or nano to fall back on", missing))
+}
+
+/// The editor command for a file: the `file_editors` entry for its extension (written with or
+/// without the dot, in any case), or `editor`
+pub fn command_for<'a>(settings: &'a Settings, path: &Path) -> &'a str {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+    extension
+        .and_then(|extension| {
+            settings.file_editors.iter().find_map(|(key, command)| {
+                (key.trim_start_matches('.').to_lowercase() == extension
+                    && !command.trim().is_empty())
+                .then_some(command.as_str())
+            })
+        })
+        .unwrap_or(&settings.editor)
+}
+
+/// Whether an editor command's program is one of `detached_editors`, which return straight
+/// away and are left running beside the TUI; matched by the program's name, not its path
+pub fn is_detached(settings: &Settings, command: &str) -> bool {
+    let Some(name) = command
+        .split_whitespace()
+        .next()
+        .and_then(|program| Path::new(program).file_name())
+    else {
+        return false;
+    };
+    settings
+        .detached_editors
+        .iter()
+        .any(|detached| Path::new(detached.trim()).file_name() == Some(name))
+}
+
+/// The command for an editor setting such as "code --wait {file}" or "nvim +{line} {file}"
+/// `{line}` is the line to start on, 1 when there's none; without `{file}` the path is
+/// appended, so a plain program name works as before
+pub fn external_command(template: &str, file: &Path, line: Option<usize>) -> io::Result<Command> {
+    let mut words = template.split_whitespace();
+    let program = words
+        .next()
+        .ok_or_else(|| io::Error::other("no editor configured"))?;
+    let mut command = Command::new(program);
+    let mut placed = false;
+    for word in words {
+        placed |= word.contains("{file}");
+        command.arg(
+            word.replace("{file}", &file.to_string_lossy())
+                .replace("{line}", &line.unwrap_or(1).to_string()),
+        );
+    }
+    if !placed {
+        command.arg(file);
+    }
+    Ok(command)
+}
+
+/// The first line that differs between two versions of a file, 1-based, as the place an edit
+/// was made; None when they're the same
+pub fn first_changed_line(before: &str, after: &str) -> Option<usize> {
+    if before == after {
+        return None;
+    }
+    let after: Vec<&str> = after.lines().collect();
+    let same = before
+        .lines()
+        .zip(&after)
+        .take_while(|(old, new)| old == *new)
+        .count();
+    // Lines removed from the end leave the cursor on the new last line
+    Some((same + 1).min(after.len().max(1)))
+}
+
+/// Tab inserts spaces, as a tab character would render at an unknown width
+const TAB: &str = "    ";
+
+/// Lines of text with a cursor, edited a key at a time
+/// Columns count characters, not bytes
+/// The text keeps the line endings it was loaded with, `\r\n` or `\n`, and whether it ended
+/// with one
+#[derive(Debug, Clone)]
+pub struct TextBuffer {
+    lines: Vec<String>,
+    line_ending: &'static str,
+    final_newline: bool,
+    pub row: usize,
+    pub col: usize,
+}
+
+impl TextBuffer {
+    pub fn new(text: &str) -> TextBuffer {
+        let mut lines: Vec<String> = text.lines().map(|l| l.to_string()).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+        TextBuffer {
+            lines,
+            line_ending: if text.contains("\r\n") { "\r\n" } else { "\n" },
+            // A new, empty note gets a final newline
+            final_newline: text.is_empty() || text.ends_with('\n'),
+            row: 0,
+            col: 0,
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// The text, with the line endings and final newline it was loaded with
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join(self.line_ending);
+        if self.final_newline {
+            text.push_str(self.line_ending);
+        }
+        text
+    }
+
+    /// The cursor's column on screen: the display width of the line before it, as wide
+    /// characters such as CJK take two columns
+    pub fn display_col(&self) -> usize {
+        let at = self.byte_index(self.row, self.col);
+        self.lines[self.row][..at].width()
+    }
+
+    fn line_len(&self, row: usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    /// Byte offset of character `col` in line `row`
+    fn byte_index(&self, row: usize, col: usize) -> usize {
+        self.lines[row]
+            .char_indices()
+            .nth(col)
+            .map(|(i, _)| i)
+            .unwrap_or(self.lines[row].len())
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        let at = self.byte_index(self.row, self.col);
+        self.lines[self.row].insert(at, c);
+        self.col += 1;
+    }
+
+    pub fn insert_tab(&mut self) {
+        let at = self.byte_index(self.row, self.col);
+        self.lines[self.row].insert_str(at, TAB);
+        self.col += TAB.len();
+    }
+
+    /// Split the line at the cursor, carrying its indentation over
+    pub fn insert_newline(&mut self) {
+        let at = self.byte_index(self.row, self.col);
+        let rest = self.lines[self.row].split_off(at);
+        let indent: String = self.lines[self.row]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .collect();
+        self.col = indent.chars().count();
+        self.lines.insert(self.row + 1, indent + &rest);
+        self.row += 1;
+    }
+
+    /// Delete the character before the cursor, joining lines at the start of one
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+            let at = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+            self.lines[self.row].push_str(&line);
+        }
+    }
+
+    /// Delete the character under the cursor, joining the next line at the end of one
+    pub fn delete(&mut self) {
+        if self.col < self.line_len(self.row) {
+            let at = self.byte_index(self.row, self.col);
+            self.lines[self.row].remove(at);
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if self.col > 0 {
+            self.col -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.col = self.line_len(self.row);
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if self.col < self.line_len(self.row) {
+            self.col += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.col = 0;
+        }
+    }
+
+    /// Move up or down by `delta` lines, keeping the column where the line is long enough
+    pub fn move_lines(&mut self, delta: isize) {
+        let last = self.lines.len() - 1;
+        self.row = self.row.saturating_add_signed(delta).min(last);
+        self.col = self.col.min(self.line_len(self.row));
+    }
+
+    pub fn home(&mut self) {
+        self.col = 0;
+    }
+
+    pub fn end(&mut self) {
+        self.col = self.line_len(self.row);
+    }
+
+    /// Put the cursor at the start of a 1-based line, or the last line if there are fewer
+    pub fn go_to_line(&mut self, line: usize) {
+        self.row = line.saturating_sub(1).min(self.lines.len() - 1);
+        self.col = 0;
+    }
+}
+
+/// `line` with its first `columns` screen columns scrolled off to the left
+/// A wide character cut in half by the edge shows as a space, so later columns stay in place
+pub fn skip_columns(line: &str, columns: usize) -> String {
+    let mut skipped = 0;
+    let mut rest = line;
+    while skipped < columns {
+        let mut chars = rest.chars();
+        let Some(c) = chars.next() else {
+            return String::new();
+        };
+        skipped += c.width().unwrap_or(0);
+        rest = chars.as_str();
+    }
+    " ".repeat(skipped - columns) + rest
+}
+
+/// A note open in the internal editor
+pub struct InternalEditor {
+    storage: Arc<dyn VaultStorage>,
+    pub path: PathBuf,
+    pub buffer: TextBuffer,
+    pub scroll: (usize, usize), // First visible line and column
+    pub page_size: usize,       // Visible lines on screen, updated on render
+    pub modified: bool,
+    pub confirm_discard: bool, // Esc was pressed with unsaved changes
+}
+
+impl InternalEditor {
+    pub fn open(storage: &Arc<dyn VaultStorage>, path: &Path) -> io::Result<InternalEditor> {
+        let text = match storage.read_to_string(path) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            result => result?,
+        };
+        Ok(InternalEditor {
+            storage: storage.clone(),
+            path: path.to_path_buf(),
+            buffer: TextBuffer::new(&text),
+            scroll: (0, 0),
+            page_size: 10,
+            modified: false,
+            confirm_discard: false,
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        self.storage.write(&self.path, self.buffer.text().as_bytes())?;
+        self.modified = false;
+        Ok(())
+    }
+
+    /// An edit was made through the buffer
+    pub fn changed(&mut self) {
+        self.modified = true;
+        self.confirm_discard = false;
+    }
+
+    /// Scroll just enough to keep the cursor inside a `width` x `height` view
+    pub fn follow_cursor(&mut self, width: usize, height: usize) {
+        let (row, col) = (self.buffer.row, self.buffer.display_col());
+        let (top, left) = &mut self.scroll;
+        if row < *top {
+            *top = row;
+        } else if height > 0 && row >= *top + height {
+            *top = row + 1 - height;
+        }
+        if col < *left {
+            *left = col;
+        } else if width > 0 && col >= *left + width {
+            *left = col + 1 - width;
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// Per-folder settings file, applying to the notes in the folder and its subfolders
+pub const FOLDER_CONFIG_FILE: &str = ".lair.toml";
+
+/// The contents of a `.lair.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct FolderConfig {
+    tags: Vec<String>, // Given to every note below the folder, as if written in each one
+    pinned: Vec<String>, // Names of entries listed first in this folder, in this order
+}
+
+/// A folder's own settings; a missing or malformed file gives none
+fn load(storage: &dyn VaultStorage, dir: &Path) -> FolderConfig {
+    storage
+        .read_to_string(&dir.join(FOLDER_CONFIG_FILE))
+        .ok()
+        .and_then(|content| toml::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// The names a folder's `.lair.toml` pins to the top of its listing, in order
+/// Unlike tags, pins apply to the folder's own entries only, not to its subfolders
+pub fn pinned(storage: &dyn VaultStorage, dir: &Path) -> Vec<String> {
+    load(storage, dir)
+        .pinned
+        .iter()
+        .map(|name| name.trim().trim_end_matches('/').to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// The default tags notes inherit from the `.lair.toml` files of their folders, from the
+/// vault root down; each folder is read once
+pub struct FolderTags {
+    vault: PathBuf,
+    storage: Arc<dyn VaultStorage>,
+    cache: HashMap<PathBuf, Vec<String>>,
+}
+
+impl FolderTags {
+    pub fn new(settings: &Settings) -> FolderTags {
+        FolderTags {
+            vault: PathBuf::from(&settings.notes_directory),
+            storage: crate::storage::open(settings),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Tags a note gets from its folders, lowercased and without `#`
+    pub fn for_note(&mut self, note: &Path) -> Vec<String> {
+        match note.parent() {
+            Some(dir) => self.for_dir(dir),
+            None => Vec::new(),
+        }
+    }
+
+    fn for_dir(&mut self, dir: &Path) -> Vec<String> {
+        if let Some(tags) = self.cache.get(dir) {
+            return tags.clone();
+        }
+        let mut tags = match dir.parent() {
+            Some(parent) if dir != self.vault && dir.starts_with(&self.vault) => {
+                self.for_dir(parent)
+            }
+            _ => Vec::new(),
+        };
+        if dir.starts_with(&self.vault) {
+            tags.extend(
+                load(self.storage.as_ref(), dir)
+                    .tags
+                    .iter()
+                    .map(|t| t.trim().trim_start_matches('#').to_lowercase())
+                    .filter(|t| !t.is_empty()),
+            );
+            tags.sort();
+            tags.dedup();
+        }
+        self.cache.insert(dir.to_path_buf(), tags.clone());
+        tags
+    }
+}
+
+/// Give a new, still empty note the default tags of its folders as frontmatter, so they stay
+/// with it if it's moved and show up when searching the vault
+pub fn apply_default_tags(settings: &Settings, note: &Path) -> io::Result<()> {
+    let mut folder_tags = FolderTags::new(settings);
+    if folder_tags.storage.len(note).is_ok_and(|len| len > 0) {
+        return Ok(());
+    }
+    let tags = folder_tags.for_note(note);
+    if tags.is_empty() {
+        return Ok(());
+    }
+    let frontmatter = format!("---\ntags: [{}]\n---\n\n", tags.join(", "));
+    folder_tags.storage.write(note, frontmatter.as_bytes())
+}
@@ -0,0 +1,141 @@
+/// Minimal YAML frontmatter support: a leading `---` block of `key: value` lines
+/// Lists may be written inline (`tags: [a, b]`) or as following `- item` lines
+#[derive(Debug, Clone, Default)]
+pub struct Frontmatter {
+    pub fields: Vec<(String, Vec<String>)>, // Field name and its value(s), in file order
+}
+
+impl Frontmatter {
+    /// First value of a field
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, values)| values.first())
+            .map(|v| v.as_str())
+    }
+
+    /// All values of a field (empty if missing)
+    pub fn list(&self, key: &str) -> Vec<String> {
+        self.fields
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, values)| values.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Strip surrounding quotes from a scalar value; `''` in single quotes is one `'`
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        value[1..value.len() - 1].replace("''", "'")
+    } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        value[1..value.len() - 1].to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// A value in single quotes, so YAML reads it back as the same text whatever it holds
+/// Line breaks become spaces, as a field is one line
+pub fn quote(value: &str) -> String {
+    let line = value.replace(['\r', '\n'], " ");
+    format!("'{}'", line.replace('\'', "''"))
+}
+
+/// Parse the frontmatter at the start of `content`, if there is any
+pub fn parse(content: &str) -> Option<Frontmatter> {
+    let rest = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))?;
+    let mut fields: Vec<(String, Vec<String>)> = Vec::new();
+
+    for line in rest.lines() {
+        let trimmed = line.trim_end();
+
+        if trimmed == "---" || trimmed == "..." {
+            return Some(Frontmatter { fields });
+        }
+
+        // "- item" continues the list of the previous key
+        if let Some(item) = trimmed.trim_start().strip_prefix("- ") {
+            if let Some((_, values)) = fields.last_mut() {
+                values.push(unquote(item));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        let values = if let Some(inner) = value.strip_prefix('[').and_then(|v| v.strip_suffix(']')) {
+            inner
+                .split(',')
+                .map(unquote)
+                .filter(|v| !v.is_empty())
+                .collect()
+        } else if value.is_empty() {
+            Vec::new()
+        } else {
+            vec![unquote(value)]
+        };
+        fields.push((key.trim().to_string(), values));
+    }
+
+    // No closing delimiter: not frontmatter after all
+    None
+}
+
+/// The note body after its frontmatter (the whole content when there is none)
+pub fn body(content: &str) -> &str {
+    let Some(rest) = content
+        .strip_prefix("---\n")
+        .or_else(|| content.strip_prefix("---\r\n"))
+    else {
+        return content;
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        offset += line.len();
+        let trimmed = line.trim_end();
+        if trimmed == "---" || trimmed == "..." {
+            return &rest[offset..];
+        }
+    }
+    content
+}
+
+/// `content` with the `key: value` lines of `fields` added to its frontmatter, starting a
+/// block when it has none; keys it already has keep their values, and `- item` lines go with
+/// the key above them
+pub fn add_fields(content: &str, fields: &str) -> String {
+    let existing = parse(content);
+    let mut added = String::new();
+    let mut keep = false;
+    for line in fields.lines().filter(|l| !l.trim().is_empty()) {
+        let continues = line.starts_with([' ', '\t']) || line.starts_with("- ");
+        if !continues {
+            let key = line.split_once(':').map_or(line, |(key, _)| key).trim();
+            keep = existing
+                .as_ref()
+                .is_none_or(|fm| !fm.fields.iter().any(|(k, _)| k.eq_ignore_ascii_case(key)));
+        }
+        if keep {
+            added.push_str(line);
+            added.push('\n');
+        }
+    }
+    if added.is_empty() {
+        return content.to_string();
+    }
+    if existing.is_none() {
+        return format!("---\n{}---\n\n{}", added, content);
+    }
+    let rest = body(content);
+    let head = &content[..content.len() - rest.len()];
+    // The closing `---` is the last line of the block
+    let close = head.trim_end().rfind('\n').map_or(0, |i| i + 1);
+    format!("{}{}{}{}", &head[..close], added, &head[close..], rest)
+}
@@ -0,0 +1,141 @@
+use chrono::{Days, NaiveDate};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// Date format of the per-day lines in a habit note
+const DAY_FORMAT: &str = "%Y-%m-%d";
+
+/// A habit note: a title line plus one `YYYY-MM-DD` line per day the habit was done
+/// Any other lines are left untouched
+pub struct Habit {
+    pub name: String,
+    pub path: PathBuf,
+    pub days: BTreeSet<NaiveDate>,
+}
+
+impl Habit {
+    fn parse(path: &Path, content: &str) -> Habit {
+        let stem = path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let name = content
+            .lines()
+            .find_map(|l| l.strip_prefix("# "))
+            .map(|title| title.trim().to_string())
+            .unwrap_or(stem);
+        let days = content.lines().filter_map(day_of_line).collect();
+        Habit {
+            name,
+            path: path.to_path_buf(),
+            days,
+        }
+    }
+
+    pub fn done_on(&self, day: NaiveDate) -> bool {
+        self.days.contains(&day)
+    }
+
+    /// Consecutive days done up to `today`; a streak still counts if today isn't done yet
+    pub fn current_streak(&self, today: NaiveDate) -> usize {
+        let mut day = if self.done_on(today) {
+            today
+        } else {
+            match today.pred_opt() {
+                Some(yesterday) => yesterday,
+                None => return 0,
+            }
+        };
+        let mut streak = 0;
+        while self.done_on(day) {
+            streak += 1;
+            match day.pred_opt() {
+                Some(previous) => day = previous,
+                None => break,
+            }
+        }
+        streak
+    }
+
+    pub fn longest_streak(&self) -> usize {
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for &day in &self.days {
+            run = match previous {
+                Some(p) if p.succ_opt() == Some(day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(day);
+        }
+        longest
+    }
+}
+
+/// The date of a `YYYY-MM-DD` day line (anything after the date is a comment)
+fn day_of_line(line: &str) -> Option<NaiveDate> {
+    let word = line.split_whitespace().next()?;
+    NaiveDate::parse_from_str(word, DAY_FORMAT).ok()
+}
+
+/// Folder holding the habit notes
+pub fn habits_dir(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(&settings.habits_directory)
+}
+
+/// Every habit note, sorted by name
+pub fn load_all(settings: &Settings) -> io::Result<Vec<Habit>> {
+    let dir = habits_dir(settings);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut habits: Vec<Habit> = fs::read_dir(&dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && crate::vault::is_note(p, settings))
+        .filter_map(|p| fs::read_to_string(&p).ok().map(|c| Habit::parse(&p, &c)))
+        .collect();
+    habits.sort_by_key(|h| h.name.to_lowercase());
+    Ok(habits)
+}
+
+/// Mark `day` done (or not done) in the habit's note, keeping the day lines sorted
+pub fn toggle_day(habit: &mut Habit, day: NaiveDate) -> io::Result<()> {
+    let content = fs::read_to_string(&habit.path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+
+    if habit.days.remove(&day) {
+        lines.retain(|l| day_of_line(l) != Some(day));
+    } else {
+        habit.days.insert(day);
+        // Insert before the first later day, or after the last day line
+        let position = lines
+            .iter()
+            .position(|l| day_of_line(l).is_some_and(|d| d > day))
+            .or_else(|| {
+                lines
+                    .iter()
+                    .rposition(|l| day_of_line(l).is_some())
+                    .map(|i| i + 1)
+            })
+            .unwrap_or(lines.len());
+        lines.insert(position, day.format(DAY_FORMAT).to_string());
+    }
+
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    fs::write(&habit.path, updated)
+}
+
+/// The `count` days ending with `today`, oldest first
+pub fn recent_days(today: NaiveDate, count: usize) -> Vec<NaiveDate> {
+    (0..count as u64)
+        .rev()
+        .filter_map(|back| today.checked_sub_days(Days::new(back)))
+        .collect()
+}
@@ -0,0 +1,64 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Themes used for fenced code blocks, on dark and light backgrounds
+const THEME: &str = "base16-ocean.dark";
+const LIGHT_THEME: &str = "InspiredGitHub";
+
+/// Syntax definitions and theme, loaded the first time a code block is highlighted
+fn assets() -> &'static (SyntaxSet, Theme) {
+    static ASSETS: OnceLock<(SyntaxSet, Theme)> = OnceLock::new();
+    ASSETS.get_or_init(|| {
+        let mut themes = ThemeSet::load_defaults();
+        let name = if crate::theme::is_light() {
+            LIGHT_THEME
+        } else {
+            THEME
+        };
+        let theme = themes.themes.remove(name).unwrap_or_default();
+        (SyntaxSet::load_defaults_newlines(), theme)
+    })
+}
+
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let fg = style.foreground;
+    let mut result = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+    if style.font_style.contains(FontStyle::BOLD) {
+        result = result.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        result = result.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        result = result.add_modifier(Modifier::UNDERLINED);
+    }
+    result
+}
+
+/// Highlight `code` as the language named by a fence tag (`rust`, `py`, `sh`, ...)
+/// Returns None for unknown languages so the caller can fall back to plain styling
+pub fn highlight(code: &str, lang: &str) -> Option<Vec<Line<'static>>> {
+    let (syntaxes, theme) = assets();
+    // Fence tags may carry extra attributes, e.g. "rust,ignore"
+    let token = lang.split([',', ' ']).next().unwrap_or(lang);
+    let syntax = syntaxes.find_syntax_by_token(token)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+
+    let mut lines = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = highlighter.highlight_line(line, syntaxes).ok()?;
+        let spans: Vec<Span<'static>> = ranges
+            .into_iter()
+            .map(|(style, text)| {
+                Span::styled(text.trim_end_matches(['\n', '\r']).to_string(), to_style(style))
+            })
+            .collect();
+        lines.push(Line::from(spans));
+    }
+    Some(lines)
+}
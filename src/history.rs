@@ -0,0 +1,98 @@
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::storage::VaultStorage;
+
+/// A commit that changed a note
+#[derive(Debug, Clone)]
+pub struct Revision {
+    pub hash: String, // Abbreviated
+    pub date: String, // YYYY-MM-DD
+    pub author: String,
+    pub subject: String,
+    path: String, // The note's path from the repository root in this commit, as it may have moved
+}
+
+/// Run git in the note's folder, which may sit anywhere inside the repository
+fn git(git: &Path, note: &Path, args: &[&str]) -> io::Result<String> {
+    let dir = note.parent().unwrap_or(Path::new("."));
+    let output = Command::new(git)
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .find(|l| !l.trim().is_empty())
+            .unwrap_or("git failed")
+            .trim()
+            .trim_start_matches("fatal: ");
+        Err(io::Error::other(message.to_string()))
+    }
+}
+
+/// The commits that changed a note, newest first, following it across renames
+pub fn log(git_path: &Path, note: &Path) -> io::Result<Vec<Revision>> {
+    let name = note
+        .file_name()
+        .ok_or_else(|| io::Error::other("not a file"))?
+        .to_string_lossy()
+        .to_string();
+    // Records start with \x1e and fields are split by \x1f; --name-only adds the path
+    let output = git(
+        git_path,
+        note,
+        &[
+            "log",
+            "--follow",
+            "--date=short",
+            "--format=%x1e%h%x1f%ad%x1f%an%x1f%s",
+            "--name-only",
+            "--",
+            &name,
+        ],
+    )?;
+    Ok(output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let mut fields = lines.next()?.split('\x1f');
+            let (hash, date, author, subject) =
+                (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+            let path = lines.rfind(|l| !l.trim().is_empty())?;
+            Some(Revision {
+                hash: hash.to_string(),
+                date: date.to_string(),
+                author: author.to_string(),
+                subject: subject.to_string(),
+                path: path.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// The note's text as a commit recorded it
+pub fn show(git_path: &Path, note: &Path, revision: &Revision) -> io::Result<String> {
+    git(
+        git_path,
+        note,
+        &["show", &format!("{}:{}", revision.hash, revision.path)],
+    )
+}
+
+/// Overwrite the note on disk with its text from a commit, leaving the index alone so the
+/// restore shows up as an ordinary change
+pub fn restore(
+    storage: &dyn VaultStorage,
+    git_path: &Path,
+    note: &Path,
+    revision: &Revision,
+) -> io::Result<()> {
+    storage.write(note, show(git_path, note, revision)?.as_bytes())
+}
@@ -0,0 +1,150 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::settings::Settings;
+
+/// Name of the per-vault configuration folder
+pub const VAULT_CONFIG_DIR: &str = ".lair";
+
+/// Events that can trigger a script in `.lair/hooks/`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    PreCreate,
+    PostCreate,
+    PostEdit,
+    PreDelete,
+}
+
+impl HookEvent {
+    /// File name of the script run for this event
+    pub fn script_name(&self) -> &'static str {
+        match self {
+            HookEvent::PreCreate => "pre-create",
+            HookEvent::PostCreate => "post-create",
+            HookEvent::PostEdit => "post-edit",
+            HookEvent::PreDelete => "pre-delete",
+        }
+    }
+}
+
+/// Result of running a hook script
+pub struct HookOutcome {
+    pub success: bool,
+    pub message: String, // First line of output (or the exit status when silent)
+}
+
+/// Directory holding the vault's hook scripts
+pub fn hooks_dir(vault: &Path) -> PathBuf {
+    vault.join(VAULT_CONFIG_DIR).join("hooks")
+}
+
+#[cfg(unix)]
+pub fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata()
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+pub fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// Run the vault's script for `event` with the note as context, if one exists
+/// The script gets the note path as its first argument plus LAIR_EVENT, LAIR_NOTE, LAIR_VAULT
+/// (and LAIR_LANG for notes with a `lang:` field) in its environment, and runs from the vault root
+/// with its output captured so the TUI stays intact
+/// Returns Ok(None) when no executable script is installed for the event
+pub fn run_hook(vault: &Path, event: HookEvent, note: &Path) -> io::Result<Option<HookOutcome>> {
+    run_create_hook(vault, event, note, None)
+}
+
+/// `run_hook` for the creation events, which also hand the script the template the note is
+/// created from, if any: as its second argument and in LAIR_TEMPLATE
+/// For pre-create the note doesn't exist yet; its path is where it's about to be written
+pub fn run_create_hook(
+    vault: &Path,
+    event: HookEvent,
+    note: &Path,
+    template: Option<&str>,
+) -> io::Result<Option<HookOutcome>> {
+    let script = hooks_dir(vault).join(event.script_name());
+    if !is_executable(&script) {
+        return Ok(None);
+    }
+
+    let mut command = Command::new(&script);
+    command.arg(note);
+    if let Some(template) = template {
+        command.arg(template).env("LAIR_TEMPLATE", template);
+    }
+    run(command, vault, event, note).map(Some)
+}
+
+/// Run a command from the settings for `event`, such as "prettier --write {file}", in the same
+/// way as a hook script; the note's path replaces `{file}`, or is appended when there's none
+pub fn run_command(vault: &Path, event: HookEvent, template: &str, note: &Path) -> io::Result<HookOutcome> {
+    let command = crate::editor::external_command(template, note, None)?;
+    run(command, vault, event, note)
+}
+
+/// Everything that runs after a note is edited: the post-edit hook, then each of the
+/// `post_edit_commands`, which all run even when one before them failed
+/// Err with the first failure, worded for the status bar or stderr
+pub fn run_post_edit(settings: &Settings, note: &Path) -> Result<(), String> {
+    let vault = Path::new(&settings.notes_directory);
+    let event = HookEvent::PostEdit;
+    let mut result = match run_hook(vault, event, note) {
+        Ok(Some(outcome)) if !outcome.success => {
+            Err(format!("{} hook failed: {}", event.script_name(), outcome.message))
+        }
+        Err(e) => Err(format!("{} hook error: {}", event.script_name(), e)),
+        _ => Ok(()),
+    };
+    for template in &settings.post_edit_commands {
+        let program = template.split_whitespace().next().unwrap_or_default();
+        let failure = match run_command(vault, event, template, note) {
+            Ok(outcome) if outcome.success => continue,
+            Ok(outcome) => format!("{} failed: {}", program, outcome.message),
+            Err(e) => format!("{} error: {}", program, e),
+        };
+        if result.is_ok() {
+            result = Err(failure);
+        }
+    }
+    result
+}
+
+/// Run a hook with the note's context in its environment, capturing its output
+fn run(mut command: Command, vault: &Path, event: HookEvent, note: &Path) -> io::Result<HookOutcome> {
+    command
+        .current_dir(vault)
+        .env("LAIR_EVENT", event.script_name())
+        .env("LAIR_NOTE", note)
+        .env("LAIR_VAULT", vault)
+        .stdin(Stdio::null());
+    // Lets a hook pick e.g. the right spellcheck dictionary
+    if let Some(language) = fs::read_to_string(note)
+        .ok()
+        .and_then(|content| crate::note::language(&content))
+    {
+        command.env("LAIR_LANG", language);
+    }
+    let output = command.output()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let first_line = stderr
+        .lines()
+        .chain(stdout.lines())
+        .find(|l| !l.trim().is_empty())
+        .map(|l| l.trim().to_string());
+
+    Ok(HookOutcome {
+        success: output.status.success(),
+        message: first_line.unwrap_or_else(|| output.status.to_string()),
+    })
+}
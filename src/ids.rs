@@ -0,0 +1,100 @@
+use chrono::{NaiveDateTime, TimeDelta};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::settings::Settings;
+
+/// Note IDs are the minute a note was created, `202406141530`
+const ID_FORMAT: &str = "%Y%m%d%H%M";
+const ID_LEN: usize = 12;
+
+/// Whether text is a note ID: twelve digits
+pub fn is_id(text: &str) -> bool {
+    text.len() == ID_LEN && text.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The ID a note's file name starts with, if any: `202406141530 plan.md`,
+/// `202406141530-plan.md` or just `202406141530.md`
+pub fn id_of(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_string_lossy();
+    let id = stem.get(..ID_LEN)?;
+    let rest = &stem[ID_LEN..];
+    (is_id(id) && (rest.is_empty() || rest.starts_with([' ', '-', '_']))).then(|| id.to_string())
+}
+
+/// An ID for a note created at `now`: its minute, or the first minute after it that no note in
+/// the vault has taken, so notes made in quick succession still get their own
+pub fn new_id(settings: &Settings, now: NaiveDateTime) -> String {
+    let taken: HashSet<String> = crate::vault::all_files(settings)
+        .iter()
+        .filter_map(|f| id_of(f))
+        .collect();
+    let mut time = now;
+    loop {
+        let id = time.format(ID_FORMAT).to_string();
+        if !taken.contains(&id) {
+            return id;
+        }
+        time += TimeDelta::minutes(1);
+    }
+}
+
+/// The file name for a note with an ID: the ID, then the name it would otherwise have had
+/// after `separator`; the ID alone when there's no name
+pub fn file_name(id: &str, name: &str, separator: char, file_format: &str) -> String {
+    if name.is_empty() {
+        format!("{}.{}", id, file_format)
+    } else {
+        format!("{}{}{}.{}", id, separator, name, file_format)
+    }
+}
+
+/// Add `id: <id>` to the start of a note's frontmatter, starting one when it has none
+pub fn write_to_frontmatter(note: &Path, id: &str) -> io::Result<()> {
+    let content = fs::read_to_string(note)?;
+    let field = format!("id: {}\n", id);
+    let updated = match content.strip_prefix("---\n") {
+        Some(rest) if crate::frontmatter::parse(&content).is_some() => {
+            format!("---\n{}{}", field, rest)
+        }
+        _ => format!("---\n{}---\n\n{}", field, content),
+    };
+    fs::write(note, updated)
+}
+
+/// Each note's frontmatter `id` as last read, reused until the note changes, so resolving
+/// `[[id]]` links doesn't read the whole vault every time
+type FrontmatterIds = BTreeMap<PathBuf, (Option<SystemTime>, Option<String>)>;
+static FRONTMATTER_IDS: Mutex<FrontmatterIds> = Mutex::new(BTreeMap::new());
+
+/// A note's frontmatter `id`, from the cache while the note is unchanged
+fn frontmatter_id(cache: &mut FrontmatterIds, note: &Path) -> Option<String> {
+    let modified = fs::metadata(note).and_then(|m| m.modified()).ok();
+    if let Some((cached_modified, id)) = cache.get(note)
+        && *cached_modified == modified
+    {
+        return id.clone();
+    }
+    let id = fs::read_to_string(note).ok().and_then(|content| {
+        crate::frontmatter::parse(&content).and_then(|fm| fm.get("id").map(|id| id.trim().to_string()))
+    });
+    cache.insert(note.to_path_buf(), (modified, id.clone()));
+    id
+}
+
+/// The note with an ID among `files`: the one whose file name starts with it, else one with
+/// it as its frontmatter `id`
+pub fn find(settings: &Settings, files: &[PathBuf], id: &str) -> Option<PathBuf> {
+    let notes = || files.iter().filter(|f| crate::vault::is_note(f, settings));
+    notes()
+        .find(|f| id_of(f).as_deref() == Some(id))
+        .or_else(|| {
+            let mut cache = FRONTMATTER_IDS.lock().ok()?;
+            notes().find(|f| frontmatter_id(&mut cache, f).as_deref() == Some(id))
+        })
+        .cloned()
+}
@@ -0,0 +1,157 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use image::imageops::FilterType;
+use pulldown_cmark::{Event, Parser, Tag};
+use ratatui::layout::Rect;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// File extensions the preview can decode
+const IMAGE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "gif"];
+
+/// Cell size assumed when the terminal doesn't report its pixel size
+const FALLBACK_CELL_SIZE: (u32, u32) = (8, 16);
+
+/// Ways a terminal can draw pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    Iterm,
+    Sixel,
+}
+
+/// Guess the terminal's graphics support from its environment
+/// `LAIR_GRAPHICS=kitty|iterm|sixel|none` overrides the guess
+pub fn detect() -> Option<GraphicsProtocol> {
+    let var = |name: &str| env::var(name).unwrap_or_default().to_lowercase();
+
+    match var("LAIR_GRAPHICS").as_str() {
+        "kitty" => return Some(GraphicsProtocol::Kitty),
+        "iterm" => return Some(GraphicsProtocol::Iterm),
+        "sixel" => return Some(GraphicsProtocol::Sixel),
+        "none" => return None,
+        _ => {}
+    }
+
+    // Multiplexers don't pass graphics through reliably
+    if env::var_os("TMUX").is_some() || var("TERM").starts_with("screen") {
+        return None;
+    }
+
+    let term = var("TERM");
+    let program = var("TERM_PROGRAM");
+    if env::var_os("KITTY_WINDOW_ID").is_some()
+        || term.contains("kitty")
+        || program == "ghostty"
+        || program == "wezterm"
+    {
+        Some(GraphicsProtocol::Kitty)
+    } else if program == "iterm.app" {
+        Some(GraphicsProtocol::Iterm)
+    } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+        Some(GraphicsProtocol::Sixel)
+    } else {
+        None
+    }
+}
+
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|i| ext.eq_ignore_ascii_case(i)))
+}
+
+/// One-line description shown in place of the image
+pub fn placeholder(path: &Path) -> String {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match image::image_dimensions(path) {
+        Ok((width, height)) => format!("🖼 {} ({}×{})", name, width, height),
+        Err(_) => format!("🖼 {}", name),
+    }
+}
+
+/// First image a markdown note embeds from the local disk, resolved against the note's folder
+pub fn first_local_image(note: &Path, content: &str) -> Option<PathBuf> {
+    let base = note.parent()?;
+    Parser::new(content).find_map(|event| match event {
+        Event::Start(Tag::Image { dest_url, .. }) if !dest_url.contains("://") => {
+            let path = base.join(dest_url.as_ref());
+            (is_image(&path) && path.is_file()).then_some(path)
+        }
+        _ => None,
+    })
+}
+
+/// Pixel size of a terminal cell
+fn cell_size() -> (u32, u32) {
+    match ratatui::crossterm::terminal::window_size() {
+        Ok(size) if size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0 => (
+            size.width as u32 / size.columns as u32,
+            size.height as u32 / size.rows as u32,
+        ),
+        _ => FALLBACK_CELL_SIZE,
+    }
+}
+
+/// Escape sequence that removes every image drawn with the kitty protocol
+pub const KITTY_CLEAR: &str = "\x1b_Ga=d,q=2\x1b\\";
+
+/// Escape sequence drawing the image at `path` scaled into `area` (in cells)
+/// The caller must move the cursor to the top-left corner of `area` first
+pub fn encode(protocol: GraphicsProtocol, path: &Path, area: Rect) -> io::Result<String> {
+    if protocol == GraphicsProtocol::Iterm {
+        // iTerm decodes and scales the file itself
+        let bytes = fs::read(path)?;
+        return Ok(format!(
+            "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+            bytes.len(),
+            area.width,
+            area.height,
+            BASE64.encode(&bytes)
+        ));
+    }
+
+    let (cell_width, cell_height) = cell_size();
+    let image = image::open(path)
+        .map_err(io::Error::other)?
+        .resize(
+            area.width as u32 * cell_width,
+            area.height as u32 * cell_height,
+            FilterType::Triangle,
+        )
+        .to_rgba8();
+    let (width, height) = image.dimensions();
+
+    match protocol {
+        GraphicsProtocol::Kitty => {
+            // Raw RGBA, sent in chunks of at most 4096 base64 bytes
+            let data = BASE64.encode(image.as_raw());
+            let chunks: Vec<&str> = data
+                .as_bytes()
+                .chunks(4096)
+                .map(|c| std::str::from_utf8(c).unwrap_or_default())
+                .collect();
+            let mut out = String::new();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = if i + 1 < chunks.len() { 1 } else { 0 };
+                if i == 0 {
+                    out.push_str(&format!(
+                        "\x1b_Ga=T,f=32,s={},v={},C=1,q=2,m={};{}\x1b\\",
+                        width, height, more, chunk
+                    ));
+                } else {
+                    out.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+                }
+            }
+            Ok(out)
+        }
+        _ => icy_sixel::SixelImage::from_rgba(image.into_raw(), width as usize, height as usize)
+            .encode()
+            .map_err(io::Error::other),
+    }
+}
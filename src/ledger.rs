@@ -0,0 +1,118 @@
+use chrono::NaiveDate;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// A captured expense; amounts are kept in cents so totals add up exactly
+pub struct Entry {
+    pub date: NaiveDate,
+    pub amount: i64,
+    pub category: String,
+    pub description: String,
+}
+
+/// Parse a "12.50" style amount into cents (negative amounts are refunds)
+fn parse_amount(text: &str) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (whole, fraction) = digits.split_once(['.', ',']).unwrap_or((digits, ""));
+    if whole.is_empty() && fraction.is_empty() || fraction.len() > 2 {
+        return None;
+    }
+    let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: i64 = match fraction.len() {
+        0 => 0,
+        1 => fraction.parse::<i64>().ok()? * 10,
+        _ => fraction.parse().ok()?,
+    };
+    let cents = whole.checked_mul(100)?.checked_add(fraction)?;
+    Some(if negative { -cents } else { cents })
+}
+
+/// Format cents as "12.50"
+pub fn format_amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!("{}{}.{:02}", sign, cents.abs() / 100, cents.abs() % 100)
+}
+
+/// Parse quick-capture input: "<amount> <category> [description]"
+pub fn parse_input(input: &str, date: NaiveDate) -> Result<Entry, String> {
+    let mut words = input.split_whitespace();
+    let amount = words
+        .next()
+        .ok_or("Type an amount, a category and an optional description")?;
+    let amount = parse_amount(amount).ok_or_else(|| format!("'{}' isn't an amount", amount))?;
+    let category = words
+        .next()
+        .ok_or("Add a category after the amount, e.g. '12.50 food lunch'")?
+        .to_lowercase();
+    Ok(Entry {
+        date,
+        amount,
+        category,
+        description: words.collect::<Vec<_>>().join(" "),
+    })
+}
+
+/// The ledger note for the month containing `date`, e.g. `ledger/2026-10.md`
+pub fn ledger_path(settings: &Settings, date: NaiveDate) -> PathBuf {
+    Path::new(&settings.notes_directory)
+        .join(&settings.ledger_directory)
+        .join(format!("{}.{}", date.format("%Y-%m"), settings.default_file_format))
+}
+
+/// Append an entry to its month's ledger note as "- date | category | amount | description"
+/// (the description is left off when empty)
+pub fn append(settings: &Settings, entry: &Entry) -> io::Result<PathBuf> {
+    let path = ledger_path(settings, entry.date);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_new = !path.exists();
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    if is_new {
+        writeln!(file, "# Ledger {}\n", entry.date.format("%Y-%m"))?;
+    }
+    let mut line = format!(
+        "- {} | {} | {}",
+        entry.date.format("%Y-%m-%d"),
+        entry.category,
+        format_amount(entry.amount)
+    );
+    if !entry.description.is_empty() {
+        line.push_str(&format!(" | {}", entry.description));
+    }
+    writeln!(file, "{}", line)?;
+    Ok(path)
+}
+
+/// Totals per category parsed from a ledger note, largest first
+/// Lines that aren't entries are ignored, so the note can hold free text too
+pub fn summary(path: &Path) -> io::Result<Vec<(String, i64)>> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut totals: Vec<(String, i64)> = Vec::new();
+    for line in content.lines() {
+        let Some(entry) = line.strip_prefix("- ") else {
+            continue;
+        };
+        let fields: Vec<&str> = entry.split('|').map(str::trim).collect();
+        let (Some(category), Some(amount)) = (fields.get(1), fields.get(2).and_then(|a| parse_amount(a)))
+        else {
+            continue;
+        };
+        match totals.iter_mut().find(|(c, _)| c == category) {
+            Some((_, total)) => *total += amount,
+            None => totals.push((category.to_string(), amount)),
+        }
+    }
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    Ok(totals)
+}
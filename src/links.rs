@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// Links to notes in this form open the note in LAIR, e.g. `lair://vault/projects/plan.md`
+pub const URI_SCHEME: &str = "lair://";
+const URI_HOST: &str = "vault/";
+
+/// How a copied link to a note is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFormat {
+    #[default]
+    Wiki, // [[plan]], or [[projects/acme/plan]] when another note has that name
+    Markdown, // [Title](/projects/acme/plan.md), from the vault root
+    Path,     // The absolute path
+    Uri,      // lair://vault/projects/acme/plan.md
+}
+
+impl LinkFormat {
+    pub const ALL: [LinkFormat; 4] = [
+        LinkFormat::Wiki,
+        LinkFormat::Markdown,
+        LinkFormat::Path,
+        LinkFormat::Uri,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LinkFormat::Wiki => "wiki link",
+            LinkFormat::Markdown => "markdown link",
+            LinkFormat::Path => "path",
+            LinkFormat::Uri => "lair:// link",
+        }
+    }
+
+    /// The format after this one, wrapping around
+    pub fn next(&self) -> LinkFormat {
+        let i = LinkFormat::ALL.iter().position(|f| f == self).unwrap_or(0);
+        LinkFormat::ALL[(i + 1) % LinkFormat::ALL.len()]
+    }
+}
+
+/// A note's title: its `title:` field, its first `# ` heading, or its file name
+pub fn title(storage: &dyn VaultStorage, note: &Path) -> String {
+    let stem = || note.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let Ok(content) = storage.read_to_string(note) else {
+        return stem();
+    };
+    crate::frontmatter::parse(&content)
+        .and_then(|fm| fm.get("title").map(|t| t.trim().to_string()))
+        .filter(|t| !t.is_empty())
+        .or_else(|| {
+            crate::frontmatter::body(&content)
+                .lines()
+                .find_map(|l| l.strip_prefix("# ").map(|t| t.trim().to_string()))
+        })
+        .unwrap_or_else(stem)
+}
+
+/// Percent-encode a vault-relative path for a URI, keeping the `/` between folders
+pub fn encode(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Undo percent-encoding, as in a URI's path
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text
+            .get(i + 1..i + 3)
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Whether a link target is a `lair://` link, which the viewer opens itself
+pub fn is_uri(target: &str) -> bool {
+    target.starts_with(URI_SCHEME)
+}
+
+/// The file a `lair://vault/path/to/note` link points at, None for any other target
+/// The host may be left out, and so may the note's extension; a query or fragment is ignored
+/// Paths leaving the vault, `..` or `%2e%2e` among them, and hidden files point nowhere
+pub fn uri_path(settings: &Settings, target: &str) -> Option<PathBuf> {
+    let rest = target.strip_prefix(URI_SCHEME)?;
+    let rest = rest.strip_prefix(URI_HOST).unwrap_or(rest);
+    let rest = rest.split(['?', '#']).next().unwrap_or("");
+    let decoded = decode(rest);
+    let relative = Path::new(decoded.trim_start_matches('/'));
+    if !crate::vault::is_plain_relative(relative) {
+        return None;
+    }
+    let path = Path::new(&settings.notes_directory).join(relative);
+    if path.extension().is_none() {
+        let with_extension = path.with_extension(&settings.default_file_format);
+        if with_extension.is_file() {
+            return Some(with_extension);
+        }
+    }
+    Some(path)
+}
+
+/// A link to `note` in the given format, for pasting into another note or document
+pub fn link(settings: &Settings, note: &Path, format: LinkFormat) -> String {
+    let vault = Path::new(&settings.notes_directory);
+    let relative = note
+        .strip_prefix(vault)
+        .unwrap_or(note)
+        .to_string_lossy()
+        .replace('\\', "/");
+    match format {
+        LinkFormat::Wiki => {
+            let stem = note.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+            let shared = crate::vault::all_files(settings)
+                .iter()
+                .filter(|f| crate::vault::is_note(f, settings))
+                .filter(|f| f.file_stem().is_some_and(|s| s.to_string_lossy().to_lowercase() == stem))
+                .count()
+                > 1;
+            let name = if shared {
+                Path::new(&relative).with_extension("").to_string_lossy().to_string()
+            } else {
+                note.file_stem().unwrap_or_default().to_string_lossy().to_string()
+            };
+            format!("[[{}]]", name)
+        }
+        LinkFormat::Markdown => {
+            let title = title(crate::storage::open(settings).as_ref(), note);
+            format!("[{}](/{})", title, relative.replace(' ', "%20"))
+        }
+        LinkFormat::Path => note.display().to_string(),
+        LinkFormat::Uri => format!("{}{}{}", URI_SCHEME, URI_HOST, encode(&relative)),
+    }
+}
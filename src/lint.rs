@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::folders::FolderTags;
+use crate::settings::Settings;
+
+/// Which checks the linter runs on notes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintSettings {
+    pub missing_title: bool,    // A note needs a `title:` field or a `# ` heading
+    pub missing_tags: bool,     // A note needs a tag, counting those of its folders
+    pub broken_links: bool,     // Links must lead to a note or file that exists
+    pub max_line_length: usize, // Longest line allowed outside code blocks, 0 for no limit
+    pub todo_days: u64, // Open TODOs in a note untouched for this many days are flagged, 0 for never
+    pub on_edit: bool,  // Check a note each time the editor is closed on it
+}
+
+impl Default for LintSettings {
+    fn default() -> Self {
+        LintSettings {
+            missing_title: true,
+            missing_tags: false,
+            broken_links: true,
+            max_line_length: 0,
+            todo_days: 30,
+            on_edit: false,
+        }
+    }
+}
+
+/// Something the linter found in a note
+#[derive(Debug, Clone)]
+pub struct Problem {
+    pub path: PathBuf,
+    pub line: Option<usize>, // 1-based, for problems with a place in the note
+    pub message: String,
+}
+
+/// Checks notes against the lint settings, listing the vault once for resolving links
+pub struct Linter {
+    settings: Settings,
+    files: Vec<PathBuf>,
+    folder_tags: FolderTags,
+}
+
+impl Linter {
+    pub fn new(settings: &Settings) -> Linter {
+        Linter {
+            settings: settings.clone(),
+            files: crate::vault::all_files(settings),
+            folder_tags: FolderTags::new(settings),
+        }
+    }
+
+    /// The problems in one note, in the order they appear in it
+    pub fn check(&mut self, path: &Path) -> Vec<Problem> {
+        let Ok(content) = fs::read_to_string(path) else {
+            return Vec::new();
+        };
+        let rules = self.settings.lint.clone();
+        let mut problems = Vec::new();
+        let mut problem = |line: Option<usize>, message: String| {
+            problems.push(Problem {
+                path: path.to_path_buf(),
+                line,
+                message,
+            })
+        };
+
+        if rules.missing_title && !has_title(&content) {
+            problem(None, "No title (a `title:` field or a `# ` heading)".to_string());
+        }
+        if rules.missing_tags
+            && crate::note::tags(&content).is_empty()
+            && self.folder_tags.for_note(path).is_empty()
+        {
+            problem(None, "No tags".to_string());
+        }
+        if rules.broken_links {
+            let (_, links) = crate::markdown::render(&content);
+            for link in links {
+                let target = link.target.trim();
+                if target.is_empty()
+                    || target.starts_with('#')
+                    || (target.contains("://") && !crate::links::is_uri(target))
+                    || target.starts_with("mailto:")
+                {
+                    continue;
+                }
+                if crate::viewer::resolve_link_in(path, &link, &self.settings, &self.files)
+                    .is_none()
+                {
+                    let line = content.lines().position(|l| l.contains(target)).map(|i| i + 1);
+                    problem(line, format!("Broken link: {}", target));
+                }
+            }
+        }
+
+        let untouched_days = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .map(|age| age.as_secs() / 86_400);
+        let stale_todos = rules.todo_days > 0 && untouched_days.is_some_and(|d| d >= rules.todo_days);
+        let mut in_code_block = false;
+        for (i, line) in content.lines().enumerate() {
+            if line.trim_start().starts_with("```") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+            if in_code_block {
+                continue;
+            }
+            let length = line.chars().count();
+            if rules.max_line_length > 0 && length > rules.max_line_length {
+                problem(Some(i + 1), format!("Line is {} characters long", length));
+            }
+            if stale_todos && is_open_todo(line) {
+                problem(
+                    Some(i + 1),
+                    format!(
+                        "Open TODO in a note untouched for {} days",
+                        untouched_days.unwrap_or_default()
+                    ),
+                );
+            }
+        }
+        problems
+    }
+}
+
+fn has_title(content: &str) -> bool {
+    let titled = crate::frontmatter::parse(content)
+        .and_then(|fm| fm.get("title").map(|t| !t.trim().is_empty()))
+        .unwrap_or(false);
+    titled || crate::frontmatter::body(content).lines().any(|l| l.starts_with("# "))
+}
+
+/// An unchecked `- [ ]` box, or a line with a `TODO` marker
+fn is_open_todo(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("- [ ]")
+        || trimmed.starts_with("* [ ]")
+        || line
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| word == "TODO")
+}
+
+/// Lint every note in the vault
+pub fn lint_vault(settings: &Settings) -> Vec<Problem> {
+    let mut linter = Linter::new(settings);
+    let notes: Vec<PathBuf> = linter
+        .files
+        .iter()
+        .filter(|f| crate::vault::is_note(f, settings))
+        .cloned()
+        .collect();
+    notes.iter().flat_map(|note| linter.check(note)).collect()
+}
+
+/// Lint the vault on a worker thread, like the statistics
+pub fn spawn_lint(settings: Settings) -> Receiver<Vec<Problem>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(lint_vault(&settings));
+    });
+    rx
+}
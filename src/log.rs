@@ -0,0 +1,93 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// How often the log moves on to a new note
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    #[default]
+    Daily, // log/2026-10-16.md
+    Weekly, // log/2026-W42.md, by ISO week
+}
+
+/// A timestamped line from a log note
+pub struct Entry {
+    pub time: NaiveDateTime,
+    pub text: String,
+}
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// The name of the log note covering `date`, without the extension
+fn period(rotation: LogRotation, date: NaiveDate) -> String {
+    match rotation {
+        LogRotation::Daily => date.format("%Y-%m-%d").to_string(),
+        LogRotation::Weekly => date.format("%G-W%V").to_string(),
+    }
+}
+
+fn log_dir(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(&settings.log_directory)
+}
+
+/// Append a line to the current log note as "- 2026-10-16 14:03 text", starting the note
+/// when the period has rolled over
+/// Every entry carries its date, so the notes read the same whichever rotation wrote them
+pub fn append(settings: &Settings, text: &str, now: NaiveDateTime) -> io::Result<PathBuf> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(io::Error::other("Type something to log"));
+    }
+    let period = period(settings.log_rotation, now.date());
+    let path = log_dir(settings).join(format!("{}.{}", period, settings.default_file_format));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let is_new = !path.exists();
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    if is_new {
+        writeln!(file, "# Log {}\n", period)?;
+    }
+    writeln!(file, "- {} {}", now.format(TIME_FORMAT), text)?;
+    Ok(path)
+}
+
+/// Every entry in the log folder, oldest first
+/// Lines that aren't entries are skipped, so log notes can be annotated by hand
+pub fn entries(settings: &Settings) -> Vec<Entry> {
+    let mut notes: Vec<PathBuf> = fs::read_dir(log_dir(settings))
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| crate::vault::is_note(p, settings))
+                .collect()
+        })
+        .unwrap_or_default();
+    notes.sort();
+    let mut entries: Vec<Entry> = notes
+        .iter()
+        .filter_map(|note| fs::read_to_string(note).ok())
+        .flat_map(|content| {
+            content
+                .lines()
+                .filter_map(|line| {
+                    let line = line.strip_prefix("- ")?;
+                    // "YYYY-MM-DD HH:MM" is 16 characters
+                    let stamp = line.get(..16)?;
+                    let time = NaiveDateTime::parse_from_str(stamp, TIME_FORMAT).ok()?;
+                    Some(Entry {
+                        time,
+                        text: line[16..].trim().to_string(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    entries.sort_by_key(|e| e.time);
+    entries
+}
@@ -1,12 +1,65 @@
+mod actions;
+mod anki;
+mod annotations;
 mod app;
+mod archive;
 mod browse;
+mod calendar;
+mod cards;
+mod citations;
+mod cli;
+mod csv;
+#[cfg(target_os = "linux")]
+mod dbus;
+mod diff;
+mod doctor;
+mod editor;
+mod folders;
+mod frontmatter;
+mod habits;
+mod highlight;
+mod history;
+mod hooks;
+mod ids;
+mod images;
+mod ledger;
+mod links;
+mod lint;
+mod log;
+mod markdown;
+mod mcp;
+mod note;
+mod notify;
+mod plan;
+mod preview;
+mod reading;
+#[cfg(unix)]
+mod remote;
+mod restructure;
+mod scan;
+mod scratch;
+mod server;
 mod settings;
+mod site;
+mod state;
+mod stats;
+mod storage;
+mod tasks;
+mod templates;
+mod theme;
+mod tools;
 mod ui;
-use crate::app::App;
+mod update;
+mod usage;
+mod vault;
+mod viewer;
+use crate::app::{App, CurrentScreen};
 use crate::ui::run_app;
+use clap::Parser;
 use ratatui::Terminal;
 use ratatui::crossterm::event::DisableMouseCapture;
 use ratatui::crossterm::event::EnableMouseCapture;
+use ratatui::crossterm::event::{DisableFocusChange, EnableFocusChange};
 use ratatui::crossterm::execute;
 use ratatui::crossterm::terminal::{
     EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
@@ -16,21 +69,36 @@ use std::error::Error;
 use std::io;
 
 pub fn main() -> Result<(), Box<dyn Error>> {
+    // A subcommand runs instead of the TUI
+    let mut open = None;
+    match cli::run(cli::Cli::parse())? {
+        Some(cli::Outcome::Exit(code)) => std::process::exit(code),
+        Some(cli::Outcome::Open { note, edit }) => open = Some((note, edit)),
+        None => {}
+    }
+
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)?;
 
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     let mut app = App::new();
+    match open {
+        Some((note, true)) => app.open_internal_editor(&note, CurrentScreen::Main),
+        Some((note, false)) => app.view_note(&note, CurrentScreen::Main),
+        None => {}
+    }
     let res = run_app(&mut terminal, &mut app);
+    app.save_usage();
 
     // restore terminal
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableFocusChange
     )?;
     terminal.show_cursor()?;
 
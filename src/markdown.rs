@@ -0,0 +1,548 @@
+use pulldown_cmark::{
+    Alignment, CodeBlockKind, Event, HeadingLevel, LinkType, Options, Parser, Tag, TagEnd,
+};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use std::ops::Range;
+
+use crate::frontmatter::Frontmatter;
+
+/// A link in the rendered text: the spans showing it and where it points
+#[derive(Debug, Clone)]
+pub struct RenderedLink {
+    pub line: usize,
+    pub spans: Range<usize>,
+    pub target: String,
+    pub wiki: bool, // `[[Note name]]` rather than a markdown link
+}
+
+/// A heading in the rendered text, as listed in the viewer's outline
+#[derive(Debug, Clone)]
+pub struct OutlineEntry {
+    pub line: usize,
+    pub level: usize, // 1 for `#`, 2 for `##`, ...
+    pub title: String,
+}
+
+/// A pipe table being read, drawn as a grid once its column widths are known
+struct TableBuffer {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Span<'static>>>>, // Spans of each cell, row by row
+    header_rows: usize,
+    links: Vec<(usize, usize, Range<usize>, String, bool)>, // Row, cell, spans, target, kind
+}
+
+/// Converts pulldown-cmark events into styled ratatui lines
+struct MarkdownWriter {
+    lines: Vec<Line<'static>>,
+    current: Vec<Span<'static>>,
+    style_stack: Vec<Style>,
+    list_stack: Vec<Option<u64>>, // Next number for ordered lists, None for bullets
+    quote_depth: usize,
+    code_block: Option<(String, String)>, // Language and buffered text of the open code block
+    in_metadata: bool, // Inside YAML frontmatter, which is shown as a header block instead
+    links: Vec<RenderedLink>,
+    open_link: Option<(usize, usize, String, bool)>, // Line, first span, target and kind of the current link
+    table: Option<TableBuffer>, // The table being read, if inside one
+}
+
+impl MarkdownWriter {
+    fn new() -> MarkdownWriter {
+        MarkdownWriter {
+            lines: Vec::new(),
+            current: Vec::new(),
+            style_stack: vec![Style::default()],
+            list_stack: Vec::new(),
+            quote_depth: 0,
+            code_block: None,
+            in_metadata: false,
+            links: Vec::new(),
+            open_link: None,
+            table: None,
+        }
+    }
+
+    fn style(&self) -> Style {
+        self.style_stack.last().copied().unwrap_or_default()
+    }
+
+    fn push_style(&mut self, patch: Style) {
+        let style = self.style().patch(patch);
+        self.style_stack.push(style);
+    }
+
+    fn pop_style(&mut self) {
+        if self.style_stack.len() > 1 {
+            self.style_stack.pop();
+        }
+    }
+
+    /// Start a line with the block quote gutter, if inside a quote
+    fn start_line(&mut self) {
+        if self.current.is_empty() && self.quote_depth > 0 {
+            self.current.push(Span::styled(
+                "▎ ".repeat(self.quote_depth),
+                Style::default().fg(Color::Green),
+            ));
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        self.start_line();
+        let style = self.style();
+        self.current.push(Span::styled(text.to_string(), style));
+    }
+
+    /// Finish the current line (if it has content)
+    fn flush_line(&mut self) {
+        if !self.current.is_empty() {
+            self.lines.push(Line::from(std::mem::take(&mut self.current)));
+        }
+    }
+
+    /// Separate blocks with a single blank line
+    fn blank_line(&mut self) {
+        self.flush_line();
+        if self.lines.last().is_some_and(|l| !l.spans.is_empty()) {
+            self.lines.push(Line::default());
+        }
+    }
+
+    fn heading_style(level: HeadingLevel) -> Style {
+        let style = Style::default().add_modifier(Modifier::BOLD);
+        match level {
+            HeadingLevel::H1 => style.fg(Color::Magenta).add_modifier(Modifier::UNDERLINED),
+            HeadingLevel::H2 => style.fg(Color::Cyan),
+            _ => style.fg(Color::Blue),
+        }
+    }
+
+    /// Emit a finished table as a box-drawn grid, padding cells to their column's width
+    fn push_table(&mut self, table: TableBuffer) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let cell_width = |cell: &[Span]| cell.iter().map(Span::width).sum::<usize>();
+        let widths: Vec<usize> = (0..columns)
+            .map(|c| {
+                table
+                    .rows
+                    .iter()
+                    .filter_map(|row| row.get(c))
+                    .map(|cell| cell_width(cell))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let border = Style::default().fg(Color::DarkGray);
+        let rule = |left: &str, middle: &str, right: &str| {
+            let segments: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+            Line::styled(format!("{}{}{}", left, segments.join(middle), right), border)
+        };
+
+        self.lines.push(rule("┌", "┬", "┐"));
+        for (r, row) in table.rows.iter().enumerate() {
+            if r > 0 && r == table.header_rows {
+                self.lines.push(rule("├", "┼", "┤"));
+            }
+            let mut spans = Vec::new();
+            let mut cell_starts = Vec::new();
+            for (c, width) in widths.iter().enumerate() {
+                spans.push(Span::styled(if c == 0 { "│ " } else { " │ " }, border));
+                let cell = row.get(c).map(Vec::as_slice).unwrap_or_default();
+                let padding = width - cell_width(cell);
+                let before = match table.alignments.get(c) {
+                    Some(Alignment::Right) => padding,
+                    Some(Alignment::Center) => padding / 2,
+                    _ => 0,
+                };
+                if before > 0 {
+                    spans.push(Span::raw(" ".repeat(before)));
+                }
+                cell_starts.push(spans.len());
+                spans.extend(cell.iter().cloned());
+                if padding > before {
+                    spans.push(Span::raw(" ".repeat(padding - before)));
+                }
+            }
+            spans.push(Span::styled(" │", border));
+            for (_, cell, range, target, wiki) in table.links.iter().filter(|l| l.0 == r) {
+                let Some(start) = cell_starts.get(*cell) else {
+                    continue;
+                };
+                self.links.push(RenderedLink {
+                    line: self.lines.len(),
+                    spans: start + range.start..start + range.end,
+                    target: target.clone(),
+                    wiki: *wiki,
+                });
+            }
+            self.lines.push(Line::from(spans));
+        }
+        self.lines.push(rule("└", "┴", "┘"));
+    }
+
+    /// Emit a finished code block, highlighted when its language is known
+    fn push_code_block(&mut self, lang: &str, code: &str) {
+        let highlighted = if lang.is_empty() {
+            None
+        } else {
+            crate::highlight::highlight(code, lang)
+        };
+        match highlighted {
+            Some(lines) => {
+                for line in lines {
+                    self.start_line();
+                    self.current.push(Span::raw("  "));
+                    self.current.extend(line.spans);
+                    self.flush_line();
+                }
+            }
+            None => {
+                let style = Style::default().fg(Color::Yellow);
+                for line in code.lines() {
+                    self.start_line();
+                    self.current.push(Span::styled(format!("  {}", line), style));
+                    self.flush_line();
+                }
+            }
+        }
+    }
+
+    /// Frontmatter as a header block: the title, then each field with aligned names
+    fn push_metadata(&mut self, frontmatter: &Frontmatter) {
+        let fields: Vec<&(String, Vec<String>)> = frontmatter
+            .fields
+            .iter()
+            .filter(|(key, values)| !values.is_empty() && !key.eq_ignore_ascii_case("title"))
+            .collect();
+        let title = frontmatter.get("title").filter(|t| !t.trim().is_empty());
+        if title.is_none() && fields.is_empty() {
+            return;
+        }
+        if let Some(title) = title {
+            self.lines.push(Line::styled(
+                title.to_string(),
+                Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD),
+            ));
+        }
+        let width = fields.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+        let key_style = Style::default().fg(Color::DarkGray);
+        for (key, values) in fields {
+            let mut spans = vec![Span::styled(format!("{:<width$}  ", key), key_style)];
+            if key.eq_ignore_ascii_case("tags") {
+                let tags: Vec<String> = values
+                    .iter()
+                    .map(|t| format!("#{}", t.trim_start_matches('#')))
+                    .collect();
+                spans.push(Span::styled(tags.join(" "), Style::default().fg(Color::Cyan)));
+            } else {
+                spans.push(Span::raw(values.join(", ")));
+            }
+            self.lines.push(Line::from(spans));
+        }
+        self.lines.push(Line::styled("─".repeat(40), Style::default().fg(Color::DarkGray)));
+        self.lines.push(Line::default());
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.blank_line();
+                self.push_style(Self::heading_style(level));
+                let marker = "#".repeat(level as usize);
+                self.push_text(&format!("{} ", marker));
+            }
+            Tag::Paragraph => {}
+            Tag::MetadataBlock(_) => self.in_metadata = true,
+            Tag::BlockQuote(_) => {
+                self.flush_line();
+                self.quote_depth += 1;
+                self.push_style(Style::default().fg(Color::Green).add_modifier(Modifier::ITALIC));
+            }
+            Tag::CodeBlock(kind) => {
+                self.blank_line();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(lang) => lang.to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                if !lang.is_empty() {
+                    self.lines.push(Line::styled(
+                        format!("```{}", lang),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                self.code_block = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush_line();
+                self.list_stack.push(start);
+            }
+            Tag::Item => {
+                self.flush_line();
+                let indent = "  ".repeat(self.list_stack.len().saturating_sub(1));
+                let marker = match self.list_stack.last_mut() {
+                    Some(Some(n)) => {
+                        let marker = format!("{}. ", n);
+                        *n += 1;
+                        marker
+                    }
+                    _ => "• ".to_string(),
+                };
+                self.start_line();
+                self.current.push(Span::styled(
+                    format!("{}{}", indent, marker),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+            Tag::Emphasis => self.push_style(Style::default().add_modifier(Modifier::ITALIC)),
+            Tag::Strong => self.push_style(Style::default().add_modifier(Modifier::BOLD)),
+            Tag::Strikethrough => {
+                self.push_style(Style::default().add_modifier(Modifier::CROSSED_OUT))
+            }
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                self.start_line();
+                let wiki = matches!(link_type, LinkType::WikiLink { .. });
+                self.open_link = Some((self.lines.len(), self.current.len(), dest_url.to_string(), wiki));
+                self.push_style(
+                    Style::default()
+                        .fg(Color::Blue)
+                        .add_modifier(Modifier::UNDERLINED),
+                );
+            }
+            Tag::Image { .. } => {
+                // The alt text follows; the image itself may be drawn below the text
+                self.push_style(Style::default().fg(Color::Magenta));
+                self.push_text("🖼 ");
+            }
+            Tag::Table(alignments) => {
+                self.blank_line();
+                self.table = Some(TableBuffer {
+                    alignments,
+                    rows: Vec::new(),
+                    header_rows: 0,
+                    links: Vec::new(),
+                });
+            }
+            Tag::TableHead => {
+                self.push_style(Style::default().add_modifier(Modifier::BOLD));
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            Tag::TableRow => {
+                if let Some(table) = &mut self.table {
+                    table.rows.push(Vec::new());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Heading(_) => {
+                self.pop_style();
+                self.blank_line();
+            }
+            TagEnd::Paragraph => {
+                // Paragraphs inside list items stay tight
+                if self.list_stack.is_empty() {
+                    self.blank_line();
+                } else {
+                    self.flush_line();
+                }
+            }
+            TagEnd::BlockQuote(_) => {
+                self.pop_style();
+                self.flush_line();
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                if self.quote_depth == 0 {
+                    self.blank_line();
+                }
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code_block.take() {
+                    self.push_code_block(&lang, &code);
+                }
+                self.blank_line();
+            }
+            TagEnd::List(_) => {
+                self.flush_line();
+                self.list_stack.pop();
+                if self.list_stack.is_empty() {
+                    self.blank_line();
+                }
+            }
+            TagEnd::Item => self.flush_line(),
+            TagEnd::MetadataBlock(_) => self.in_metadata = false,
+            TagEnd::TableCell => {
+                let cell = std::mem::take(&mut self.current);
+                if let Some(row) = self.table.as_mut().and_then(|t| t.rows.last_mut()) {
+                    row.push(cell);
+                }
+            }
+            TagEnd::TableHead => {
+                self.pop_style();
+                if let Some(table) = &mut self.table {
+                    table.header_rows = table.rows.len();
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.push_table(table);
+                }
+                self.blank_line();
+            }
+            TagEnd::Link if self.table.is_some() => {
+                // Placed once the table is drawn and the cell's position is known
+                self.pop_style();
+                if let Some(((_, start, target, wiki), table)) =
+                    self.open_link.take().zip(self.table.as_mut())
+                {
+                    let row = table.rows.len().saturating_sub(1);
+                    let cell = table.rows.last().map(Vec::len).unwrap_or(0);
+                    table.links.push((row, cell, start..self.current.len(), target, wiki));
+                }
+            }
+            TagEnd::Link => {
+                self.pop_style();
+                if let Some((line, start, target, wiki)) = self.open_link.take() {
+                    // A link broken across lines is only highlighted on its last line
+                    let start = if line == self.lines.len() { start } else { 0 };
+                    self.links.push(RenderedLink {
+                        line: self.lines.len(),
+                        spans: start..self.current.len(),
+                        target,
+                        wiki,
+                    });
+                }
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough | TagEnd::Image => {
+                self.pop_style()
+            }
+            _ => {}
+        }
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(_) if self.in_metadata => {}
+            Event::Text(text) if self.code_block.is_some() => {
+                // Buffered so the whole block can be highlighted at once
+                if let Some((_, code)) = &mut self.code_block {
+                    code.push_str(&text);
+                }
+            }
+            Event::Text(text) => self.push_text(&text),
+            Event::Code(code) => {
+                self.start_line();
+                self.current.push(Span::styled(
+                    code.to_string(),
+                    Style::default().fg(Color::Yellow).bg(Color::DarkGray),
+                ));
+            }
+            Event::SoftBreak => self.push_text(" "),
+            Event::HardBreak => self.flush_line(),
+            Event::Rule => {
+                self.flush_line();
+                self.lines.push(Line::styled("─".repeat(40), Style::default().fg(Color::DarkGray)));
+                self.blank_line();
+            }
+            Event::TaskListMarker(checked) => {
+                let marker = if checked { "[x] " } else { "[ ] " };
+                self.push_text(marker);
+            }
+            Event::Html(html) | Event::InlineHtml(html) => self.push_text(html.trim_end()),
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> (Text<'static>, Vec<RenderedLink>) {
+        self.flush_line();
+        // Drop the trailing separator line
+        while self.lines.last().is_some_and(|l| l.spans.is_empty()) {
+            self.lines.pop();
+        }
+        (Text::from(self.lines), self.links)
+    }
+}
+
+/// The markdown notes are written in: tables, task lists, strikethrough, frontmatter and
+/// `[[wiki links]]` on top of CommonMark
+pub fn parser_options() -> Options {
+    Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TABLES
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_WIKILINKS
+}
+
+/// Render markdown into styled text for the preview, along with the links it contains
+/// Frontmatter becomes a header block listing its fields
+pub fn render(content: &str) -> (Text<'static>, Vec<RenderedLink>) {
+    let options = parser_options();
+    let mut writer = MarkdownWriter::new();
+    if let Some(frontmatter) = crate::frontmatter::parse(content) {
+        writer.push_metadata(&frontmatter);
+    }
+    for event in Parser::new_ext(content, options) {
+        writer.event(event);
+    }
+    writer.finish()
+}
+
+/// Draw rows of plain cells as a grid like a pipe table's, the first row as its header
+pub fn grid(rows: Vec<Vec<String>>, alignments: Vec<Alignment>) -> Text<'static> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let rows = rows
+        .into_iter()
+        .enumerate()
+        .map(|(r, row)| {
+            row.into_iter()
+                .map(|cell| vec![if r == 0 { Span::styled(cell, bold) } else { Span::raw(cell) }])
+                .collect()
+        })
+        .collect();
+    let mut writer = MarkdownWriter::new();
+    writer.push_table(TableBuffer {
+        alignments,
+        rows,
+        header_rows: 1,
+        links: Vec::new(),
+    });
+    writer.finish().0
+}
+
+/// The headings in rendered text, found by the bold `# ` marker span each one starts with
+/// (code blocks and plain text never produce that span on its own)
+pub fn outline(text: &Text) -> Vec<OutlineEntry> {
+    text.lines
+        .iter()
+        .enumerate()
+        .filter_map(|(line, rendered)| {
+            let marker = rendered.spans.first()?;
+            let hashes = marker.content.strip_suffix(' ')?;
+            let is_marker = (1..=6).contains(&hashes.len())
+                && hashes.chars().all(|c| c == '#')
+                && marker.style.add_modifier.contains(Modifier::BOLD);
+            is_marker.then(|| OutlineEntry {
+                line,
+                level: hashes.len(),
+                title: rendered.spans[1..]
+                    .iter()
+                    .map(|s| s.content.as_ref())
+                    .collect::<String>()
+                    .trim()
+                    .to_string(),
+            })
+        })
+        .collect()
+}
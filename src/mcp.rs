@@ -0,0 +1,215 @@
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use crate::hooks;
+use crate::settings::Settings;
+
+/// Protocol versions spoken, newest first; a client asking for another gets the newest
+const PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Serve the vault's tools over stdin and stdout, one JSON-RPC message per line, until stdin
+/// closes
+pub fn serve(settings: &Settings) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle(settings, &message),
+            Err(e) => Some(error(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        if let Some(reply) = reply {
+            writeln!(stdout, "{}", reply)?;
+            stdout.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn error(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// The reply to a message; notifications, which have no `id`, get none
+fn handle(settings: &Settings, message: &Value) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let params = &message["params"];
+    let result = match message["method"].as_str().unwrap_or("") {
+        "initialize" => {
+            let asked = params["protocolVersion"].as_str().unwrap_or("");
+            let version = PROTOCOL_VERSIONS
+                .iter()
+                .find(|v| **v == asked)
+                .unwrap_or(&PROTOCOL_VERSIONS[0]);
+            json!({
+                "protocolVersion": version,
+                "capabilities": { "tools": {} },
+                "serverInfo": { "name": "lair", "version": env!("CARGO_PKG_VERSION") },
+            })
+        }
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tools() }),
+        "tools/call" => {
+            let Some(name) = params["name"].as_str() else {
+                return Some(error(id, INVALID_PARAMS, "no tool name"));
+            };
+            let arguments = &params["arguments"];
+            let (text, is_error) = match call(settings, name, arguments) {
+                Ok(text) => (text, false),
+                Err(message) => (message, true),
+            };
+            json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+        }
+        method => return Some(error(id, METHOD_NOT_FOUND, &format!("no method {}", method))),
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+/// The tools offered, with the JSON schema of their arguments
+fn tools() -> Value {
+    let path = json!({ "type": "string", "description": "Path relative to the vault; the extension may be left off" });
+    json!([
+        {
+            "name": "list_notes",
+            "description": "List the vault's notes, or a folder's, with each note's path, title, modified time and tags",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "folder": { "type": "string", "description": "Folder relative to the vault" } },
+            },
+        },
+        {
+            "name": "read_note",
+            "description": "Read a note's text",
+            "inputSchema": { "type": "object", "properties": { "path": path }, "required": ["path"] },
+        },
+        {
+            "name": "search_notes",
+            "description": "Find the notes with lines containing the query, ignoring case, and those lines",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "query": { "type": "string" } },
+                "required": ["query"],
+            },
+        },
+        {
+            "name": "create_note",
+            "description": "Create a note in today's folder, named as the user's settings name notes, holding the content given or else filled from their template",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "name": { "type": "string" }, "content": { "type": "string" } },
+            },
+        },
+        {
+            "name": "append_to_note",
+            "description": "Append text to the end of a note, created if it doesn't exist, or to today's daily note when no path is given",
+            "inputSchema": {
+                "type": "object",
+                "properties": { "path": path, "text": { "type": "string" } },
+                "required": ["text"],
+            },
+        },
+    ])
+}
+
+/// Run a tool, answering with its text, or Err with what went wrong
+fn call(settings: &Settings, name: &str, arguments: &Value) -> Result<String, String> {
+    let vault = Path::new(&settings.notes_directory);
+    let storage = crate::storage::open(settings);
+    let argument = |key: &str| arguments[key].as_str().map(str::trim).filter(|v| !v.is_empty());
+    let pretty = |value: &Value| serde_json::to_string_pretty(value).unwrap_or_default();
+    match name {
+        "list_notes" => {
+            let dir = match argument("folder") {
+                Some(folder) if !crate::vault::is_plain_relative(Path::new(folder)) => {
+                    return Err("The folder must be a path inside the vault".to_string());
+                }
+                Some(folder) => vault.join(folder),
+                None => vault.to_path_buf(),
+            };
+            let records: Vec<Value> = crate::vault::files_in(storage.as_ref(), settings, &dir)
+                .iter()
+                .filter(|f| crate::vault::is_note(f, settings))
+                .map(|f| crate::cli::note_record(storage.as_ref(), vault, f))
+                .collect();
+            Ok(pretty(&records.into()))
+        }
+        "read_note" => {
+            let path = argument("path").ok_or("No path given")?;
+            let note = crate::server::note_path(settings, path)
+                .ok_or_else(|| format!("No note {} in the vault", path))?;
+            storage
+                .read_to_string(&note)
+                .map_err(|e| format!("Unable to read {}: {}", path, e))
+        }
+        "search_notes" => {
+            let query = argument("query").ok_or("No query given")?;
+            let records: Vec<Value> = crate::cli::search_notes(settings, query)
+                .iter()
+                .map(|(note, matches)| {
+                    crate::cli::search_record(storage.as_ref(), vault, note, matches)
+                })
+                .collect();
+            Ok(pretty(&records.into()))
+        }
+        "create_note" => {
+            let name = argument("name").unwrap_or("");
+            let content = arguments["content"].as_str();
+            let (note, problem) = crate::cli::create_note(settings, name, None, content)?;
+            let mut record = crate::cli::note_record(storage.as_ref(), vault, &note);
+            if let Some(problem) = problem {
+                record["warning"] = problem.into();
+            }
+            Ok(pretty(&record))
+        }
+        "append_to_note" => {
+            let text = arguments["text"].as_str().unwrap_or("").trim_end();
+            if text.trim().is_empty() {
+                return Err("Nothing to append".to_string());
+            }
+            let note = match argument("path") {
+                Some(path) => named_note(settings, path)?,
+                None => crate::cli::capture_path(settings, None),
+            };
+            let created = crate::note::capture(settings, &note, text)
+                .map_err(|e| format!("Unable to append to {}: {}", note.display(), e))?;
+            let mut problem = None;
+            if created {
+                problem = crate::cli::post_create(vault, &note).err();
+            }
+            if let Err(message) = hooks::run_post_edit(settings, &note) {
+                problem = Some(message);
+            }
+            let mut record = crate::cli::note_record(storage.as_ref(), vault, &note);
+            if let Some(problem) = problem {
+                record["warning"] = problem.into();
+            }
+            Ok(pretty(&record))
+        }
+        _ => Err(format!("No tool {}", name)),
+    }
+}
+
+/// The note a vault-relative path names, which may not exist yet, with the default extension
+/// when it has none; Err for paths leaving the vault and files that aren't notes
+pub fn named_note(settings: &Settings, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative);
+    if !crate::vault::is_plain_relative(relative) {
+        return Err("The path must be inside the vault".to_string());
+    }
+    let mut note = Path::new(&settings.notes_directory).join(relative);
+    if note.extension().is_none() {
+        note.set_extension(&settings.default_file_format);
+    }
+    if !crate::vault::is_note(&note, settings) {
+        return Err(format!("{} isn't a note", relative.display()));
+    }
+    Ok(note)
+}
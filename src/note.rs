@@ -0,0 +1,346 @@
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// Which characters can be typed into a new note or folder name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameCharacters {
+    #[default]
+    Unicode, // Letters and symbols of any script ("réunion", "メモ"), but nothing a path can't hold
+    Ascii, // Only a-z, A-Z, 0-9, space, `-`, `_` and `.`
+}
+
+/// Characters some filesystem rejects in a name, or that would make it a path
+const RESERVED: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+impl NameCharacters {
+    pub fn allows(&self, c: char) -> bool {
+        match self {
+            NameCharacters::Unicode => !c.is_control() && !RESERVED.contains(&c),
+            NameCharacters::Ascii => c.is_ascii_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'),
+        }
+    }
+}
+
+/// Inline `#tag` words in the body (headings like "# Title" don't count)
+fn inline_tags(content: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    let mut in_code_block = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if in_code_block {
+            continue;
+        }
+        for word in line.split_whitespace() {
+            let Some(tag) = word.strip_prefix('#') else {
+                continue;
+            };
+            let tag: String = tag
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '-' || *c == '_' || *c == '/')
+                .collect();
+            // "#1" is more likely an issue number than a tag
+            if tag.chars().any(|c| c.is_alphabetic()) {
+                tags.push(tag);
+            }
+        }
+    }
+    tags
+}
+
+/// Tags of a note: frontmatter `tags:` plus inline #tags, lowercased and deduplicated
+pub fn tags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = crate::frontmatter::parse(content)
+        .map(|fm| fm.list("tags"))
+        .unwrap_or_default()
+        .into_iter()
+        .chain(inline_tags(content))
+        .map(|t| t.trim_start_matches('#').to_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Words per minute assumed for the reading time
+const READING_WPM: usize = 200;
+
+/// Figures about one note, for the note info popup
+pub struct NoteInfo {
+    pub words: usize, // In the body, frontmatter left out
+    pub reading_minutes: usize,
+    pub headings: usize,
+    pub links: usize,
+    pub tasks: usize,
+    pub tasks_done: usize,
+}
+
+pub fn info(content: &str) -> NoteInfo {
+    let words = crate::frontmatter::body(content).split_whitespace().count();
+    let (text, links) = crate::markdown::render(content);
+    let boxes: Vec<&str> = content
+        .lines()
+        .map(|l| l.trim_start().trim_start_matches(['-', '*']).trim_start())
+        .filter(|l| l.starts_with("[ ]") || l.starts_with("[x]") || l.starts_with("[X]"))
+        .collect();
+    NoteInfo {
+        words,
+        reading_minutes: words.div_ceil(READING_WPM),
+        headings: crate::markdown::outline(&text).len(),
+        links: links.len(),
+        tasks: boxes.len(),
+        tasks_done: boxes.iter().filter(|b| !b.starts_with("[ ]")).count(),
+    }
+}
+
+/// Language of a note from its frontmatter `lang:` (or `language:`) field, lowercased
+pub fn language(content: &str) -> Option<String> {
+    let frontmatter = crate::frontmatter::parse(content)?;
+    frontmatter
+        .get("lang")
+        .or_else(|| frontmatter.get("language"))
+        .map(|l| l.trim().to_lowercase())
+        .filter(|l| !l.is_empty())
+}
+
+/// A name made safe and tidy for a file: lowercase letters and digits, with every run of
+/// anything else (spaces, punctuation, characters filesystems reject) made a single `-`
+/// "Meeting With ACME!" becomes `meeting-with-acme`
+pub fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+/// A line for appending with the time it was written: "- 2026-10-16 14:03 text"
+pub fn timestamped(text: &str) -> String {
+    format!("- {} {}", chrono::Local::now().format("%Y-%m-%d %H:%M"), text)
+}
+
+/// Add a line to the end of a note, on a line of its own even when the note doesn't end with
+/// a newline
+pub fn append_line(storage: &dyn VaultStorage, path: &Path, line: &str) -> io::Result<()> {
+    let content = storage.read_to_string(path)?;
+    let separator = if content.is_empty() || content.ends_with('\n') { "" } else { "\n" };
+    storage.append(path, format!("{}{}\n", separator, line).as_bytes())
+}
+
+/// Add captured text to the end of a note, creating it and its folders if it doesn't exist
+/// Several lines go in as a block, a single one as a line, timestamped if `append_timestamp`
+/// is set; a new note gets its frontmatter first
+/// Returns whether the note was created
+pub fn capture(settings: &Settings, path: &Path, text: &str) -> io::Result<bool> {
+    let storage = crate::storage::open(settings);
+    let created = !storage.exists(path);
+    if created {
+        if let Some(parent) = path.parent() {
+            storage.create_dir_all(parent)?;
+        }
+        storage.write(path, b"")?;
+        fill_created_note(settings, path)?;
+    }
+    if text.contains('\n') {
+        append_block(storage.as_ref(), path, text)?;
+    } else if settings.append_timestamp {
+        append_line(storage.as_ref(), path, &timestamped(text))?;
+    } else {
+        append_line(storage.as_ref(), path, text)?;
+    }
+    Ok(created)
+}
+
+/// Add a block of lines to the end of a note, after a blank line unless the note is empty
+/// Returns the line the block starts on, 1-based
+pub fn append_block(storage: &dyn VaultStorage, path: &Path, block: &str) -> io::Result<usize> {
+    let content = storage.read_to_string(path)?;
+    let separator = if content.is_empty() || content.ends_with("\n\n") {
+        ""
+    } else if content.ends_with('\n') {
+        "\n"
+    } else {
+        "\n\n"
+    };
+    storage.append(path, format!("{}{}\n", separator, block.trim_end_matches('\n')).as_bytes())?;
+    Ok(format!("{}{}", content, separator).lines().count() + 1)
+}
+
+/// The file name for a new note: the name given, with the extension added if it's missing,
+/// or a timestamp when there's none
+pub fn note_file_name(note_name: Option<&str>, file_format: &str) -> String {
+    let now = chrono::Local::now();
+    match note_name.map(str::trim) {
+        Some(trimmed) if !trimmed.is_empty() => {
+            if trimmed.ends_with(&format!(".{}", file_format)) {
+                trimmed.to_string()
+            } else {
+                format!("{}.{}", trimmed, file_format)
+            }
+        }
+        // No name provided, use timestamp
+        _ => format!("notes-{}.{}", now.format("%y-%m-%d_%H-%M-%S"), file_format),
+    }
+}
+
+/// The folder a new note goes in: target_dir if provided, else today's date-based folder
+pub fn note_directory(notes_dir: &str, target_dir: Option<&PathBuf>) -> PathBuf {
+    if let Some(target) = target_dir {
+        // Use provided target directory
+        target.clone()
+    } else {
+        // Use date-based folder structure (YY-MM-DD)
+        let base_dir = PathBuf::from(notes_dir);
+        let date_folder = chrono::Local::now().format("%y-%m-%d").to_string();
+        base_dir.join(&date_folder)
+    }
+}
+
+/// Create a new note file with date-based organization
+/// Returns the full path to the created note file
+/// If target_dir is provided, creates the note in that directory instead of date-based folder
+/// A new file starts with `content` (such as a rendered template), or empty
+pub fn create_note_file(
+    storage: &dyn VaultStorage,
+    notes_dir: &str,
+    note_name: Option<&str>,
+    file_format: &str,
+    target_dir: Option<&PathBuf>,
+    content: Option<&str>,
+) -> io::Result<PathBuf> {
+    // Determine the target directory
+    let date_dir = note_directory(notes_dir, target_dir);
+    
+    // Ensure the date directory exists
+    storage.create_dir_all(&date_dir)?;
+    
+    // Determine the file name
+    let file_name = note_file_name(note_name, file_format);
+    if !crate::vault::is_file_name(&file_name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} isn't a plain file name", file_name),
+        ));
+    }
+    
+    let file_path = date_dir.join(&file_name);
+    
+    // Create the file if it doesn't exist
+    if !storage.exists(&file_path) {
+        storage.write(&file_path, content.unwrap_or("").as_bytes())?;
+    }
+    
+    Ok(file_path)
+}
+
+/// The name typed for a new note, without the note extension if it was typed too
+fn typed_note_name(settings: &Settings, input: &str) -> String {
+    let typed = input.trim();
+    typed
+        .strip_suffix(&format!(".{}", settings.default_file_format))
+        .unwrap_or(typed)
+        .to_string()
+}
+
+/// The file name for a new note named `input`, slugified and starting with an ID if set to be
+pub fn new_note_file_name(settings: &Settings, input: &str) -> String {
+    let typed = typed_note_name(settings, input);
+    let format = &settings.default_file_format;
+    let slugified = settings.slugify_names && !typed.is_empty();
+    if settings.note_ids {
+        let id = crate::ids::new_id(settings, chrono::Local::now().naive_local());
+        let (name, separator) = if slugified {
+            (crate::note::slug(&typed), '-')
+        } else {
+            (typed, ' ')
+        };
+        crate::ids::file_name(&id, &name, separator, format)
+    } else if slugified {
+        note_file_name(Some(&crate::note::slug(&typed)), format)
+    } else {
+        note_file_name(Some(input), format)
+    }
+}
+
+/// The title of a new note named `input`: what was typed when the file name is slugified,
+/// else the file name without its extension
+pub fn new_note_title(settings: &Settings, input: &str) -> String {
+    let typed = typed_note_name(settings, input);
+    if settings.slugify_names && !typed.is_empty() {
+        return typed;
+    }
+    Path::new(&note_file_name(Some(input), &settings.default_file_format))
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// Finish a note just created for the name `input` in `folder`, each if set to be: the
+/// `frontmatter_template`, a `# ` heading with the typed name when it was slugified and
+/// no template gave the note its text, and its ID in the frontmatter
+pub fn fill_new_note(
+    settings: &Settings,
+    note: &Path,
+    input: &str,
+    folder: &Path,
+    from_template: bool,
+) -> Result<(), String> {
+    let title = new_note_title(settings, input);
+    let storage = crate::storage::open(settings);
+    if settings.new_note_frontmatter {
+        let fields = crate::templates::render_frontmatter(settings, &title, folder);
+        storage
+            .read_to_string(note)
+            .and_then(|content| {
+                storage.write(note, crate::frontmatter::add_fields(&content, &fields).as_bytes())
+            })
+            .map_err(|e| format!("Unable to add the note's frontmatter: {}", e))?;
+    }
+    if settings.slugify_names && !from_template && !typed_note_name(settings, input).is_empty() {
+        crate::note::append_block(storage.as_ref(), note, &format!("# {}", title))
+            .map_err(|e| format!("Unable to add the note's heading: {}", e))?;
+    }
+    if settings.note_ids
+        && settings.note_id_frontmatter
+        && let Some(id) = crate::ids::id_of(note)
+    {
+        crate::ids::write_to_frontmatter(note, &id)
+            .map_err(|e| format!("Unable to add the note's ID: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Finish a note LAIR named itself, such as the daily note or a capture's note, as
+/// `fill_new_note` does a note named in the popup
+pub fn fill_created_note(settings: &Settings, note: &Path) -> io::Result<()> {
+    let name = note.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let folder = note.parent().unwrap_or(Path::new(&settings.notes_directory));
+    fill_new_note(settings, note, &name, folder, true).map_err(io::Error::other)
+}
+
+/// `file_name` with the first number from 2 up that no file in `folder` has yet:
+/// `plan.md` becomes `plan-2.md`, then `plan-3.md`
+pub fn numbered_file_name(storage: &dyn VaultStorage, folder: &Path, file_name: &str) -> String {
+    let path = Path::new(file_name);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (2..)
+        .map(|n| format!("{}-{}{}", stem, n, extension))
+        .find(|name| !storage.exists(&folder.join(name)))
+        .unwrap_or_else(|| file_name.to_string())
+}
@@ -0,0 +1,72 @@
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// How an event is announced
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifyStyle {
+    #[default]
+    Off,
+    Bell,  // Terminal bell
+    Flash, // Briefly invert the screen header
+    Desktop, // Desktop notification, when the terminal isn't focused
+}
+
+/// Events that can produce a notification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotifyEvent {
+    ScanComplete, // A slow background scan of the vault finished
+    Export,       // A note, its tasks or annotations were exported
+    Sync,         // Completed tasks were synced back
+    Backup,       // The vault was committed
+}
+
+/// Per-event notification styles
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub scan_complete: NotifyStyle,
+    pub export: NotifyStyle,
+    pub sync: NotifyStyle,
+    pub backup: NotifyStyle,
+}
+
+impl NotificationSettings {
+    pub fn style_for(&self, event: NotifyEvent) -> NotifyStyle {
+        match event {
+            NotifyEvent::ScanComplete => self.scan_complete,
+            NotifyEvent::Export => self.export,
+            NotifyEvent::Sync => self.sync,
+            NotifyEvent::Backup => self.backup,
+        }
+    }
+}
+
+/// Show a desktop notification with notify-send, or osascript on macOS
+pub fn send_desktop(notifier: &Path, message: &str) -> io::Result<()> {
+    let mut command = Command::new(notifier);
+    if notifier.file_name().is_some_and(|n| n == "osascript") {
+        let quoted = message.replace('\\', "\\\\").replace('"', "\\\"");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"LAIR\"",
+            quoted
+        ));
+    } else {
+        command.arg("LAIR").arg(message);
+    }
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    Ok(())
+}
+
+/// Ring the terminal bell
+pub fn ring_bell() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07");
+    let _ = stdout.flush();
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A single filesystem change a bulk operation wants to make
+#[derive(Debug, Clone)]
+pub enum Operation {
+    Move { from: PathBuf, to: PathBuf },
+    RemoveDir(PathBuf),
+    RemoveFile(PathBuf),
+}
+
+impl Operation {
+    /// Human readable description, with paths shown relative to `root`
+    pub fn describe(&self, root: &Path) -> String {
+        let relative = |p: &Path| p.strip_prefix(root).unwrap_or(p).display().to_string();
+        match self {
+            Operation::Move { from, to } => format!("move {} → {}", relative(from), relative(to)),
+            Operation::RemoveDir(dir) => format!("remove empty folder {}", relative(dir)),
+            Operation::RemoveFile(file) => format!("remove empty file {}", relative(file)),
+        }
+    }
+
+    fn apply(&self) -> io::Result<()> {
+        match self {
+            Operation::Move { from, to } => {
+                if to.exists() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!("{} already exists", to.display()),
+                    ));
+                }
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::rename(from, to)
+            }
+            Operation::RemoveDir(dir) => fs::remove_dir(dir),
+            Operation::RemoveFile(file) => fs::remove_file(file),
+        }
+    }
+}
+
+/// Outcome of a single operation once the plan has been applied
+#[derive(Debug, Clone)]
+pub enum OperationResult {
+    Done,
+    Failed(String),
+}
+
+/// A reviewable set of operations produced by a bulk action (restructure, purge, ...)
+/// Plans are always shown on the review screen first; while dry-run is on they can only be
+/// inspected and exported, never applied
+pub struct Plan {
+    pub title: String,
+    pub root: PathBuf, // Folder the plan operates on, used for display
+    pub operations: Vec<Operation>,
+    pub results: Option<Vec<OperationResult>>, // Filled in once applied
+}
+
+impl Plan {
+    pub fn new(title: impl Into<String>, root: PathBuf, operations: Vec<Operation>) -> Plan {
+        Plan {
+            title: title.into(),
+            root,
+            operations,
+            results: None,
+        }
+    }
+
+    pub fn is_applied(&self) -> bool {
+        self.results.is_some()
+    }
+
+    /// Apply every operation, recording per-operation results
+    /// Folders emptied by moves and removed files are removed afterwards
+    /// Returns (succeeded, failed)
+    pub fn apply(&mut self) -> (usize, usize) {
+        let mut results = Vec::with_capacity(self.operations.len());
+        let mut emptied: Vec<PathBuf> = Vec::new();
+
+        for op in &self.operations {
+            match op.apply() {
+                Ok(()) => {
+                    if let Operation::Move { from, .. } | Operation::RemoveFile(from) = op
+                        && let Some(parent) = from.parent()
+                    {
+                        emptied.push(parent.to_path_buf());
+                    }
+                    results.push(OperationResult::Done);
+                }
+                Err(e) => results.push(OperationResult::Failed(e.to_string())),
+            }
+        }
+
+        emptied.sort();
+        emptied.dedup();
+        for dir in emptied {
+            // Only succeeds for empty directories, which is exactly what we want
+            let _ = fs::remove_dir(&dir);
+        }
+
+        let failed = results
+            .iter()
+            .filter(|r| matches!(r, OperationResult::Failed(_)))
+            .count();
+        let succeeded = results.len() - failed;
+        self.results = Some(results);
+        (succeeded, failed)
+    }
+
+    /// One line per operation, annotated with its result once applied
+    pub fn report_lines(&self) -> Vec<String> {
+        self.operations
+            .iter()
+            .enumerate()
+            .map(|(i, op)| {
+                let description = op.describe(&self.root);
+                match self.results.as_ref().and_then(|r| r.get(i)) {
+                    Some(OperationResult::Done) => format!("[done] {}", description),
+                    Some(OperationResult::Failed(e)) => format!("[failed] {} ({})", description, e),
+                    None => format!("[planned] {}", description),
+                }
+            })
+            .collect()
+    }
+
+    /// Write the report to `<vault>/.lair/logs/` and return the log file path
+    pub fn export_log(&self, vault: &Path) -> io::Result<PathBuf> {
+        let logs_dir = vault.join(crate::hooks::VAULT_CONFIG_DIR).join("logs");
+        fs::create_dir_all(&logs_dir)?;
+
+        let now = chrono::Local::now();
+        let slug: String = self
+            .title
+            .to_lowercase()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '-' })
+            .collect();
+        let log_path = logs_dir.join(format!("{}-{}.log", now.format("%Y-%m-%d_%H-%M-%S"), slug));
+
+        let status = if self.is_applied() { "applied" } else { "dry run" };
+        let mut content = format!(
+            "{}\nfolder: {}\nstatus: {}\ndate: {}\n\n",
+            self.title,
+            self.root.display(),
+            status,
+            now.to_rfc3339()
+        );
+        for line in self.report_lines() {
+            content.push_str(&line);
+            content.push('\n');
+        }
+
+        fs::write(&log_path, content)?;
+        Ok(log_path)
+    }
+}
+
+/// Plan removing every empty folder below `folder` (deepest first, so nested empties go too)
+pub fn plan_purge_empty_folders(folder: &Path) -> io::Result<Vec<Operation>> {
+    fn visit(dir: &Path, operations: &mut Vec<Operation>) -> io::Result<bool> {
+        let mut empty = true;
+        let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .collect();
+        entries.sort();
+        for entry in entries {
+            // The vault's config folder is never purged
+            if entry.ends_with(crate::hooks::VAULT_CONFIG_DIR) {
+                empty = false;
+                continue;
+            }
+            if entry.is_dir() && visit(&entry, operations)? {
+                operations.push(Operation::RemoveDir(entry));
+            } else {
+                empty = false;
+            }
+        }
+        Ok(empty)
+    }
+
+    let mut operations = Vec::new();
+    visit(folder, &mut operations)?;
+    Ok(operations)
+}
@@ -0,0 +1,393 @@
+use ratatui::style::{Color, Style};
+use serde::{Deserialize, Serialize};
+use ratatui::text::{Line, Span, Text};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+use crate::images;
+use crate::markdown::RenderedLink;
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// Files larger than this many bytes are read a window at a time (0 for never)
+static LARGE_FILE_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// How much of a large file is read at once
+pub const WINDOW_BYTES: u64 = 64 * 1024;
+
+/// How much of a file is looked at to tell whether it's binary
+const SNIFF_BYTES: u64 = 8 * 1024;
+
+/// How much of a binary file the hexdump shows
+const HEXDUMP_BYTES: u64 = 16 * 1024;
+
+/// Leading bytes of common binary formats, and what to call them
+const MAGIC_NUMBERS: [(&[u8], &str); 12] = [
+    (b"%PDF", "PDF document"),
+    (b"PK\x03\x04", "ZIP archive"),
+    (b"\x1f\x8b", "gzip archive"),
+    (b"\x28\xb5\x2f\xfd", "zstd archive"),
+    (b"7z\xbc\xaf\x27\x1c", "7-Zip archive"),
+    (b"\x7fELF", "ELF executable"),
+    (b"MZ", "Windows executable"),
+    (b"SQLite format 3\0", "SQLite database"),
+    (b"ID3", "MP3 audio"),
+    (b"OggS", "Ogg media"),
+    (b"fLaC", "FLAC audio"),
+    (b"RIFF", "RIFF media (WAV / AVI)"),
+];
+
+/// Apply the large file threshold from the settings
+pub fn configure(settings: &Settings) {
+    LARGE_FILE_BYTES.store(settings.large_file_kb.saturating_mul(1024), Ordering::Relaxed);
+}
+
+/// Where the browse screen shows the preview pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewPosition {
+    #[default]
+    Right,
+    Bottom,
+    Off,
+}
+
+/// Limits for the preview's share of the browse screen, in percent
+pub const MIN_PREVIEW_SIZE: u16 = 10;
+pub const MAX_PREVIEW_SIZE: u16 = 90;
+
+/// Size of a note's source text
+#[derive(Debug, Clone, Copy)]
+pub struct Counts {
+    pub words: usize,
+    pub chars: usize,
+    pub lines: usize,
+}
+
+impl Counts {
+    pub fn of(content: &str) -> Counts {
+        Counts {
+            words: content.split_whitespace().count(),
+            chars: content.chars().count(),
+            lines: content.lines().count(),
+        }
+    }
+
+    /// Short form for a pane footer, e.g. "120 words · 640 chars · 18 lines"
+    pub fn summary(&self) -> String {
+        format!("{} words · {} chars · {} lines", self.words, self.chars, self.lines)
+    }
+}
+
+/// Rendered contents of the note shown in the preview pane
+pub struct Preview {
+    pub path: PathBuf,
+    pub text: Text<'static>,
+    pub image: Option<PathBuf>, // Image file itself, or the first image a note embeds
+    pub links: Vec<RenderedLink>,
+    pub counts: Option<Counts>,   // None for images, large and unreadable files
+    pub window: Option<Window>,   // The part that was read, for files over the size limit
+    pub binary: bool,             // Not text, so summarized instead of shown
+    pub storage: Arc<dyn VaultStorage>, // Where the file was read from, for reloading it
+    modified: Option<SystemTime>, // When the file was last changed, as of loading it
+}
+
+/// The loaded part of a large file, which is shown as plain text
+#[derive(Debug, Clone)]
+pub struct Window {
+    pub start: u64, // Byte offset of the first loaded line
+    pub end: u64,   // Byte offset just past the last loaded line
+    pub file_size: u64,
+    line_starts: Vec<u64>, // Byte offset of each loaded line
+}
+
+impl Window {
+    /// Byte offset of loaded line `line`
+    pub fn line_start(&self, line: usize) -> Option<u64> {
+        self.line_starts.get(line).copied()
+    }
+
+    /// Index of the loaded line starting at byte `offset`
+    pub fn line_at(&self, offset: u64) -> Option<usize> {
+        self.line_starts.binary_search(&offset).ok()
+    }
+
+    /// Where in the file the window is, e.g. "bytes 256.0 KiB–512.0 KiB of 200.0 MiB"
+    pub fn summary(&self) -> String {
+        format!(
+            "bytes {}–{} of {}",
+            format_bytes(self.start),
+            format_bytes(self.end),
+            format_bytes(self.file_size)
+        )
+    }
+}
+
+/// A byte count in the largest fitting binary unit
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+impl Preview {
+    /// Whether the file changed on disk since it was loaded
+    pub fn is_stale(&self) -> bool {
+        self.storage.modified(&self.path).ok() != self.modified
+    }
+}
+
+/// Whether a file should be rendered as markdown
+fn is_markdown(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+/// What kind of binary file starts with `head`, or None if it looks like text
+/// Text is UTF-8 without NUL bytes; a character cut off at the end of `head` is allowed
+fn binary_kind(head: &[u8]) -> Option<&'static str> {
+    if let Some((_, kind)) = MAGIC_NUMBERS.iter().find(|(magic, _)| head.starts_with(magic)) {
+        return Some(kind);
+    }
+    if head.get(4..8) == Some(b"ftyp") {
+        return Some("MP4 / QuickTime media");
+    }
+    let invalid = match std::str::from_utf8(head) {
+        Ok(_) => false,
+        Err(e) => e.error_len().is_some(),
+    };
+    (invalid || head.contains(&0)).then_some("unknown format")
+}
+
+/// A binary file's size and kind, in place of its contents
+fn binary_summary(
+    storage: &Arc<dyn VaultStorage>,
+    path: &Path,
+    kind: &str,
+    modified: Option<SystemTime>,
+) -> Preview {
+    let size = storage.len(path).unwrap_or(0);
+    let dim = Style::default().fg(Color::DarkGray);
+    Preview {
+        path: path.to_path_buf(),
+        text: Text::from(vec![
+            Line::from(format!("Binary file: {}, {}", kind, format_bytes(size))),
+            Line::default(),
+            Line::styled("Press V to see a hexdump", dim),
+        ]),
+        image: None,
+        links: Vec::new(),
+        counts: None,
+        window: None,
+        binary: true,
+        storage: storage.clone(),
+        modified,
+    }
+}
+
+/// The start of a file as a hexdump: offset, 16 bytes in hex, and the printable ones
+pub fn load_hexdump(storage: &Arc<dyn VaultStorage>, path: &Path) -> Preview {
+    let modified = storage.modified(path).ok();
+    let size = storage.len(path).unwrap_or(0);
+    let offset_style = Style::default().fg(Color::DarkGray);
+    let text = match storage.read_range(path, 0, HEXDUMP_BYTES) {
+        Ok(bytes) => {
+            let mut lines: Vec<Line> = bytes
+                .chunks(16)
+                .enumerate()
+                .map(|(i, row)| {
+                    let hex: Vec<String> = row.iter().map(|b| format!("{:02x}", b)).collect();
+                    let ascii: String = row
+                        .iter()
+                        .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                        .collect();
+                    Line::from(vec![
+                        Span::styled(format!("{:08x}  ", i * 16), offset_style),
+                        Span::raw(format!("{:<48} ", hex.join(" "))),
+                        Span::styled(format!("|{}|", ascii), Style::default().fg(Color::Cyan)),
+                    ])
+                })
+                .collect();
+            if size > bytes.len() as u64 {
+                lines.push(Line::styled(
+                    format!("… {} more", format_bytes(size - bytes.len() as u64)),
+                    offset_style,
+                ));
+            }
+            Text::from(lines)
+        }
+        Err(e) => Text::from(Line::styled(format!("Unable to preview: {}", e), offset_style)),
+    };
+    Preview {
+        path: path.to_path_buf(),
+        text,
+        image: None,
+        links: Vec::new(),
+        counts: None,
+        window: None,
+        binary: true,
+        storage: storage.clone(),
+        modified,
+    }
+}
+
+/// Read and render a file for the preview pane
+/// Files over the size limit only get their first window read, binary files a summary
+pub fn load(storage: &Arc<dyn VaultStorage>, path: &Path) -> Preview {
+    let modified = storage.modified(path).ok();
+    if images::is_image(path) {
+        return Preview {
+            path: path.to_path_buf(),
+            text: Text::from(images::placeholder(path)),
+            image: Some(path.to_path_buf()),
+            links: Vec::new(),
+            counts: None,
+            window: None,
+            binary: false,
+            storage: storage.clone(),
+            modified,
+        };
+    }
+    if let Ok(head) = storage.read_range(path, 0, SNIFF_BYTES)
+        && let Some(kind) = binary_kind(&head)
+    {
+        return binary_summary(storage, path, kind, modified);
+    }
+    let limit = LARGE_FILE_BYTES.load(Ordering::Relaxed);
+    if limit > 0
+        && let Ok(len) = storage.len(path)
+        && len > limit
+    {
+        return load_window(storage, path, 0);
+    }
+
+    let mut image = None;
+    let mut links = Vec::new();
+    let content = storage.read_to_string(path);
+    let counts = content.as_deref().ok().map(Counts::of);
+    let text = match content {
+        Ok(content) if is_markdown(path) => {
+            image = images::first_local_image(path, &content);
+            let content = crate::citations::resolve(&content).unwrap_or(content);
+            let (mut text, rendered_links) = crate::cards::render(path, &content)
+                .unwrap_or_else(|| crate::markdown::render(&content));
+            crate::theme::adapt(&mut text);
+            links = rendered_links;
+            text
+        }
+        Ok(content) => match crate::csv::delimiter(path) {
+            Some(delimiter) => crate::csv::render(&content, delimiter),
+            None => Text::from(content),
+        },
+        Err(e) => Text::from(Line::styled(
+            format!("Unable to preview: {}", e),
+            Style::default().fg(Color::DarkGray),
+        )),
+    };
+    Preview {
+        path: path.to_path_buf(),
+        text,
+        image,
+        links,
+        counts,
+        window: None,
+        binary: false,
+        storage: storage.clone(),
+        modified,
+    }
+}
+
+/// Read the lines of a large file from byte `start` (the start of a line) onwards, up to
+/// `WINDOW_BYTES`, as plain text
+pub fn load_window(storage: &Arc<dyn VaultStorage>, path: &Path, start: u64) -> Preview {
+    let modified = storage.modified(path).ok();
+    let (text, window) = match read_window(storage.as_ref(), path, start) {
+        Ok((lines, window)) => (Text::from(lines), Some(window)),
+        Err(e) => (
+            Text::from(Line::styled(
+                format!("Unable to preview: {}", e),
+                Style::default().fg(Color::DarkGray),
+            )),
+            None,
+        ),
+    };
+    Preview {
+        path: path.to_path_buf(),
+        text,
+        image: None,
+        links: Vec::new(),
+        counts: None,
+        window,
+        binary: false,
+        storage: storage.clone(),
+        modified,
+    }
+}
+
+fn read_window(
+    storage: &dyn VaultStorage,
+    path: &Path,
+    start: u64,
+) -> io::Result<(Vec<Line<'static>>, Window)> {
+    let file_size = storage.len(path)?;
+    let start = start.min(file_size);
+    let mut bytes = storage.read_range(path, start, WINDOW_BYTES)?;
+    // Stop at the last complete line, unless the window holds less than one
+    if start + (bytes.len() as u64) < file_size
+        && let Some(newline) = bytes.iter().rposition(|&b| b == b'\n')
+    {
+        bytes.truncate(newline + 1);
+    }
+    let end = start + bytes.len() as u64;
+
+    let body = bytes.strip_suffix(b"\n").unwrap_or(&bytes);
+    let mut lines = Vec::new();
+    let mut line_starts = Vec::new();
+    let mut offset = start;
+    for line in body.split(|&b| b == b'\n') {
+        line_starts.push(offset);
+        offset += line.len() as u64 + 1;
+        let line = String::from_utf8_lossy(line);
+        lines.push(Line::raw(line.trim_end_matches('\r').to_string()));
+    }
+    Ok((
+        lines,
+        Window {
+            start,
+            end,
+            file_size,
+            line_starts,
+        },
+    ))
+}
+
+/// Start of the line that begins at most `WINDOW_BYTES / 2` before byte `offset`, for
+/// moving a window back through a large file
+pub fn earlier_line_start(storage: &dyn VaultStorage, path: &Path, offset: u64) -> io::Result<u64> {
+    let from = offset.saturating_sub(WINDOW_BYTES / 2);
+    if from == 0 {
+        return Ok(0);
+    }
+    let bytes = storage.read_range(path, from - 1, offset - from + 1)?;
+    // The first newline ends a line that may have started before `from`; a line too long
+    // to have one is cut at `from`
+    Ok(bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|i| from + i as u64)
+        .filter(|&line| line < offset)
+        .unwrap_or(from))
+}
@@ -0,0 +1,232 @@
+use chrono::NaiveDate;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// Date format of the "added" field
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Where an item on the reading list stands
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadingStatus {
+    ToRead,
+    Reading,
+    Done,
+    Dropped,
+}
+
+impl ReadingStatus {
+    /// The word stored in the list note
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            ReadingStatus::ToRead => "to-read",
+            ReadingStatus::Reading => "reading",
+            ReadingStatus::Done => "done",
+            ReadingStatus::Dropped => "dropped",
+        }
+    }
+
+    fn parse(word: &str) -> Option<ReadingStatus> {
+        [
+            ReadingStatus::ToRead,
+            ReadingStatus::Reading,
+            ReadingStatus::Done,
+            ReadingStatus::Dropped,
+        ]
+        .into_iter()
+        .find(|s| s.keyword().eq_ignore_ascii_case(word))
+    }
+}
+
+/// A line of the reading list: "- status | added | title | source"
+/// The source (a URL, or an author for books) is optional
+#[derive(Debug, Clone)]
+pub struct ReadingItem {
+    pub status: ReadingStatus,
+    pub added: Option<NaiveDate>,
+    pub title: String,
+    pub source: Option<String>,
+    line: usize, // Index of the item's line in the list note
+}
+
+impl ReadingItem {
+    fn parse(line: usize, text: &str) -> Option<ReadingItem> {
+        let fields: Vec<&str> = text.strip_prefix("- ")?.split('|').map(str::trim).collect();
+        let status = ReadingStatus::parse(fields.first()?)?;
+        let title = fields.get(2).filter(|t| !t.is_empty())?.to_string();
+        Some(ReadingItem {
+            status,
+            added: fields
+                .get(1)
+                .and_then(|d| NaiveDate::parse_from_str(d, DATE_FORMAT).ok()),
+            title,
+            source: fields
+                .get(3)
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string()),
+            line,
+        })
+    }
+
+    fn to_line(&self) -> String {
+        let added = self
+            .added
+            .map(|d| d.format(DATE_FORMAT).to_string())
+            .unwrap_or_default();
+        let mut line = format!("- {} | {} | {}", self.status.keyword(), added, self.title);
+        if let Some(source) = &self.source {
+            line.push_str(&format!(" | {}", source));
+        }
+        line
+    }
+}
+
+/// The reading list note
+pub fn list_path(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(&settings.reading_list)
+}
+
+/// Every item on the reading list, in the order they appear
+/// Other lines of the note are ignored
+pub fn load(settings: &Settings) -> io::Result<Vec<ReadingItem>> {
+    let content = match fs::read_to_string(list_path(settings)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(content
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| ReadingItem::parse(i, line))
+        .collect())
+}
+
+/// Capture "title", "url" or "title url" as a new item to read
+pub fn add(settings: &Settings, input: &str, today: NaiveDate) -> io::Result<ReadingItem> {
+    let (urls, words): (Vec<&str>, Vec<&str>) = input
+        .split_whitespace()
+        .partition(|w| w.starts_with("http://") || w.starts_with("https://"));
+    let source = urls.first().map(|u| u.to_string());
+    let title = match (words.join(" "), &source) {
+        (title, _) if !title.is_empty() => title,
+        (_, Some(url)) => url.clone(),
+        _ => return Err(io::Error::other("type a title or a URL")),
+    };
+    let path = list_path(settings);
+    let mut content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => "# Reading list\n\n".to_string(),
+        Err(e) => return Err(e),
+    };
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    let item = ReadingItem {
+        status: ReadingStatus::ToRead,
+        added: Some(today),
+        title: title.replace('|', "/"), // The field separator can't appear in a field
+        source,
+        line: content.lines().count(),
+    };
+    content.push_str(&item.to_line());
+    content.push('\n');
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    Ok(item)
+}
+
+/// Rewrite (or with None, remove) an item's line, as long as the note still has it there
+fn replace_line(
+    settings: &Settings,
+    item: &ReadingItem,
+    replacement: Option<String>,
+) -> io::Result<()> {
+    let path = list_path(settings);
+    let content = fs::read_to_string(&path)?;
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let unchanged = lines
+        .get(item.line)
+        .and_then(|l| ReadingItem::parse(item.line, l))
+        .is_some_and(|current| current.title == item.title);
+    if !unchanged {
+        return Err(io::Error::other(
+            "the reading list changed on disk, reopen it",
+        ));
+    }
+    match replacement {
+        Some(line) => lines[item.line] = line,
+        None => {
+            lines.remove(item.line);
+        }
+    }
+    let mut updated = lines.join("\n");
+    updated.push('\n');
+    fs::write(&path, updated)
+}
+
+/// Change an item's status in place
+pub fn set_status(
+    settings: &Settings,
+    item: &ReadingItem,
+    status: ReadingStatus,
+) -> io::Result<()> {
+    let updated = ReadingItem {
+        status,
+        ..item.clone()
+    };
+    replace_line(settings, item, Some(updated.to_line()))
+}
+
+/// File name for a literature note: the title's letters and digits, dash-separated
+fn note_name(title: &str) -> String {
+    let name = title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if name.is_empty() {
+        "untitled".to_string()
+    } else {
+        name
+    }
+}
+
+/// Move a finished item off the list into a literature note of its own
+/// An existing note of the same name is never overwritten
+pub fn finish(settings: &Settings, item: &ReadingItem, today: NaiveDate) -> io::Result<PathBuf> {
+    let dir = Path::new(&settings.notes_directory).join(&settings.literature_directory);
+    fs::create_dir_all(&dir)?;
+    let name = note_name(&item.title);
+    let mut path = dir.join(format!("{}.{}", name, settings.default_file_format));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}.{}", name, n, settings.default_file_format));
+        n += 1;
+    }
+
+    let mut note = format!("---\ntitle: \"{}\"\n", item.title.replace('"', "'"));
+    if let Some(source) = &item.source {
+        note.push_str(&format!("source: {}\n", source));
+    }
+    if let Some(added) = item.added {
+        note.push_str(&format!("added: {}\n", added.format(DATE_FORMAT)));
+    }
+    note.push_str(&format!(
+        "finished: {}\n---\n# {}\n\n## Notes\n\n",
+        today.format(DATE_FORMAT),
+        item.title
+    ));
+    fs::write(&path, note)?;
+
+    if let Err(e) = replace_line(settings, item, None) {
+        // Keep the item on the list rather than have it in two places
+        let _ = fs::remove_file(&path);
+        return Err(e);
+    }
+    Ok(path)
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use crate::settings::Settings;
+
+/// How long `lair remote` waits for the running LAIR to answer; it can't while an external
+/// editor has the terminal
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What `lair remote` asks the running LAIR to do, one JSON object per line on the socket
+/// Notes are absolute paths, resolved by the command before sending
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    Open { note: PathBuf, edit: bool },
+    Capture { note: PathBuf, text: String },
+}
+
+/// The answer to a request, sent back as a line starting `ok ` or `error `
+pub type Reply = Result<String, String>;
+
+/// Where requests are handed to the TUI, each with the way to answer it
+pub type Requests = Sender<(Request, Sender<Reply>)>;
+
+/// Hand a request to the TUI and wait for its answer; None once the TUI has gone
+pub fn ask(requests: &Requests, request: Request) -> Option<Reply> {
+    let (reply_tx, reply_rx) = mpsc::channel();
+    requests.send((request, reply_tx)).ok()?;
+    Some(
+        reply_rx
+            .recv_timeout(REPLY_TIMEOUT)
+            .unwrap_or_else(|_| Err("LAIR didn't answer; is an editor open in it?".to_string())),
+    )
+}
+
+/// The control socket: `lair/lair.sock` in the runtime directory, or beside the settings
+/// where there's none
+/// The `lair` folder is private to this user, so the socket is never reachable by others,
+/// not even in the moment between binding it and tightening its own permissions
+pub fn socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(Settings::config_dir)
+        .join("lair")
+        .join("lair.sock")
+}
+
+/// The listening end, owned by the TUI, which passes the requests on to `requests`
+/// The socket file is removed when it's dropped
+pub struct Server {
+    path: PathBuf,
+}
+
+impl Server {
+    /// Listen on the control socket, unless another LAIR is already listening there
+    /// A socket file left behind by one that has exited is replaced
+    pub fn start(path: &Path, requests: Requests) -> io::Result<Server> {
+        if path.exists() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    "another LAIR is listening",
+                ));
+            }
+            fs::remove_file(path)?;
+        }
+        if let Some(parent) = path.parent() {
+            fs::DirBuilder::new().recursive(true).mode(0o700).create(parent)?;
+            // A folder made before, by hand or by an older LAIR, may be open to others
+            fs::set_permissions(parent, fs::Permissions::from_mode(0o700))?;
+        }
+        let listener = UnixListener::bind(path)?;
+        // Only this user may drive the TUI
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if !serve(stream, &requests) {
+                    break;
+                }
+            }
+        });
+        Ok(Server {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Read one request from a connection, hand it to the TUI and write back its answer
+/// Returns false once the TUI has gone, to stop listening
+fn serve(mut stream: UnixStream, requests: &Requests) -> bool {
+    let mut line = String::new();
+    if stream.set_read_timeout(Some(REPLY_TIMEOUT)).is_err()
+        || BufReader::new(&stream).read_line(&mut line).is_err()
+    {
+        return true;
+    }
+    let reply = match serde_json::from_str::<Request>(&line) {
+        Ok(request) => match ask(requests, request) {
+            Some(reply) => reply,
+            None => return false,
+        },
+        Err(e) => Err(format!("Not a request: {}", e)),
+    };
+    // The client may have given up waiting
+    let _ = match reply {
+        Ok(message) => writeln!(stream, "ok {}", message),
+        Err(message) => writeln!(stream, "error {}", message),
+    };
+    true
+}
+
+/// Send a request to the running LAIR and wait for its answer
+/// Err when no LAIR is listening or it couldn't be reached
+pub fn send(request: &Request) -> io::Result<Reply> {
+    let mut stream = UnixStream::connect(socket_path())?;
+    stream.set_read_timeout(Some(REPLY_TIMEOUT + Duration::from_secs(1)))?;
+    writeln!(stream, "{}", serde_json::to_string(request).map_err(io::Error::other)?)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let line = line.trim_end();
+    Ok(match line.split_once(' ') {
+        Some(("ok", message)) => Ok(message.to_string()),
+        Some(("error", message)) => Err(message.to_string()),
+        _ => Err(format!("Unexpected answer: {}", line)),
+    })
+}
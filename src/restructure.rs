@@ -0,0 +1,147 @@
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::plan::Operation;
+
+/// Date format used for date-bucketed folders (matches create_note_file)
+const DATE_FOLDER_FORMAT: &str = "%y-%m-%d";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestructureMode {
+    /// Move the contents of date folders up into the parent, prefixing names with the date
+    Flatten,
+    /// Move the files of a flat folder into date folders
+    Rebucket,
+}
+
+impl RestructureMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RestructureMode::Flatten => "Flatten date folders",
+            RestructureMode::Rebucket => "Re-bucket into date folders",
+        }
+    }
+
+    pub fn toggle(&self) -> RestructureMode {
+        match self {
+            RestructureMode::Flatten => RestructureMode::Rebucket,
+            RestructureMode::Rebucket => RestructureMode::Flatten,
+        }
+    }
+}
+
+/// Parse a folder name like "24-06-14" as a date
+fn parse_date_folder(name: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(name, DATE_FOLDER_FORMAT).ok()
+}
+
+/// Split a "24-06-14_name.md" file name into its date prefix and the rest
+fn split_date_prefix(name: &str) -> Option<(NaiveDate, &str)> {
+    let (prefix, rest) = name.split_at_checked(8)?;
+    let rest = rest.strip_prefix('_')?;
+    parse_date_folder(prefix).map(|date| (date, rest))
+}
+
+/// Pick a target path that neither exists on disk nor is already claimed by the plan
+fn unique_target(target: PathBuf, taken: &mut HashSet<PathBuf>) -> PathBuf {
+    let mut candidate = target.clone();
+    let mut n = 2;
+    while candidate.exists() || taken.contains(&candidate) {
+        let stem = target.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+        let name = match target.extension().and_then(|e| e.to_str()) {
+            Some(ext) => format!("{}-{}.{}", stem, n, ext),
+            None => format!("{}-{}", stem, n),
+        };
+        candidate = target.with_file_name(name);
+        n += 1;
+    }
+    taken.insert(candidate.clone());
+    candidate
+}
+
+/// Sorted direct children of a directory
+fn read_sorted(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .collect();
+    entries.sort();
+    Ok(entries)
+}
+
+/// Plan flattening every date folder directly inside `folder`
+/// "24-06-14/standup.md" becomes "24-06-14_standup.md"
+pub fn plan_flatten(folder: &Path) -> io::Result<Vec<Operation>> {
+    let mut moves = Vec::new();
+    let mut taken = HashSet::new();
+
+    for date_dir in read_sorted(folder)? {
+        if !date_dir.is_dir() {
+            continue;
+        }
+        let Some(date_name) = date_dir.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if parse_date_folder(date_name).is_none() {
+            continue;
+        }
+
+        for entry in read_sorted(&date_dir)? {
+            let Some(name) = entry.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            // Don't add the date twice if the name already carries it
+            let new_name = if name.contains(date_name) {
+                name.to_string()
+            } else {
+                format!("{}_{}", date_name, name)
+            };
+            let to = unique_target(folder.join(new_name), &mut taken);
+            moves.push(Operation::Move { from: entry, to });
+        }
+    }
+
+    Ok(moves)
+}
+
+/// Plan re-bucketing the files directly inside `folder` into date folders
+/// The date comes from a "24-06-14_" name prefix, falling back to the modification time
+pub fn plan_rebucket(folder: &Path) -> io::Result<Vec<Operation>> {
+    let mut moves = Vec::new();
+    let mut taken = HashSet::new();
+
+    for file in read_sorted(folder)? {
+        if !file.is_file() {
+            continue;
+        }
+        let Some(name) = file.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+
+        let (date, new_name) = match split_date_prefix(name) {
+            Some((date, rest)) => (date, rest.to_string()),
+            None => {
+                let modified = fs::metadata(&file)?.modified()?;
+                let date = chrono::DateTime::<chrono::Local>::from(modified).date_naive();
+                (date, name.to_string())
+            }
+        };
+
+        let date_dir = folder.join(date.format(DATE_FOLDER_FORMAT).to_string());
+        let to = unique_target(date_dir.join(new_name), &mut taken);
+        moves.push(Operation::Move { from: file, to });
+    }
+
+    Ok(moves)
+}
+
+/// Build the plan for the given mode
+pub fn plan(mode: RestructureMode, folder: &Path) -> io::Result<Vec<Operation>> {
+    match mode {
+        RestructureMode::Flatten => plan_flatten(folder),
+        RestructureMode::Rebucket => plan_rebucket(folder),
+    }
+}
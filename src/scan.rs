@@ -0,0 +1,37 @@
+use crate::browse::{self, BrowseList, BrowseView};
+use crate::settings::Settings;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Messages streamed from the scanning thread to the UI
+pub enum ScanUpdate {
+    /// Items to append to the list, in display order
+    Batch(BrowseList),
+    /// The scan finished (with an error message if the root couldn't be read)
+    Done(Result<(), String>),
+}
+
+/// Scan the browse tree on a worker thread, streaming results through a channel
+/// Dropping the receiver cancels the scan at the next batch
+pub fn spawn_scan(
+    settings: Settings,
+    root: PathBuf,
+    expanded_folders: HashSet<PathBuf>,
+    view: BrowseView,
+) -> Receiver<ScanUpdate> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let batch_tx = tx.clone();
+        let mut emit = |batch| batch_tx.send(ScanUpdate::Batch(batch)).is_ok();
+        let result = match view {
+            BrowseView::Tree => {
+                browse::scan_list_items_with_paths(&settings, &root, &expanded_folders, &mut emit)
+            }
+            BrowseView::Languages => browse::scan_language_groups(&settings, &root, &mut emit),
+        };
+        let _ = tx.send(ScanUpdate::Done(result.map_err(|e| e.to_string())));
+    });
+    rx
+}
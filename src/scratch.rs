@@ -0,0 +1,59 @@
+use chrono::NaiveDateTime;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::settings::Settings;
+
+/// The scratch note, for jottings not worth a note of their own: `scratch_note` in the vault
+pub fn path(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(settings.scratch_note.trim_start_matches('/'))
+}
+
+/// Create the scratch note unless it exists, returning it and whether it's new
+pub fn ensure(settings: &Settings) -> io::Result<(PathBuf, bool)> {
+    let path = path(settings);
+    if path.exists() {
+        return Ok((path, false));
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::File::create(&path)?;
+    crate::note::fill_created_note(settings, &path)?;
+    Ok((path, true))
+}
+
+/// Empty the scratch note
+pub fn clear(settings: &Settings) -> io::Result<()> {
+    fs::write(path(settings), "")
+}
+
+/// Move the scratch note's text to a note of its own in the archive folder,
+/// `archive/scratch/2026-10-16-1403.md`, and empty it; None when there was nothing to keep
+pub fn archive(settings: &Settings, now: NaiveDateTime) -> io::Result<Option<PathBuf>> {
+    let path = path(settings);
+    let text = match fs::read_to_string(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        result => result?,
+    };
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    let folder = crate::archive::archive_dir(settings).join("scratch");
+    fs::create_dir_all(&folder)?;
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| settings.default_file_format.clone());
+    let stamp = now.format("%Y-%m-%d-%H%M").to_string();
+    let mut target = folder.join(format!("{}.{}", stamp, extension));
+    let mut n = 2;
+    while target.exists() {
+        target = folder.join(format!("{}-{}.{}", stamp, n, extension));
+        n += 1;
+    }
+    fs::write(&target, text)?;
+    fs::write(&path, "")?;
+    Ok(Some(target))
+}
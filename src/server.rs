@@ -0,0 +1,406 @@
+use std::collections::BTreeMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+
+use crate::hooks::{self, HookEvent};
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// Largest request body taken, so a client can't fill memory
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+/// Longest request or header line taken, and most headers, before the client is known
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// How long a client has to send its request
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A request, as much of it as the API needs
+struct Request {
+    method: String,
+    path: String, // Percent-decoded, without the query
+    query: BTreeMap<String, String>,
+    headers: BTreeMap<String, String>, // Names lowercased
+    body: Vec<u8>,
+}
+
+/// What is sent back: a status, a content type and the body
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, value: &serde_json::Value) -> Response {
+        Response {
+            status,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Response {
+        Response::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    fn empty(status: u16) -> Response {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }
+    }
+}
+
+/// Serve the vault's API on localhost until the process is stopped, a thread per connection
+/// Every request but a CORS preflight needs `Authorization: Bearer <api_token>`
+pub fn serve(settings: &Settings, port: u16) -> io::Result<()> {
+    let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+    for stream in listener.incoming().flatten() {
+        let settings = settings.clone();
+        thread::spawn(move || {
+            let response = match read_request(&settings, &stream) {
+                Ok(request) => handle(&settings, &request),
+                Err(e) => Response::error(400, &e.to_string()),
+            };
+            // The client may have gone
+            let _ = write_response(stream, &response);
+        });
+    }
+    Ok(())
+}
+
+/// Read a request; the body is only read once the token checks out, and left empty otherwise
+fn read_request(settings: &Settings, stream: &TcpStream) -> io::Result<Request> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    read_line(&mut reader, &mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err(io::Error::other("malformed request line"));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let request_path = crate::links::decode(path);
+    let query = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (decode_query(key), decode_query(value)))
+        .collect();
+    let method = method.to_string();
+
+    let mut headers = BTreeMap::new();
+    loop {
+        read_line(&mut reader, &mut line)?;
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(io::Error::other("too many headers"));
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+    let mut body = Vec::new();
+    if method != "OPTIONS" && authorized(settings, &headers) {
+        let length: usize = headers
+            .get("content-length")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(0);
+        if length > MAX_BODY {
+            return Err(io::Error::other("request body too large"));
+        }
+        body = vec![0; length];
+        reader.read_exact(&mut body)?;
+    }
+    Ok(Request {
+        method,
+        path: request_path,
+        query,
+        headers,
+        body,
+    })
+}
+
+/// One line of the request's head into `line`, refused when it runs past `MAX_LINE`
+fn read_line(reader: &mut impl BufRead, line: &mut String) -> io::Result<()> {
+    line.clear();
+    reader.take(MAX_LINE).read_line(line)?;
+    if line.len() as u64 == MAX_LINE && !line.ends_with('\n') {
+        return Err(io::Error::other("request line too long"));
+    }
+    Ok(())
+}
+
+/// A query string value, where `+` is a space
+fn decode_query(text: &str) -> String {
+    crate::links::decode(&text.replace('+', " "))
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\n\
+         Content-Type: {}; charset=utf-8\r\n\
+         Content-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\n\
+         Access-Control-Allow-Headers: Authorization, Content-Type\r\n\
+         Access-Control-Allow-Methods: GET, POST, PUT, OPTIONS\r\n\
+         Connection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)
+}
+
+/// Whether the request carries the API token, compared without stopping at the first
+/// difference
+fn authorized(settings: &Settings, headers: &BTreeMap<String, String>) -> bool {
+    let token = settings.api_token.trim();
+    let Some(given) = headers
+        .get("authorization")
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    !token.is_empty()
+        && given.len() == token.len()
+        && given.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+fn handle(settings: &Settings, request: &Request) -> Response {
+    if request.method == "OPTIONS" {
+        return Response::empty(204);
+    }
+    if !authorized(settings, &request.headers) {
+        return Response::error(401, "missing or wrong token");
+    }
+    let vault = Path::new(&settings.notes_directory);
+    let storage = crate::storage::open(settings);
+    let route = request.path.trim_end_matches('/');
+    match (request.method.as_str(), route) {
+        ("GET", "/notes") => list(settings, request.query.get("folder")),
+        ("POST", "/notes") => create(settings, &request.body),
+        ("POST", "/clip") => clip(settings, &request.body),
+        ("GET", "/search") => match request.query.get("q").filter(|q| !q.trim().is_empty()) {
+            Some(query) => {
+                let records: Vec<serde_json::Value> = crate::cli::search_notes(settings, query)
+                    .iter()
+                    .map(|(note, matches)| {
+                        crate::cli::search_record(storage.as_ref(), vault, note, matches)
+                    })
+                    .collect();
+                Response::json(200, &records.into())
+            }
+            None => Response::error(400, "no q to search for"),
+        },
+        (method, route) if route.starts_with("/notes/") => {
+            let Some(note) = note_path(settings, &route["/notes/".len()..]) else {
+                return Response::error(404, "no such note");
+            };
+            match method {
+                "GET" => match storage.read(&note) {
+                    Ok(content) => Response {
+                        status: 200,
+                        content_type: "text/markdown",
+                        body: content,
+                    },
+                    Err(e) => Response::error(500, &e.to_string()),
+                },
+                "PUT" => put(settings, storage.as_ref(), &note, &request.body),
+                _ => Response::error(405, "notes can be read with GET and written with PUT"),
+            }
+        }
+        _ => Response::error(404, "no such endpoint"),
+    }
+}
+
+/// An existing note of the vault from its vault-relative path, the extension optional
+/// Paths leaving the vault and files it hides (`.lair`, ignored ones) aren't found
+pub fn note_path(settings: &Settings, relative: &str) -> Option<PathBuf> {
+    let relative = Path::new(relative);
+    if !crate::vault::is_plain_relative(relative) {
+        return None;
+    }
+    let mut note = Path::new(&settings.notes_directory).join(relative);
+    if note.extension().is_none() {
+        note.set_extension(&settings.default_file_format);
+    }
+    (crate::vault::is_note(&note, settings) && crate::vault::all_files(settings).contains(&note))
+        .then_some(note)
+}
+
+/// `GET /notes[?folder=<folder>]`: the records of the vault's notes, or a folder's
+fn list(settings: &Settings, folder: Option<&String>) -> Response {
+    let vault = Path::new(&settings.notes_directory);
+    let dir = match folder {
+        Some(folder) => {
+            let folder = Path::new(folder);
+            if !crate::vault::is_plain_relative(folder) {
+                return Response::error(400, "folder must be a path inside the vault");
+            }
+            vault.join(folder)
+        }
+        None => vault.to_path_buf(),
+    };
+    let storage = crate::storage::open(settings);
+    let records: Vec<serde_json::Value> = crate::vault::files_in(storage.as_ref(), settings, &dir)
+        .iter()
+        .filter(|f| crate::vault::is_note(f, settings))
+        .map(|f| crate::cli::note_record(storage.as_ref(), vault, f))
+        .collect();
+    Response::json(200, &records.into())
+}
+
+/// `POST /notes` with `{"name": ..., "content": ...}`, both optional: a new note in today's
+/// folder, as `lair new` makes it
+fn create(settings: &Settings, body: &[u8]) -> Response {
+    let fields: serde_json::Value = if body.is_empty() {
+        serde_json::json!({})
+    } else {
+        match serde_json::from_slice(body) {
+            Ok(fields) => fields,
+            Err(e) => return Response::error(400, &format!("body isn't JSON: {}", e)),
+        }
+    };
+    let name = fields["name"].as_str().unwrap_or("");
+    let content = fields["content"].as_str();
+    match crate::cli::create_note(settings, name, None, content) {
+        Ok((note, problem)) => {
+            let storage = crate::storage::open(settings);
+            let vault = Path::new(&settings.notes_directory);
+            let mut record = crate::cli::note_record(storage.as_ref(), vault, &note);
+            if let Some(problem) = problem {
+                record["warning"] = problem.into();
+            }
+            Response::json(201, &record)
+        }
+        Err(message) if message.ends_with("already exists") => Response::error(409, &message),
+        Err(message) => Response::error(500, &message),
+    }
+}
+
+/// Longest note name a clipping's title gives, in characters
+const MAX_CLIP_NAME: usize = 80;
+
+/// `POST /clip` with `{"url": ..., "title": ..., "text": ...}`, title and text optional: the
+/// clipping as a new note in `clip_folder`, with its source in the frontmatter and the text
+/// quoted; a name already taken gets a number
+fn clip(settings: &Settings, body: &[u8]) -> Response {
+    let fields: serde_json::Value = match serde_json::from_slice(body) {
+        Ok(fields) => fields,
+        Err(e) => return Response::error(400, &format!("body isn't JSON: {}", e)),
+    };
+    let Some(url) = fields["url"].as_str().map(str::trim).filter(|u| !u.is_empty()) else {
+        return Response::error(400, "no url to clip");
+    };
+    let one_line = |text: &str| text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let title = fields["title"]
+        .as_str()
+        .map(one_line)
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| url.to_string());
+    let text = fields["text"].as_str().unwrap_or("").trim();
+
+    // The name keeps what the name_characters setting allows of the title
+    let name: String = title
+        .chars()
+        .map(|c| if settings.name_characters.allows(c) { c } else { ' ' })
+        .collect();
+    let name: String = one_line(&name).chars().take(MAX_CLIP_NAME).collect();
+    let vault = Path::new(&settings.notes_directory);
+    let folder = vault.join(&settings.clip_folder);
+    let storage = crate::storage::open(settings);
+    let mut file_name = crate::note::new_note_file_name(settings, name.trim());
+    if storage.exists(&folder.join(&file_name)) {
+        file_name = crate::note::numbered_file_name(storage.as_ref(), &folder, &file_name);
+    }
+    let note = folder.join(&file_name);
+    match hooks::run_create_hook(vault, HookEvent::PreCreate, &note, None) {
+        Ok(Some(outcome)) if !outcome.success => {
+            return Response::error(409, &format!("pre-create hook failed: {}", outcome.message));
+        }
+        Err(e) => return Response::error(500, &format!("pre-create hook error: {}", e)),
+        _ => {}
+    }
+
+    let mut content = format!(
+        "---
+title: {}
+source: {}
+clipped: {}
+---
+
+",
+        crate::frontmatter::quote(&title),
+        crate::frontmatter::quote(&one_line(url)),
+        chrono::Local::now().format("%Y-%m-%d %H:%M")
+    );
+    for line in text.lines() {
+        content.push_str(format!("> {}", line).trim_end());
+        content.push('\n');
+    }
+    if !text.is_empty() {
+        content.push('\n');
+    }
+    let link_text = title.replace('[', "\\[").replace(']', "\\]");
+    content.push_str(&format!("[{}]({})\n", link_text, one_line(url)));
+    let note = match crate::note::create_note_file(
+        storage.as_ref(),
+        &settings.notes_directory,
+        Some(&file_name),
+        &settings.default_file_format,
+        Some(&folder),
+        Some(&content),
+    ) {
+        Ok(note) => note,
+        Err(e) => return Response::error(500, &format!("Unable to create {}: {}", note.display(), e)),
+    };
+    let problem = crate::folders::apply_default_tags(settings, &note)
+        .map_err(|e| format!("Unable to add the folder's tags: {}", e))
+        .and_then(|()| crate::note::fill_new_note(settings, &note, &name, &folder, true))
+        .and_then(|()| crate::cli::post_create(vault, &note))
+        .err();
+    let mut record = crate::cli::note_record(storage.as_ref(), vault, &note);
+    if let Some(problem) = problem {
+        record["warning"] = problem.into();
+    }
+    Response::json(201, &record)
+}
+
+/// `PUT /notes/<path>`: replace a note's text with the body, then run the post-edit hook and
+/// commands
+fn put(settings: &Settings, storage: &dyn VaultStorage, note: &Path, body: &[u8]) -> Response {
+    let Ok(text) = std::str::from_utf8(body) else {
+        return Response::error(400, "notes are UTF-8 text");
+    };
+    if let Err(e) = storage.write(note, text.as_bytes()) {
+        return Response::error(500, &e.to_string());
+    }
+    let vault = Path::new(&settings.notes_directory);
+    let mut record = crate::cli::note_record(storage, vault, note);
+    if let Err(message) = hooks::run_post_edit(settings, note) {
+        record["warning"] = message.into();
+    }
+    Response::json(200, &record)
+}
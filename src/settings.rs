@@ -1,12 +1,71 @@
+use crate::archive::ArchiveRule;
+use crate::links::LinkFormat;
+use crate::lint::LintSettings;
+use crate::log::LogRotation;
+use crate::note::NameCharacters;
+use crate::notify::NotificationSettings;
+use crate::preview::PreviewPosition;
+use crate::tasks::TaskSettings;
+use crate::theme::PreviewTheme;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::PathBuf;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)] // Fields missing from older settings files fall back to their defaults
 pub struct Settings {
     pub notes_directory: String,
-    pub editor: String,
+    pub editor: String, // Command for editing a note, "{file}" / "{line}" mark the path and line; "internal" for the built-in one
+    pub file_editors: BTreeMap<String, String>, // Command per file extension ("md", "png"), `editor` for the rest
+    pub alternate_editors: Vec<String>, // Commands offered by O ("open with…") besides the usual editor
+    pub post_edit_commands: Vec<String>, // Run after each edit, like the post-edit hook: "prettier --write {file}"
+    pub detached_editors: Vec<String>, // Programs ("code", "gedit") left running while LAIR watches the file
+    pub append_timestamp: bool, // Quick appends (a) start out timestamped
+    pub name_characters: NameCharacters, // What new note and folder names may contain: unicode or ascii
+    pub new_note_frontmatter: bool, // New notes start with a frontmatter block from frontmatter_template
+    pub frontmatter_template: String, // `key: value` lines, with the same {{variables}} as templates
+    pub note_ids: bool, // New note file names start with an ID, the minute they're made: 202406141530
+    pub note_id_frontmatter: bool, // With note_ids, the ID is also written to the note's frontmatter as `id:`
+    pub slugify_names: bool, // New note names become file names like meeting-with-acme.md, the typed name its heading
     pub default_file_format: String,
+    pub dry_run: bool, // Bulk operations can be reviewed and exported but not applied
+    pub archive_directory: String, // Relative to the notes directory
+    pub scratch_note: String, // The scratch note F2 opens, relative to the notes directory
+    pub archive_rules: Vec<ArchiveRule>,
+    pub notifications: NotificationSettings, // Bell / flash per event type
+    pub tick_rate_ms: u64, // How often the UI redraws while waiting for input
+    pub low_power: bool,   // Only redraw on input or background activity
+    pub control_socket: bool, // Listen on a socket for `lair remote` commands while the TUI runs (not on Windows)
+    pub dbus_service: bool, // On Linux, offer org.lair.Capture on the session bus while the TUI runs
+    pub api_token: String, // Clients of `lair serve` send it as "Authorization: Bearer <token>"; no API while empty
+    pub api_port: u16, // Port `lair serve` listens on, on localhost only
+    pub clip_folder: String, // Relative to the notes directory, where web clippings sent to `lair serve` go
+    pub tasks: TaskSettings, // Where checkbox tasks are exported to
+    pub habits_directory: String, // Relative to the notes directory, one note per habit
+    pub ledger_directory: String, // Relative to the notes directory, one ledger note per month
+    pub log_directory: String, // Relative to the notes directory, where the daily log notes go
+    pub templates_directory: String, // Note templates, relative to the notes directory ("~/" allowed); .lair/templates when empty
+    pub snippets_directory: String,  // Snippets to add to notes, like templates_directory; .lair/snippets when empty
+    pub log_rotation: LogRotation, // Start a new log note each day or each week
+    pub reading_list: String, // Relative to the notes directory
+    pub literature_directory: String, // Relative to the notes directory, where finished reads go
+    pub bibliography: String, // BibTeX or CSL JSON file for [@key] citations ("~/" allowed)
+    pub preview_position: PreviewPosition, // Right of or below the note list, or hidden
+    pub preview_size: u16, // Percentage of the browse area given to the preview
+    pub diff_tool: String, // Command comparing two notes, "{left}" / "{right}" mark the paths; built-in viewer when empty
+    pub large_file_kb: u64, // Files above this size are previewed a window at a time, 0 for never
+    pub workspace_editor: String, // Editor for opening the whole vault, `editor` when empty
+    pub check_for_updates: bool, // Look for newer releases on GitHub at startup
+    pub usage_insights: bool, // Count the screens, actions and keys used, in a local file
+    pub show_archived: bool, // Include the archive folder in browsing, search, links and stats
+    pub preview_theme: PreviewTheme, // Colors of the rendered note: auto, dark or light
+    pub lint: LintSettings, // Checks run on notes for the problems list
+    pub anki_file: String, // Where flashcards are exported for Anki ("~/" allowed), ~/lair-anki.txt when empty
+    pub anki_deck: String, // Deck the exported cards go in, with the notes' folders as subdecks
+    pub export_directory: String, // Where notes exported to HTML or PDF go ("~/" allowed), beside the note when empty
+    pub pdf_converter: String, // Command turning HTML into PDF, "{input}" / "{output}" mark the paths; wkhtmltopdf or weasyprint when empty
+    pub link_format: LinkFormat, // How C copies a link to a note: wiki, markdown, path or uri
 }
 
 impl Default for Settings {
@@ -18,18 +77,70 @@ impl Default for Settings {
                 .to_string_lossy()
                 .to_string(),
             editor: "nvim".to_string(),
+            file_editors: BTreeMap::new(),
+            alternate_editors: Vec::new(),
+            post_edit_commands: Vec::new(),
+            detached_editors: Vec::new(),
+            append_timestamp: false,
+            name_characters: NameCharacters::default(),
+            new_note_frontmatter: false,
+            frontmatter_template: "title: {{title}}\ncreated: {{datetime}}\ntags: []".to_string(),
+            note_ids: false,
+            note_id_frontmatter: false,
+            slugify_names: false,
             default_file_format: "md".to_string(),
+            dry_run: false,
+            archive_directory: "archive".to_string(),
+            scratch_note: "scratch.md".to_string(),
+            archive_rules: Vec::new(),
+            notifications: NotificationSettings::default(),
+            tick_rate_ms: 100,
+            low_power: false,
+            control_socket: false,
+            dbus_service: false,
+            api_token: String::new(),
+            api_port: 7727,
+            clip_folder: "inbox".to_string(),
+            tasks: TaskSettings::default(),
+            habits_directory: "habits".to_string(),
+            ledger_directory: "ledger".to_string(),
+            log_directory: "log".to_string(),
+            templates_directory: String::new(),
+            snippets_directory: String::new(),
+            log_rotation: LogRotation::default(),
+            reading_list: "reading-list.md".to_string(),
+            literature_directory: "literature".to_string(),
+            bibliography: String::new(),
+            preview_position: PreviewPosition::Right,
+            preview_size: 50,
+            diff_tool: String::new(),
+            large_file_kb: 4096,
+            workspace_editor: String::new(),
+            check_for_updates: false,
+            usage_insights: false,
+            show_archived: false,
+            preview_theme: PreviewTheme::default(),
+            lint: LintSettings::default(),
+            anki_file: String::new(),
+            anki_deck: "LAIR".to_string(),
+            export_directory: String::new(),
+            pdf_converter: String::new(),
+            link_format: LinkFormat::default(),
         }
     }
 }
 
 impl Settings {
-    /// Get the path to the settings file
-    fn settings_path() -> PathBuf {
+    /// Directory holding the settings file and other per-user files
+    pub fn config_dir() -> PathBuf {
         dirs::config_dir()
             .unwrap_or_else(|| PathBuf::from("."))
             .join("escritoire")
-            .join("settings.json")
+    }
+
+    /// Get the path to the settings file
+    fn settings_path() -> PathBuf {
+        Self::config_dir().join("settings.json")
     }
 
     /// Load settings from JSON file, or return default if file doesn't exist
@@ -0,0 +1,248 @@
+use pulldown_cmark::{CowStr, Event, LinkType, Parser, Tag};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use crate::markdown::RenderedLink;
+use crate::settings::Settings;
+
+/// The style of every page, kept plain so the site reads like a document
+const STYLE: &str = "body { max-width: 46rem; margin: 2rem auto; padding: 0 1rem; \
+font-family: system-ui, sans-serif; line-height: 1.6; color: #222; }
+nav { margin-bottom: 2rem; font-size: 0.9rem; }
+a { color: #2458a6; }
+pre, code { background: #f4f4f4; border-radius: 3px; }
+pre { padding: 0.8rem; overflow-x: auto; }
+table { border-collapse: collapse; }
+th, td { border: 1px solid #ccc; padding: 0.3rem 0.6rem; }
+blockquote { margin-left: 0; padding-left: 1rem; border-left: 3px solid #ccc; color: #555; }
+";
+
+/// Render the vault as a static site in `out`: a page per note, in the same folders, with
+/// links between notes (wiki links too) pointing at their pages, the other files copied as
+/// they are, and an index page listing every note by folder
+/// A note at the vault root called `index`, or an `index.html`, is the front page instead of
+/// the list
+/// Hidden files and folders (`.gitignore`, `.obsidian`) are left out, and nothing is written
+/// when two files would end up at the same path, as `plan.md` and `plan.markdown` would
+/// Returns how many notes were rendered
+pub fn export(settings: &Settings, out: &Path) -> io::Result<usize> {
+    let vault = Path::new(&settings.notes_directory);
+    let out = std::path::absolute(out)?;
+    if out.starts_with(std::path::absolute(vault)?) {
+        return Err(io::Error::other("the site can't go inside the vault"));
+    }
+    let storage = crate::storage::open(settings);
+    let files: Vec<PathBuf> = crate::vault::files_in(storage.as_ref(), settings, vault)
+        .into_iter()
+        .filter(|file| !crate::vault::is_hidden(settings, file))
+        .collect();
+    let mut targets: BTreeMap<PathBuf, &Path> = BTreeMap::new();
+    for file in &files {
+        let relative = file.strip_prefix(vault).unwrap_or(file);
+        let target = if crate::vault::is_note(file, settings) {
+            relative.with_extension("html")
+        } else {
+            relative.to_path_buf()
+        };
+        if let Some(other) = targets.insert(target.clone(), relative) {
+            return Err(io::Error::other(format!(
+                "{} and {} would both be written to {}",
+                other.display(),
+                relative.display(),
+                target.display()
+            )));
+        }
+    }
+    if let Some(file) = targets.get(Path::new("style.css")) {
+        return Err(io::Error::other(format!(
+            "{} would be overwritten by the site's style sheet",
+            file.display()
+        )));
+    }
+    let mut notes = Vec::new();
+    for file in &files {
+        let relative = file.strip_prefix(vault).unwrap_or(file);
+        if !crate::vault::is_note(file, settings) {
+            let target = out.join(relative);
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(target, storage.read(file)?)?;
+            continue;
+        }
+        let page = out.join(relative).with_extension("html");
+        if let Some(parent) = page.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = storage.read_to_string(file)?;
+        let title = crate::links::title(storage.as_ref(), file);
+        let from = page.parent().unwrap_or(&out);
+        let body = render(settings, &files, file, &content, |path| {
+            let path = out.join(path.strip_prefix(vault).unwrap_or(path));
+            let path = if crate::vault::is_note(&path, settings) {
+                path.with_extension("html")
+            } else {
+                path
+            };
+            relative_href(from, &path)
+        });
+        fs::write(&page, page_html(&title, Some(&root_prefix(relative)), &body))?;
+        notes.push((relative.to_path_buf(), title));
+    }
+    fs::write(out.join("style.css"), STYLE)?;
+    let index = out.join("index.html");
+    if !targets.contains_key(Path::new("index.html")) {
+        fs::write(index, page_html("Index", Some(""), &index_html(&notes)))?;
+    }
+    Ok(notes.len())
+}
+
+/// Render one note as a standalone page in `dir`, named after it, its links pointing at the
+/// files of the vault they name; returns the page's path
+pub fn export_note(settings: &Settings, note: &Path, dir: &Path) -> io::Result<PathBuf> {
+    let dir = std::path::absolute(dir)?;
+    let name = note.file_stem().unwrap_or_default();
+    export_note_as(settings, note, &dir.join(name).with_extension("html"))
+}
+
+/// Render one note as a standalone page at `page`, whose links work from its folder
+pub fn export_note_as(settings: &Settings, note: &Path, page: &Path) -> io::Result<PathBuf> {
+    let page = std::path::absolute(page)?;
+    let from = page.parent().unwrap_or(Path::new("/"));
+    fs::create_dir_all(from)?;
+    let storage = crate::storage::open(settings);
+    let content = storage.read_to_string(note)?;
+    let vault = Path::new(&settings.notes_directory);
+    let files = crate::vault::files_in(storage.as_ref(), settings, vault);
+    let body = render(settings, &files, note, &content, |path| {
+        std::path::absolute(path)
+            .map(|path| relative_href(from, &path))
+            .unwrap_or_default()
+    });
+    let title = crate::links::title(storage.as_ref(), note);
+    fs::write(&page, page_html(&title, None, &body))?;
+    Ok(page)
+}
+
+/// A note's body as HTML, with its links to notes and files of the vault given by `href`
+fn render(
+    settings: &Settings,
+    files: &[PathBuf],
+    note: &Path,
+    content: &str,
+    href_to: impl Fn(&Path) -> String,
+) -> String {
+    // Web addresses, `#section` links and anything else outside the vault are left as written
+    let href = |target: &str, wiki: bool| -> Option<String> {
+        let link = RenderedLink {
+            line: 0,
+            spans: 0..0,
+            target: target.to_string(),
+            wiki,
+        };
+        let resolved = crate::viewer::resolve_link_in(note, &link, settings, files)?;
+        let fragment = target.split_once('#').map(|(_, f)| format!("#{}", f)).unwrap_or_default();
+        Some(format!("{}{}", href_to(&resolved), fragment))
+    };
+    let events = Parser::new_ext(content, crate::markdown::parser_options()).map(|event| match event {
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let wiki = matches!(link_type, LinkType::WikiLink { .. });
+            let dest_url = href(&dest_url, wiki).map(CowStr::from).unwrap_or(dest_url);
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => {
+            let dest_url = href(&dest_url, false).map(CowStr::from).unwrap_or(dest_url);
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            })
+        }
+        event => event,
+    });
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events);
+    html
+}
+
+/// The href from a page in the folder `from` to `to`: `../other/note.html`
+fn relative_href(from: &Path, to: &Path) -> String {
+    let from: Vec<Component> = from.components().collect();
+    let to_parts: Vec<Component> = to.components().collect();
+    let shared = from.iter().zip(&to_parts).take_while(|(a, b)| a == b).count();
+    let mut href = "../".repeat(from.len() - shared);
+    let rest: PathBuf = to_parts[shared..].iter().collect();
+    href.push_str(&crate::links::encode(&rest.to_string_lossy()));
+    href
+}
+
+/// The way from a page back up to the site's root, for the stylesheet and index links
+fn root_prefix(relative: &Path) -> String {
+    "../".repeat(relative.components().count().saturating_sub(1))
+}
+
+/// A whole page; in a site, `root` leads back to its stylesheet and index, while a page on
+/// its own carries its style
+fn page_html(title: &str, root: Option<&str>, body: &str) -> String {
+    let (style, nav) = match root {
+        Some(root) => (
+            format!("<link rel=\"stylesheet\" href=\"{}style.css\">", root),
+            format!("<nav><a href=\"{}index.html\">Index</a></nav>\n", root),
+        ),
+        None => (format!("<style>\n{}</style>", STYLE), String::new()),
+    };
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{title}</title>\n{style}\n</head>\n\
+         <body>\n{nav}<main>\n{body}</main>\n</body>\n</html>\n",
+        title = escape(title),
+    )
+}
+
+/// The index page's body: the notes' titles linked, under a heading per folder
+fn index_html(notes: &[(PathBuf, String)]) -> String {
+    let mut folders: BTreeMap<String, Vec<&(PathBuf, String)>> = BTreeMap::new();
+    for note in notes {
+        let folder = note.0.parent().map(|p| p.to_string_lossy().to_string()).unwrap_or_default();
+        folders.entry(folder).or_default().push(note);
+    }
+    let mut html = String::from("<h1>Index</h1>\n");
+    for (folder, notes) in folders {
+        if !folder.is_empty() {
+            html.push_str(&format!("<h2>{}</h2>\n", escape(&folder)));
+        }
+        html.push_str("<ul>\n");
+        for (relative, title) in notes {
+            let href = crate::links::encode(&relative.with_extension("html").to_string_lossy());
+            html.push_str(&format!("<li><a href=\"{}\">{}</a></li>\n", href, escape(title)));
+        }
+        html.push_str("</ul>\n");
+    }
+    html
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::settings::Settings;
+
+/// Layout adjustments made from the keyboard, and where notes were last edited, remembered
+/// between sessions
+/// Kept out of the settings file, whose values stay the defaults these override
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiState {
+    pub preview_size: Option<u16>, // Percentage of the browse area given to the preview
+    pub positions: BTreeMap<PathBuf, usize>, // Line each note was last edited at, 1-based
+}
+
+impl UiState {
+    fn path() -> PathBuf {
+        Settings::config_dir().join("state.json")
+    }
+
+    /// The saved state, or nothing remembered if it's missing or unreadable
+    pub fn load() -> UiState {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
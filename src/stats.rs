@@ -0,0 +1,102 @@
+use crate::settings::Settings;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Aggregate numbers about the vault, shown on the stats screen and exported by `lair stats`
+#[derive(Debug, Clone, Default)]
+pub struct VaultStats {
+    pub notes: usize,
+    pub other_files: usize, // Attachments and anything else that isn't a note
+    pub words: usize,
+    pub activity: BTreeMap<String, usize>, // YYYY-MM-DD -> notes last modified that day
+    pub tags: BTreeMap<String, usize>,     // Tag -> number of notes carrying it
+}
+
+impl VaultStats {
+    /// Tags sorted by how many notes use them, most used first
+    pub fn top_tags(&self) -> Vec<(&String, &usize)> {
+        let mut tags: Vec<(&String, &usize)> = self.tags.iter().collect();
+        tags.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        tags
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "notes": self.notes,
+            "other_files": self.other_files,
+            "words": self.words,
+            "activity": self.activity,
+            "tags": self.tags,
+        })
+    }
+
+    /// One `section,key,value` row per figure, ready for spreadsheets and plotting tools
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("section,key,value\n");
+        csv.push_str(&format!("count,notes,{}\n", self.notes));
+        csv.push_str(&format!("count,other_files,{}\n", self.other_files));
+        csv.push_str(&format!("count,words,{}\n", self.words));
+        for (day, count) in &self.activity {
+            csv.push_str(&format!("activity,{},{}\n", day, count));
+        }
+        for (tag, count) in &self.tags {
+            csv.push_str(&format!("tag,{},{}\n", csv_field(tag), count));
+        }
+        csv
+    }
+}
+
+/// Quote a CSV field if it contains separators or quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Walk the vault and compute its statistics
+pub fn compute(settings: &Settings) -> VaultStats {
+    let mut stats = VaultStats::default();
+    let mut folder_tags = crate::folders::FolderTags::new(settings);
+
+    for path in crate::vault::all_files(settings) {
+        if !crate::vault::is_note(&path, settings) {
+            stats.other_files += 1;
+            continue;
+        }
+        stats.notes += 1;
+
+        if let Ok(modified) = fs::metadata(&path).and_then(|m| m.modified()) {
+            let day = chrono::DateTime::<chrono::Local>::from(modified)
+                .format("%Y-%m-%d")
+                .to_string();
+            *stats.activity.entry(day).or_default() += 1;
+        }
+
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        stats.words += content.split_whitespace().count();
+        let mut tags = crate::note::tags(&content);
+        tags.extend(folder_tags.for_note(&path));
+        tags.sort();
+        tags.dedup();
+        for tag in tags {
+            *stats.tags.entry(tag).or_default() += 1;
+        }
+    }
+
+    stats
+}
+
+/// Compute the statistics on a worker thread so opening the screen never blocks on a large vault
+pub fn spawn_compute(settings: Settings) -> Receiver<VaultStats> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(compute(&settings));
+    });
+    rx
+}
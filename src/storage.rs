@@ -0,0 +1,359 @@
+#[cfg(test)]
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+#[cfg(test)]
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::settings::Settings;
+
+/// How the vault's files are reached, so walking, reading and writing the vault doesn't assume
+/// they're on the local disk; paths are the same absolute paths either way
+pub trait VaultStorage: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Up to `len` bytes from byte `start` on, for reading a window of a large file
+    fn read_range(&self, path: &Path, start: u64, len: u64) -> io::Result<Vec<u8>>;
+
+    /// The entries directly inside a folder, in no particular order
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>>;
+
+    fn is_dir(&self, path: &Path) -> bool;
+
+    fn is_file(&self, path: &Path) -> bool;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.is_file(path) || self.is_dir(path)
+    }
+
+    /// A file's size in bytes
+    fn len(&self, path: &Path) -> io::Result<u64>;
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime>;
+
+    /// Create or replace a file; its folder must exist
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    /// Add to the end of a file, which must exist
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()>;
+
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+
+    /// Remove a folder, which must be empty
+    fn remove_dir(&self, dir: &Path) -> io::Result<()>;
+}
+
+/// A vault in a folder on this machine
+pub struct LocalStorage;
+
+impl VaultStorage for LocalStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn read_range(&self, path: &Path, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = Vec::new();
+        file.take(len).read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        Ok(fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .collect())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        Ok(fs::metadata(path)?.len())
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::OpenOptions::new().append(true).open(path)?.write_all(contents)
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+        fs::remove_dir(dir)
+    }
+}
+
+/// A vault held in memory, for tests; folders exist once they're created
+#[cfg(test)]
+#[derive(Default)]
+pub struct InMemoryStorage {
+    entries: Mutex<BTreeMap<PathBuf, Entry>>,
+}
+
+#[cfg(test)]
+enum Entry {
+    File { contents: Vec<u8>, modified: SystemTime },
+    Dir,
+}
+
+#[cfg(test)]
+impl InMemoryStorage {
+    pub fn new() -> InMemoryStorage {
+        InMemoryStorage::default()
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+    }
+}
+
+#[cfg(test)]
+impl VaultStorage for InMemoryStorage {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.entries.lock().unwrap().get(path) {
+            Some(Entry::File { contents, .. }) => Ok(contents.clone()),
+            Some(Entry::Dir) => Err(io::Error::new(io::ErrorKind::IsADirectory, "is a folder")),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    fn read_range(&self, path: &Path, start: u64, len: u64) -> io::Result<Vec<u8>> {
+        let contents = self.read(path)?;
+        let start = (start as usize).min(contents.len());
+        let end = start.saturating_add(len as usize).min(contents.len());
+        Ok(contents[start..end].to_vec())
+    }
+
+    fn read_dir(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let entries = self.entries.lock().unwrap();
+        if !matches!(entries.get(dir), Some(Entry::Dir)) {
+            return Err(Self::not_found(dir));
+        }
+        Ok(entries
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        matches!(self.entries.lock().unwrap().get(path), Some(Entry::Dir))
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        matches!(self.entries.lock().unwrap().get(path), Some(Entry::File { .. }))
+    }
+
+    fn len(&self, path: &Path) -> io::Result<u64> {
+        self.read(path).map(|contents| contents.len() as u64)
+    }
+
+    fn modified(&self, path: &Path) -> io::Result<SystemTime> {
+        match self.entries.lock().unwrap().get(path) {
+            Some(Entry::File { modified, .. }) => Ok(*modified),
+            Some(Entry::Dir) => Ok(SystemTime::UNIX_EPOCH),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        let parent = path.parent().unwrap_or(Path::new("/"));
+        if !matches!(entries.get(parent), Some(Entry::Dir)) {
+            return Err(Self::not_found(parent));
+        }
+        if matches!(entries.get(path), Some(Entry::Dir)) {
+            return Err(io::Error::new(io::ErrorKind::IsADirectory, "is a folder"));
+        }
+        let file = Entry::File {
+            contents: contents.to_vec(),
+            modified: SystemTime::now(),
+        };
+        entries.insert(path.to_path_buf(), file);
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        match self.entries.lock().unwrap().get_mut(path) {
+            Some(Entry::File { contents: existing, modified }) => {
+                existing.extend_from_slice(contents);
+                *modified = SystemTime::now();
+                Ok(())
+            }
+            Some(Entry::Dir) => Err(io::Error::new(io::ErrorKind::IsADirectory, "is a folder")),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    fn create_dir_all(&self, dir: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        for ancestor in dir.ancestors() {
+            match entries.get(ancestor) {
+                Some(Entry::File { .. }) => {
+                    return Err(io::Error::new(io::ErrorKind::AlreadyExists, "is a file"));
+                }
+                Some(Entry::Dir) => break,
+                None => {
+                    entries.insert(ancestor.to_path_buf(), Entry::Dir);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(path) {
+            Some(Entry::File { .. }) => {
+                entries.remove(path);
+                Ok(())
+            }
+            Some(Entry::Dir) => Err(io::Error::new(io::ErrorKind::IsADirectory, "is a folder")),
+            None => Err(Self::not_found(path)),
+        }
+    }
+
+    fn remove_dir(&self, dir: &Path) -> io::Result<()> {
+        let mut entries = self.entries.lock().unwrap();
+        if !matches!(entries.get(dir), Some(Entry::Dir)) {
+            return Err(Self::not_found(dir));
+        }
+        if entries.keys().any(|path| path.parent() == Some(dir)) {
+            return Err(io::Error::new(io::ErrorKind::DirectoryNotEmpty, "folder isn't empty"));
+        }
+        entries.remove(dir);
+        Ok(())
+    }
+}
+
+/// The storage for the configured vault
+/// Every vault is a local folder for now; other backends would be chosen here
+pub fn open(_settings: &Settings) -> Arc<dyn VaultStorage> {
+    Arc::new(LocalStorage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vault() -> InMemoryStorage {
+        let storage = InMemoryStorage::new();
+        storage.create_dir_all(Path::new("/vault/24-06-14")).unwrap();
+        storage
+            .write(Path::new("/vault/24-06-14/standup.md"), b"# Standup\n")
+            .unwrap();
+        storage
+    }
+
+    #[test]
+    fn reads_back_what_was_written() {
+        let storage = vault();
+        let note = Path::new("/vault/24-06-14/standup.md");
+        assert_eq!(storage.read_to_string(note).unwrap(), "# Standup\n");
+        assert_eq!(storage.len(note).unwrap(), 10);
+        assert_eq!(storage.read_range(note, 2, 3).unwrap(), b"Sta");
+        assert_eq!(storage.read_range(note, 8, 100).unwrap(), b"p\n");
+        assert!(storage.modified(note).is_ok());
+    }
+
+    #[test]
+    fn appends_only_to_existing_files() {
+        let storage = vault();
+        let note = Path::new("/vault/24-06-14/standup.md");
+        storage.append(note, b"- shipped\n").unwrap();
+        assert_eq!(storage.read_to_string(note).unwrap(), "# Standup\n- shipped\n");
+        assert!(storage.append(Path::new("/vault/24-06-14/missing.md"), b"x").is_err());
+    }
+
+    #[test]
+    fn lists_only_direct_children() {
+        let storage = vault();
+        storage.create_dir_all(Path::new("/vault/projects/acme")).unwrap();
+        let mut entries = storage.read_dir(Path::new("/vault")).unwrap();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [PathBuf::from("/vault/24-06-14"), PathBuf::from("/vault/projects")]
+        );
+        assert!(storage.is_dir(Path::new("/vault/projects")));
+        assert!(!storage.is_file(Path::new("/vault/projects")));
+        assert!(storage.read_dir(Path::new("/vault/missing")).is_err());
+    }
+
+    #[test]
+    fn writes_need_an_existing_folder() {
+        let storage = vault();
+        assert!(storage.write(Path::new("/vault/inbox/clip.md"), b"x").is_err());
+        storage.create_dir_all(Path::new("/vault/inbox")).unwrap();
+        storage.write(Path::new("/vault/inbox/clip.md"), b"x").unwrap();
+        assert!(storage.exists(Path::new("/vault/inbox/clip.md")));
+    }
+
+    #[test]
+    fn removes_files_and_empty_folders_only() {
+        let storage = vault();
+        let folder = Path::new("/vault/24-06-14");
+        let note = folder.join("standup.md");
+        assert!(storage.remove_dir(folder).is_err());
+        assert!(storage.remove_file(folder).is_err());
+        storage.remove_file(&note).unwrap();
+        assert!(!storage.exists(&note));
+        storage.remove_dir(folder).unwrap();
+        assert!(!storage.exists(folder));
+    }
+
+    #[test]
+    fn walks_an_in_memory_vault() {
+        let storage = vault();
+        storage.create_dir_all(Path::new("/vault/projects")).unwrap();
+        storage
+            .write(Path::new("/vault/projects/plan.md"), b"plan")
+            .unwrap();
+        let settings = Settings {
+            notes_directory: "/vault".to_string(),
+            ..Settings::default()
+        };
+        let files = crate::vault::files_in(&storage, &settings, Path::new("/vault"));
+        assert_eq!(
+            files,
+            [
+                PathBuf::from("/vault/24-06-14/standup.md"),
+                PathBuf::from("/vault/projects/plan.md"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::settings::Settings;
+use crate::tools::{Tool, Tools};
+
+/// Where exported tasks go
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskBackend {
+    #[default]
+    TodoTxt,
+    Taskwarrior,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TaskSettings {
+    pub backend: TaskBackend,
+    pub todo_file: String, // todo.txt location ("~/" allowed), ~/todo.txt when empty
+}
+
+impl Default for TaskSettings {
+    fn default() -> Self {
+        TaskSettings {
+            backend: TaskBackend::TodoTxt,
+            todo_file: String::new(),
+        }
+    }
+}
+
+impl TaskSettings {
+    fn todo_path(&self) -> PathBuf {
+        let home = || dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+        if self.todo_file.is_empty() {
+            home().join("todo.txt")
+        } else if let Some(rest) = self.todo_file.strip_prefix("~/") {
+            home().join(rest)
+        } else {
+            PathBuf::from(&self.todo_file)
+        }
+    }
+}
+
+/// A `- [ ] task` line in a note
+struct NoteTask {
+    text: String,
+    done: bool,
+}
+
+/// Split a checkbox list line into (prefix up to the box, done, task text)
+fn parse_task_line(line: &str) -> Option<(&str, bool, &str)> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let rest = trimmed
+        .strip_prefix("- ")
+        .or_else(|| trimmed.strip_prefix("* "))
+        .or_else(|| trimmed.strip_prefix("+ "))?;
+    let done = if rest.starts_with("[ ] ") {
+        false
+    } else if rest.starts_with("[x] ") || rest.starts_with("[X] ") {
+        true
+    } else {
+        return None;
+    };
+    let text = rest[4..].trim();
+    (!text.is_empty()).then_some((&line[..indent + 2], done, text))
+}
+
+/// Checkbox tasks of a note, skipping code blocks
+fn note_tasks(content: &str) -> Vec<NoteTask> {
+    let mut tasks = Vec::new();
+    let mut in_code_block = false;
+    for line in content.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            continue;
+        }
+        if !in_code_block && let Some((_, done, text)) = parse_task_line(line) {
+            tasks.push(NoteTask {
+                text: text.to_string(),
+                done,
+            });
+        }
+    }
+    tasks
+}
+
+/// Task text reduced to lowercase letters and digits, so the rendered and raw forms compare equal
+fn normalize(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Tick or untick a checkbox in a note, found by its text and state
+/// `occurrence` picks between tasks with the same text; when the text doesn't match (markup
+/// such as link targets is lost in rendering), `index` is the task's position among all of
+/// them. Returns the new state
+pub fn toggle(
+    note: &Path,
+    text: &str,
+    done: bool,
+    occurrence: usize,
+    index: usize,
+) -> io::Result<bool> {
+    let content = fs::read_to_string(note)?;
+    let wanted = normalize(text);
+    let mut in_code_block = false;
+    let mut tasks = Vec::new(); // (byte offset of the line, prefix length, done, text)
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        } else if !in_code_block
+            && let Some((prefix, state, task)) = parse_task_line(line.trim_end_matches(['\n', '\r']))
+        {
+            tasks.push((offset, prefix.len(), state, normalize(task)));
+        }
+        offset += line.len();
+    }
+
+    let found = tasks
+        .iter()
+        .filter(|(_, _, state, task)| *state == done && *task == wanted)
+        .nth(occurrence)
+        .or_else(|| tasks.get(index).filter(|(_, _, state, _)| *state == done));
+    let Some(&(start, prefix, _, _)) = found else {
+        return Err(io::Error::other("the task changed on disk, reload the note"));
+    };
+    let box_start = start + prefix;
+    let replacement = if done { "[ ]" } else { "[x]" };
+    let mut updated = content;
+    updated.replace_range(box_start..box_start + 3, replacement);
+    fs::write(note, updated)?;
+    Ok(!done)
+}
+
+/// An exported task and the note it came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TaskLink {
+    note: PathBuf, // Relative to the vault
+    text: String,
+    id: String, // Taskwarrior UUID, or the `lair:` tag value in todo.txt
+    backend: TaskBackend,
+}
+
+/// Mapping of exported tasks, kept in `.lair/tasks.json`
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct TaskMap {
+    links: Vec<TaskLink>,
+}
+
+impl TaskMap {
+    fn path(vault: &Path) -> PathBuf {
+        vault.join(crate::hooks::VAULT_CONFIG_DIR).join("tasks.json")
+    }
+
+    fn load(vault: &Path) -> TaskMap {
+        fs::read_to_string(Self::path(vault))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, vault: &Path) -> io::Result<()> {
+        let path = Self::path(vault);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)
+    }
+}
+
+/// A UUID-shaped id, unique enough to tell exported tasks apart
+fn new_id(note: &Path, text: &str, n: usize) -> String {
+    let mut hasher = DefaultHasher::new();
+    (note, text, n, std::time::SystemTime::now()).hash(&mut hasher);
+    let high = hasher.finish();
+    (high, "lair").hash(&mut hasher);
+    let low = hasher.finish();
+    let hex = format!("{:016x}{:016x}", high, low);
+    // Version 4 / variant 1 bits, so Taskwarrior accepts it as a UUID
+    format!(
+        "{}-{}-4{}-a{}-{}",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[13..16],
+        &hex[17..20],
+        &hex[20..32]
+    )
+}
+
+/// Project name for a note: its file name without spaces
+fn project(note: &Path) -> String {
+    note.file_stem()
+        .map(|s| s.to_string_lossy().replace(char::is_whitespace, "-"))
+        .unwrap_or_default()
+}
+
+fn taskwarrior(tools: &Tools) -> io::Result<&Path> {
+    tools.path(Tool::Taskwarrior).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "task (Taskwarrior) not found on PATH - set tasks.backend to \"todotxt\" to export to todo.txt instead",
+        )
+    })
+}
+
+/// Export the open tasks of `note` that haven't been exported yet
+/// Returns how many tasks were added to the backend
+pub fn export_note(settings: &Settings, tools: &Tools, note: &Path) -> io::Result<usize> {
+    let vault = Path::new(&settings.notes_directory);
+    let relative = note.strip_prefix(vault).unwrap_or(note).to_path_buf();
+    let backend = settings.tasks.backend;
+    let mut map = TaskMap::load(vault);
+
+    let exported: HashSet<&str> = map
+        .links
+        .iter()
+        .filter(|l| l.note == relative && l.backend == backend)
+        .map(|l| l.text.as_str())
+        .collect();
+    let new_links: Vec<TaskLink> = note_tasks(&fs::read_to_string(note)?)
+        .into_iter()
+        .filter(|t| !t.done && !exported.contains(t.text.as_str()))
+        .enumerate()
+        .map(|(n, t)| TaskLink {
+            id: new_id(&relative, &t.text, n),
+            note: relative.clone(),
+            text: t.text,
+            backend,
+        })
+        .collect();
+    if new_links.is_empty() {
+        return Ok(0);
+    }
+
+    let project = project(note);
+    match backend {
+        TaskBackend::TodoTxt => {
+            let today = chrono::Local::now().format("%Y-%m-%d");
+            let mut lines = String::new();
+            for link in &new_links {
+                lines.push_str(&format!("{} {} +{} lair:{}\n", today, link.text, project, link.id));
+            }
+            let mut file = fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(settings.tasks.todo_path())?;
+            file.write_all(lines.as_bytes())?;
+        }
+        TaskBackend::Taskwarrior => {
+            let tasks: Vec<serde_json::Value> = new_links
+                .iter()
+                .map(|link| {
+                    serde_json::json!({
+                        "uuid": link.id,
+                        "description": link.text,
+                        "status": "pending",
+                        "project": project,
+                    })
+                })
+                .collect();
+            let mut child = Command::new(taskwarrior(tools)?)
+                .arg("import")
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::piped())
+                .spawn()?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(serde_json::Value::from(tasks).to_string().as_bytes())?;
+            }
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(io::Error::other(stderr.lines().next().unwrap_or("task import failed").to_string()));
+            }
+        }
+    }
+
+    let count = new_links.len();
+    map.links.extend(new_links);
+    map.save(vault)?;
+    Ok(count)
+}
+
+/// Ids of exported tasks the backend reports as completed
+fn completed_ids(settings: &Settings, tools: &Tools) -> io::Result<HashSet<String>> {
+    match settings.tasks.backend {
+        TaskBackend::TodoTxt => {
+            let content = match fs::read_to_string(settings.tasks.todo_path()) {
+                Ok(content) => content,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+                Err(e) => return Err(e),
+            };
+            Ok(content
+                .lines()
+                .filter(|line| line.starts_with("x "))
+                .flat_map(|line| line.split_whitespace())
+                .filter_map(|word| word.strip_prefix("lair:"))
+                .map(str::to_string)
+                .collect())
+        }
+        TaskBackend::Taskwarrior => {
+            let output = Command::new(taskwarrior(tools)?)
+                .args(["rc.verbose=nothing", "status:completed", "export"])
+                .stdin(Stdio::null())
+                .output()?;
+            let tasks: Vec<serde_json::Value> =
+                serde_json::from_slice(&output.stdout).map_err(io::Error::other)?;
+            Ok(tasks
+                .iter()
+                .filter_map(|t| t.get("uuid").and_then(|u| u.as_str()))
+                .map(str::to_string)
+                .collect())
+        }
+    }
+}
+
+/// Tick the checkboxes of exported tasks that were completed in the backend
+/// Synced tasks are dropped from the mapping; returns how many boxes were ticked
+pub fn sync_completed(settings: &Settings, tools: &Tools) -> io::Result<usize> {
+    let vault = Path::new(&settings.notes_directory);
+    let backend = settings.tasks.backend;
+    let mut map = TaskMap::load(vault);
+    let completed = completed_ids(settings, tools)?;
+
+    let (done, pending): (Vec<TaskLink>, Vec<TaskLink>) = map
+        .links
+        .into_iter()
+        .partition(|l| l.backend == backend && completed.contains(&l.id));
+
+    let mut ticked = 0;
+    let notes: HashSet<&PathBuf> = done.iter().map(|l| &l.note).collect();
+    for relative in notes {
+        let path = vault.join(relative);
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let mut texts: Vec<&str> = done
+            .iter()
+            .filter(|l| &l.note == relative)
+            .map(|l| l.text.as_str())
+            .collect();
+
+        let mut changed = false;
+        let mut updated = String::with_capacity(content.len());
+        for line in content.split_inclusive('\n') {
+            match parse_task_line(line.trim_end_matches(['\n', '\r'])) {
+                Some((prefix, false, text)) if texts.contains(&text) => {
+                    texts.retain(|t| *t != text);
+                    updated.push_str(prefix);
+                    updated.push_str(&line[prefix.len()..].replacen("[ ]", "[x]", 1));
+                    ticked += 1;
+                    changed = true;
+                }
+                _ => updated.push_str(line),
+            }
+        }
+        if changed {
+            fs::write(&path, updated)?;
+        }
+    }
+
+    map.links = pending;
+    map.save(vault)?;
+    Ok(ticked)
+}
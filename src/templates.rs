@@ -0,0 +1,299 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::settings::Settings;
+
+/// A folder setting: relative to the vault, from home with "~/", or `default` in `.lair/`
+/// when empty
+fn configured_dir(settings: &Settings, configured: &str, default: &str) -> PathBuf {
+    let configured = configured.trim();
+    if configured.is_empty() {
+        crate::vault::config_dir(settings).join(default)
+    } else if let Some(rest) = configured.strip_prefix("~/") {
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from(".")).join(rest)
+    } else {
+        // An absolute path replaces the vault's
+        Path::new(&settings.notes_directory).join(configured)
+    }
+}
+
+/// Note templates live in `.lair/templates/` unless `templates_directory` says otherwise, so
+/// by default they're versioned with the vault
+/// Packs pulled from a git repository each get a folder of their own there
+pub fn templates_dir(settings: &Settings) -> PathBuf {
+    configured_dir(settings, &settings.templates_directory, "templates")
+}
+
+/// Snippets, pieces of text to add to existing notes, live in `.lair/snippets/` unless
+/// `snippets_directory` says otherwise
+pub fn snippets_dir(settings: &Settings) -> PathBuf {
+    configured_dir(settings, &settings.snippets_directory, "snippets")
+}
+
+/// Every template, by path relative to the templates folder without the extension
+/// (`meeting`, `team-pack/adr`), sorted
+pub fn list(settings: &Settings) -> Vec<String> {
+    list_in(settings, &templates_dir(settings))
+}
+
+/// Every snippet, named the same way as templates
+pub fn list_snippets(settings: &Settings) -> Vec<String> {
+    list_in(settings, &snippets_dir(settings))
+}
+
+fn list_in(settings: &Settings, root: &Path) -> Vec<String> {
+    fn walk(dir: &Path, root: &Path, settings: &Settings, names: &mut Vec<String>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.is_dir() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    walk(&path, root, settings, names);
+                }
+            } else if crate::vault::is_note(&path, settings)
+                && let Ok(relative) = path.with_extension("").strip_prefix(root)
+            {
+                names.push(relative.to_string_lossy().to_string());
+            }
+        }
+    }
+
+    let mut names = Vec::new();
+    walk(root, root, settings, &mut names);
+    names.sort();
+    names
+}
+
+/// The file in `root` for a name from `list` or `list_snippets`
+fn find_in(settings: &Settings, root: &Path, name: &str) -> Option<PathBuf> {
+    let base = root.join(name);
+    let file_name = base.file_name()?.to_string_lossy().to_string();
+    fs::read_dir(base.parent()?)
+        .ok()?
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .find(|p| {
+            p.file_stem().is_some_and(|s| s.to_string_lossy() == file_name)
+                && crate::vault::is_note(p, settings)
+        })
+}
+
+/// Answers to a template's `{{prompt:<name>}}` questions, by name
+pub type Answers = BTreeMap<String, String>;
+
+/// The value of a template variable, None for a name that isn't one
+/// `date:` takes any strftime format, e.g. `{{date:%B %Y}}`; `prompt:` is whatever was typed
+/// when asked, and stays as written when it wasn't asked
+fn variable(
+    name: &str,
+    now: &DateTime<Local>,
+    title: &str,
+    folder: &str,
+    answers: &Answers,
+) -> Option<String> {
+    match name {
+        "title" => Some(title.to_string()),
+        "folder" => Some(folder.to_string()),
+        "date" => Some(now.format("%Y-%m-%d").to_string()),
+        "time" => Some(now.format("%H:%M").to_string()),
+        "datetime" => Some(now.format("%Y-%m-%d %H:%M").to_string()),
+        "weekday" => Some(now.format("%A").to_string()),
+        _ => {
+            if let Some(prompt) = name.strip_prefix("prompt:") {
+                return answers.get(prompt.trim()).cloned();
+            }
+            let format = name.strip_prefix("date:")?;
+            // A bad format would make chrono panic when written out
+            let valid = !StrftimeItems::new(format).any(|item| matches!(item, Item::Error));
+            valid.then(|| now.format(format).to_string())
+        }
+    }
+}
+
+/// Fill in every `{{variable}}` in a template's text; anything else in braces is left as written
+/// With `quote` set, values are YAML-quoted unless the text already has them in quotes
+fn expand(
+    text: &str,
+    now: &DateTime<Local>,
+    title: &str,
+    folder: &str,
+    answers: &Answers,
+    quote: bool,
+) -> String {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + length + 2;
+        expanded.push_str(&rest[..start]);
+        match variable(rest[start + 2..end - 2].trim(), now, title, folder, answers) {
+            Some(value) if quote && !expanded.ends_with(['"', '\'']) => {
+                expanded.push_str(&crate::frontmatter::quote(&value))
+            }
+            Some(value) => expanded.push_str(&value),
+            None => expanded.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
+
+/// The name `{{folder}}` gives a folder, empty for the vault root
+fn folder_name(settings: &Settings, folder: &Path) -> String {
+    if folder == Path::new(&settings.notes_directory) {
+        String::new()
+    } else {
+        folder.file_name().unwrap_or_default().to_string_lossy().to_string()
+    }
+}
+
+/// A template's text for a new note in `folder`, with its variables filled in: `{{title}}`
+/// (the note's name), `{{folder}}` (its folder's name, empty at the vault root), `{{date}}`,
+/// `{{time}}`, `{{datetime}}`, `{{weekday}}`, `{{date:<format>}}` and `{{prompt:<name>}}`
+pub fn render(
+    settings: &Settings,
+    name: &str,
+    title: &str,
+    folder: &Path,
+    answers: &Answers,
+) -> io::Result<String> {
+    let path = find_in(settings, &templates_dir(settings), name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "template not found"))?;
+    let folder = folder_name(settings, folder);
+    Ok(expand(&fs::read_to_string(path)?, &chrono::Local::now(), title, &folder, answers, false))
+}
+
+/// The `{{prompt:<name>}}` questions a template asks, each once, in the order they first appear
+pub fn prompts(settings: &Settings, name: &str) -> io::Result<Vec<String>> {
+    let path = find_in(settings, &templates_dir(settings), name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "template not found"))?;
+    let text = fs::read_to_string(path)?;
+    let mut prompts: Vec<String> = Vec::new();
+    let mut rest = text.as_str();
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        if let Some(prompt) = rest[start + 2..start + 2 + length].trim().strip_prefix("prompt:") {
+            let prompt = prompt.trim().to_string();
+            if !prompt.is_empty() && !prompts.contains(&prompt) {
+                prompts.push(prompt);
+            }
+        }
+        rest = &rest[start + 2 + length + 2..];
+    }
+    Ok(prompts)
+}
+
+/// The `frontmatter_template` setting for a new note in `folder`, with its variables filled in
+/// as for a template and quoted, so a title like `Re: plans` stays one value
+pub fn render_frontmatter(settings: &Settings, title: &str, folder: &Path) -> String {
+    let folder = folder_name(settings, folder);
+    let now = chrono::Local::now();
+    expand(&settings.frontmatter_template, &now, title, &folder, &Answers::new(), true)
+}
+
+/// A snippet's text for adding to `note`, with the same variables as a template; `{{title}}`
+/// and `{{folder}}` are those of the note
+pub fn render_snippet(settings: &Settings, name: &str, note: &Path) -> io::Result<String> {
+    let path = find_in(settings, &snippets_dir(settings), name)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "snippet not found"))?;
+    let title = note.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let folder = note.parent().map(|p| folder_name(settings, p)).unwrap_or_default();
+    let text = fs::read_to_string(path)?;
+    Ok(expand(&text, &chrono::Local::now(), &title, &folder, &Answers::new(), false))
+}
+
+/// Folder name for a pack pulled from `url`: its last path segment without `.git`
+pub fn pack_name(url: &str) -> Option<String> {
+    let segment = url
+        .trim_end_matches('/')
+        .rsplit(['/', ':'])
+        .next()?
+        .trim_end_matches(".git");
+    (!segment.is_empty() && segment != "." && segment != "..").then(|| segment.to_string())
+}
+
+fn run_git(git: &Path, args: &[&str], dir: Option<&Path>) -> Result<(), String> {
+    let mut command = Command::new(git);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+    let output = command
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| e.to_string())?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(stderr.lines().last().unwrap_or("git failed").trim().to_string())
+    }
+}
+
+/// Clone the templates pack at `url` into its own folder, or update it if it's there
+/// already. Returns a line describing what happened
+pub fn pull(
+    git: &Path,
+    settings: &Settings,
+    url: &str,
+    name: Option<&str>,
+) -> Result<String, String> {
+    let name = match name {
+        Some(name) => name.to_string(),
+        None => pack_name(url).ok_or("can't tell the pack's name from the URL")?,
+    };
+    let dir = templates_dir(settings).join(&name);
+    if dir.join(".git").is_dir() {
+        return update(git, &dir).map(|()| format!("Updated {}", name));
+    }
+    if dir.exists() {
+        return Err(format!("{} exists and isn't a git checkout", dir.display()));
+    }
+    fs::create_dir_all(templates_dir(settings)).map_err(|e| e.to_string())?;
+    let target = dir.to_string_lossy().to_string();
+    run_git(git, &["clone", "--depth", "1", "--quiet", "--", url, &target], None)?;
+    let count = list(settings)
+        .iter()
+        .filter(|t| Path::new(t).starts_with(&name))
+        .count();
+    Ok(format!("Added {} with {} template(s)", name, count))
+}
+
+/// Bring a pulled pack up to date; local edits that conflict make this fail rather than merge
+fn update(git: &Path, dir: &Path) -> Result<(), String> {
+    run_git(git, &["pull", "--ff-only", "--quiet"], Some(dir))
+}
+
+/// Update every pulled pack, one line per pack
+pub fn update_all(git: &Path, settings: &Settings) -> Vec<Result<String, String>> {
+    let Ok(entries) = fs::read_dir(templates_dir(settings)) else {
+        return Vec::new();
+    };
+    let mut packs: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.join(".git").is_dir())
+        .collect();
+    packs.sort();
+    packs
+        .iter()
+        .map(|dir| {
+            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+            update(git, dir)
+                .map(|()| format!("Updated {}", name))
+                .map_err(|e| format!("{}: {}", name, e))
+        })
+        .collect()
+}
@@ -0,0 +1,176 @@
+use ratatui::style::{Color, Style};
+use ratatui::text::Text;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::sync::OnceLock;
+
+use crate::settings::Settings;
+
+/// Colors for the rendered note, chosen to suit the terminal's background
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PreviewTheme {
+    #[default]
+    Auto, // Dark unless the terminal reports a light background in COLORFGBG
+    Dark,
+    Light,
+}
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColorSupport {
+    None,      // NO_COLOR is set
+    Basic,     // The 16 ANSI colors
+    Indexed,   // The 256-color palette
+    TrueColor, // Any RGB color
+}
+
+struct Palette {
+    light: bool,
+    colors: ColorSupport,
+}
+
+static PALETTE: OnceLock<Palette> = OnceLock::new();
+
+/// Settle the preview colors from the settings and the environment, once at startup
+pub fn configure(settings: &Settings) {
+    let light = match settings.preview_theme {
+        PreviewTheme::Light => true,
+        PreviewTheme::Dark => false,
+        PreviewTheme::Auto => background_is_light(),
+    };
+    let _ = PALETTE.set(Palette {
+        light,
+        colors: detect_colors(),
+    });
+}
+
+fn palette() -> &'static Palette {
+    PALETTE.get_or_init(|| Palette {
+        light: false,
+        colors: detect_colors(),
+    })
+}
+
+/// Whether fenced code should use a theme made for light backgrounds
+pub fn is_light() -> bool {
+    palette().light
+}
+
+/// COLORFGBG ("15;0", "0;default;15") ends with the background's ANSI color number;
+/// 7 and 9-15 are the light ones
+fn background_is_light() -> bool {
+    env::var("COLORFGBG")
+        .ok()
+        .and_then(|value| value.rsplit(';').next()?.parse::<u8>().ok())
+        .is_some_and(|background| background == 7 || (9..=15).contains(&background))
+}
+
+/// https://no-color.org, then COLORTERM and TERM for what the terminal can draw
+fn detect_colors() -> ColorSupport {
+    if env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+        return ColorSupport::None;
+    }
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorSupport::TrueColor;
+    }
+    if env::var("TERM").is_ok_and(|term| term.contains("256color")) {
+        ColorSupport::Indexed
+    } else {
+        ColorSupport::Basic
+    }
+}
+
+/// The standard RGB values of the 16 ANSI colors, for picking the nearest one
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+fn nearest_basic(r: u8, g: u8, b: u8) -> Color {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    BASIC_COLORS
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+/// The closest entry in the 6x6x6 color cube of the 256-color palette
+fn nearest_indexed(r: u8, g: u8, b: u8) -> Color {
+    let level = |c: u8| (c as u16 * 5 + 127) / 255;
+    Color::Indexed((16 + 36 * level(r) + 6 * level(g) + level(b)) as u8)
+}
+
+/// Named colors that are hard to read on a light background, swapped for darker ones
+fn for_light_background(color: Color) -> Color {
+    match color {
+        Color::White => Color::Black,
+        Color::Gray => Color::DarkGray,
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow | Color::Yellow => Color::Rgb(150, 110, 0),
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan | Color::Cyan => Color::Rgb(0, 120, 140),
+        other => other,
+    }
+}
+
+fn adapt_color(color: Option<Color>, palette: &Palette, background: bool) -> Option<Color> {
+    let mut color = color?;
+    if palette.light {
+        color = match color {
+            // Inline code sits on a dark block, which would swallow the light page
+            Color::DarkGray if background => Color::Gray,
+            other => for_light_background(other),
+        };
+    }
+    match (palette.colors, color) {
+        (ColorSupport::None, _) => None,
+        (ColorSupport::Basic, Color::Rgb(r, g, b)) => Some(nearest_basic(r, g, b)),
+        (ColorSupport::Indexed, Color::Rgb(r, g, b)) => Some(nearest_indexed(r, g, b)),
+        (_, color) => Some(color),
+    }
+}
+
+fn adapt_style(style: Style, palette: &Palette) -> Style {
+    Style {
+        fg: adapt_color(style.fg, palette, false),
+        bg: adapt_color(style.bg, palette, true),
+        ..style
+    }
+}
+
+/// Fit a rendered note's colors to the theme and to what the terminal can show
+/// Bold, italics and the other modifiers are kept even without colors
+pub fn adapt(text: &mut Text) {
+    let palette = palette();
+    if !palette.light && palette.colors == ColorSupport::TrueColor {
+        return;
+    }
+    for line in &mut text.lines {
+        line.style = adapt_style(line.style, palette);
+        for span in &mut line.spans {
+            span.style = adapt_style(span.style, palette);
+        }
+    }
+}
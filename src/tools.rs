@@ -0,0 +1,122 @@
+use std::env;
+use std::path::{Path, PathBuf};
+
+/// Optional external programs some actions shell out to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tool {
+    Ripgrep,
+    Pandoc,
+    Git,
+    Fzf,
+    Speech,
+    Taskwarrior,
+    Pager,
+    MarkdownRenderer,
+    Notifier,
+    Curl,
+    Clipboard,
+    Paste,
+    PdfConverter,
+}
+
+impl Tool {
+    pub const ALL: [Tool; 13] = [
+        Tool::Ripgrep,
+        Tool::Pandoc,
+        Tool::Git,
+        Tool::Fzf,
+        Tool::Speech,
+        Tool::Taskwarrior,
+        Tool::Pager,
+        Tool::MarkdownRenderer,
+        Tool::Notifier,
+        Tool::Curl,
+        Tool::Clipboard,
+        Tool::Paste,
+        Tool::PdfConverter,
+    ];
+
+    /// Executable names to look for, in order of preference
+    fn candidates(&self) -> &'static [&'static str] {
+        match self {
+            Tool::Ripgrep => &["rg"],
+            Tool::Pandoc => &["pandoc"],
+            Tool::Git => &["git"],
+            Tool::Fzf => &["fzf"],
+            // All of these read a text file with `-f`
+            Tool::Speech => &["espeak-ng", "espeak", "say"],
+            Tool::Taskwarrior => &["task"],
+            // Only used when $PAGER isn't set
+            Tool::Pager => &["less", "more"],
+            Tool::MarkdownRenderer => &["glow", "bat"],
+            // osascript is the macOS fallback, used with `display notification`
+            Tool::Notifier => &["notify-send", "osascript"],
+            Tool::Curl => &["curl"],
+            Tool::Clipboard => &["wl-copy", "xclip", "xsel", "pbcopy", "clip.exe"],
+            Tool::Paste => &["wl-paste", "xclip", "xsel", "pbpaste"],
+            // Both take the HTML file, then the PDF to write
+            Tool::PdfConverter => &["wkhtmltopdf", "weasyprint"],
+        }
+    }
+
+    /// Name shown when the tool is missing
+    pub fn label(&self) -> &'static str {
+        match self {
+            Tool::Ripgrep => "rg",
+            Tool::Pandoc => "pandoc",
+            Tool::Git => "git",
+            Tool::Fzf => "fzf",
+            Tool::Speech => "espeak or say",
+            Tool::Taskwarrior => "task",
+            Tool::Pager => "less or $PAGER",
+            Tool::MarkdownRenderer => "glow or bat",
+            Tool::Notifier => "notify-send",
+            Tool::Curl => "curl",
+            Tool::Clipboard => "wl-copy, xclip or pbcopy",
+            Tool::Paste => "wl-paste, xclip or pbpaste",
+            Tool::PdfConverter => "wkhtmltopdf or weasyprint",
+        }
+    }
+}
+
+/// Find an executable on PATH
+pub fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = env::var_os("PATH")?;
+    env::split_paths(&path).find_map(|dir| {
+        let candidate = dir.join(name);
+        crate::hooks::is_executable(&candidate).then_some(candidate)
+    })
+}
+
+/// Which optional tools are installed, detected once at startup
+/// Only PATH is searched, nothing is executed, so this stays cheap
+pub struct Tools {
+    found: Vec<(Tool, PathBuf)>,
+}
+
+impl Tools {
+    pub fn detect() -> Tools {
+        let found = Tool::ALL
+            .iter()
+            .filter_map(|&tool| {
+                tool.candidates()
+                    .iter()
+                    .find_map(|name| find_in_path(name))
+                    .map(|path| (tool, path))
+            })
+            .collect();
+        Tools { found }
+    }
+
+    /// Path of the tool's executable, if it was found
+    pub fn path(&self, tool: Tool) -> Option<&Path> {
+        self.found
+            .iter()
+            .find(|(t, _)| *t == tool)
+            .map(|(_, path)| path.as_path())
+    }
+
+    pub fn has(&self, tool: Tool) -> bool {
+        self.path(tool).is_some()
+    }
+}
@@ -1,4 +1,17 @@
-use crate::app::{App, CurrentScreen};
+use crate::actions::{self, Action};
+use crate::app::{App, CurrentScreen, move_list_selection};
+use crate::browse::BrowseView;
+use crate::diff::Version;
+use crate::hooks::HookEvent;
+use crate::images::{self, GraphicsProtocol};
+use crate::note::{
+    create_note_file, fill_new_note, new_note_file_name, new_note_title, note_directory,
+    numbered_file_name,
+};
+use crate::preview::PreviewPosition;
+use crate::reading::ReadingStatus;
+#[cfg(unix)]
+use crate::remote::{Reply, Request};
 use crossterm::event::KeyModifiers;
 use ratatui::Terminal;
 use ratatui::crossterm::cursor;
@@ -9,92 +22,265 @@ use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    text::Line,
-    widgets::{Block, Borders, Clear, List, ListItem, Paragraph},
+    text::{Line, Span, Text},
+    widgets::{
+        BarChart, Block, Borders, Cell, Clear, List, ListItem, ListState, Paragraph, Row,
+        Scrollbar, ScrollbarOrientation, ScrollbarState, Table, Wrap,
+    },
 };
 use std::io::{self, Error, Write};
-use std::path::PathBuf;
-use std::fs;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+#[cfg(unix)]
+use std::sync::mpsc::Sender;
+use std::time::Duration;
 
-/// Launch editor to edit a file, then return to the TUI
-/// This function temporarily restores the terminal to normal mode,
-/// launches the editor, then restores the TUI state
-fn launch_editor(file_path: &std::path::Path, editor: &str) -> io::Result<()> {
+/// Hand the terminal to another program while `run` executes, then restore the TUI
+/// Raw mode and the alternate screen are left so the program gets a normal terminal
+fn with_terminal_suspended<T>(run: impl FnOnce() -> io::Result<T>) -> io::Result<T> {
     let mut stdout = io::stdout();
 
     // Temporarily leave alternate screen and restore terminal
     terminal::disable_raw_mode()?;
     execute!(stdout, terminal::LeaveAlternateScreen, cursor::Show)?;
     stdout.flush()?;
-    
-    // Launch editor
-    let _status = Command::new(editor).arg(file_path).status()?;
+
+    let result = run();
 
     // Re-enter alternate screen and raw mode
     terminal::enable_raw_mode()?;
     execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
     stdout.flush()?;
-    
-    // Clear any residual output from the editor
+
+    // Clear any residual output from the program
     execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
     stdout.flush()?;
 
-    Ok(())
-}
-
-/// Create a new note file with date-based organization
-/// Returns the full path to the created note file
-/// If target_dir is provided, creates the note in that directory instead of date-based folder
-fn create_note_file(
-    notes_dir: &str,
-    note_name: Option<&str>,
-    file_format: &str,
-    target_dir: Option<&PathBuf>,
-) -> io::Result<PathBuf> {
-    let now = chrono::Utc::now();
-    
-    // Determine the target directory
-    let date_dir = if let Some(target) = target_dir {
-        // Use provided target directory
-        target.clone()
-    } else {
-        // Use date-based folder structure (YY-MM-DD)
-        let base_dir = PathBuf::from(notes_dir);
-        let date_folder = now.format("%y-%m-%d").to_string();
-        base_dir.join(&date_folder)
-    };
-    
-    // Ensure the date directory exists
-    fs::create_dir_all(&date_dir)?;
-    
-    // Determine the file name
-    let file_name = if let Some(name) = note_name {
-        let trimmed = name.trim();
-        if trimmed.is_empty() {
-            // Empty name, use timestamp
-            format!("notes-{}.{}", now.format("%y-%m-%d_%H-%M-%S"), file_format)
-        } else {
-            // Use provided name, ensure it has the correct extension
-            if trimmed.ends_with(&format!(".{}", file_format)) {
-                trimmed.to_string()
+    result
+}
+
+/// Launch editor to edit a file, then return to the TUI
+/// `line` fills in the editor command's `{line}`, for starting at a particular place
+/// A fallback editor, or a failure to start one, is reported in the status bar
+/// Returns whether the editor has finished, as it hasn't when left running detached
+fn launch_editor(app: &mut App, file_path: &Path, line: Option<usize>) -> bool {
+    let (editor, fallback) = match crate::editor::resolve(app.editor_for(file_path)) {
+        Ok(resolved) => resolved,
+        Err(message) => {
+            app.status_message = Some(message);
+            return true;
+        }
+    };
+    app.status_message = fallback;
+    run_editor(app, &editor, file_path, line)
+}
+
+/// Run an editor command on a file as it is, reporting in the status bar if it can't start
+/// One of `detached_editors` is started beside the TUI, which watches the file for saves
+/// instead of waiting; returns whether the editor has finished
+/// Without a `line` the note opens where it was last edited, and the first line an edit
+/// changes is remembered for next time
+fn run_editor(app: &mut App, editor: &str, file_path: &Path, line: Option<usize>) -> bool {
+    let line = line.or_else(|| app.last_position(file_path));
+    if crate::editor::is_detached(&app.settings, editor) {
+        let spawned = crate::editor::external_command(editor, file_path, line).and_then(|mut command| {
+            command
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+        });
+        match spawned {
+            Ok(child) => {
+                app.watch_detached_edit(file_path, child);
+                if app.status_message.is_none() {
+                    app.status_message = Some("Opened in the editor; saves show up here".to_string());
+                }
+            }
+            Err(e) => app.status_message = Some(format!("Unable to start {}: {}", editor, e)),
+        }
+        return false;
+    }
+    let before = app.storage.read_to_string(file_path).ok();
+    let started = crate::editor::external_command(editor, file_path, line)
+        .and_then(|mut command| with_terminal_suspended(|| command.status().map(|_| ())));
+    if let Err(e) = started {
+        app.status_message = Some(format!("Unable to start {}: {}", editor, e));
+    }
+    let after = app.storage.read_to_string(file_path).ok();
+    if let Some(line) = before
+        .as_deref()
+        .zip(after.as_deref())
+        .and_then(|(before, after)| crate::editor::first_changed_line(before, after))
+    {
+        app.remember_position(file_path, line);
+    }
+    true
+}
+
+/// Open the whole vault in an editor, started from inside it with `.` as the file, then
+/// return to the TUI
+fn launch_workspace_editor(vault: &std::path::Path, editor: &str) -> io::Result<()> {
+    let mut command = crate::editor::external_command(editor, Path::new("."), None)?;
+    with_terminal_suspended(|| command.current_dir(vault).status().map(|_| ()))
+}
+
+/// Open the scratch note in the editor, creating it first if need be
+/// Returns whether an external editor had the terminal, so the screen must be redrawn whole
+fn open_scratch(app: &mut App) -> bool {
+    let path = match crate::scratch::ensure(&app.settings) {
+        Ok((path, created)) => {
+            if created {
+                app.run_hook(HookEvent::PostCreate, &path);
+            }
+            path
+        }
+        Err(e) => {
+            app.status_message = Some(format!("Unable to create the scratch note: {}", e));
+            return false;
+        }
+    };
+    if app.uses_internal_editor(&path) {
+        let from = app.current_screen;
+        app.open_internal_editor(&path, from);
+        return false;
+    }
+    if !launch_editor(app, &path, None) {
+        return false;
+    }
+    app.run_hook(HookEvent::PostEdit, &path);
+    app.lint_edited(&path);
+    true
+}
+
+/// Carry out a request from `lair remote`, answering it before an editor takes the terminal
+/// Nothing is done while the internal editor is open, as it may have unsaved changes
+/// Returns whether an external editor had the terminal, so the screen must be redrawn whole
+#[cfg(unix)]
+fn handle_remote_request(app: &mut App, request: Request, reply: Sender<Reply>) -> bool {
+    if app.current_screen == CurrentScreen::InternalEdit {
+        let _ = reply.send(Err("LAIR is in its internal editor".to_string()));
+        return false;
+    }
+    let from = app.current_screen;
+    match request {
+        Request::Open { note, edit } => {
+            if !app.storage.is_file(&note) {
+                let _ = reply.send(Err(format!("No note {}", note.display())));
+                return false;
+            }
+            let _ = reply.send(Ok(format!("Opened {}", note.display())));
+            if !edit {
+                app.view_note(&note, from);
+            } else if app.uses_internal_editor(&note) {
+                app.open_internal_editor(&note, from);
             } else {
-                format!("{}.{}", trimmed, file_format)
+                if launch_editor(app, &note, None) {
+                    app.run_hook(HookEvent::PostEdit, &note);
+                    app.lint_edited(&note);
+                }
+                app.preview = None;
+                return true;
+            }
+        }
+        Request::Capture { note, text } => {
+            match crate::note::capture(&app.settings, &note, &text) {
+                Ok(created) => {
+                    let _ = reply.send(Ok(note.display().to_string()));
+                    if created {
+                        app.run_hook(HookEvent::PostCreate, &note);
+                    }
+                    app.run_hook(HookEvent::PostEdit, &note);
+                    if app.status_message.is_none() {
+                        app.status_message = Some(format!("Captured into {}", note.display()));
+                    }
+                }
+                Err(e) => {
+                    let _ = reply.send(Err(format!("Unable to capture: {}", e)));
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Open the note named in the new note popup as `file_name` and leave the popup, creating it
+/// first from the chosen template if any when `create` is set; otherwise the note is already
+/// there and is opened as it is
+fn finish_new_note(app: &mut App, file_name: &str, create: bool) {
+    if create && app.ask_template_prompts(file_name) {
+        // Created once the template's questions are answered
+        return;
+    }
+    let folder = note_directory(&app.settings.notes_directory, app.target_directory.as_ref());
+    let file_path = if create {
+        // The pre-create hook can veto the note, leaving the popup open
+        let template = app.note_template.clone();
+        if !app.run_create_hook(HookEvent::PreCreate, &folder.join(file_name), template.as_deref()) {
+            app.current_screen = CurrentScreen::Editing;
+            return;
+        }
+        let title = new_note_title(&app.settings, &app.note_name_input);
+        let content = app.template_content(&title, &folder);
+        match create_note_file(
+            app.storage.as_ref(),
+            &app.settings.notes_directory,
+            Some(file_name),
+            &app.settings.default_file_format,
+            app.target_directory.as_ref(),
+            content.as_deref(),
+        ) {
+            Ok(file_path) => {
+                app.apply_folder_tags(&file_path);
+                if let Err(message) = fill_new_note(
+                    &app.settings,
+                    &file_path,
+                    &app.note_name_input,
+                    &folder,
+                    content.is_some(),
+                ) {
+                    app.status_message = Some(message);
+                }
+                app.run_create_hook(HookEvent::PostCreate, &file_path, template.as_deref());
+                file_path
+            }
+            Err(e) => {
+                // Stay in editing screen on error
+                app.status_message = Some(format!("Error creating note file: {}", e));
+                app.current_screen = CurrentScreen::Editing;
+                return;
             }
         }
     } else {
-        // No name provided, use timestamp
-        format!("notes-{}.{}", now.format("%y-%m-%d_%H-%M-%S"), file_format)
+        folder.join(file_name)
     };
-    
-    let file_path = date_dir.join(&file_name);
-    
-    // Create empty file if it doesn't exist
-    if !file_path.exists() {
-        fs::File::create(&file_path)?;
+    let target_dir = app.target_directory.take();
+
+    // Launch editor with the note; the internal one opens once the screen to return to is
+    // settled, below
+    let internal = app.uses_internal_editor(&file_path);
+    if !internal && launch_editor(app, &file_path, None) {
+        app.run_hook(HookEvent::PostEdit, &file_path);
+        app.lint_edited(&file_path);
+    }
+
+    // Return to appropriate screen after editor exits
+    if let Some(dir) = target_dir {
+        // Came from browse screen, return there, expanding the target directory and
+        // reloading to show the new note
+        app.current_screen = CurrentScreen::Browsing;
+        app.expanded_folders.insert(dir);
+        app.load_browse_items();
+    } else {
+        // Came from main screen
+        app.current_screen = CurrentScreen::Main;
+    }
+    app.note_name_input.clear();
+    app.current_file = Some(file_path.to_string_lossy().to_string());
+    if internal {
+        let from = app.current_screen;
+        app.open_internal_editor(&file_path, from);
     }
-    
-    Ok(file_path)
 }
 
 /// Helper function to create a centered rect using up certain percentage of the available rect `r`
@@ -118,20 +304,80 @@ pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
+/// Style for screen headers, inverted while a flash notification is showing
+fn header_style(app: &App) -> Style {
+    let style = Style::default()
+        .fg(Color::Cyan)
+        .add_modifier(Modifier::BOLD);
+    if app.is_flashing() {
+        style.add_modifier(Modifier::REVERSED)
+    } else {
+        style
+    }
+}
+
+/// "item 42/318" position indicator for a list, shown in the bottom border
+fn list_position(state: &ListState, len: usize) -> Line<'static> {
+    let current = state.selected().map(|i| i + 1).unwrap_or(0);
+    Line::from(format!(" item {}/{} ", current.min(len), len)).right_aligned()
+}
+
+/// Draw a vertical scrollbar over the right border of a list that doesn't fit its area
+fn render_list_scrollbar(f: &mut Frame, area: Rect, state: &ListState, len: usize) {
+    let visible = area.height.saturating_sub(2) as usize;
+    if len <= visible {
+        return;
+    }
+    let mut scrollbar_state = ScrollbarState::new(len).position(state.selected().unwrap_or(0));
+    let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(None)
+        .end_symbol(None);
+    f.render_stateful_widget(
+        scrollbar,
+        area.inner(ratatui::layout::Margin { vertical: 1, horizontal: 0 }),
+        &mut scrollbar_state,
+    );
+}
+
 /// Main UI function that dispatches to screen-specific renderers
 pub fn ui(f: &mut Frame, app: &mut App) {
+    app.image_placement = None;
     match app.current_screen {
         CurrentScreen::Main => render_main_screen(f, app),
         CurrentScreen::Browsing => render_browsing_screen(f, app),
         CurrentScreen::Editing => render_editing_screen(f, app),
         CurrentScreen::CreatingFolder => render_creating_folder_screen(f, app),
         CurrentScreen::Settings => render_settings_screen(f, app),
+        CurrentScreen::PlanReview => render_plan_review_screen(f, app),
+        CurrentScreen::ConfirmDelete => render_confirm_delete_screen(f, app),
+        CurrentScreen::NoteInfo => render_note_info_screen(f, app),
+        CurrentScreen::InternalEdit => render_internal_edit_screen(f, app),
+        CurrentScreen::Stats => render_stats_screen(f, app),
+        CurrentScreen::Actions => render_actions_screen(f, app),
+        CurrentScreen::Viewing => render_viewing_screen(f, app),
+        CurrentScreen::Habits => render_habits_screen(f, app),
+        CurrentScreen::Ledger => render_ledger_screen(f, app),
+        CurrentScreen::Log => render_log_screen(f, app),
+        CurrentScreen::Reading => render_reading_screen(f, app),
+        CurrentScreen::Citing => render_citing_screen(f, app),
+        CurrentScreen::Comparing => render_comparing_screen(f, app),
+        CurrentScreen::Changelog => render_changelog_screen(f, app),
+        CurrentScreen::Usage => render_usage_screen(f, app),
+        CurrentScreen::Problems => render_problems_screen(f, app),
+        CurrentScreen::History => render_history_screen(f, app),
+        CurrentScreen::OpenWith => render_open_with_screen(f, app),
+        CurrentScreen::Calendar => render_calendar_screen(f, app),
+        CurrentScreen::QuickAppend => render_quick_append_screen(f, app),
+        CurrentScreen::Snippets => render_snippets_screen(f, app),
+        CurrentScreen::NoteExists => render_note_exists_screen(f, app),
+        CurrentScreen::JournalEntry => render_journal_entry_screen(f, app),
+        CurrentScreen::TemplatePrompt => render_template_prompt_screen(f, app),
         CurrentScreen::Exiting => render_exiting_screen(f, app),
     }
 }
 
 /// Main screen - shows welcome message and options
-fn render_main_screen(f: &mut Frame, _app: &mut App) {
+fn render_main_screen(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
@@ -141,24 +387,41 @@ fn render_main_screen(f: &mut Frame, _app: &mut App) {
         ])
         .split(f.area());
 
-    // Header
+    // Header, with a badge in the corner when the update check found a newer release
+    let mut header_block = Block::default().borders(Borders::ALL);
+    if let Some(latest) = app.update.as_ref().and_then(|info| info.latest()) {
+        header_block = header_block.title_top(
+            Line::styled(
+                format!(" {} available (U) ", latest.tag),
+                Style::default().fg(Color::Green),
+            )
+            .alignment(Alignment::Right),
+        );
+    }
     let header = Paragraph::new("LAIR - Note Management")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
+        .style(header_style(app))
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+        .block(header_block);
     f.render_widget(header, chunks[0]);
 
     // Main content area - centered options
-    let main_area = centered_rect(60, 40, chunks[1]);
+    let main_area = centered_rect(60, 50, chunks[1]);
     let options = vec![
         Line::from("(N) New Note"),
         Line::from("(B) Browse Notes"),
         Line::from("(Q) Quit"),
         Line::from("(S) Settings"),
+        Line::from("(I) Vault Statistics"),
+        Line::from("(H) Habits"),
+        Line::from("($) Log Expense"),
+        Line::from("(L) Daily Log"),
+        Line::from("(J) Journal Entry"),
+        Line::from("(C) Calendar"),
+        Line::from("(F2) Scratch Note"),
+        Line::from("(R) Reading List"),
+        Line::from("(W) Open Vault in Editor"),
+        Line::from("(Y) Your Usage"),
+        Line::from("(P) Problems"),
     ];
     let content = Paragraph::new(options)
         .style(Style::default().fg(Color::White))
@@ -166,12 +429,14 @@ fn render_main_screen(f: &mut Frame, _app: &mut App) {
         .block(Block::default().borders(Borders::ALL).title("Options"));
     f.render_widget(content, main_area);
 
-    // Footer with help text
+    // Footer with help text (or a pending status message)
     let help_text = "Press 'N' for new note, 'B' to browse, 'Q' to quit";
-    let footer = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
     f.render_widget(footer, chunks[2]);
 }
 
@@ -186,259 +451,1980 @@ fn render_browsing_screen(f: &mut Frame, app: &mut App) {
         ])
         .split(f.area());
 
-    // Header
-    let header = Paragraph::new("Browse Notes")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
+    // Header - show a breadcrumb when the tree is re-rooted into a subfolder
+    let mut header_text = if app.browse_root.is_some() {
+        format!("Browse Notes: {}", app.browse_breadcrumb())
+    } else {
+        "Browse Notes".to_string()
+    };
+    if app.browse_view == BrowseView::Languages {
+        header_text.push_str(" (by language)");
+    }
+    if app.settings.show_archived {
+        header_text.push_str(" (archive shown)");
+    }
+    let header = Paragraph::new(header_text)
+        .style(header_style(app))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
 
-    // Note list
+    // Note list beside (or above) the preview of the selected note
+    let preview_size = app.preview_size();
+    let split = [
+        Constraint::Percentage(100 - preview_size),
+        Constraint::Percentage(preview_size),
+    ];
+    let panes = match app.settings.preview_position {
+        PreviewPosition::Right => Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(split)
+            .split(chunks[1]),
+        PreviewPosition::Bottom => Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(split)
+            .split(chunks[1]),
+        PreviewPosition::Off => Layout::default()
+            .constraints([Constraint::Min(0)])
+            .split(chunks[1]),
+    };
+
+    // Page size excludes the borders
+    app.list_page_size = panes[0].height.saturating_sub(2).max(1) as usize;
     let notes: Vec<ListItem> = app
         .browse_items
         .iter()
-        .map(|(text, _)| ListItem::new(text.as_str()))
+        .zip(&app.browse_paths)
+        .map(|((text, _), path)| match path {
+            Some(path) if app.marked.contains(path) => ListItem::new(Line::from(vec![
+                Span::styled("* ", Style::default().fg(Color::Magenta)),
+                Span::raw(text.as_str()),
+            ])),
+            _ => ListItem::new(text.as_str()),
+        })
         .collect();
     let list = List::new(notes)
-        .block(Block::default().borders(Borders::ALL).title("Notes"))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(if app.is_scanning() { "Notes (scanning…)" } else { "Notes" })
+                .title_bottom(list_position(&app.browse_list_state, app.browse_items.len())),
+        )
         .highlight_style(
             Style::default()
                 .fg(Color::Yellow)
                 .add_modifier(Modifier::BOLD),
         );
-    f.render_stateful_widget(list, chunks[1], &mut app.browse_list_state);
+    f.render_stateful_widget(list, panes[0], &mut app.browse_list_state);
+    render_list_scrollbar(f, panes[0], &app.browse_list_state, app.browse_items.len());
 
-    // Footer
-    let help_text = "↑↓ Navigate | Space/→: Expand/Collapse | Enter: Open | N: New Note | F: New Folder | Esc: Back | Q: Quit";
-    let footer = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+    if let Some(&preview_area) = panes.get(1) {
+        render_preview(f, preview_area, app);
+    }
+
+    // Footer - a pending status message takes the place of the help text
+    let help_text = "↑↓/PgUp/PgDn/gg/G Navigate | Space/→: Expand/Collapse | Enter: Open | O: Open With | a: Append | V: View | M: Mark | I: Note Info | H: History | C: Copy Link | L: By Language | @: Cite | +/-: Preview Size | R: Re-root | Bksp: Up | N: New Note | F: New Folder | T: Restructure | P: Purge Empty | A: Archive | Ctrl+A: Show Archived | D: Delete | X: Actions | Esc: Back | Q: Quit";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
     f.render_widget(footer, chunks[2]);
 }
 
-/// New Note screen - shows popup dialog for entering note name
-fn render_editing_screen(f: &mut Frame, app: &mut App) {
-    // Create a centered popup dialog
-    let popup_area = centered_rect(60, 30, f.area());
-
-    // Split the popup into sections
-    let popup_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3), // Title
-            Constraint::Length(5), // Input field
-            Constraint::Length(3), // Help text
-        ])
-        .split(popup_area);
-
-    // Title
-    let title = Paragraph::new("New Note")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(Clear, popup_area); // Clear the area first
-    f.render_widget(title, popup_chunks[0]);
+/// Preview pane - the selected note, rendered
+/// On terminals with graphics support, images are drawn into the pane after the frame
+fn render_preview(f: &mut Frame, area: Rect, app: &mut App) {
+    app.update_preview();
+    let (title, text, image, footer) = match &app.preview {
+        Some(preview) => (
+            preview
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            preview.text.clone(),
+            preview.image.clone(),
+            match (&preview.counts, &preview.window) {
+                (Some(counts), _) => Some(counts.summary()),
+                (None, Some(window)) => Some(window.summary()),
+                (None, None) => None,
+            },
+        ),
+        None => (
+            String::new(),
+            Text::styled("Select a note to preview it", Style::default().fg(Color::DarkGray)),
+            None,
+            None,
+        ),
+    };
+    let mut text = text;
+    // Wrapping would break up the grid of a table
+    let wrap = !app
+        .preview
+        .as_ref()
+        .is_some_and(|p| crate::csv::delimiter(&p.path).is_some() && p.window.is_none());
+    if let Some(path) = app.preview.as_ref().map(|p| p.path.clone()) {
+        let annotations = app.annotations_for(&path);
+        if !annotations.is_empty() {
+            let placed = crate::annotations::place(&annotations, &text);
+            crate::annotations::mark(&mut text, &placed);
+            text.lines.push(Line::default());
+            text.lines.extend(crate::annotations::footnotes(&annotations, &placed));
+        }
+    }
+    let mut block = Block::default().borders(Borders::ALL).title(format!("Preview {}", title));
+    if let Some(footer) = footer {
+        block = block.title_bottom(Line::from(format!(" {} ", footer)).alignment(Alignment::Right));
+    }
+    let inner = block.inner(area);
+    f.render_widget(block, area);
 
-    // Input field - show the current input with a cursor indicator
-    let input_display = if app.note_name_input.is_empty() {
-        "Enter note name...".to_string()
-    } else {
-        format!("{}_", app.note_name_input)
+    // Popups drawn over the browse screen would end up underneath the image
+    let image = image.filter(|_| {
+        app.graphics.is_some() && matches!(app.current_screen, CurrentScreen::Browsing)
+    });
+    let text_area = match image {
+        Some(image) => {
+            // Image files get the whole pane below their caption, notes share it with the image
+            let text_height = if app.preview.as_ref().is_some_and(|p| p.path == image) {
+                1
+            } else {
+                inner.height / 2
+            };
+            let parts = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(text_height), Constraint::Min(0)])
+                .split(inner);
+            app.image_placement = Some((image, parts[1]));
+            parts[0]
+        }
+        None => inner,
     };
-    let input_style = if app.note_name_input.is_empty() {
-        Style::default().fg(Color::DarkGray)
-    } else {
-        Style::default().fg(Color::White)
+    let mut paragraph = Paragraph::new(text);
+    if wrap {
+        paragraph = paragraph.wrap(Wrap { trim: false });
+    }
+    f.render_widget(paragraph, text_area);
+}
+
+/// Draw the preview image after a frame, or remove the previous one, when it changed
+fn sync_inline_image<B: ratatui::backend::Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> io::Result<()> {
+    if app.image_placement == app.drawn_image {
+        return Ok(());
+    }
+    let Some(protocol) = app.graphics else {
+        return Ok(());
     };
-    let input = Paragraph::new(input_display)
-        .style(input_style)
-        .block(Block::default().borders(Borders::ALL).title("Note Name"));
-    f.render_widget(input, popup_chunks[1]);
+    let mut stdout = io::stdout();
 
-    // Help text
-    let help_text = "Enter: Create & Edit | Esc: Cancel";
-    let footer = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(footer, popup_chunks[2]);
-}
+    if app.drawn_image.take().is_some() {
+        if protocol == GraphicsProtocol::Kitty {
+            write!(stdout, "{}", images::KITTY_CLEAR)?;
+        } else {
+            // Sixel / iTerm images live in the cells, so only a full redraw removes them
+            terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+            terminal
+                .draw(|f| ui(f, app))
+                .map_err(|e| Error::other(format!("{}", e)))?;
+        }
+    }
 
-/// New Folder screen - shows popup dialog for entering folder name
-fn render_creating_folder_screen(f: &mut Frame, app: &mut App) {
-    // Create a centered popup dialog
-    let popup_area = centered_rect(60, 30, f.area());
+    if let Some((path, area)) = app.image_placement.clone() {
+        match images::encode(protocol, &path, area) {
+            Ok(sequence) => {
+                execute!(stdout, cursor::MoveTo(area.x, area.y))?;
+                write!(stdout, "{}", sequence)?;
+            }
+            Err(e) => app.status_message = Some(format!("Unable to draw image: {}", e)),
+        }
+        app.drawn_image = Some((path, area));
+    }
+    stdout.flush()
+}
 
-    // Split the popup into sections
-    let popup_chunks = Layout::default()
+/// Viewing screen - the whole note, rendered and scrollable, without leaving LAIR
+fn render_viewing_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(3), // Title
-            Constraint::Length(5), // Input field
-            Constraint::Length(3), // Help text
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Note
+            Constraint::Length(3), // Footer
         ])
-        .split(popup_area);
-
-    // Title
-    let title = Paragraph::new("New Folder")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
-    f.render_widget(Clear, popup_area); // Clear the area first
-    f.render_widget(title, popup_chunks[0]);
+        .split(f.area());
 
-    // Input field - show the current input with a cursor indicator
-    let input_display = if app.folder_name_input.is_empty() {
-        "Enter folder name (empty for timestamp)...".to_string()
-    } else {
-        format!("{}_", app.folder_name_input)
+    let header_style = header_style(app);
+    let Some(path) = app.viewer.as_ref().map(|v| v.page.path.clone()) else {
+        return;
     };
-    let input_style = if app.folder_name_input.is_empty() {
-        Style::default().fg(Color::DarkGray)
-    } else {
-        Style::default().fg(Color::White)
+    let annotations = app.annotations_for(&path);
+    let tab_names: Vec<String> = app
+        .tab_paths()
+        .iter()
+        .map(|p| p.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default())
+        .collect();
+    let active_tab = app.tab_index;
+    let (wrap, line_numbers) = (app.viewer_wraps(), app.viewer_line_numbers);
+    let Some(viewer) = &mut app.viewer else {
+        return;
     };
-    let input = Paragraph::new(input_display)
-        .style(input_style)
-        .block(Block::default().borders(Borders::ALL).title("Folder Name"));
-    f.render_widget(input, popup_chunks[1]);
 
-    // Help text
-    let help_text = "Enter: Create Folder | Esc: Cancel";
-    let footer = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::DarkGray))
+    // With several notes open, the header lists them as numbered tabs
+    let header_line = if tab_names.len() > 1 {
+        let spans: Vec<Span> = tab_names
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, name)| {
+                let style = if i == active_tab {
+                    Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+                } else {
+                    Style::default().fg(Color::DarkGray)
+                };
+                [Span::styled(format!(" {} {} ", i + 1, name), style), Span::raw(" ")]
+            })
+            .collect();
+        Line::from(spans)
+    } else {
+        Line::from(tab_names.into_iter().next().unwrap_or_default())
+    };
+    let header = Paragraph::new(header_line)
+        .style(header_style)
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
-    f.render_widget(footer, popup_chunks[2]);
+    f.render_widget(header, chunks[0]);
+
+    // Page size excludes the borders
+    viewer.page_size = chunks[1].height.saturating_sub(2).max(1) as usize;
+    let mut position = match &viewer.page.window {
+        Some(window) => format!(" {} ", window.summary()),
+        None => format!(
+            " line {}/{} ",
+            (viewer.scroll + 1).min(viewer.line_count()),
+            viewer.line_count()
+        ),
+    };
+    if let Some(query) = &viewer.search {
+        let current = viewer.current_match.map(|i| i + 1).unwrap_or(0);
+        position = format!(" '{}' {}/{} |{}", query, current, viewer.match_count(), position);
+    }
+    if let Some(counts) = viewer.page.counts {
+        position = format!(" {} |{}", counts.summary(), position);
+    }
+    // The outline pane takes the left side while open
+    let mut body_area = chunks[1];
+    if let Some(selected) = viewer.outline {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Length(32), Constraint::Min(0)])
+            .split(chunks[1]);
+        body_area = columns[1];
+        let items: Vec<ListItem> = viewer
+            .headings()
+            .into_iter()
+            .map(|heading| {
+                let indent = "  ".repeat(heading.level.saturating_sub(1));
+                ListItem::new(format!("{}{}", indent, heading.title))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Outline"))
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol("> ");
+        let mut state = ListState::default().with_selected(Some(selected));
+        f.render_stateful_widget(list, columns[0], &mut state);
+    }
+    let mut text = viewer.highlighted_text();
+    let placed = crate::annotations::place(&annotations, &viewer.page.text);
+    crate::annotations::mark(&mut text, &placed);
+    crate::viewer::skip_columns(&mut text, viewer.column);
+    // Line numbers count from the top of the file, which a window of a large file doesn't know
+    if line_numbers && viewer.page.window.is_none() {
+        crate::viewer::number_lines(&mut text);
+    }
+    let mut body = Paragraph::new(text);
+    if wrap {
+        body = body.wrap(Wrap { trim: false });
+    }
+    let body = body
+        .scroll((viewer.scroll.min(u16::MAX as usize) as u16, 0))
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title_bottom(Line::from(position).alignment(Alignment::Right)),
+        );
+    f.render_widget(body, body_area);
+
+    let help_text = "↑↓/jk Scroll | PgUp/PgDn | gg/G Top/Bottom | /: Search | n/N: Next/Prev | Tab/S-Tab: Links | Enter: Follow | B/Bksp: Back | O: Outline | W: Wrap | L: Line numbers | A: Annotate | t: Tasks | E: Edit | 1-9/[/]: Tabs | X: Close tab | Esc: Browse";
+    // While picking a line, show what's already said about it
+    let line_comments: Vec<String> = viewer
+        .line_cursor
+        .map(|line| {
+            annotations
+                .iter()
+                .zip(&placed)
+                .enumerate()
+                .filter(|(_, (_, placed))| **placed == Some(line))
+                .map(|(i, (annotation, _))| format!("◆{} {}", i + 1, annotation.comment))
+                .collect()
+        })
+        .unwrap_or_default();
+    let footer = match (&viewer.search_input, &app.status_message) {
+        (Some(input), _) => Paragraph::new(format!("/{}█", input)).alignment(Alignment::Left),
+        (None, _) if viewer.comment_input.is_some() => Paragraph::new(format!(
+            "Comment: {}█",
+            viewer.comment_input.as_deref().unwrap_or("")
+        ))
+        .alignment(Alignment::Left),
+        (None, None) if viewer.line_cursor.is_some() && !line_comments.is_empty() => {
+            Paragraph::new(line_comments.join(" | "))
+                .style(Style::default().fg(Color::Yellow))
+                .alignment(Alignment::Left)
+        }
+        (None, None) if viewer.outline.is_some() => {
+            Paragraph::new("↑↓/jk Select heading | Enter: Go to it | Esc/O: Close outline")
+                .style(Style::default().fg(Color::DarkGray))
+                .alignment(Alignment::Center)
+        }
+        (None, None) if viewer.task.is_some() => {
+            Paragraph::new("↑↓/jk or t/T: Next/Prev task | Space/x: Toggle | Esc: Done")
+                .style(Style::default().fg(Color::DarkGray))
+                .alignment(Alignment::Center)
+        }
+        (None, None) if viewer.line_cursor.is_some() => {
+            Paragraph::new("↑↓/jk Pick line | Enter: Comment | D: Delete annotations | Esc: Cancel")
+                .style(Style::default().fg(Color::DarkGray))
+                .alignment(Alignment::Center)
+        }
+        (None, Some(message)) => Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center),
+        (None, None) => Paragraph::new(help_text)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center),
+    }
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
 }
 
-fn render_settings_screen(f: &mut Frame, app: &mut App) {
+/// Diff viewer - the lines that differ between two notes
+fn render_comparing_screen(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
             Constraint::Length(3), // Header
-            Constraint::Min(0),    // Settings fields
+            Constraint::Min(0),    // Differences
             Constraint::Length(3), // Footer
         ])
         .split(f.area());
 
-    // Header
-    let header = Paragraph::new("Settings")
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+    let header_style = header_style(app);
+    let vault = PathBuf::from(&app.settings.notes_directory);
+    let Some(comparison) = &mut app.comparison else {
+        return;
+    };
+    // Vault-relative paths, as two versions of a doc often share a file name
+    let name = |version: &Version| {
+        let path = version.path.strip_prefix(&vault).unwrap_or(&version.path).display();
+        match &version.revision {
+            Some(revision) => format!("{} @ {}", path, revision),
+            None => path.to_string(),
+        }
+    };
+    let header = Paragraph::new(format!(
+        "{} → {}",
+        name(&comparison.left),
+        name(&comparison.right)
+    ))
+    .style(header_style)
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
     f.render_widget(header, chunks[0]);
 
-    // Settings fields area
-    let settings_area = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(5), // Notes Directory
+    // Page size excludes the borders
+    comparison.page_size = chunks[1].height.saturating_sub(2).max(1) as usize;
+    let (removed, added) = comparison.counts();
+    let summary = if removed + added == 0 {
+        " identical ".to_string()
+    } else {
+        format!(" -{} +{} ", removed, added)
+    };
+    let scroll = (comparison.scroll.min(u16::MAX as usize) as u16, 0);
+    if comparison.side_by_side {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(chunks[1]);
+        let (left, right) = comparison.side_texts();
+        let left = Paragraph::new(left).scroll(scroll).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {} ", name(&comparison.left))),
+        );
+        let right = Paragraph::new(right).scroll(scroll).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!(" {} ", name(&comparison.right)))
+                .title_bottom(Line::from(summary).alignment(Alignment::Right)),
+        );
+        f.render_widget(left, columns[0]);
+        f.render_widget(right, columns[1]);
+    } else {
+        let body = Paragraph::new(comparison.text()).scroll(scroll).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title_bottom(Line::from(summary).alignment(Alignment::Right)),
+        );
+        f.render_widget(body, chunks[1]);
+    }
+
+    let help_text = "↑↓/jk Scroll | PgUp/PgDn | gg/G Top/Bottom | n/N: Next/Prev change | V: Side by side | S: Swap sides | Esc: Close";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Changelog screen - release notes of the versions newer than the running one
+fn render_changelog_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Release notes
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let header = Paragraph::new(format!(
+        "What's new since {}",
+        crate::update::CURRENT_VERSION
+    ))
+    .style(header_style(app))
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let mut lines: Vec<Line> = Vec::new();
+    for release in app.update.iter().flat_map(|info| &info.newer) {
+        lines.push(Line::styled(
+            format!("{}  {}", release.tag, release.published),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ));
+        lines.extend(crate::markdown::render(&release.notes).0.lines);
+        lines.push(Line::raw(""));
+    }
+    let body = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .scroll((app.changelog_scroll, 0))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(body, chunks[1]);
+
+    let help_text = "↑↓/jk Scroll | PgUp/PgDn | Esc: Back | Updating is up to you; LAIR never installs anything";
+    let footer = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Usage screen - the screens, actions and keys used most, counted locally
+fn render_usage_screen(f: &mut Frame, app: &mut App) {
+    use crate::usage::Usage;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Counts
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let Some(usage) = &app.usage else {
+        return;
+    };
+    let header = Paragraph::new(format!(
+        "Your Usage since {} (stored on this machine only)",
+        usage.since
+    ))
+    .style(header_style(app))
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(25), // Screens
+            Constraint::Percentage(35), // Actions
+            Constraint::Percentage(40), // Keys
+        ])
+        .split(chunks[1]);
+    let rows = chunks[1].height.saturating_sub(2) as usize;
+    let column = |title: &'static str, entries: Vec<(&str, u64)>| {
+        let items: Vec<ListItem> = entries
+            .into_iter()
+            .map(|(name, uses)| ListItem::new(format!("{:>6}  {}", uses, name)))
+            .collect();
+        let block = Block::default().borders(Borders::ALL).title(title);
+        if items.is_empty() {
+            List::new(vec![ListItem::new(Span::styled(
+                "Nothing yet",
+                Style::default().fg(Color::DarkGray),
+            ))])
+            .block(block)
+        } else {
+            List::new(items).block(block)
+        }
+    };
+    f.render_widget(column("Screens", Usage::top(&usage.screens, rows)), columns[0]);
+    f.render_widget(column("Actions", Usage::top(&usage.actions, rows)), columns[1]);
+    f.render_widget(column("Keys", Usage::top(&usage.keys, rows)), columns[2]);
+
+    let help_text = "D: Clear | Esc: Back | Turn recording off in Settings with Y";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Problems screen - what the linter found in the vault's notes
+fn render_problems_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Problems
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let title = if app.is_linting() {
+        "Problems (checking…)"
+    } else {
+        "Problems"
+    };
+    let header = Paragraph::new(title)
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    if app.problems.is_empty() {
+        let message = if app.is_linting() {
+            "Checking notes…"
+        } else {
+            "No problems found"
+        };
+        let empty = Paragraph::new(message)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(empty, chunks[1]);
+    } else {
+        let vault = PathBuf::from(&app.settings.notes_directory);
+        let items: Vec<ListItem> = app
+            .problems
+            .iter()
+            .map(|problem| {
+                let mut place = problem
+                    .path
+                    .strip_prefix(&vault)
+                    .unwrap_or(&problem.path)
+                    .display()
+                    .to_string();
+                if let Some(line) = problem.line {
+                    place = format!("{}:{}", place, line);
+                }
+                ListItem::new(Line::from(vec![
+                    Span::styled(place, Style::default().fg(Color::Cyan)),
+                    Span::raw("  "),
+                    Span::raw(problem.message.clone()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title_bottom(list_position(&app.problem_list_state, app.problems.len())),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, chunks[1], &mut app.problem_list_state);
+    }
+
+    let help_text = "↑↓/jk Navigate | Enter: View note | E: Edit at line | R: Check again | Esc: Back";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// History screen - the commits that changed a note, in a vault kept in git
+fn render_history_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Commits
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let vault = PathBuf::from(&app.settings.notes_directory);
+    let Some((note, revisions)) = &app.history else {
+        return;
+    };
+    let name = note.strip_prefix(&vault).unwrap_or(note).display();
+    let header = Paragraph::new(format!("History of {}", name))
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let items: Vec<ListItem> = revisions
+        .iter()
+        .enumerate()
+        .map(|(i, revision)| {
+            let mark = if app.history_mark == Some(i) { "* " } else { "  " };
+            ListItem::new(Line::from(vec![
+                Span::raw(mark),
+                Span::styled(revision.hash.clone(), Style::default().fg(Color::Yellow)),
+                Span::raw("  "),
+                Span::styled(revision.date.clone(), Style::default().fg(Color::Cyan)),
+                Span::raw("  "),
+                Span::raw(revision.subject.clone()),
+                Span::styled(
+                    format!("  ({})", revision.author),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title_bottom(list_position(&app.history_list_state, revisions.len())),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, chunks[1], &mut app.history_list_state);
+
+    let help_text = if app.history_mark.is_some() {
+        "↑↓/jk Navigate | Enter/D: Compare with marked | M: Unmark | R: Restore | Esc: Back"
+    } else {
+        "↑↓/jk Navigate | Enter/D: Compare with note | M: Mark to compare | R: Restore | Esc: Back"
+    };
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Plan review screen - preview/report of the operations a bulk action would make
+fn render_plan_review_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Planned operations
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let Some(plan) = &app.bulk_plan else {
+        return;
+    };
+
+    let mode = if plan.is_applied() {
+        "applied"
+    } else if app.settings.dry_run {
+        "DRY RUN"
+    } else {
+        "preview"
+    };
+    let header = Paragraph::new(format!("{}: {} [{}]", plan.title, plan.root.display(), mode))
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let lines = plan.report_lines();
+    let operations: Vec<ListItem> = if lines.is_empty() {
+        vec![ListItem::new("Nothing to do").style(Style::default().fg(Color::DarkGray))]
+    } else {
+        lines
+            .into_iter()
+            .map(|line| {
+                let style = if line.starts_with("[failed]") {
+                    Style::default().fg(Color::Red)
+                } else if line.starts_with("[done]") {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default()
+                };
+                ListItem::new(line).style(style)
+            })
+            .collect()
+    };
+    app.list_page_size = chunks[1].height.saturating_sub(2).max(1) as usize;
+    let count = plan.operations.len();
+    let title = format!("{} operations", count);
+    let list = List::new(operations)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .title_bottom(list_position(&app.plan_list_state, count)),
+        )
+        .highlight_style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_stateful_widget(list, chunks[1], &mut app.plan_list_state);
+    render_list_scrollbar(f, chunks[1], &app.plan_list_state, count);
+
+    let help_text = if app.restructure_folder.is_some() {
+        "↑↓/PgUp/PgDn Scroll | Tab: Switch Mode | Enter: Apply | E: Export Log | Ctrl+D: Dry Run | Esc: Back"
+    } else {
+        "↑↓/PgUp/PgDn Scroll | Enter: Apply | E: Export Log | Ctrl+D: Dry Run | Esc: Back"
+    };
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// New Note screen - shows popup dialog for entering note name
+fn render_editing_screen(f: &mut Frame, app: &mut App) {
+    // Create a centered popup dialog, taller when there are templates to pick from
+    let has_templates = !app.template_names.is_empty();
+    let popup_area = centered_rect(60, if has_templates { 60 } else { 30 }, f.area());
+
+    // Split the popup into sections
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Title
+            Constraint::Length(5), // Input field
+            if has_templates { Constraint::Min(0) } else { Constraint::Length(0) }, // Template picker
+            Constraint::Length(3), // Help text
+        ])
+        .split(popup_area);
+
+    // Title
+    let title = Paragraph::new("New Note")
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(Clear, popup_area); // Clear the area first
+    f.render_widget(title, popup_chunks[0]);
+
+    // Input field - show the current input with a cursor indicator
+    let input_display = if app.note_name_input.is_empty() {
+        "Enter note name...".to_string()
+    } else {
+        format!("{}_", app.note_name_input)
+    };
+    let input_style = if app.note_name_input.is_empty() {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default().fg(Color::White)
+    };
+    let input_title = match &app.note_template {
+        Some(template) => format!("Note Name (template: {})", template),
+        None => "Note Name".to_string(),
+    };
+    let input = Paragraph::new(input_display)
+        .style(input_style)
+        .block(Block::default().borders(Borders::ALL).title(input_title));
+    f.render_widget(input, popup_chunks[1]);
+
+    if has_templates {
+        let selected = app
+            .note_template
+            .as_ref()
+            .and_then(|name| app.template_names.iter().position(|n| n == name))
+            .map_or(0, |i| i + 1);
+        let items: Vec<ListItem> = std::iter::once("(no template)".to_string())
+            .chain(app.template_names.iter().cloned())
+            .map(ListItem::new)
+            .collect();
+        let mut state = ListState::default();
+        state.select(Some(selected));
+        let picker = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Template"))
+            .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+            .highlight_symbol("> ");
+        f.render_widget(Clear, popup_chunks[2]);
+        f.render_stateful_widget(picker, popup_chunks[2], &mut state);
+    }
+
+    // Help text (or a pending status message)
+    let help_text = "Enter: Create & Edit | ↑↓/Tab: Template | Esc: Cancel";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, popup_chunks[3]);
+}
+
+/// New Folder screen - shows popup dialog for entering folder name
+fn render_creating_folder_screen(f: &mut Frame, app: &mut App) {
+    // Create a centered popup dialog
+    let popup_area = centered_rect(60, 30, f.area());
+
+    // Split the popup into sections
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Title
+            Constraint::Length(5), // Input field
+            Constraint::Length(3), // Help text
+        ])
+        .split(popup_area);
+
+    // Title
+    let title = Paragraph::new("New Folder")
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(Clear, popup_area); // Clear the area first
+    f.render_widget(title, popup_chunks[0]);
+
+    // Input field - show the current input with a cursor indicator
+    let input_display = if app.folder_name_input.is_empty() {
+        "Enter folder name (empty for timestamp)...".to_string()
+    } else {
+        format!("{}_", app.folder_name_input)
+    };
+    let input_style = if app.folder_name_input.is_empty() {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        Style::default().fg(Color::White)
+    };
+    let input = Paragraph::new(input_display)
+        .style(input_style)
+        .block(Block::default().borders(Borders::ALL).title("Folder Name"));
+    f.render_widget(input, popup_chunks[1]);
+
+    // Help text
+    let help_text = "Enter: Create Folder | Esc: Cancel";
+    let footer = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, popup_chunks[2]);
+}
+
+fn render_settings_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Settings fields
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    // Header
+    let header = Paragraph::new("Settings")
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    // Settings fields area
+    let settings_area = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(5), // Notes Directory
             Constraint::Length(5), // Editor
             Constraint::Length(5), // File Format
+            Constraint::Length(1), // Update check
+            Constraint::Length(1), // Usage insights
+        ])
+        .split(chunks[1]);
+
+    // Helper function to render a settings field
+    let render_field = |f: &mut Frame, area: Rect, label: &str, value: &str, is_active: bool| {
+        let field_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Length(20), // Label
+                Constraint::Min(0),     // Value
+            ])
+            .split(area);
+
+        // Label
+        let label_style = if is_active {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let label_text = Paragraph::new(label)
+            .style(label_style)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(label_text, field_chunks[0]);
+
+        // Value input field
+        let value_display = if value.is_empty() {
+            format!("{}_", "Enter value...")
+        } else {
+            format!("{}_", value)
+        };
+        let value_style = if is_active {
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        };
+        let value_text = Paragraph::new(value_display)
+            .style(value_style)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(value_text, field_chunks[1]);
+    };
+
+    // Notes Directory field
+    let is_active = app.active_settings_field == Some(crate::app::SettingsField::NotesDirectory);
+    render_field(
+        f,
+        settings_area[0],
+        "Notes Directory:",
+        &app.settings_field_inputs[0],
+        is_active,
+    );
+
+    // Editor field
+    let is_active = app.active_settings_field == Some(crate::app::SettingsField::Editor);
+    render_field(
+        f,
+        settings_area[1],
+        "Editor:",
+        &app.settings_field_inputs[1],
+        is_active,
+    );
+
+    // File Format field
+    let is_active = app.active_settings_field == Some(crate::app::SettingsField::FileFormat);
+    render_field(
+        f,
+        settings_area[2],
+        "File Format:",
+        &app.settings_field_inputs[2],
+        is_active,
+    );
+
+    let update_check = if app.settings.check_for_updates {
+        "on"
+    } else {
+        "off"
+    };
+    let update_line = Paragraph::new(format!(
+        " Check for updates at startup: {} (U to toggle)",
+        update_check
+    ))
+    .style(Style::default().fg(Color::White));
+    f.render_widget(update_line, settings_area[3]);
+    let usage_insights = if app.settings.usage_insights {
+        "on"
+    } else {
+        "off"
+    };
+    let usage_line = Paragraph::new(format!(
+        " Record usage insights, kept on this machine: {} (Y to toggle)",
+        usage_insights
+    ))
+    .style(Style::default().fg(Color::White));
+    f.render_widget(usage_line, settings_area[4]);
+
+    // Footer
+    let help_text = if app.active_settings_field.is_some() {
+        "Type to edit | Enter: Save | Esc: Cancel/Back"
+    } else {
+        "↑↓ Navigate | Enter: Edit | S: Save | U: Update check | Y: Usage insights | Esc: Back"
+    };
+    let footer = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Stats screen - note counts, recent activity and the most used tags
+fn render_stats_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Length(5), // Counts
+            Constraint::Min(0),    // Activity and tags
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let title = if app.is_computing_stats() && app.stats.is_some() {
+        "Vault Statistics (updating…)"
+    } else {
+        "Vault Statistics"
+    };
+    let header = Paragraph::new(title)
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let Some(stats) = &app.stats else {
+        let placeholder = Paragraph::new("Computing statistics…")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(placeholder, chunks[1]);
+        return;
+    };
+
+    let counts = vec![
+        Line::from(format!("Notes: {}    Other files: {}", stats.notes, stats.other_files)),
+        Line::from(format!("Words: {}", stats.words)),
+        Line::from(format!("Active days: {}    Tags: {}", stats.activity.len(), stats.tags.len())),
+    ];
+    let counts = Paragraph::new(counts)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title("Totals"));
+    f.render_widget(counts, chunks[1]);
+
+    let body = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+        .split(chunks[2]);
+
+    // Notes touched per day over the last two weeks
+    let today = chrono::Local::now().date_naive();
+    let days: Vec<(String, u64)> = (0..14)
+        .rev()
+        .map(|ago| {
+            let day = today - chrono::Duration::days(ago);
+            let count = stats
+                .activity
+                .get(&day.format("%Y-%m-%d").to_string())
+                .copied()
+                .unwrap_or(0);
+            (day.format("%d").to_string(), count as u64)
+        })
+        .collect();
+    let bars: Vec<(&str, u64)> = days.iter().map(|(label, count)| (label.as_str(), *count)).collect();
+    let activity = BarChart::default()
+        .block(Block::default().borders(Borders::ALL).title("Activity (last 14 days)"))
+        .data(&bars)
+        .bar_width(3)
+        .bar_style(Style::default().fg(Color::Cyan));
+    f.render_widget(activity, body[0]);
+
+    let tags: Vec<ListItem> = stats
+        .top_tags()
+        .into_iter()
+        .map(|(tag, count)| ListItem::new(format!("#{} ({})", tag, count)))
+        .collect();
+    let tags = List::new(tags).block(Block::default().borders(Borders::ALL).title("Tags"));
+    f.render_widget(tags, body[1]);
+
+    let footer = Paragraph::new("Esc: Back | Export with `lair stats --json` or `--csv`")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[3]);
+}
+
+/// Delete confirmation dialog, drawn over the browser
+fn render_confirm_delete_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let area = centered_rect(50, 25, f.area());
+    let name = app
+        .pending_delete
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let delete_text = vec![
+        Line::from(""),
+        Line::from(format!("Delete '{}'?", name)),
+        Line::from(""),
+        Line::from("(Y) Yes"),
+        Line::from("(N) No"),
+    ];
+
+    let delete_dialog = Paragraph::new(delete_text)
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Delete")
+                .border_style(Style::default().fg(Color::Red)),
+        );
+
+    f.render_widget(Clear, area); // Clear the area first
+    f.render_widget(delete_dialog, area);
+}
+
+/// A note with the new note's name is already there: open it, rename, or number the new one
+fn render_note_exists_screen(f: &mut Frame, app: &mut App) {
+    render_editing_screen(f, app);
+
+    let area = centered_rect(50, 30, f.area());
+    let (file_name, numbered) = app.taken_note_name.clone().unwrap_or_default();
+    let text = vec![
+        Line::from(""),
+        Line::from(format!("'{}' already exists", file_name)),
+        Line::from(""),
+        Line::from("(O) Open it"),
+        Line::from(format!("(N) Create '{}'", numbered)),
+        Line::from("(R) Rename"),
+    ];
+
+    let dialog = Paragraph::new(text)
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Center)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Note exists")
+                .border_style(Style::default().fg(Color::Yellow)),
+        );
+
+    f.render_widget(Clear, area); // Clear the area first
+    f.render_widget(dialog, area);
+}
+
+/// Internal editor - the note's text with a cursor, for when no editor program is at hand
+fn render_internal_edit_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Text
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let header_style = header_style(app);
+    let Some(editor) = &mut app.internal_editor else {
+        return;
+    };
+    let name = editor
+        .path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let title = if editor.modified {
+        format!("{} [modified]", name)
+    } else {
+        name
+    };
+    let header = Paragraph::new(title)
+        .style(header_style)
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let block = Block::default().borders(Borders::ALL).title_bottom(
+        Line::from(format!(
+            " line {}/{} col {} ",
+            editor.buffer.row + 1,
+            editor.buffer.lines().len(),
+            editor.buffer.col + 1
+        ))
+        .right_aligned(),
+    );
+    let inner = block.inner(chunks[1]);
+    editor.page_size = (inner.height as usize).max(1);
+    editor.follow_cursor(inner.width as usize, inner.height as usize);
+    let (top, left) = editor.scroll;
+    let lines: Vec<Line> = editor
+        .buffer
+        .lines()
+        .iter()
+        .skip(top)
+        .take(inner.height as usize)
+        .map(|line| Line::from(crate::editor::skip_columns(line, left)))
+        .collect();
+    f.render_widget(Paragraph::new(lines).block(block), chunks[1]);
+    f.set_cursor_position((
+        inner.x + (editor.buffer.display_col() - left) as u16,
+        inner.y + (editor.buffer.row - top) as u16,
+    ));
+
+    let help_text = "Arrows/Home/End/PgUp/PgDn Move | Ctrl+S: Save | Esc: Close";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Note info popup - figures about the selected note, over the browse screen
+fn render_note_info_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let Some((path, info)) = &app.note_info else {
+        return;
+    };
+    let area = centered_rect(50, 40, f.area());
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tasks = if info.tasks == 0 {
+        "none".to_string()
+    } else {
+        format!(
+            "{}/{} done ({}%)",
+            info.tasks_done,
+            info.tasks,
+            info.tasks_done * 100 / info.tasks
+        )
+    };
+    let label = Style::default().fg(Color::DarkGray);
+    let row = |name: &str, value: String| {
+        Line::from(vec![Span::styled(format!("{:>14}  ", name), label), Span::raw(value)])
+    };
+    let lines = vec![
+        Line::from(""),
+        row("Words", info.words.to_string()),
+        row("Reading time", format!("{} min", info.reading_minutes)),
+        row("Headings", info.headings.to_string()),
+        row("Links", info.links.to_string()),
+        row("Tasks", tasks),
+    ];
+    let popup = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} ", name))
+            .title_bottom(Line::from(" Esc: Close ").right_aligned()),
+    );
+    f.render_widget(Clear, area);
+    f.render_widget(popup, area);
+}
+
+/// Days shown in the habit grid
+const HABIT_DAYS: usize = 14;
+
+/// Habit tracker - a grid of habits by day, with streaks
+fn render_habits_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Grid
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let header = Paragraph::new("Habits")
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let today = chrono::Local::now().date_naive();
+    let days = crate::habits::recent_days(today, HABIT_DAYS);
+    let selected_day = HABIT_DAYS.saturating_sub(1 + app.habit_day);
+
+    if app.habits.is_empty() {
+        let dir = crate::habits::habits_dir(&app.settings);
+        let empty = Paragraph::new(format!(
+            "No habits yet - add one note per habit to {}",
+            dir.display()
+        ))
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(empty, chunks[1]);
+    } else {
+        let header_row = Row::new(
+            std::iter::once(Cell::from("Habit"))
+                .chain(days.iter().map(|d| Cell::from(d.format("%d").to_string())))
+                .chain([Cell::from("Streak"), Cell::from("Best")]),
+        )
+        .style(Style::default().fg(Color::DarkGray));
+
+        let selected_row = app.habit_list_state.selected();
+        let rows: Vec<Row> = app
+            .habits
+            .iter()
+            .enumerate()
+            .map(|(row, habit)| {
+                let name_style = if selected_row == Some(row) {
+                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                let day_cells = days.iter().enumerate().map(|(col, day)| {
+                    let (mark, color) = if habit.done_on(*day) {
+                        ("■", Color::Green)
+                    } else {
+                        ("·", Color::DarkGray)
+                    };
+                    let mut style = Style::default().fg(color);
+                    if selected_row == Some(row) && col == selected_day {
+                        style = style.add_modifier(Modifier::REVERSED);
+                    }
+                    Cell::from(mark).style(style)
+                });
+                Row::new(
+                    std::iter::once(Cell::from(habit.name.clone()).style(name_style))
+                        .chain(day_cells)
+                        .chain([
+                            Cell::from(habit.current_streak(today).to_string()),
+                            Cell::from(habit.longest_streak().to_string()),
+                        ]),
+                )
+            })
+            .collect();
+
+        let widths = std::iter::once(Constraint::Min(16))
+            .chain(std::iter::repeat_n(Constraint::Length(2), HABIT_DAYS))
+            .chain([Constraint::Length(6), Constraint::Length(4)]);
+        let table = Table::new(rows, widths)
+            .header(header_row)
+            .block(Block::default().borders(Borders::ALL).title(format!(
+                "Last {} days - {}",
+                HABIT_DAYS,
+                days[selected_day].format("%a %Y-%m-%d")
+            )));
+        f.render_widget(table, chunks[1]);
+    }
+
+    let help_text = "↑↓/jk Habit | ←→/hl Day | Space/Enter: Toggle | Esc: Back";
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(help_text).style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Month calendar - days with a daily note in green, days with other notes in cyan
+fn render_calendar_screen(f: &mut Frame, app: &mut App) {
+    use chrono::Datelike;
+    use crate::calendar::DayNotes;
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Month
+            Constraint::Length(3), // Footer
+        ])
+        .split(f.area());
+
+    let date = app.calendar_date;
+    let header = Paragraph::new(date.format("%B %Y").to_string())
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
+
+    let today = chrono::Local::now().date_naive();
+    let first = date.with_day(1).unwrap_or(date);
+    let offset = first.weekday().num_days_from_monday() as usize;
+    let mut cells: Vec<Cell> = vec![Cell::from(""); offset];
+    for day in 1..=crate::calendar::days_in_month(date) {
+        let mut style = match app.calendar_days.get(day as usize - 1) {
+            Some(DayNotes::Daily) => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+            Some(DayNotes::Notes) => Style::default().fg(Color::Cyan),
+            _ => Style::default().fg(Color::DarkGray),
+        };
+        if first.with_day(day) == Some(today) {
+            style = style.add_modifier(Modifier::UNDERLINED);
+        }
+        if day == date.day() {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        cells.push(Cell::from(format!("{:>3}", day)).style(style));
+    }
+    let mut rows = Vec::new();
+    while !cells.is_empty() {
+        let rest = cells.split_off(cells.len().min(7));
+        rows.push(Row::new(std::mem::replace(&mut cells, rest)));
+    }
+    let header_row = Row::new(["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"])
+        .style(Style::default().fg(Color::DarkGray));
+
+    let status = match app.calendar_days.get(date.day() as usize - 1) {
+        Some(DayNotes::Daily) => "daily note",
+        Some(DayNotes::Notes) => "notes",
+        _ => "empty",
+    };
+    // Seven 4-wide columns, and up to six weeks under the day names
+    let (width, height) = (36.min(chunks[1].width), 10.min(chunks[1].height));
+    let area = Rect::new(
+        chunks[1].x + (chunks[1].width - width) / 2,
+        chunks[1].y + (chunks[1].height - height) / 2,
+        width,
+        height,
+    );
+    let table = Table::new(rows, [Constraint::Length(4); 7])
+        .header(header_row)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "{} - {}",
+            date.format("%a %Y-%m-%d"),
+            status
+        )));
+    f.render_widget(table, area);
+
+    let help_text = "←→↑↓/hjkl Day | PgUp/PgDn/[ ] Month | { } Year | T: Today | /: Go to date | Enter: Daily note | Esc: Back";
+    let footer = match (&app.calendar_input, &app.status_message) {
+        (Some(input), _) => Paragraph::new(format!("Go to: {}_", input))
+            .style(Style::default().fg(Color::White))
+            .alignment(Alignment::Left),
+        (None, Some(message)) => Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center),
+        (None, None) => Paragraph::new(help_text)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center),
+    }
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
+
+/// Citation completion popup over the browse screen
+fn render_citing_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let popup_area = centered_rect(70, 60, f.area());
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Input field
+            Constraint::Min(0),    // Matching references
+            Constraint::Length(3), // Help text
+        ])
+        .split(popup_area);
+    f.render_widget(Clear, popup_area);
+
+    let input = Paragraph::new(format!("[@{}_]", app.citation_input)).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Insert Citation: type a key, author or title"),
+    );
+    f.render_widget(input, popup_chunks[0]);
+
+    let items: Vec<ListItem> = app
+        .citation_matches()
+        .iter()
+        .map(|reference| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<24} ", reference.key), Style::default().fg(Color::Cyan)),
+                Span::raw(format!("{} - {}", reference.label(), reference.title)),
+            ]))
+        })
+        .collect();
+    let count = items.len();
+    let selected = app.citation_list_state.selected().unwrap_or(0);
+    app.citation_list_state
+        .select((count > 0).then(|| selected.min(count - 1)));
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title_bottom(list_position(&app.citation_list_state, count)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, popup_chunks[1], &mut app.citation_list_state);
+
+    let footer = Paragraph::new("↑↓ Select | Tab: Complete | Enter: Cite at end of note | Esc: Cancel")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, popup_chunks[2]);
+}
+
+/// Reading list screen - items to triage, with their status
+fn render_reading_screen(f: &mut Frame, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Header
+            Constraint::Min(0),    // Items
+            Constraint::Length(3), // Footer / add line
         ])
-        .split(chunks[1]);
+        .split(f.area());
 
-    // Helper function to render a settings field
-    let render_field = |f: &mut Frame, area: Rect, label: &str, value: &str, is_active: bool| {
-        let field_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Length(20), // Label
-                Constraint::Min(0),     // Value
-            ])
-            .split(area);
+    let header = Paragraph::new("Reading List")
+        .style(header_style(app))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(header, chunks[0]);
 
-        // Label
-        let label_style = if is_active {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::White)
-        };
-        let label_text = Paragraph::new(label)
-            .style(label_style)
+    if app.reading_items.is_empty() {
+        let empty = Paragraph::new("Nothing to read yet - press A to add a title or URL")
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
-        f.render_widget(label_text, field_chunks[0]);
+        f.render_widget(empty, chunks[1]);
+    } else {
+        let items: Vec<ListItem> = app
+            .reading_items
+            .iter()
+            .map(|item| {
+                let (status_style, title_style) = match item.status {
+                    ReadingStatus::ToRead => (Style::default().fg(Color::White), Style::default()),
+                    ReadingStatus::Reading => (
+                        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    ReadingStatus::Done => (Style::default().fg(Color::Green), Style::default()),
+                    ReadingStatus::Dropped => (
+                        Style::default().fg(Color::DarkGray),
+                        Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT),
+                    ),
+                };
+                let mut spans = vec![
+                    Span::styled(format!("{:<8} ", item.status.keyword()), status_style),
+                    Span::styled(item.title.clone(), title_style),
+                ];
+                if let Some(source) = &item.source
+                    && *source != item.title
+                {
+                    spans.push(Span::styled(
+                        format!("  {}", source),
+                        Style::default().fg(Color::DarkGray),
+                    ));
+                }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(crate::reading::list_path(&app.settings).display().to_string())
+                    .title_bottom(list_position(&app.reading_list_state, app.reading_items.len())),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(list, chunks[1], &mut app.reading_list_state);
+    }
 
-        // Value input field
-        let value_display = if value.is_empty() {
-            format!("{}_", "Enter value...")
-        } else {
-            format!("{}_", value)
-        };
-        let value_style = if is_active {
-            Style::default()
-                .fg(Color::Yellow)
-                .add_modifier(Modifier::BOLD)
-        } else {
-            Style::default().fg(Color::White)
-        };
-        let value_text = Paragraph::new(value_display)
-            .style(value_style)
-            .block(Block::default().borders(Borders::ALL));
-        f.render_widget(value_text, field_chunks[1]);
-    };
+    let help_text = "↑↓/jk Navigate | A: Add | R: Reading | D: Done (to literature note) | X: Dropped | T: To Read | Esc: Back";
+    let footer = match (&app.reading_input, &app.status_message) {
+        (Some(input), _) => Paragraph::new(format!("Add (title and/or URL): {}█", input))
+            .alignment(Alignment::Left),
+        (None, Some(message)) => Paragraph::new(message.as_str())
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center),
+        (None, None) => Paragraph::new(help_text)
+            .style(Style::default().fg(Color::DarkGray))
+            .alignment(Alignment::Center),
+    }
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, chunks[2]);
+}
 
-    // Notes Directory field
-    let is_active = app.active_settings_field == Some(crate::app::SettingsField::NotesDirectory);
-    render_field(
-        f,
-        settings_area[0],
-        "Notes Directory:",
-        &app.settings_field_inputs[0],
-        is_active,
-    );
+/// Expense quick-capture popup, with this month's totals per category
+fn render_ledger_screen(f: &mut Frame, app: &mut App) {
+    render_main_screen(f, app);
 
-    // Editor field
-    let is_active = app.active_settings_field == Some(crate::app::SettingsField::Editor);
-    render_field(
-        f,
-        settings_area[1],
-        "Editor:",
-        &app.settings_field_inputs[1],
-        is_active,
-    );
+    let popup_area = centered_rect(60, 60, f.area());
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Input field
+            Constraint::Min(0),    // Summary
+            Constraint::Length(3), // Help text / status
+        ])
+        .split(popup_area);
+    f.render_widget(Clear, popup_area);
 
-    // File Format field
-    let is_active = app.active_settings_field == Some(crate::app::SettingsField::FileFormat);
-    render_field(
-        f,
-        settings_area[2],
-        "File Format:",
-        &app.settings_field_inputs[2],
-        is_active,
+    let (input_display, input_style) = if app.ledger_input.is_empty() {
+        ("12.50 food lunch with Sam".to_string(), Style::default().fg(Color::DarkGray))
+    } else {
+        (format!("{}_", app.ledger_input), Style::default().fg(Color::White))
+    };
+    let input = Paragraph::new(input_display).style(input_style).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title("Log Expense: amount category description"),
     );
+    f.render_widget(input, popup_chunks[0]);
 
-    // Footer
-    let help_text = if app.active_settings_field.is_some() {
-        "Type to edit | Enter: Save | Esc: Cancel/Back"
+    let total: i64 = app.ledger_summary.iter().map(|(_, cents)| cents).sum();
+    let mut lines: Vec<Line> = app
+        .ledger_summary
+        .iter()
+        .map(|(category, cents)| {
+            Line::from(format!("{:<20} {:>12}", category, crate::ledger::format_amount(*cents)))
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push(Line::styled("Nothing logged this month", Style::default().fg(Color::DarkGray)));
     } else {
-        "↑↓ Navigate | Enter: Edit | S: Save | Esc: Back"
+        lines.push(Line::from(""));
+        lines.push(Line::styled(
+            format!("{:<20} {:>12}", "total", crate::ledger::format_amount(total)),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+    let month = chrono::Local::now().format("%B %Y").to_string();
+    let summary = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(format!("Totals - {}", month)));
+    f.render_widget(summary, popup_chunks[1]);
+
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new("Enter: Add | Esc: Done").style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, popup_chunks[2]);
+}
+
+/// Log mode popup - a line for the next entry above every log note's entries, by day
+fn render_log_screen(f: &mut Frame, app: &mut App) {
+    render_main_screen(f, app);
+
+    let popup_area = centered_rect(80, 80, f.area());
+    let popup_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Input field
+            Constraint::Min(0),    // Entries
+            Constraint::Length(3), // Help text / status
+        ])
+        .split(popup_area);
+    f.render_widget(Clear, popup_area);
+
+    let (input_display, input_style) = if app.log_input.is_empty() {
+        ("What are you doing?".to_string(), Style::default().fg(Color::DarkGray))
+    } else {
+        (format!("{}_", app.log_input), Style::default().fg(Color::White))
     };
-    let footer = Paragraph::new(help_text)
+    let input = Paragraph::new(input_display)
+        .style(input_style)
+        .block(Block::default().borders(Borders::ALL).title("Daily Log"));
+    f.render_widget(input, popup_chunks[0]);
+
+    let mut lines: Vec<Line> = Vec::new();
+    let mut day = None;
+    for entry in &app.log_entries {
+        let date = entry.time.date();
+        if day != Some(date) {
+            if day.is_some() {
+                lines.push(Line::from(""));
+            }
+            lines.push(Line::styled(
+                date.format("%A %Y-%m-%d").to_string(),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+            day = Some(date);
+        }
+        lines.push(Line::from(vec![
+            Span::styled(entry.time.format("%H:%M  ").to_string(), Style::default().fg(Color::Cyan)),
+            Span::raw(entry.text.clone()),
+        ]));
+    }
+    if lines.is_empty() {
+        lines.push(Line::styled("Nothing logged yet", Style::default().fg(Color::DarkGray)));
+    }
+    // Newest entries at the bottom; scrolling goes back from there
+    let height = popup_chunks[1].height.saturating_sub(2) as usize;
+    let max_scroll = lines.len().saturating_sub(height);
+    app.log_scroll = app.log_scroll.min(max_scroll);
+    let top = (max_scroll - app.log_scroll).min(u16::MAX as usize) as u16;
+    let entries = Paragraph::new(lines)
+        .scroll((top, 0))
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(entries, popup_chunks[1]);
+
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new("Enter: Add | ↑↓/PgUp/PgDn: Scroll | Esc: Done")
+            .style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+    f.render_widget(footer, popup_chunks[2]);
+}
+
+/// Action menu popup - actions whose tools are missing are greyed out with the reason
+fn render_actions_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let area = centered_rect(60, 50, f.area());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(4)])
+        .split(area);
+
+    let items: Vec<ListItem> = Action::ALL
+        .iter()
+        .map(|action| {
+            if action.unavailable_reason(&app.tools, &app.settings).is_some() {
+                ListItem::new(format!("{} (unavailable)", action.label()))
+                    .style(Style::default().fg(Color::DarkGray))
+            } else {
+                ListItem::new(action.label())
+            }
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title("Actions"))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+
+    // Explain the selected action's missing tools (and any fallback)
+    let detail = app
+        .selected_action()
+        .and_then(|action| {
+            action
+                .unavailable_reason(&app.tools, &app.settings)
+                .or_else(|| action.note(&app.tools).map(str::to_string))
+        })
+        .or_else(|| {
+            app.selected_language()
+                .map(|language| format!("Finding and searching is limited to notes in '{}'", language))
+        })
+        .unwrap_or_else(|| "Enter: Run | Esc: Back".to_string());
+    let detail = Paragraph::new(detail)
+        .style(Style::default().fg(Color::DarkGray))
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, chunks[0], &mut app.action_list_state);
+    f.render_widget(detail, chunks[1]);
+}
+
+/// Quick append popup - one line for the end of the selected note
+fn render_quick_append_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let wide = centered_rect(60, 100, f.area());
+    let height = 6.min(wide.height);
+    let area = Rect::new(wide.x, wide.y + (wide.height - height) / 2, wide.width, height);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(3)])
+        .split(area);
+
+    let name = app
+        .get_selected_file_path()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let title = if app.append_timestamp {
+        format!("Append to {} (timestamped)", name)
+    } else {
+        format!("Append to {}", name)
+    };
+    let input = Paragraph::new(format!("{}_", app.append_input))
+        .style(Style::default().fg(Color::White))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new("Enter: Append | Tab: Timestamp on/off | Esc: Cancel")
+            .style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, chunks[0]);
+    f.render_widget(footer, chunks[1]);
+}
+
+/// One of the chosen template's questions, over the new note popup
+fn render_template_prompt_screen(f: &mut Frame, app: &mut App) {
+    render_editing_screen(f, app);
+
+    let wide = centered_rect(60, 100, f.area());
+    let height = 6.min(wide.height);
+    let area = Rect::new(wide.x, wide.y + (wide.height - height) / 2, wide.width, height);
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(3), Constraint::Length(3)])
+        .split(area);
+
+    let prompt = app.template_prompts.first().cloned().unwrap_or_default();
+    let title = match app.template_prompts.len() {
+        0 | 1 => prompt,
+        n => format!("{} ({} more)", prompt, n - 1),
+    };
+    let input = Paragraph::new(format!("{}_", app.prompt_input))
+        .style(Style::default().fg(Color::White))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    let footer = Paragraph::new("Enter: Next | Esc: Back to the Name")
         .style(Style::default().fg(Color::DarkGray))
         .alignment(Alignment::Center)
         .block(Block::default().borders(Borders::ALL));
-    f.render_widget(footer, chunks[2]);
+
+    f.render_widget(Clear, area);
+    f.render_widget(input, chunks[0]);
+    f.render_widget(footer, chunks[1]);
+}
+
+/// A popup for writing a few lines into today's daily note
+fn render_journal_entry_screen(f: &mut Frame, app: &mut App) {
+    match app.journal_from {
+        CurrentScreen::Browsing => render_browsing_screen(f, app),
+        _ => render_main_screen(f, app),
+    }
+
+    let area = centered_rect(60, 50, f.area());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(3)])
+        .split(area);
+
+    let title = format!("Journal entry, {}", chrono::Local::now().format("%H:%M"));
+    let block = Block::default().borders(Borders::ALL).title(title);
+    let inner = block.inner(chunks[0]);
+    let buffer = &app.journal_input;
+    // Scroll just enough to keep the cursor in view
+    let top = (buffer.row + 1).saturating_sub(inner.height as usize);
+    let left = (buffer.display_col() + 1).saturating_sub(inner.width as usize);
+    let lines: Vec<Line> = buffer
+        .lines()
+        .iter()
+        .skip(top)
+        .take(inner.height as usize)
+        .map(|line| Line::from(crate::editor::skip_columns(line, left)))
+        .collect();
+    let footer = match &app.status_message {
+        Some(message) => Paragraph::new(message.as_str()).style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new("Enter: New Line | Ctrl+S: Add to Today's Note | Esc: Cancel")
+            .style(Style::default().fg(Color::DarkGray)),
+    }
+    .alignment(Alignment::Center)
+    .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(lines).block(block), chunks[0]);
+    f.render_widget(footer, chunks[1]);
+    f.set_cursor_position((
+        inner.x + (buffer.display_col() - left) as u16,
+        inner.y + (buffer.row - top) as u16,
+    ));
+}
+
+/// Snippet picker popup - the snippets that can be added to the selected note
+fn render_snippets_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let area = centered_rect(60, 50, f.area());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(area);
+
+    let items: Vec<ListItem> = app
+        .snippet_names
+        .iter()
+        .map(|name| ListItem::new(name.clone()))
+        .collect();
+    let name = app
+        .get_selected_file_path()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!("Add a snippet to {}", name)))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+        .highlight_symbol("> ");
+    let help = Paragraph::new("↑↓/jk Select | Enter: Add to the end | Esc: Back")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, chunks[0], &mut app.snippet_list_state);
+    f.render_widget(help, chunks[1]);
+}
+
+/// "Open with…" popup - the alternate editors for the selected file, numbered for picking
+fn render_open_with_screen(f: &mut Frame, app: &mut App) {
+    render_browsing_screen(f, app);
+
+    let area = centered_rect(60, 40, f.area());
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(area);
+
+    let items: Vec<ListItem> = app
+        .settings
+        .alternate_editors
+        .iter()
+        .enumerate()
+        .map(|(i, command)| {
+            let number = if i < 9 { format!("{} ", i + 1) } else { "  ".to_string() };
+            ListItem::new(Line::from(vec![
+                Span::styled(number, Style::default().fg(Color::DarkGray)),
+                Span::raw(command.clone()),
+            ]))
+        })
+        .collect();
+    let name = app
+        .get_selected_file_path()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!("Open {} with", name)))
+        .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD));
+    let help = Paragraph::new("Enter/1-9: Open | Esc: Back")
+        .style(Style::default().fg(Color::DarkGray))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(list, chunks[0], &mut app.open_with_list_state);
+    f.render_widget(help, chunks[1]);
 }
 
 /// Exiting screen - confirmation dialog
@@ -470,28 +2456,110 @@ fn render_exiting_screen(f: &mut Frame, _app: &mut App) {
     f.render_widget(exit_dialog, area);
 }
 
+/// Handle page / end-of-list keys (PageUp/PageDown, Home/End, vim gg/G) for a list
+/// Returns true if the key was consumed
+fn handle_list_jump_keys(
+    code: KeyCode,
+    state: &mut ListState,
+    len: usize,
+    page_size: usize,
+    pending_g: &mut bool,
+) -> bool {
+    let was_g = std::mem::take(pending_g);
+    let page = page_size.max(1) as isize;
+    match code {
+        KeyCode::PageUp => move_list_selection(state, len, -page),
+        KeyCode::PageDown => move_list_selection(state, len, page),
+        KeyCode::Home => move_list_selection(state, len, isize::MIN),
+        KeyCode::End | KeyCode::Char('G') => move_list_selection(state, len, isize::MAX),
+        KeyCode::Char('g') if was_g => move_list_selection(state, len, isize::MIN),
+        KeyCode::Char('g') => *pending_g = true,
+        _ => return false,
+    }
+    true
+}
+
+/// Lower bound for the configured tick rate, so a typo can't spin the CPU
+const MIN_TICK_RATE_MS: u64 = 10;
+
+/// How often low-power mode checks the shown note for changes on disk while idle
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Main event loop function
 pub fn run_app<B: ratatui::backend::Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
 ) -> io::Result<bool> {
+    let mut needs_redraw = true;
     loop {
-        terminal
-            .draw(|f| ui(f, app))
-            .map_err(|e| Error::other(format!("{}", e)))?;
+        // Pick up results from background work before drawing
+        needs_redraw |= app.tick();
+        #[cfg(unix)]
+        while let Some((request, reply)) = app.next_remote_request() {
+            if handle_remote_request(app, request, reply) {
+                terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+            }
+            needs_redraw = true;
+        }
 
-        let Event::Key(key) = event::read()? else {
+        // In low-power mode, only redraw when something actually changed
+        if needs_redraw || !app.settings.low_power {
+            terminal
+                .draw(|f| ui(f, app))
+                .map_err(|e| Error::other(format!("{}", e)))?;
+            sync_inline_image(terminal, app)?;
+            needs_redraw = false;
+        }
+
+        // Wait briefly for input so background results keep flowing in
+        // With nothing running in the background, low-power mode sleeps until input arrives,
+        // waking now and then to pick up edits to the shown note
+        if !app.settings.low_power || app.is_busy() {
+            let tick_rate = Duration::from_millis(app.settings.tick_rate_ms.max(MIN_TICK_RATE_MS));
+            if !event::poll(tick_rate)? {
+                continue;
+            }
+        } else if (app.is_watching_note() || app.is_listening()) && !event::poll(WATCH_INTERVAL)? {
             continue;
+        }
+        // Any event (including resizes) needs a redraw
+        needs_redraw = true;
+        let key = match event::read()? {
+            Event::Key(key) => key,
+            Event::FocusGained => {
+                app.focused = Some(true);
+                continue;
+            }
+            Event::FocusLost => {
+                app.focused = Some(false);
+                continue;
+            }
+            _ => continue,
         };
         if key.kind == KeyEventKind::Press {
+            // Status messages only last until the next key press
+            app.status_message = None;
+            app.record_key(&key);
+            // F2 opens the scratch note from any screen but the internal editor, which may have
+            // unsaved changes
+            if key.code == KeyCode::F(2) && app.current_screen != CurrentScreen::InternalEdit {
+                if open_scratch(app) {
+                    if let Some(viewer) = &mut app.viewer {
+                        viewer.reload();
+                    }
+                    app.preview = None;
+                    app.load_browse_items();
+                    terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                }
+                continue;
+            }
             match app.current_screen {
                 CurrentScreen::Main => match key.code {
                     KeyCode::Char('q') | KeyCode::Char('Q') => {
                         app.current_screen = CurrentScreen::Exiting;
                     }
                     KeyCode::Char('n') | KeyCode::Char('N') => {
-                        app.current_screen = CurrentScreen::Editing;
-                        app.note_name_input.clear(); // Clear input when entering
+                        app.start_new_note();
                     }
                     KeyCode::Char('b') | KeyCode::Char('B') => {
                         app.load_browse_items();
@@ -502,16 +2570,132 @@ pub fn run_app<B: ratatui::backend::Backend>(
                         app.reset_settings_inputs(); // Reset to current saved values
                         app.active_settings_field = None;
                     }
+                    KeyCode::Char('i') | KeyCode::Char('I') => {
+                        app.open_stats();
+                    }
+                    KeyCode::Char('h') | KeyCode::Char('H') => {
+                        app.open_habits();
+                    }
+                    KeyCode::Char('$') => {
+                        app.open_ledger();
+                    }
+                    KeyCode::Char('l') | KeyCode::Char('L') => {
+                        app.open_log();
+                    }
+                    KeyCode::Char('j') | KeyCode::Char('J') => {
+                        app.open_journal_entry();
+                    }
+                    KeyCode::Char('r') | KeyCode::Char('R') => {
+                        app.open_reading();
+                    }
+                    KeyCode::Char('c') | KeyCode::Char('C') => {
+                        app.open_calendar();
+                    }
+                    KeyCode::Char('w') | KeyCode::Char('W') => {
+                        let vault = PathBuf::from(&app.settings.notes_directory);
+                        let editor = match app.settings.workspace_editor.trim() {
+                            "" => app.settings.editor.clone(),
+                            editor => editor.to_string(),
+                        };
+                        if crate::editor::is_internal(&editor) {
+                            app.status_message = Some(
+                                "The internal editor opens one note at a time; set workspace_editor to open the vault"
+                                    .to_string(),
+                            );
+                            continue;
+                        }
+                        let (editor, fallback) = match crate::editor::resolve(&editor) {
+                            Ok(resolved) => resolved,
+                            Err(message) => {
+                                app.status_message = Some(message);
+                                continue;
+                            }
+                        };
+                        app.status_message = fallback;
+                        if let Err(e) = launch_workspace_editor(&vault, &editor) {
+                            app.status_message = Some(format!("Unable to start {}: {}", editor, e));
+                        }
+                        app.preview = None; // Notes may have changed
+                        terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                    }
+                    KeyCode::Char('u') | KeyCode::Char('U') => {
+                        app.open_changelog();
+                    }
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        app.open_usage();
+                    }
+                    KeyCode::Char('p') | KeyCode::Char('P') => {
+                        app.open_problems();
+                    }
                     _ => {}
                 },
                 CurrentScreen::Browsing => {
+                    if !matches!(key.code, KeyCode::Char('c') | KeyCode::Char('C')) {
+                        app.copied_link = None;
+                    }
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.browse_list_state,
+                        app.browse_items.len(),
+                        app.list_page_size,
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
                     match key.code {
                         KeyCode::Esc => {
+                            app.browse_root = None;
                             app.current_screen = CurrentScreen::Main;
                         }
                         KeyCode::Char('q') | KeyCode::Char('Q') => {
                             app.current_screen = CurrentScreen::Exiting;
                         }
+                        KeyCode::Char('r') | KeyCode::Char('R') => {
+                            // Re-root the tree into the selected folder
+                            app.reroot_to_selected();
+                        }
+                        KeyCode::Backspace => {
+                            // Go up one level from a re-rooted tree
+                            app.reroot_up();
+                        }
+                        KeyCode::Char('t') | KeyCode::Char('T') => {
+                            // Open the restructure assistant for the selected folder
+                            app.start_restructure();
+                        }
+                        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_dry_run();
+                        }
+                        KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_show_archived();
+                        }
+                        // Ctrl+arrows move the divider between the note list and the preview
+                        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down
+                            if key.modifiers.contains(KeyModifiers::CONTROL) =>
+                        {
+                            match (app.settings.preview_position, key.code) {
+                                (PreviewPosition::Bottom, KeyCode::Up)
+                                | (PreviewPosition::Right | PreviewPosition::Off, KeyCode::Left) => {
+                                    app.resize_preview(5);
+                                }
+                                (PreviewPosition::Bottom, KeyCode::Down)
+                                | (PreviewPosition::Right | PreviewPosition::Off, KeyCode::Right) => {
+                                    app.resize_preview(-5);
+                                }
+                                _ => {}
+                            }
+                        }
+                        KeyCode::Char('d') | KeyCode::Char('D') | KeyCode::Delete => {
+                            app.request_delete_selected();
+                        }
+                        KeyCode::Char('p') | KeyCode::Char('P') => {
+                            // Review purging empty folders below the selected folder
+                            app.start_purge_empty_folders();
+                        }
+                        KeyCode::Char('a') => app.open_quick_append(),
+                        KeyCode::Char('A') => {
+                            // Review expired / stale notes before archiving them
+                            app.start_archive_review();
+                        }
                         KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('K') => {
                             app.browse_up();
                         }
@@ -520,9 +2704,14 @@ pub fn run_app<B: ratatui::backend::Backend>(
                         }
                         KeyCode::Enter => {
                             // Open the selected file
-                            if let Some(file_path) = app.get_selected_file_path() {
-                                if let Err(_e) = launch_editor(file_path, &app.settings.editor) {
-                                    // Error launching editor - continue in TUI
+                            if let Some(file_path) = app.get_selected_file_path().cloned() {
+                                if app.uses_internal_editor(&file_path) {
+                                    app.open_internal_editor(&file_path, CurrentScreen::Browsing);
+                                    continue;
+                                }
+                                if launch_editor(app, &file_path, None) {
+                                    app.run_hook(HookEvent::PostEdit, &file_path);
+                                    app.lint_edited(&file_path);
                                 }
                                 app.current_file = Some(file_path.to_string_lossy().to_string());
                                 // Reload browse items to reflect any changes made in the editor
@@ -536,8 +2725,29 @@ pub fn run_app<B: ratatui::backend::Backend>(
                         KeyCode::Char('n') | KeyCode::Char('N') => {
                             // Create new note in selected directory
                             app.target_directory = Some(app.get_selected_directory());
-                            app.note_name_input.clear();
-                            app.current_screen = CurrentScreen::Editing;
+                            app.start_new_note();
+                        }
+                        KeyCode::Char('x') | KeyCode::Char('X') => {
+                            app.open_actions();
+                        }
+                        KeyCode::Char('v') | KeyCode::Char('V') => {
+                            app.open_viewer();
+                        }
+                        KeyCode::Char('@') => app.open_citations(),
+                        KeyCode::Char('m') | KeyCode::Char('M') => app.toggle_mark(),
+                        KeyCode::Char('i') | KeyCode::Char('I') => app.open_note_info(),
+                        KeyCode::Char('h') | KeyCode::Char('H') => app.open_history(),
+                        KeyCode::Char('o') | KeyCode::Char('O') => app.open_open_with(),
+                        KeyCode::Char('c') | KeyCode::Char('C') => {
+                            if let Some(note) = app.get_selected_file_path().cloned() {
+                                app.copy_link(&note);
+                            }
+                        }
+                        KeyCode::Char('+') | KeyCode::Char('=') => app.resize_preview(5),
+                        KeyCode::Char('-') => app.resize_preview(-5),
+                        KeyCode::Char('l') | KeyCode::Char('L') => {
+                            // Group notes by their `lang:` frontmatter, or back to the tree
+                            app.toggle_browse_view();
                         }
                         KeyCode::Char('f') | KeyCode::Char('F') => {
                             // Create new folder - go to folder creation screen
@@ -551,55 +2761,34 @@ pub fn run_app<B: ratatui::backend::Backend>(
                 CurrentScreen::Editing => {
                     match key.code {
                         KeyCode::Enter => {
-                            // Create note and launch editor
-                            let note_name = if app.note_name_input.trim().is_empty() {
-                                None
-                            } else {
-                                Some(app.note_name_input.as_str())
-                            };
-                            
-                            match create_note_file(
+                            let file_name = new_note_file_name(&app.settings, &app.note_name_input);
+                            let folder = note_directory(
                                 &app.settings.notes_directory,
-                                note_name,
-                                &app.settings.default_file_format,
                                 app.target_directory.as_ref(),
-                            ) {
-                                Ok(file_path) => {
-                                    let target_dir = app.target_directory.take();
-                                    
-                                    // Launch editor with the new note
-                                    if let Err(_e) = launch_editor(&file_path, &app.settings.editor) {
-                                        // Error launching editor - continue in TUI
-                                    }
-
-                                    // Return to appropriate screen after editor exits
-                                    if target_dir.is_some() {
-                                        // Came from browse screen, return there
-                                        app.current_screen = CurrentScreen::Browsing;
-                                        // Expand the target directory and reload to show new note
-                                        if let Some(dir) = target_dir {
-                                            app.expanded_folders.insert(dir);
-                                        }
-                                        app.load_browse_items(); // Reload to show new note
-                                    } else {
-                                        // Came from main screen
-                                        app.current_screen = CurrentScreen::Main;
-                                    }
-                                    app.note_name_input.clear();
-                                    app.current_file = Some(file_path.to_string_lossy().to_string());
-                                }
-                                Err(e) => {
-                                    eprintln!("Error creating note file: {}", e);
-                                    // Stay in editing screen on error
-                                }
+                            );
+                            if !crate::vault::is_file_name(&file_name) {
+                                app.status_message = Some(
+                                    "A note's name can't hold a / or .., or start with a .".to_string(),
+                                );
+                            } else if app.storage.exists(&folder.join(&file_name)) {
+                                // Ask rather than quietly opening the note that's there
+                                let numbered =
+                                    numbered_file_name(app.storage.as_ref(), &folder, &file_name);
+                                app.taken_note_name = Some((file_name, numbered));
+                                app.current_screen = CurrentScreen::NoteExists;
+                            } else {
+                                finish_new_note(app, &file_name, true);
                             }
                         }
                         KeyCode::Backspace => {
                             // Remove last character
                             app.note_name_input.pop();
                         }
+                        KeyCode::Tab | KeyCode::Down => app.move_template(1),
+                        KeyCode::Up => app.move_template(-1),
                         KeyCode::Esc => {
                             // Cancel and return to previous screen
+                            app.note_template = None;
                             if app.target_directory.is_some() {
                                 app.current_screen = CurrentScreen::Browsing;
                             } else {
@@ -609,11 +2798,13 @@ pub fn run_app<B: ratatui::backend::Backend>(
                             app.target_directory = None;
                             app.current_file = None;
                         }
-                        KeyCode::Char(c) => {
-                            // Add character to input (allow alphanumeric, spaces, dashes, underscores, dots)
-                            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.' {
-                                app.note_name_input.push(c);
-                            }
+                        // Add character to input (those name_characters allows; anything printable
+                        // when the name is slugified)
+                        KeyCode::Char(c)
+                            if app.settings.name_characters.allows(c)
+                                || (app.settings.slugify_names && !c.is_control()) =>
+                        {
+                            app.note_name_input.push(c);
                         }
                         _ => {}
                     }
@@ -623,7 +2814,7 @@ pub fn run_app<B: ratatui::backend::Backend>(
                         KeyCode::Enter => {
                             // Create folder (load_browse_items is called inside create_new_folder)
                             if let Err(e) = app.create_new_folder() {
-                                eprintln!("Error creating folder: {}", e);
+                                app.status_message = Some(format!("Error creating folder: {}", e));
                             } else {
                                 // Return to browse screen
                                 app.current_screen = CurrentScreen::Browsing;
@@ -639,11 +2830,9 @@ pub fn run_app<B: ratatui::backend::Backend>(
                             app.folder_name_input.clear();
                             app.target_directory = None;
                         }
-                        KeyCode::Char(c) => {
-                            // Add character to input (allow alphanumeric, spaces, dashes, underscores, dots)
-                            if c.is_alphanumeric() || c == ' ' || c == '-' || c == '_' || c == '.' {
-                                app.folder_name_input.push(c);
-                            }
+                        // Add character to input (those name_characters allows)
+                        KeyCode::Char(c) if app.settings.name_characters.allows(c) => {
+                            app.folder_name_input.push(c);
                         }
                         _ => {}
                     }
@@ -710,6 +2899,16 @@ pub fn run_app<B: ratatui::backend::Backend>(
                                 app.current_screen = CurrentScreen::Main;
                             }
                         }
+                        KeyCode::Char('u') | KeyCode::Char('U')
+                            if app.active_settings_field.is_none() =>
+                        {
+                            app.toggle_update_checks();
+                        }
+                        KeyCode::Char('y') | KeyCode::Char('Y')
+                            if app.active_settings_field.is_none() =>
+                        {
+                            app.toggle_usage_insights();
+                        }
                         KeyCode::Backspace => {
                             // Handle backspace when editing
                             if let Some(field) = app.active_settings_field {
@@ -749,6 +2948,776 @@ pub fn run_app<B: ratatui::backend::Backend>(
                         _ => {}
                     }
                 }
+                CurrentScreen::PlanReview => {
+                    let len = app.bulk_plan.as_ref().map(|p| p.operations.len()).unwrap_or(0);
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.plan_list_state,
+                        len,
+                        app.list_page_size,
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('K') => {
+                            app.plan_list_state.select_previous();
+                        }
+                        KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('J') => {
+                            app.plan_list_state.select_next();
+                        }
+                        KeyCode::Tab if app.restructure_folder.is_some() => {
+                            app.toggle_restructure_mode();
+                        }
+                        KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            app.toggle_dry_run();
+                        }
+                        KeyCode::Enter => {
+                            app.apply_plan();
+                        }
+                        KeyCode::Char('e') | KeyCode::Char('E') => {
+                            app.export_plan_log();
+                        }
+                        KeyCode::Esc => {
+                            app.close_plan();
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::InternalEdit => {
+                    let Some(editor) = &mut app.internal_editor else {
+                        app.current_screen = CurrentScreen::Main;
+                        continue;
+                    };
+                    if key.code != KeyCode::Esc {
+                        editor.confirm_discard = false;
+                    }
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    let page = editor.page_size as isize;
+                    let buffer = &mut editor.buffer;
+                    match key.code {
+                        KeyCode::Char('s') if ctrl => app.save_internal_edit(),
+                        KeyCode::Esc => app.close_internal_editor(),
+                        KeyCode::Char(c) if !ctrl => {
+                            buffer.insert_char(c);
+                            editor.changed();
+                        }
+                        KeyCode::Enter => {
+                            buffer.insert_newline();
+                            editor.changed();
+                        }
+                        KeyCode::Tab => {
+                            buffer.insert_tab();
+                            editor.changed();
+                        }
+                        KeyCode::Backspace => {
+                            buffer.backspace();
+                            editor.changed();
+                        }
+                        KeyCode::Delete => {
+                            buffer.delete();
+                            editor.changed();
+                        }
+                        KeyCode::Left => buffer.move_left(),
+                        KeyCode::Right => buffer.move_right(),
+                        KeyCode::Up => buffer.move_lines(-1),
+                        KeyCode::Down => buffer.move_lines(1),
+                        KeyCode::PageUp => buffer.move_lines(-page),
+                        KeyCode::PageDown => buffer.move_lines(page),
+                        KeyCode::Home => buffer.home(),
+                        KeyCode::End => buffer.end(),
+                        _ => {}
+                    }
+                }
+                CurrentScreen::NoteInfo => {
+                    if matches!(
+                        key.code,
+                        KeyCode::Esc | KeyCode::Enter | KeyCode::Char('i') | KeyCode::Char('I')
+                    ) {
+                        app.note_info = None;
+                        app.current_screen = CurrentScreen::Browsing;
+                    }
+                }
+                CurrentScreen::TemplatePrompt => match key.code {
+                    KeyCode::Enter => {
+                        if let Some(file_name) = app.answer_template_prompt() {
+                            finish_new_note(app, &file_name, true);
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app.template_answers.clear();
+                        app.pending_note = None;
+                        app.current_screen = CurrentScreen::Editing;
+                    }
+                    KeyCode::Backspace => {
+                        app.prompt_input.pop();
+                    }
+                    KeyCode::Char(c) => app.prompt_input.push(c),
+                    _ => {}
+                },
+                CurrentScreen::JournalEntry => {
+                    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+                    let buffer = &mut app.journal_input;
+                    match key.code {
+                        KeyCode::Char('s') if ctrl => app.submit_journal_entry(),
+                        KeyCode::Esc => app.current_screen = app.journal_from,
+                        KeyCode::Char(c) if !ctrl => buffer.insert_char(c),
+                        KeyCode::Enter => buffer.insert_newline(),
+                        KeyCode::Tab => buffer.insert_tab(),
+                        KeyCode::Backspace => buffer.backspace(),
+                        KeyCode::Delete => buffer.delete(),
+                        KeyCode::Left => buffer.move_left(),
+                        KeyCode::Right => buffer.move_right(),
+                        KeyCode::Up => buffer.move_lines(-1),
+                        KeyCode::Down => buffer.move_lines(1),
+                        KeyCode::Home => buffer.home(),
+                        KeyCode::End => buffer.end(),
+                        _ => {}
+                    }
+                }
+                CurrentScreen::NoteExists => match key.code {
+                    KeyCode::Char('o') | KeyCode::Char('O') | KeyCode::Enter => {
+                        if let Some((file_name, _)) = app.taken_note_name.take() {
+                            finish_new_note(app, &file_name, false);
+                        }
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') => {
+                        if let Some((_, numbered)) = app.taken_note_name.take() {
+                            finish_new_note(app, &numbered, true);
+                        }
+                    }
+                    KeyCode::Char('r') | KeyCode::Char('R') | KeyCode::Esc => {
+                        app.taken_note_name = None;
+                        app.current_screen = CurrentScreen::Editing;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::ConfirmDelete => match key.code {
+                    KeyCode::Char('y') | KeyCode::Char('Y') => {
+                        app.confirm_delete();
+                    }
+                    KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                        app.pending_delete = None;
+                        app.current_screen = CurrentScreen::Browsing;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Stats => {
+                    if key.code == KeyCode::Esc {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                }
+                CurrentScreen::Viewing => {
+                    let wraps = app.viewer_wraps();
+                    let Some(viewer) = &mut app.viewer else {
+                        app.current_screen = CurrentScreen::Browsing;
+                        continue;
+                    };
+                    // Typing a search query
+                    if let Some(input) = &mut viewer.search_input {
+                        match key.code {
+                            KeyCode::Char(c) => input.push(c),
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            KeyCode::Enter => {
+                                let query = std::mem::take(input);
+                                viewer.search_input = None;
+                                viewer.set_search(query);
+                                if viewer.search.is_some() && viewer.match_count() == 0 {
+                                    app.status_message = Some("No matches".to_string());
+                                }
+                            }
+                            KeyCode::Esc => viewer.search_input = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if let Some(input) = &mut viewer.comment_input {
+                        match key.code {
+                            KeyCode::Char(c) => input.push(c),
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            KeyCode::Enter => app.save_annotation(),
+                            KeyCode::Esc => viewer.comment_input = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if viewer.line_cursor.is_some() {
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => viewer.move_line_cursor(false),
+                            KeyCode::Down | KeyCode::Char('j') => viewer.move_line_cursor(true),
+                            KeyCode::Enter => viewer.comment_input = Some(String::new()),
+                            KeyCode::Char('d') | KeyCode::Char('D') => {
+                                app.delete_annotations_at_cursor();
+                            }
+                            KeyCode::Esc => viewer.line_cursor = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if viewer.outline.is_some() {
+                        match key.code {
+                            KeyCode::Up | KeyCode::Char('k') => viewer.move_outline(-1),
+                            KeyCode::Down | KeyCode::Char('j') => viewer.move_outline(1),
+                            KeyCode::Home | KeyCode::Char('g') => viewer.move_outline(isize::MIN),
+                            KeyCode::End | KeyCode::Char('G') => viewer.move_outline(isize::MAX),
+                            KeyCode::Enter => viewer.jump_to_outline(),
+                            KeyCode::Esc | KeyCode::Char('o') | KeyCode::Char('O') => {
+                                viewer.outline = None;
+                            }
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    if viewer.task.is_some() {
+                        match key.code {
+                            KeyCode::Char(' ') | KeyCode::Char('x') => app.toggle_task_at_cursor(),
+                            KeyCode::Down | KeyCode::Char('j') | KeyCode::Char('t') => {
+                                viewer.move_task(true);
+                            }
+                            KeyCode::Up | KeyCode::Char('k') | KeyCode::Char('T') => {
+                                viewer.move_task(false);
+                            }
+                            KeyCode::Esc => viewer.task = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    let was_g = std::mem::take(&mut app.pending_g);
+                    let page = viewer.page_size.max(1) as isize;
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => viewer.scroll_by(-1),
+                        KeyCode::Down | KeyCode::Char('j') => viewer.scroll_by(1),
+                        KeyCode::PageUp => viewer.scroll_by(-page),
+                        KeyCode::PageDown | KeyCode::Char(' ') => viewer.scroll_by(page),
+                        KeyCode::Home => viewer.scroll_by(isize::MIN),
+                        KeyCode::End | KeyCode::Char('G') => viewer.scroll_by(isize::MAX),
+                        KeyCode::Char('g') if was_g => viewer.scroll_by(isize::MIN),
+                        KeyCode::Char('g') => app.pending_g = true,
+                        KeyCode::Char('e') | KeyCode::Char('E') => {
+                            // Hand over to the editor, then show the edited note
+                            let path = viewer.page.path.clone();
+                            if app.uses_internal_editor(&path) {
+                                app.open_internal_editor(&path, CurrentScreen::Viewing);
+                                continue;
+                            }
+                            if launch_editor(app, &path, None) {
+                                app.run_hook(HookEvent::PostEdit, &path);
+                                app.lint_edited(&path);
+                            }
+                            if let Some(viewer) = &mut app.viewer {
+                                viewer.reload();
+                            }
+                            app.preview = None;
+                            terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                        }
+                        KeyCode::Char('/') => viewer.search_input = Some(String::new()),
+                        KeyCode::Left if !wraps => viewer.scroll_columns(-8),
+                        KeyCode::Right if !wraps => viewer.scroll_columns(8),
+                        KeyCode::Char('w') | KeyCode::Char('W') => app.toggle_viewer_wrap(),
+                        KeyCode::Char('l') | KeyCode::Char('L') => app.toggle_viewer_line_numbers(),
+                        KeyCode::Char('a') | KeyCode::Char('A') => viewer.start_annotating(),
+                        KeyCode::Char('o') | KeyCode::Char('O') => {
+                            let found = viewer.open_outline();
+                            if !found {
+                                app.status_message = Some("No headings in this note".to_string());
+                            }
+                        }
+                        KeyCode::Char('t') | KeyCode::Char('T') => {
+                            let found = viewer.move_task(key.code == KeyCode::Char('t'));
+                            if !found {
+                                app.status_message = Some("No checkboxes in this note".to_string());
+                            }
+                        }
+                        KeyCode::Char('n') => viewer.next_match(true),
+                        KeyCode::Char('N') => viewer.next_match(false),
+                        KeyCode::Esc if viewer.search.is_some() => viewer.clear_search(),
+                        KeyCode::Tab => viewer.move_link(true),
+                        KeyCode::BackTab => viewer.move_link(false),
+                        KeyCode::Enter => app.follow_link(),
+                        KeyCode::Backspace | KeyCode::Char('b') => {
+                            let went_back = viewer.back();
+                            if !went_back {
+                                app.status_message = Some("No previous note".to_string());
+                            }
+                        }
+                        KeyCode::Char(']') => app.cycle_tab(true),
+                        KeyCode::Char('[') => app.cycle_tab(false),
+                        KeyCode::Char(c @ '1'..='9') => {
+                            app.switch_tab(c as usize - '1' as usize);
+                        }
+                        KeyCode::Char('x') | KeyCode::Char('X') => app.close_tab(),
+                        // Leaving keeps the tabs, for V to come back to
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.current_screen = app.viewer_return;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::Comparing => {
+                    let Some(comparison) = &mut app.comparison else {
+                        app.current_screen = CurrentScreen::Browsing;
+                        continue;
+                    };
+                    let was_g = std::mem::take(&mut app.pending_g);
+                    let page = comparison.page_size.max(1) as isize;
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => comparison.scroll_by(-1),
+                        KeyCode::Down | KeyCode::Char('j') => comparison.scroll_by(1),
+                        KeyCode::PageUp => comparison.scroll_by(-page),
+                        KeyCode::PageDown | KeyCode::Char(' ') => comparison.scroll_by(page),
+                        KeyCode::Home => comparison.scroll_by(isize::MIN),
+                        KeyCode::End | KeyCode::Char('G') => comparison.scroll_by(isize::MAX),
+                        KeyCode::Char('g') if was_g => comparison.scroll_by(isize::MIN),
+                        KeyCode::Char('g') => app.pending_g = true,
+                        KeyCode::Char('n') | KeyCode::Char('N') => {
+                            let moved = comparison.next_change(key.code == KeyCode::Char('n'));
+                            if !moved {
+                                app.status_message = Some("No more changes".to_string());
+                            }
+                        }
+                        KeyCode::Char('v') | KeyCode::Char('V') => comparison.toggle_layout(),
+                        KeyCode::Char('s') | KeyCode::Char('S') => {
+                            if let Err(e) = comparison.swap() {
+                                app.status_message = Some(format!("Unable to compare the notes: {}", e));
+                            }
+                        }
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.comparison = None;
+                            app.current_screen = app.comparison_return;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::Problems => {
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.problem_list_state,
+                        app.problems.len(),
+                        app.list_page_size,
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            move_list_selection(&mut app.problem_list_state, app.problems.len(), -1);
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            move_list_selection(&mut app.problem_list_state, app.problems.len(), 1);
+                        }
+                        KeyCode::Enter => app.open_problem(),
+                        KeyCode::Char('e') | KeyCode::Char('E') => {
+                            // Edit the note at the problem's line
+                            let Some(problem) = app
+                                .problem_list_state
+                                .selected()
+                                .and_then(|i| app.problems.get(i))
+                            else {
+                                continue;
+                            };
+                            let (path, line) = (problem.path.clone(), problem.line);
+                            if app.uses_internal_editor(&path) {
+                                app.open_internal_editor(&path, CurrentScreen::Problems);
+                                if let (Some(editor), Some(line)) = (&mut app.internal_editor, line) {
+                                    editor.buffer.go_to_line(line);
+                                }
+                                continue;
+                            }
+                            if launch_editor(app, &path, line) {
+                                app.run_hook(HookEvent::PostEdit, &path);
+                                app.lint_edited(&path);
+                            }
+                            app.preview = None;
+                            terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                        }
+                        KeyCode::Char('r') | KeyCode::Char('R') => app.open_problems(),
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::History => {
+                    if !matches!(key.code, KeyCode::Char('r') | KeyCode::Char('R')) {
+                        app.cancel_history_restore();
+                    }
+                    let count = app.history.as_ref().map_or(0, |(_, revisions)| revisions.len());
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.history_list_state,
+                        count,
+                        app.list_page_size,
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            move_list_selection(&mut app.history_list_state, count, -1);
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            move_list_selection(&mut app.history_list_state, count, 1);
+                        }
+                        KeyCode::Enter | KeyCode::Char('d') | KeyCode::Char('D') => {
+                            if app.settings.diff_tool.trim().is_empty() {
+                                app.compare_revision();
+                            } else if let Some((left, right)) = app.revision_sides() {
+                                let compared = with_terminal_suspended(|| {
+                                    actions::run_diff_tool_on_versions(
+                                        &app.settings.diff_tool,
+                                        &left,
+                                        &right,
+                                    )
+                                });
+                                if let Err(e) = compared {
+                                    app.status_message = Some(format!("Diff tool failed: {}", e));
+                                }
+                                terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                            }
+                        }
+                        KeyCode::Char('m') | KeyCode::Char('M') => app.toggle_history_mark(),
+                        KeyCode::Char('r') | KeyCode::Char('R') => app.restore_revision(),
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.history = None;
+                            app.current_screen = CurrentScreen::Browsing;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::Usage => match key.code {
+                    KeyCode::Char('d') | KeyCode::Char('D') => app.clear_usage(),
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Changelog => match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        app.changelog_scroll = app.changelog_scroll.saturating_sub(1);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        app.changelog_scroll = app.changelog_scroll.saturating_add(1);
+                    }
+                    KeyCode::PageUp => {
+                        app.changelog_scroll = app.changelog_scroll.saturating_sub(10);
+                    }
+                    KeyCode::PageDown | KeyCode::Char(' ') => {
+                        app.changelog_scroll = app.changelog_scroll.saturating_add(10);
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Citing => {
+                    let count = app.citation_matches().len();
+                    match key.code {
+                        KeyCode::Up => move_list_selection(&mut app.citation_list_state, count, -1),
+                        KeyCode::Down => move_list_selection(&mut app.citation_list_state, count, 1),
+                        KeyCode::Tab => app.complete_citation(),
+                        KeyCode::Enter => app.insert_citation(),
+                        KeyCode::Esc => {
+                            app.current_screen = CurrentScreen::Browsing;
+                        }
+                        KeyCode::Backspace => {
+                            app.citation_input.pop();
+                            app.citation_list_state.select(Some(0));
+                        }
+                        KeyCode::Char(c) => {
+                            app.citation_input.push(c);
+                            app.citation_list_state.select(Some(0));
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::Reading if app.reading_input.is_some() => match key.code {
+                    KeyCode::Enter => app.submit_reading_item(),
+                    KeyCode::Esc => app.reading_input = None,
+                    KeyCode::Backspace => {
+                        if let Some(input) = &mut app.reading_input {
+                            input.pop();
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(input) = &mut app.reading_input {
+                            input.push(c);
+                        }
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Reading => match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        move_list_selection(&mut app.reading_list_state, app.reading_items.len(), -1);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        move_list_selection(&mut app.reading_list_state, app.reading_items.len(), 1);
+                    }
+                    KeyCode::Char('a') | KeyCode::Char('A') => {
+                        app.reading_input = Some(String::new());
+                    }
+                    KeyCode::Char('r') | KeyCode::Char('R') => {
+                        app.set_reading_status(ReadingStatus::Reading);
+                    }
+                    KeyCode::Char('d') | KeyCode::Char('D') => {
+                        app.set_reading_status(ReadingStatus::Done);
+                    }
+                    KeyCode::Char('x') | KeyCode::Char('X') => {
+                        app.set_reading_status(ReadingStatus::Dropped);
+                    }
+                    KeyCode::Char('t') | KeyCode::Char('T') => {
+                        app.set_reading_status(ReadingStatus::ToRead);
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Log => match key.code {
+                    KeyCode::Enter => app.submit_log_entry(),
+                    KeyCode::Backspace => {
+                        app.log_input.pop();
+                    }
+                    KeyCode::Up => app.log_scroll = app.log_scroll.saturating_add(1),
+                    KeyCode::Down => app.log_scroll = app.log_scroll.saturating_sub(1),
+                    KeyCode::PageUp => app.log_scroll = app.log_scroll.saturating_add(10),
+                    KeyCode::PageDown => app.log_scroll = app.log_scroll.saturating_sub(10),
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Char(c) => app.log_input.push(c),
+                    _ => {}
+                },
+                CurrentScreen::Ledger => match key.code {
+                    KeyCode::Enter => app.submit_ledger_entry(),
+                    KeyCode::Backspace => {
+                        app.ledger_input.pop();
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    KeyCode::Char(c) => app.ledger_input.push(c),
+                    _ => {}
+                },
+                CurrentScreen::Habits => match key.code {
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        move_list_selection(&mut app.habit_list_state, app.habits.len(), -1);
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        move_list_selection(&mut app.habit_list_state, app.habits.len(), 1);
+                    }
+                    KeyCode::Left | KeyCode::Char('h') => {
+                        app.habit_day = (app.habit_day + 1).min(HABIT_DAYS - 1);
+                    }
+                    KeyCode::Right | KeyCode::Char('l') => {
+                        app.habit_day = app.habit_day.saturating_sub(1);
+                    }
+                    KeyCode::Char(' ') | KeyCode::Enter => {
+                        app.toggle_habit();
+                    }
+                    KeyCode::Esc => {
+                        app.current_screen = CurrentScreen::Main;
+                    }
+                    _ => {}
+                },
+                CurrentScreen::Snippets => {
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.snippet_list_state,
+                        app.snippet_names.len(),
+                        app.list_page_size,
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            move_list_selection(&mut app.snippet_list_state, app.snippet_names.len(), -1);
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            move_list_selection(&mut app.snippet_list_state, app.snippet_names.len(), 1);
+                        }
+                        KeyCode::Enter => app.insert_snippet(),
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.current_screen = CurrentScreen::Browsing;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::QuickAppend => match key.code {
+                    KeyCode::Char(c) => app.append_input.push(c),
+                    KeyCode::Backspace => {
+                        app.append_input.pop();
+                    }
+                    KeyCode::Tab => app.append_timestamp = !app.append_timestamp,
+                    KeyCode::Enter => app.submit_quick_append(),
+                    KeyCode::Esc => app.current_screen = CurrentScreen::Browsing,
+                    _ => {}
+                },
+                CurrentScreen::Calendar => {
+                    if let Some(input) = &mut app.calendar_input {
+                        match key.code {
+                            KeyCode::Char(c) => input.push(c),
+                            KeyCode::Backspace => {
+                                input.pop();
+                            }
+                            KeyCode::Enter => app.submit_calendar_input(),
+                            KeyCode::Esc => app.calendar_input = None,
+                            _ => {}
+                        }
+                        continue;
+                    }
+                    let date = app.calendar_date;
+                    let moved = match key.code {
+                        KeyCode::Left | KeyCode::Char('h') => date.pred_opt(),
+                        KeyCode::Right | KeyCode::Char('l') => date.succ_opt(),
+                        KeyCode::Up | KeyCode::Char('k') => date.checked_sub_days(chrono::Days::new(7)),
+                        KeyCode::Down | KeyCode::Char('j') => date.checked_add_days(chrono::Days::new(7)),
+                        KeyCode::PageUp | KeyCode::Char('[') => Some(crate::calendar::add_months(date, -1)),
+                        KeyCode::PageDown | KeyCode::Char(']') => Some(crate::calendar::add_months(date, 1)),
+                        KeyCode::Char('{') => Some(crate::calendar::add_months(date, -12)),
+                        KeyCode::Char('}') => Some(crate::calendar::add_months(date, 12)),
+                        KeyCode::Home | KeyCode::Char('t') | KeyCode::Char('T') => {
+                            Some(chrono::Local::now().date_naive())
+                        }
+                        _ => None,
+                    };
+                    if let Some(moved) = moved {
+                        app.calendar_go(moved);
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Char('/') | KeyCode::Char('g') => app.calendar_input = Some(String::new()),
+                        KeyCode::Enter => {
+                            let Some(path) = app.calendar_daily_note() else {
+                                continue;
+                            };
+                            if app.uses_internal_editor(&path) {
+                                app.open_internal_editor(&path, CurrentScreen::Calendar);
+                                continue;
+                            }
+                            if launch_editor(app, &path, None) {
+                                app.run_hook(HookEvent::PostEdit, &path);
+                                app.lint_edited(&path);
+                            }
+                            app.calendar_days = crate::calendar::month(&app.settings, app.calendar_date);
+                            terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                        }
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.current_screen = CurrentScreen::Main;
+                        }
+                        _ => {}
+                    }
+                }
+                CurrentScreen::OpenWith => {
+                    let count = app.settings.alternate_editors.len();
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            move_list_selection(&mut app.open_with_list_state, count, -1);
+                            continue;
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            move_list_selection(&mut app.open_with_list_state, count, 1);
+                            continue;
+                        }
+                        KeyCode::Char(c @ '1'..='9') if (c as usize - '1' as usize) < count => {
+                            app.open_with_list_state.select(Some(c as usize - '1' as usize));
+                        }
+                        KeyCode::Enter => {}
+                        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q') => {
+                            app.current_screen = CurrentScreen::Browsing;
+                            continue;
+                        }
+                        _ => continue,
+                    }
+                    // Enter or a number: open the file with the chosen command
+                    app.current_screen = CurrentScreen::Browsing;
+                    let (Some(editor), Some(file_path)) =
+                        (app.selected_alternate_editor(), app.get_selected_file_path().cloned())
+                    else {
+                        continue;
+                    };
+                    if crate::editor::is_internal(&editor) {
+                        app.open_internal_editor(&file_path, CurrentScreen::Browsing);
+                        continue;
+                    }
+                    if run_editor(app, &editor, &file_path, None) {
+                        app.run_hook(HookEvent::PostEdit, &file_path);
+                        app.lint_edited(&file_path);
+                    }
+                    app.preview = None;
+                    app.load_browse_items();
+                    terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                }
+                CurrentScreen::Actions => {
+                    if handle_list_jump_keys(
+                        key.code,
+                        &mut app.action_list_state,
+                        Action::ALL.len(),
+                        Action::ALL.len(),
+                        &mut app.pending_g,
+                    ) {
+                        continue;
+                    }
+                    match key.code {
+                        KeyCode::Up | KeyCode::Char('k') => {
+                            move_list_selection(&mut app.action_list_state, Action::ALL.len(), -1);
+                        }
+                        KeyCode::Down | KeyCode::Char('j') => {
+                            move_list_selection(&mut app.action_list_state, Action::ALL.len(), 1);
+                        }
+                        KeyCode::Enter => {
+                            if let Some(action) = app.selected_action() {
+                                let external_diff = (action == Action::CompareNotes
+                                    && !app.settings.diff_tool.trim().is_empty())
+                                .then(|| app.marked_pair())
+                                .flatten();
+                                if let Some((left, right)) = external_diff {
+                                    let compared = with_terminal_suspended(|| {
+                                        actions::run_diff_tool(&app.settings.diff_tool, &left, &right)
+                                    });
+                                    app.finish_external_diff(compared);
+                                    terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                                } else if action.is_interactive() && action.unavailable_reason(&app.tools, &app.settings).is_none() {
+                                    match (action, app.get_selected_file_path().cloned()) {
+                                        (Action::OpenInPager, Some(note)) => {
+                                            let paged = with_terminal_suspended(|| {
+                                                actions::page_note(&app.tools, &note)
+                                            });
+                                            app.finish_paging(paged);
+                                        }
+                                        (Action::OpenInPager, None) => {
+                                            app.status_message = Some("Select a note first".to_string());
+                                        }
+                                        _ => {
+                                            let language = app.selected_language();
+                                            let picked = with_terminal_suspended(|| {
+                                                actions::pick_note(
+                                                    action,
+                                                    &app.tools,
+                                                    &app.settings,
+                                                    language.as_deref(),
+                                                )
+                                            });
+                                            app.finish_pick(picked);
+                                        }
+                                    }
+                                    // The screen was handed to another program, so redraw everything
+                                    terminal.clear().map_err(|e| Error::other(format!("{}", e)))?;
+                                } else {
+                                    app.run_action(action);
+                                }
+                            }
+                        }
+                        KeyCode::Esc => {
+                            app.current_screen = CurrentScreen::Browsing;
+                        }
+                        _ => {}
+                    }
+                }
                 CurrentScreen::Exiting => match key.code {
                     KeyCode::Char('y') | KeyCode::Char('Y') => {
                         return Ok(false);
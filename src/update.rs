@@ -0,0 +1,101 @@
+use serde_json::Value;
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// The version this build was made from
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+const RELEASES_URL: &str = "https://api.github.com/repos/f5aaff/LAIR/releases?per_page=20";
+
+/// A published release, as listed on GitHub
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub tag: String,
+    pub published: String, // YYYY-MM-DD
+    pub notes: String,     // Release notes, in markdown
+}
+
+/// Releases newer than the running version, newest first
+#[derive(Debug, Clone, Default)]
+pub struct UpdateInfo {
+    pub newer: Vec<Release>,
+}
+
+impl UpdateInfo {
+    /// The newest release, when there is one to update to
+    pub fn latest(&self) -> Option<&Release> {
+        self.newer.first()
+    }
+}
+
+/// The numeric parts of a version tag: "v1.2.3" gives [1, 2, 3]
+fn version_parts(tag: &str) -> Vec<u64> {
+    tag.trim_start_matches(['v', 'V'])
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Whether `tag` names a later version than `current`
+pub fn is_newer(tag: &str, current: &str) -> bool {
+    let (tag, current) = (version_parts(tag), version_parts(current));
+    !tag.is_empty() && tag > current
+}
+
+/// The stable releases newer than the running version in a GitHub releases listing
+fn parse_releases(json: &str) -> io::Result<UpdateInfo> {
+    let value: Value = serde_json::from_str(json).map_err(io::Error::other)?;
+    let Some(releases) = value.as_array() else {
+        return Err(io::Error::other("unexpected response from GitHub"));
+    };
+    let text = |release: &Value, key: &str| {
+        release
+            .get(key)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut newer: Vec<Release> = releases
+        .iter()
+        .filter(|r| !r["draft"].as_bool().unwrap_or(false))
+        .filter(|r| !r["prerelease"].as_bool().unwrap_or(false))
+        .map(|r| Release {
+            tag: text(r, "tag_name"),
+            published: text(r, "published_at").chars().take(10).collect(),
+            notes: text(r, "body").replace("\r\n", "\n"),
+        })
+        .filter(|r| is_newer(&r.tag, CURRENT_VERSION))
+        .collect();
+    newer.sort_by_key(|r| std::cmp::Reverse(version_parts(&r.tag)));
+    Ok(UpdateInfo { newer })
+}
+
+/// Ask GitHub for newer releases with curl, on a worker thread so a slow or missing
+/// network never holds up startup. Nothing is downloaded or installed
+pub fn spawn_check(curl: PathBuf) -> Receiver<io::Result<UpdateInfo>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let result = Command::new(curl)
+            .args(["--silent", "--show-error", "--fail", "--location"])
+            .args(["--max-time", "10"])
+            .args(["--header", "Accept: application/vnd.github+json"])
+            .arg(RELEASES_URL)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .and_then(|output| {
+                if !output.status.success() {
+                    return Err(io::Error::other("the release list couldn't be fetched"));
+                }
+                parse_releases(&String::from_utf8_lossy(&output.stdout))
+            });
+        let _ = tx.send(result);
+    });
+    rx
+}
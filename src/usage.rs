@@ -0,0 +1,110 @@
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::settings::Settings;
+
+/// Recorded events are written out after this many, besides on exit
+const SAVE_EVERY: usize = 50;
+
+/// Counts of the screens, actions and keys used, kept on this machine only
+/// Never sent anywhere; it's there to show which parts of LAIR you actually use
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Usage {
+    pub since: String, // Date recording started, YYYY-MM-DD
+    pub screens: BTreeMap<String, u64>,
+    pub actions: BTreeMap<String, u64>,
+    pub keys: BTreeMap<String, u64>, // "<screen> <key>"
+    #[serde(skip)]
+    unsaved: usize,
+}
+
+impl Usage {
+    fn path() -> PathBuf {
+        Settings::config_dir().join("usage.json")
+    }
+
+    /// The recorded usage, starting afresh if there's none yet or it's unreadable
+    pub fn load() -> Usage {
+        let usage: Option<Usage> = fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok());
+        usage.unwrap_or_else(|| Usage {
+            since: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            ..Usage::default()
+        })
+    }
+
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = Self::path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json)?;
+        self.unsaved = 0;
+        Ok(())
+    }
+
+    /// Forget everything recorded so far
+    pub fn clear() -> io::Result<()> {
+        match fs::remove_file(Self::path()) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn count(map: &mut BTreeMap<String, u64>, name: String) {
+        *map.entry(name).or_insert(0) += 1;
+    }
+
+    /// Count one event, saving now and then so a crash loses little
+    fn recorded(&mut self) {
+        self.unsaved += 1;
+        if self.unsaved >= SAVE_EVERY {
+            let _ = self.save();
+        }
+    }
+
+    pub fn record_screen(&mut self, screen: &str) {
+        Self::count(&mut self.screens, screen.to_string());
+        self.recorded();
+    }
+
+    pub fn record_action(&mut self, action: &str) {
+        Self::count(&mut self.actions, action.to_string());
+        self.recorded();
+    }
+
+    pub fn record_key(&mut self, screen: &str, key: &KeyEvent) {
+        Self::count(&mut self.keys, format!("{} {}", screen, key_name(key)));
+        self.recorded();
+    }
+
+    /// The most used entries of one kind, most used first
+    pub fn top(map: &BTreeMap<String, u64>, count: usize) -> Vec<(&str, u64)> {
+        let mut entries: Vec<(&str, u64)> = map.iter().map(|(k, v)| (k.as_str(), *v)).collect();
+        entries.sort_by_key(|(_, uses)| std::cmp::Reverse(*uses));
+        entries.truncate(count);
+        entries
+    }
+}
+
+/// A key as it would be written in the help text, e.g. "j", "Ctrl+d" or "PageDown"
+fn key_name(key: &KeyEvent) -> String {
+    let name = match key.code {
+        KeyCode::Char(' ') => "Space".to_string(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::F(n) => format!("F{}", n),
+        code => format!("{:?}", code),
+    };
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        format!("Ctrl+{}", name)
+    } else {
+        name
+    }
+}
@@ -0,0 +1,131 @@
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::{Path, PathBuf};
+
+/// Vault-specific ignore file, using .gitignore syntax
+pub const LAIR_IGNORE_FILE: &str = ".lairignore";
+
+/// The vault's own config folder (hooks, logs, ...)
+pub fn config_dir(settings: &Settings) -> PathBuf {
+    Path::new(&settings.notes_directory).join(crate::hooks::VAULT_CONFIG_DIR)
+}
+
+/// The archive folder while archived notes are hidden (`show_archived` off), relative to
+/// the vault root
+pub fn hidden_archive(settings: &Settings) -> Option<&str> {
+    let relative = settings.archive_directory.trim_matches('/');
+    (!settings.show_archived && !relative.is_empty()).then_some(relative)
+}
+
+/// Decides which vault paths are hidden from the browser and vault-wide scans
+/// Honors `.gitignore` and `.lairignore` in the vault root, always hides the `.git` and
+/// `.lair` folders, and hides the archive folder unless archived notes are shown
+pub struct VaultFilter {
+    config_dir: PathBuf,
+    git_dir: PathBuf,
+    archive_dir: Option<PathBuf>,
+    ignore: Gitignore,
+}
+
+impl VaultFilter {
+    pub fn new(settings: &Settings) -> VaultFilter {
+        let vault = Path::new(&settings.notes_directory);
+        let mut builder = GitignoreBuilder::new(vault);
+        for name in [".gitignore", LAIR_IGNORE_FILE] {
+            let file = vault.join(name);
+            if file.is_file() {
+                // A malformed ignore file only loses its bad lines
+                let _ = builder.add(file);
+            }
+        }
+        VaultFilter {
+            config_dir: config_dir(settings),
+            git_dir: vault.join(".git"),
+            archive_dir: hidden_archive(settings).map(|relative| vault.join(relative)),
+            ignore: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        path == self.config_dir
+            || path == self.git_dir
+            || self.archive_dir.as_deref() == Some(path)
+            || (!is_dir && path.file_name() == Some(crate::folders::FOLDER_CONFIG_FILE.as_ref()))
+            || (path.starts_with(self.ignore.path())
+                && self.ignore.matched_path_or_any_parents(path, is_dir).is_ignore())
+    }
+}
+
+/// Whether a path given relative to the vault, by a client of `lair serve` or `lair mcp`, stays
+/// inside it: no root, no `..` and no hidden folder or file such as `.lair` on the way
+pub fn is_plain_relative(path: &Path) -> bool {
+    path.components().all(|c| match c {
+        std::path::Component::Normal(name) => !name.to_string_lossy().starts_with('.'),
+        _ => false,
+    })
+}
+
+/// Whether a name for a new note is a plain file name, with no folder, `..` or leading `.`,
+/// so the note lands in the folder it's created in
+pub fn is_file_name(name: &str) -> bool {
+    let path = Path::new(name);
+    path.components().count() == 1 && is_plain_relative(path)
+}
+
+/// Whether a vault file is hidden, or inside a hidden folder: `.gitignore`, `.obsidian/...`
+pub fn is_hidden(settings: &Settings, path: &Path) -> bool {
+    let relative = path.strip_prefix(&settings.notes_directory).unwrap_or(path);
+    relative
+        .components()
+        .any(|c| c.as_os_str().to_string_lossy().starts_with('.'))
+}
+
+/// Extensions treated as notes in addition to the configured default format
+const NOTE_EXTENSIONS: [&str; 3] = ["md", "markdown", "txt"];
+
+/// Whether a file is a note (as opposed to an attachment or other file)
+pub fn is_note(path: &Path, settings: &Settings) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|ext| {
+            ext.eq_ignore_ascii_case(&settings.default_file_format)
+                || NOTE_EXTENSIONS.iter().any(|n| ext.eq_ignore_ascii_case(n))
+        })
+}
+
+/// Every file in the vault that isn't ignored, sorted
+pub fn all_files(settings: &Settings) -> Vec<PathBuf> {
+    files_under(settings, Path::new(&settings.notes_directory))
+}
+
+/// Every file below `dir` that isn't ignored, sorted
+/// Unreadable folders are skipped rather than failing the whole walk
+pub fn files_under(settings: &Settings, dir: &Path) -> Vec<PathBuf> {
+    files_in(crate::storage::open(settings).as_ref(), settings, dir)
+}
+
+/// Every file below `dir` in `storage` that isn't ignored, sorted
+pub fn files_in(storage: &dyn VaultStorage, settings: &Settings, dir: &Path) -> Vec<PathBuf> {
+    fn walk(storage: &dyn VaultStorage, dir: &Path, filter: &VaultFilter, files: &mut Vec<PathBuf>) {
+        let Ok(entries) = storage.read_dir(dir) else {
+            return;
+        };
+        for path in entries {
+            let is_dir = storage.is_dir(&path);
+            if filter.is_ignored(&path, is_dir) {
+                continue;
+            }
+            if is_dir {
+                walk(storage, &path, filter, files);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+
+    let mut files = Vec::new();
+    walk(storage, dir, &VaultFilter::new(settings), &mut files);
+    files.sort();
+    files
+}
@@ -0,0 +1,554 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::markdown::{OutlineEntry, RenderedLink};
+use crate::preview::{self, Preview};
+use crate::settings::Settings;
+use crate::storage::VaultStorage;
+
+/// A note the viewer came from, restored when going back
+struct Visit {
+    path: PathBuf,
+    scroll: usize,
+    link: Option<usize>,
+}
+
+/// A note opened full screen in the read-only viewer
+pub struct Viewer {
+    pub page: Preview,
+    pub scroll: usize,       // First visible line
+    pub page_size: usize,    // Visible lines on screen, updated on render
+    pub link: Option<usize>, // Index of the link under the cursor
+    pub search_input: Option<String>, // Query being typed after `/`
+    pub search: Option<String>,       // Active search
+    matches: Vec<(usize, Range<usize>)>, // Line and byte range of each match
+    pub current_match: Option<usize>,
+    pub line_cursor: Option<usize>,    // Line being picked for an annotation
+    pub comment_input: Option<String>, // Annotation comment being typed
+    pub task: Option<usize>,           // Line of the checkbox under the cursor
+    pub outline: Option<usize>,        // Heading selected in the outline pane, while it's open
+    pub column: usize,                 // Columns scrolled right, while lines aren't wrapped
+    back_stack: Vec<Visit>,
+}
+
+impl Viewer {
+    pub fn open(storage: &Arc<dyn VaultStorage>, path: &Path) -> Viewer {
+        Viewer {
+            page: load_page(storage, path),
+            scroll: 0,
+            page_size: 10,
+            link: None,
+            search_input: None,
+            search: None,
+            matches: Vec::new(),
+            current_match: None,
+            line_cursor: None,
+            comment_input: None,
+            task: None,
+            outline: None,
+            column: 0,
+            back_stack: Vec::new(),
+        }
+    }
+
+    /// Re-read the note (after editing it), keeping the position and history
+    pub fn reload(&mut self) {
+        self.page = match &self.page.window {
+            Some(window) => preview::load_window(&self.page.storage, &self.page.path, window.start),
+            None => load_page(&self.page.storage, &self.page.path),
+        };
+        self.link = self.link.filter(|&i| i < self.page.links.len());
+        self.line_cursor = self
+            .line_cursor
+            .map(|l| l.min(self.line_count().saturating_sub(1)));
+        self.task = self.task.filter(|&l| self.task_at(l).is_some());
+        self.outline = self.outline.map(|i| i.min(self.headings().len().saturating_sub(1)));
+        if let Some(query) = self.search.clone() {
+            self.find_matches(&query);
+        }
+        self.scroll_by(0);
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.page.text.lines.len()
+    }
+
+    /// Scroll by `delta` lines, clamped so the last line stays reachable
+    /// isize::MIN / isize::MAX jump to the top / bottom
+    pub fn scroll_by(&mut self, delta: isize) {
+        if let Some(window) = &self.page.window {
+            // For a large file, the top / bottom of the file rather than of the window
+            let start = match delta {
+                isize::MIN if window.start > 0 => Some(0),
+                isize::MAX if window.end < window.file_size => {
+                    let storage = self.page.storage.as_ref();
+                    preview::earlier_line_start(storage, &self.page.path, window.file_size).ok()
+                }
+                _ => None,
+            };
+            if let Some(start) = start {
+                self.load_window(start);
+            }
+        }
+        let max = self.line_count().saturating_sub(self.page_size) as isize;
+        self.scroll = (self.scroll as isize).saturating_add(delta).clamp(0, max.max(0)) as usize;
+        self.follow_window();
+    }
+
+    /// Read another window of a large file, dropping positions that pointed into the old one
+    fn load_window(&mut self, start: u64) {
+        self.page = preview::load_window(&self.page.storage, &self.page.path, start);
+        self.link = None;
+        self.line_cursor = None;
+        self.task = None;
+        self.outline = None;
+        if let Some(query) = self.search.clone() {
+            self.find_matches(&query);
+        }
+    }
+
+    /// Move the window of a large file along when the screen gets near either end of it
+    /// Keeps a couple of screens loaded on each side, so scrolling doesn't stop at the edges
+    fn follow_window(&mut self) {
+        let Some(window) = self.page.window.clone() else {
+            return;
+        };
+        let margin = self.page_size * 2;
+        if self.scroll + self.page_size + margin > self.line_count()
+            && window.end < window.file_size
+        {
+            let first = self.scroll.saturating_sub(margin);
+            if let Some(start) = window.line_start(first).filter(|_| first > 0) {
+                self.load_window(start);
+                self.scroll -= first;
+            }
+        } else if self.scroll < margin
+            && window.start > 0
+            && let Ok(start) =
+                preview::earlier_line_start(self.page.storage.as_ref(), &self.page.path, window.start)
+        {
+            self.load_window(start);
+            if let Some(old_start) = self.page.window.as_ref().and_then(|w| w.line_at(window.start))
+            {
+                self.scroll += old_start;
+            }
+        }
+        let max = self.line_count().saturating_sub(self.page_size);
+        self.scroll = self.scroll.min(max);
+    }
+
+    pub fn selected_link(&self) -> Option<&RenderedLink> {
+        self.link.and_then(|i| self.page.links.get(i))
+    }
+
+    /// Move the link cursor forwards (or backwards), wrapping around, and scroll it into view
+    /// The first press picks the first link at or below the top of the screen
+    pub fn move_link(&mut self, forward: bool) {
+        let count = self.page.links.len();
+        if count == 0 {
+            return;
+        }
+        let next = match self.link {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => self
+                .page
+                .links
+                .iter()
+                .position(|l| l.line >= self.scroll)
+                .unwrap_or(0),
+        };
+        self.link = Some(next);
+
+        self.scroll_into_view(self.page.links[next].line);
+    }
+
+    /// Start picking a line to annotate, from the top of the screen
+    pub fn start_annotating(&mut self) {
+        if self.line_count() > 0 {
+            self.line_cursor = Some(self.scroll.min(self.line_count() - 1));
+        }
+    }
+
+    /// Move the annotation line cursor, skipping blank lines and keeping it on screen
+    pub fn move_line_cursor(&mut self, forward: bool) {
+        let Some(current) = self.line_cursor else {
+            return;
+        };
+        let lines = &self.page.text.lines;
+        let is_blank = |i: usize| crate::annotations::plain_line(&lines[i]).is_empty();
+        let next = if forward {
+            (current + 1..lines.len()).find(|&i| !is_blank(i))
+        } else {
+            (0..current).rev().find(|&i| !is_blank(i))
+        };
+        if let Some(next) = next {
+            self.line_cursor = Some(next);
+            self.scroll_into_view(next);
+        }
+    }
+
+    /// The checkbox shown on rendered line `line`, as (done, task text)
+    fn task_at(&self, line: usize) -> Option<(bool, String)> {
+        let text = crate::annotations::plain_line(self.page.text.lines.get(line)?);
+        let rest = text.strip_prefix("• ").or_else(|| {
+            let digits = text.find(|c: char| !c.is_ascii_digit())?;
+            (digits > 0).then(|| text[digits..].strip_prefix(". "))?
+        })?;
+        if let Some(task) = rest.strip_prefix("[ ] ") {
+            Some((false, task.to_string()))
+        } else {
+            rest.strip_prefix("[x] ").map(|task| (true, task.to_string()))
+        }
+    }
+
+    /// Move the task cursor to the next (or previous) checkbox, wrapping around
+    /// The first press picks the first checkbox at or below the top of the screen
+    pub fn move_task(&mut self, forward: bool) -> bool {
+        let tasks: Vec<usize> = (0..self.line_count())
+            .filter(|&l| self.task_at(l).is_some())
+            .collect();
+        if tasks.is_empty() {
+            return false;
+        }
+        let next = match self.task.and_then(|t| tasks.iter().position(|&l| l == t)) {
+            Some(i) if forward => tasks[(i + 1) % tasks.len()],
+            Some(i) => tasks[(i + tasks.len() - 1) % tasks.len()],
+            None => tasks.iter().copied().find(|&l| l >= self.scroll).unwrap_or(tasks[0]),
+        };
+        self.task = Some(next);
+        self.scroll_into_view(next);
+        true
+    }
+
+    /// The checkbox under the task cursor, as (done, text, how many identical ones come
+    /// before it, its position among all checkboxes)
+    pub fn selected_task(&self) -> Option<(bool, String, usize, usize)> {
+        let line = self.task?;
+        let (done, text) = self.task_at(line)?;
+        let earlier: Vec<(bool, String)> = (0..line).filter_map(|l| self.task_at(l)).collect();
+        let occurrence = earlier
+            .iter()
+            .filter(|(d, t)| *d == done && *t == text)
+            .count();
+        Some((done, text, occurrence, earlier.len()))
+    }
+
+    /// The note's headings, for the outline pane
+    pub fn headings(&self) -> Vec<OutlineEntry> {
+        crate::markdown::outline(&self.page.text)
+    }
+
+    /// Open the outline with the section at the top of the screen selected
+    /// Returns false when the note has no headings
+    pub fn open_outline(&mut self) -> bool {
+        let headings = self.headings();
+        if headings.is_empty() {
+            return false;
+        }
+        let current = headings.iter().rposition(|h| h.line <= self.scroll);
+        self.outline = Some(current.unwrap_or(0));
+        true
+    }
+
+    /// Move the outline selection by `delta` headings, clamped to the list
+    pub fn move_outline(&mut self, delta: isize) {
+        let count = self.headings().len();
+        if let Some(selected) = &mut self.outline
+            && count > 0
+        {
+            let target = (*selected as isize).saturating_add(delta);
+            *selected = target.clamp(0, count as isize - 1) as usize;
+        }
+    }
+
+    /// Scroll the selected heading to the top of the screen and close the outline
+    pub fn jump_to_outline(&mut self) {
+        if let Some(heading) = self.outline.take().and_then(|i| self.headings().get(i).cloned()) {
+            self.scroll = heading.line;
+            self.scroll_by(0);
+        }
+    }
+
+    /// Scroll sideways by `delta` columns, for lines too long to show unwrapped
+    pub fn scroll_columns(&mut self, delta: isize) {
+        let widest = self.page.text.lines.iter().map(|l| l.width()).max().unwrap_or(0);
+        self.column = self.column.saturating_add_signed(delta).min(widest.saturating_sub(1));
+    }
+
+    /// The page text with search matches and the link under the cursor highlighted
+    pub fn highlighted_text(&self) -> Text<'static> {
+        let mut text = self.page.text.clone();
+        if let Some(line) = self
+            .line_cursor
+            .or(self.task)
+            .and_then(|l| text.lines.get_mut(l))
+        {
+            line.style = line.style.bg(Color::DarkGray);
+        }
+        if let Some(link) = self.selected_link()
+            && let Some(line) = text.lines.get_mut(link.line)
+        {
+            for span in line.spans.iter_mut().take(link.spans.end).skip(link.spans.start) {
+                span.style = span.style.add_modifier(Modifier::REVERSED);
+            }
+        }
+
+        let match_style = Style::default().bg(Color::Yellow).fg(Color::Black);
+        for (i, (line_index, range)) in self.matches.iter().enumerate() {
+            let style = if self.current_match == Some(i) {
+                match_style.add_modifier(Modifier::BOLD | Modifier::REVERSED)
+            } else {
+                match_style
+            };
+            if let Some(line) = text.lines.get_mut(*line_index) {
+                style_range(line, range.clone(), style);
+            }
+        }
+        text
+    }
+
+    /// Search the page for `query` (case-insensitive for ASCII) and jump to the first match
+    /// at or below the top of the screen
+    pub fn set_search(&mut self, query: String) {
+        if query.is_empty() {
+            self.clear_search();
+            return;
+        }
+        self.find_matches(&query);
+        self.search = Some(query);
+        self.current_match = self
+            .matches
+            .iter()
+            .position(|(line, _)| *line >= self.scroll)
+            .or(if self.matches.is_empty() { None } else { Some(0) });
+        self.scroll_to_match();
+    }
+
+    pub fn clear_search(&mut self) {
+        self.search = None;
+        self.matches.clear();
+        self.current_match = None;
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    fn find_matches(&mut self, query: &str) {
+        let needle = query.to_ascii_lowercase();
+        self.matches = self
+            .page
+            .text
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(i, line)| {
+                let haystack: String = line
+                    .spans
+                    .iter()
+                    .map(|s| s.content.as_ref())
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                haystack
+                    .match_indices(&needle)
+                    .map(|(start, m)| (i, start..start + m.len()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        self.current_match = self.current_match.filter(|&i| i < self.matches.len());
+    }
+
+    /// Move to the next (or previous) match, wrapping around
+    pub fn next_match(&mut self, forward: bool) {
+        let count = self.matches.len();
+        if count == 0 {
+            return;
+        }
+        self.current_match = Some(match self.current_match {
+            Some(i) if forward => (i + 1) % count,
+            Some(i) => (i + count - 1) % count,
+            None => 0,
+        });
+        self.scroll_to_match();
+    }
+
+    fn scroll_to_match(&mut self) {
+        if let Some((line, _)) = self.current_match.and_then(|i| self.matches.get(i)) {
+            self.scroll_into_view(*line);
+        }
+    }
+
+    /// Scroll so `line` is visible, centring it when it was off screen
+    fn scroll_into_view(&mut self, line: usize) {
+        if line < self.scroll || line >= self.scroll + self.page_size {
+            self.scroll = line.saturating_sub(self.page_size / 2);
+            self.scroll_by(0);
+        }
+    }
+
+    /// Open `path`, remembering the current note so `back` can return to it
+    pub fn follow(&mut self, path: &Path) {
+        let visit = Visit {
+            path: self.page.path.clone(),
+            scroll: self.scroll,
+            link: self.link,
+        };
+        let back_stack = std::mem::take(&mut self.back_stack);
+        *self = Viewer::open(&self.page.storage.clone(), path);
+        self.back_stack = back_stack;
+        self.back_stack.push(visit);
+    }
+
+    /// Return to the previous note; false when there is nothing to go back to
+    pub fn back(&mut self) -> bool {
+        let Some(visit) = self.back_stack.pop() else {
+            return false;
+        };
+        let back_stack = std::mem::take(&mut self.back_stack);
+        let page_size = self.page_size;
+        *self = Viewer::open(&self.page.storage.clone(), &visit.path);
+        self.back_stack = back_stack;
+        self.page_size = page_size;
+        self.scroll = visit.scroll;
+        self.link = visit.link;
+        true
+    }
+}
+
+/// A note as the viewer shows it: binary files as a hexdump rather than a summary
+fn load_page(storage: &Arc<dyn VaultStorage>, path: &Path) -> Preview {
+    let page = preview::load(storage, path);
+    if page.binary {
+        preview::load_hexdump(storage, path)
+    } else {
+        page
+    }
+}
+
+/// Patch `style` onto the bytes `range` of a line's text, splitting spans where needed
+fn style_range(line: &mut Line<'static>, range: Range<usize>, style: Style) {
+    let mut spans = Vec::with_capacity(line.spans.len() + 2);
+    let mut offset = 0;
+    for span in line.spans.drain(..) {
+        let content = span.content.to_string();
+        let (start, end) = (offset, offset + content.len());
+        offset = end;
+        if range.end <= start || range.start >= end {
+            spans.push(span);
+            continue;
+        }
+        let cut_start = range.start.max(start) - start;
+        let cut_end = range.end.min(end) - start;
+        if cut_start > 0 {
+            spans.push(Span::styled(content[..cut_start].to_string(), span.style));
+        }
+        spans.push(Span::styled(
+            content[cut_start..cut_end].to_string(),
+            span.style.patch(style),
+        ));
+        if cut_end < content.len() {
+            spans.push(Span::styled(content[cut_end..].to_string(), span.style));
+        }
+    }
+    line.spans = spans;
+}
+
+/// Find the note a link points at
+/// Markdown links are paths relative to the linking note; wiki links name a note anywhere in
+/// the vault, by file name or by vault-relative path, with or without the extension
+pub fn resolve_link(from: &Path, link: &RenderedLink, settings: &Settings) -> Option<PathBuf> {
+    let files = if link.wiki {
+        crate::vault::all_files(settings)
+    } else {
+        Vec::new()
+    };
+    resolve_link_in(from, link, settings, &files)
+}
+
+/// `resolve_link` against the vault's files listed beforehand, for resolving many links
+/// Only wiki links look at `files`
+pub fn resolve_link_in(
+    from: &Path,
+    link: &RenderedLink,
+    settings: &Settings,
+    files: &[PathBuf],
+) -> Option<PathBuf> {
+    let target = link.target.split('#').next().unwrap_or("").trim();
+    if target.is_empty() {
+        return None;
+    }
+
+    let vault = Path::new(&settings.notes_directory);
+    if !link.wiki {
+        // `lair://` links and those starting with `/` are from the vault root
+        let path = match crate::links::uri_path(settings, target) {
+            Some(path) => path,
+            None => match target.strip_prefix('/') {
+                Some(relative) if vault.join(relative.replace("%20", " ")).is_file() => {
+                    vault.join(relative.replace("%20", " "))
+                }
+                _ => from.parent()?.join(target.replace("%20", " ")),
+            },
+        };
+        return path.is_file().then_some(path);
+    }
+
+    let wanted = target.to_lowercase();
+    files
+        .iter()
+        .filter(|f| crate::vault::is_note(f, settings))
+        .find(|f| {
+            let relative = f.strip_prefix(vault).unwrap_or(f);
+            let stem = f.file_stem().map(|s| s.to_string_lossy().to_lowercase());
+            let relative_stem = relative.with_extension("").to_string_lossy().to_lowercase();
+            stem.as_deref() == Some(wanted.as_str())
+                || relative_stem == wanted
+                || relative.to_string_lossy().to_lowercase() == wanted
+        })
+        .cloned()
+        // `[[202406141530]]` finds the note with that ID
+        .or_else(|| {
+            crate::ids::is_id(&wanted)
+                .then(|| crate::ids::find(settings, files, &wanted))
+                .flatten()
+        })
+}
+
+/// Drop the first `columns` characters of every line, for scrolling sideways
+pub fn skip_columns(text: &mut Text<'static>, columns: usize) {
+    if columns == 0 {
+        return;
+    }
+    for line in &mut text.lines {
+        let mut left = columns;
+        line.spans.retain_mut(|span| {
+            if left == 0 {
+                return true;
+            }
+            let count = span.content.chars().count();
+            if count <= left {
+                left -= count;
+                return false;
+            }
+            span.content = span.content.chars().skip(left).collect::<String>().into();
+            left = 0;
+            true
+        });
+    }
+}
+
+/// Prefix each line with its number in a dim gutter
+pub fn number_lines(text: &mut Text<'static>) {
+    let width = text.lines.len().to_string().len();
+    for (i, line) in text.lines.iter_mut().enumerate() {
+        let number = Span::styled(
+            format!("{:>width$} │ ", i + 1, width = width),
+            Style::default().fg(Color::DarkGray),
+        );
+        line.spans.insert(0, number);
+    }
+}